@@ -24,9 +24,10 @@ use std::mem;
 use std::ptr;
 use sha3;
 use std::slice;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::io::{Read, Write, self};
-use std::fs::{self, File};
+use std::fs::{self, File, OpenOptions};
+use memmap::{Mmap, Protection};
 
 pub const ETHASH_EPOCH_LENGTH: u64 = 30000;
 pub const ETHASH_CACHE_ROUNDS: usize = 3;
@@ -101,17 +102,17 @@ impl Light {
 		light_compute(self, header_hash, nonce)
 	}
 
-	pub fn file_path(block_number: u64) -> PathBuf {
-		let mut home = ::std::env::home_dir().unwrap();
-		home.push(".ethash");
-		home.push("light");
-		let seed_hash = get_seedhash(block_number);
-		home.push(to_hex(&seed_hash));
-		home
+	/// Block number the cache was created for.
+	pub fn block_number(&self) -> u64 {
+		self.block_number
 	}
 
-	pub fn from_file(block_number: u64) -> io::Result<Light> {
-		let path = Light::file_path(block_number);
+	pub fn file_path(cache_dir: &Path, block_number: u64) -> PathBuf {
+		cache_dir.join("light").join(to_hex(&get_seedhash(block_number)))
+	}
+
+	pub fn from_file(cache_dir: &Path, block_number: u64) -> io::Result<Light> {
+		let path = Light::file_path(cache_dir, block_number);
 		let mut file = try!(File::open(path));
 
 		let cache_size = get_cache_size(block_number);
@@ -129,8 +130,8 @@ impl Light {
 		})
 	}
 
-	pub fn to_file(&self) -> io::Result<()> {
-		let path = Light::file_path(self.block_number);
+	pub fn to_file(&self, cache_dir: &Path) -> io::Result<()> {
+		let path = Light::file_path(cache_dir, self.block_number);
 		try!(fs::create_dir_all(path.parent().unwrap()));
 		let mut file = try!(File::create(path));
 
@@ -141,6 +142,75 @@ impl Light {
 	}
 }
 
+/// Full dataset of an epoch, memory-mapped from its file.
+pub struct Full {
+	block_number: u64,
+	mmap: Mmap,
+}
+
+impl Full {
+	/// Path of the dataset file of the epoch of `block_number`.
+	pub fn file_path(cache_dir: &Path, block_number: u64) -> PathBuf {
+		cache_dir.join("full").join(to_hex(&get_seedhash(block_number)))
+	}
+
+	/// Open a dataset generated earlier.
+	pub fn from_file(cache_dir: &Path, block_number: u64) -> io::Result<Full> {
+		let mmap = try!(Mmap::open_path(Full::file_path(cache_dir, block_number), Protection::Read));
+		if mmap.len() != get_data_size(block_number) {
+			return Err(io::Error::new(io::ErrorKind::Other, "Dataset file size mismatch"));
+		}
+		Ok(Full {
+			block_number: block_number,
+			mmap: mmap,
+		})
+	}
+
+	/// Generate the dataset of the epoch of `light` into its file. It is written to a temporary
+	/// file first so that an interrupted generation doesn't leave a truncated dataset behind.
+	pub fn generate(cache_dir: &Path, light: &Light) -> io::Result<Full> {
+		let path = Full::file_path(cache_dir, light.block_number);
+		let tmp_path = path.with_extension("tmp");
+		try!(fs::create_dir_all(path.parent().unwrap()));
+		let data_size = get_data_size(light.block_number);
+		{
+			let file = try!(OpenOptions::new().read(true).write(true).create(true).truncate(true).open(&tmp_path));
+			try!(file.set_len(data_size as u64));
+		}
+
+		let mut mmap = try!(Mmap::open_path(&tmp_path, Protection::ReadWrite));
+		{
+			let buf = unsafe { mmap.as_mut_slice() };
+			let nodes = unsafe { slice::from_raw_parts_mut(buf.as_mut_ptr() as *mut Node, data_size / NODE_BYTES) };
+			for (i, node) in nodes.iter_mut().enumerate() {
+				*node = calculate_dag_item(i as u32, light);
+			}
+		}
+		try!(mmap.flush());
+		try!(fs::rename(&tmp_path, &path));
+		Ok(Full {
+			block_number: light.block_number,
+			mmap: mmap,
+		})
+	}
+
+	/// Block number the dataset was generated for.
+	pub fn block_number(&self) -> u64 {
+		self.block_number
+	}
+
+	/// Calculate the boundary data from the full dataset
+	/// `header_hash` - The header hash to pack into the mix
+	/// `nonce` - The nonce to pack into the mix
+	pub fn compute(&self, header_hash: &H256, nonce: u64) -> ProofOfWork {
+		let nodes = unsafe {
+			let buf = self.mmap.as_slice();
+			slice::from_raw_parts(buf.as_ptr() as *const Node, buf.len() / NODE_BYTES)
+		};
+		hash_compute(self.mmap.len(), header_hash, nonce, |index| nodes[index as usize].clone())
+	}
+}
+
 #[inline]
 fn fnv_hash(x: u32, y: u32) -> u32 {
 	return x.wrapping_mul(FNV_PRIME) ^ y;
@@ -208,10 +278,11 @@ pub fn quick_get_difficulty(header_hash: &H256, nonce: u64, mix_hash: &H256) ->
 /// `nonce` - The nonce to pack into the mix
 pub fn light_compute(light: &Light, header_hash: &H256, nonce: u64) -> ProofOfWork {
 	let full_size = get_data_size(light.block_number);
-	hash_compute(light, full_size, header_hash, nonce)
+	hash_compute(full_size, header_hash, nonce, |index| calculate_dag_item(index, light))
 }
 
-fn hash_compute(light: &Light, full_size: usize,  header_hash: &H256, nonce: u64) -> ProofOfWork {
+/// Hashimoto over a dataset of `full_size` bytes whose items are given by `lookup`.
+fn hash_compute<F>(full_size: usize, header_hash: &H256, nonce: u64, lookup: F) -> ProofOfWork where F: Fn(u32) -> Node {
 	if full_size % MIX_WORDS != 0 {
 		panic!("Unaligned full size");
 	}
@@ -234,7 +305,7 @@ fn hash_compute(light: &Light, full_size: usize,  header_hash: &H256, nonce: u64
 		for i in 0..(ETHASH_ACCESSES as u32) {
 			let index = fnv_hash(f_mix.get_unchecked(0).as_words().get_unchecked(0) ^ i, *mix.get_unchecked(0).as_words().get_unchecked((i as usize) % MIX_WORDS)) % num_full_pages;
 			for n in 0..MIX_NODES {
-				let tmp_node = calculate_dag_item(index * MIX_NODES as u32 + n as u32, light);
+				let tmp_node = lookup(index * MIX_NODES as u32 + n as u32);
 				for w in 0..NODE_WORDS {
 					*mix.get_unchecked_mut(n).as_words_mut().get_unchecked_mut(w) = fnv_hash(*mix.get_unchecked(n).as_words().get_unchecked(w), *tmp_node.as_words().get_unchecked(w));
 				}
@@ -322,7 +393,8 @@ fn light_new(block_number: u64) -> Light {
 }
 
 static CHARS: &'static[u8] = b"0123456789abcdef";
-fn to_hex(bytes: &[u8]) -> String {
+/// Hex representation of `bytes`, used for cache file names.
+pub fn to_hex(bytes: &[u8]) -> String {
 	let mut v = Vec::with_capacity(bytes.len() * 2);
 	for &byte in bytes.iter() {
 		v.push(CHARS[(byte >> 4) as usize]);
@@ -18,38 +18,79 @@
 //! See https://github.com/ethereum/wiki/wiki/Ethash
 extern crate primal;
 extern crate sha3;
+extern crate memmap;
 #[macro_use]
 extern crate log;
+#[cfg(test)]
+extern crate ethcore_devtools as devtools;
 mod compute;
 
-use std::mem;
-use compute::Light;
-pub use compute::{get_seedhash, quick_get_difficulty, H256, ProofOfWork, ETHASH_EPOCH_LENGTH};
+use std::env;
+use std::fs;
+use std::thread;
+use std::path::{Path, PathBuf};
+pub use compute::{get_seedhash, quick_get_difficulty, H256, ProofOfWork, Light, Full, ETHASH_EPOCH_LENGTH};
 
 use std::sync::{Arc, Mutex};
 
-struct LightCache {
-	recent_epoch: Option<u64>,
-	recent: Option<Arc<Light>>,
-	prev_epoch: Option<u64>,
-	prev: Option<Arc<Light>>,
+/// Number of light caches kept in memory by default.
+pub const DEFAULT_LIGHT_CACHES: usize = 3;
+/// Number of full datasets kept, the current epoch's and the next one's.
+const MAX_DATASETS: usize = 2;
+/// Generation of the next epoch's dataset starts this many blocks before the epoch boundary.
+pub const NEXT_EPOCH_PREPARE_BLOCKS: u64 = 3000;
+
+/// Directory of light caches and datasets when no other is given.
+pub fn default_cache_dir() -> PathBuf {
+	env::home_dir().unwrap().join(".ethash")
+}
+
+struct Datasets {
+	// ordered by epoch
+	loaded: Vec<Arc<Full>>,
+	generating: Vec<u64>,
+}
+
+impl Datasets {
+	fn insert(&mut self, full: Arc<Full>) {
+		self.loaded.push(full);
+		self.loaded.sort_by(|a, b| a.block_number().cmp(&b.block_number()));
+		if self.loaded.len() > MAX_DATASETS {
+			self.loaded.remove(0);
+		}
+	}
+
+	fn epochs(&self) -> Vec<u64> {
+		self.loaded.iter().map(|f| f.block_number() / ETHASH_EPOCH_LENGTH).chain(self.generating.iter().cloned()).collect()
+	}
 }
 
 /// Light/Full cache manager.
 pub struct EthashManager {
-	cache: Mutex<LightCache>,
+	cache_dir: PathBuf,
+	max_lights: usize,
+	// epoch and cache, least recently used first
+	lights: Mutex<Vec<(u64, Arc<Light>)>>,
+	datasets: Arc<Mutex<Datasets>>,
 }
 
 impl EthashManager {
-	/// Create a new new instance of ethash manager
-	pub fn new() -> EthashManager {
+	/// Create a new new instance of ethash manager keeping its files in `cache_dir`
+	pub fn new(cache_dir: &Path) -> EthashManager {
+		EthashManager::with_light_caches(cache_dir, DEFAULT_LIGHT_CACHES)
+	}
+
+	/// Create a new instance of ethash manager which keeps at most `max_lights` light caches in memory
+	pub fn with_light_caches(cache_dir: &Path, max_lights: usize) -> EthashManager {
+		assert!(max_lights > 0, "At least one light cache must be kept");
 		EthashManager {
-			cache: Mutex::new(LightCache {
-				recent_epoch: None,
-				recent: None,
-				prev_epoch: None,
-				prev: None,
-			}),
+			cache_dir: cache_dir.to_path_buf(),
+			max_lights: max_lights,
+			lights: Mutex::new(Vec::new()),
+			datasets: Arc::new(Mutex::new(Datasets {
+				loaded: Vec::new(),
+				generating: Vec::new(),
+			})),
 		}
 	}
 
@@ -59,61 +100,144 @@ impl EthashManager {
 	/// `header_hash` - The header hash to pack into the mix
 	/// `nonce` - The nonce to pack into the mix
 	pub fn compute_light(&self, block_number: u64, header_hash: &H256, nonce: u64) -> ProofOfWork {
+		self.light(block_number).compute(header_hash, nonce)
+	}
+
+	/// Calculate the data from the full dataset, as needed for mining. Returns `None` while the dataset
+	/// of the epoch is generated in the background. Close to the epoch boundary the dataset of the
+	/// next epoch is generated too.
+	pub fn compute_full(&self, block_number: u64, header_hash: &H256, nonce: u64) -> Option<ProofOfWork> {
+		let full = self.prepare_full(block_number);
+		if block_number % ETHASH_EPOCH_LENGTH >= ETHASH_EPOCH_LENGTH - NEXT_EPOCH_PREPARE_BLOCKS {
+			self.prepare_full(block_number + ETHASH_EPOCH_LENGTH);
+		}
+		full.map(|full| full.compute(header_hash, nonce))
+	}
+
+	/// Get the full dataset of the epoch of `block_number`, loading it from disk. If there is none,
+	/// start generating it in the background and return `None`.
+	pub fn prepare_full(&self, block_number: u64) -> Option<Arc<Full>> {
 		let epoch = block_number / ETHASH_EPOCH_LENGTH;
-		let light = {
-			let mut lights = self.cache.lock().unwrap();
-			let light = match lights.recent_epoch.clone() {
-				Some(ref e) if *e == epoch => lights.recent.clone(),
-				_ => match lights.prev_epoch.clone() {
-					Some(e) if e == epoch => {
-						// swap
-						let t = lights.prev_epoch;
-						lights.prev_epoch = lights.recent_epoch;
-						lights.recent_epoch = t;
-						let t = lights.prev.clone();
-						lights.prev = lights.recent.clone();
-						lights.recent = t;
-						lights.recent.clone()
-					}
-					_ => None,
-				}
-			};
-			match light {
-				None => {
-					let light = match Light::from_file(block_number) {
-						Ok(light) => Arc::new(light),
-						Err(e) => {
-							debug!("Light cache file not found for {}:{}", block_number, e);
-							let light = Light::new(block_number);
-							if let Err(e) = light.to_file() {
-								warn!("Light cache file write error: {}", e);
-							}
-							Arc::new(light)
-						}
-					};
-					lights.prev_epoch = mem::replace(&mut lights.recent_epoch, Some(epoch));
-					lights.prev = mem::replace(&mut lights.recent, Some(light.clone()));
-					light
+		let mut datasets = self.datasets.lock().unwrap();
+		if let Some(full) = datasets.loaded.iter().find(|f| f.block_number() / ETHASH_EPOCH_LENGTH == epoch) {
+			return Some(full.clone());
+		}
+		if datasets.generating.contains(&epoch) {
+			return None;
+		}
+		if let Ok(full) = Full::from_file(&self.cache_dir, block_number) {
+			let full = Arc::new(full);
+			datasets.insert(full.clone());
+			return Some(full);
+		}
+
+		datasets.generating.push(epoch);
+		let light = self.light(block_number);
+		let cache_dir = self.cache_dir.clone();
+		let shared = self.datasets.clone();
+		thread::spawn(move || {
+			info!("Generating ethash dataset for epoch {}", epoch);
+			let generated = Full::generate(&cache_dir, &light);
+			let mut datasets = shared.lock().unwrap();
+			datasets.generating.retain(|e| *e != epoch);
+			match generated {
+				Ok(full) => {
+					datasets.insert(Arc::new(full));
+					remove_stale_datasets(&cache_dir, &datasets.epochs());
+				},
+				Err(e) => warn!("Error generating ethash dataset for epoch {}: {}", epoch, e),
+			}
+		});
+		None
+	}
+
+	fn light(&self, block_number: u64) -> Arc<Light> {
+		let epoch = block_number / ETHASH_EPOCH_LENGTH;
+		let mut lights = self.lights.lock().unwrap();
+		if let Some(index) = lights.iter().position(|&(e, _)| e == epoch) {
+			let entry = lights.remove(index);
+			let light = entry.1.clone();
+			lights.push(entry);
+			return light;
+		}
+
+		let light = match Light::from_file(&self.cache_dir, block_number) {
+			Ok(light) => Arc::new(light),
+			Err(e) => {
+				debug!("Light cache file not found for {}:{}", block_number, e);
+				let light = Light::new(block_number);
+				if let Err(e) = light.to_file(&self.cache_dir) {
+					warn!("Light cache file write error: {}", e);
 				}
-				Some(light) => light
+				Arc::new(light)
 			}
 		};
-		light.compute(header_hash, nonce)
+		if lights.len() == self.max_lights {
+			lights.remove(0);
+		}
+		lights.push((epoch, light.clone()));
+		light
+	}
+}
+
+/// Remove dataset files, complete or not, of epochs other than `keep`.
+fn remove_stale_datasets(cache_dir: &Path, keep: &[u64]) {
+	let keep: Vec<PathBuf> = keep.iter().map(|e| Full::file_path(cache_dir, e * ETHASH_EPOCH_LENGTH)).collect();
+	let entries = match fs::read_dir(cache_dir.join("full")) {
+		Ok(entries) => entries,
+		Err(_) => return,
+	};
+	for path in entries.filter_map(|e| e.ok()).map(|e| e.path()) {
+		if !keep.iter().any(|k| *k == path || k.with_extension("tmp") == path) {
+			debug!("Removing stale ethash dataset {}", path.display());
+			if let Err(e) = fs::remove_file(&path) {
+				warn!("Error removing {}: {}", path.display(), e);
+			}
+		}
 	}
 }
 
-#[test]
-fn test_lru() {
-	let ethash = EthashManager::new();
-	let hash = [0u8; 32];
-	ethash.compute_light(1, &hash, 1);
-	ethash.compute_light(50000, &hash, 1);
-	assert_eq!(ethash.cache.lock().unwrap().recent_epoch.unwrap(), 1);
-	assert_eq!(ethash.cache.lock().unwrap().prev_epoch.unwrap(), 0);
-	ethash.compute_light(1, &hash, 1);
-	assert_eq!(ethash.cache.lock().unwrap().recent_epoch.unwrap(), 0);
-	assert_eq!(ethash.cache.lock().unwrap().prev_epoch.unwrap(), 1);
-	ethash.compute_light(70000, &hash, 1);
-	assert_eq!(ethash.cache.lock().unwrap().recent_epoch.unwrap(), 2);
-	assert_eq!(ethash.cache.lock().unwrap().prev_epoch.unwrap(), 0);
+#[cfg(test)]
+mod tests {
+	use std::fs;
+	use devtools::RandomTempPath;
+	use super::*;
+	use super::remove_stale_datasets;
+
+	fn cached_epochs(ethash: &EthashManager) -> Vec<u64> {
+		ethash.lights.lock().unwrap().iter().map(|&(e, _)| e).collect()
+	}
+
+	#[test]
+	fn test_lru() {
+		let temp = RandomTempPath::create_dir();
+		let ethash = EthashManager::with_light_caches(temp.as_path(), 2);
+		let hash = [0u8; 32];
+		ethash.compute_light(1, &hash, 1);
+		ethash.compute_light(50000, &hash, 1);
+		assert_eq!(cached_epochs(&ethash), vec![0, 1]);
+		ethash.compute_light(1, &hash, 1);
+		assert_eq!(cached_epochs(&ethash), vec![1, 0]);
+		ethash.compute_light(70000, &hash, 1);
+		assert_eq!(cached_epochs(&ethash), vec![0, 2]);
+		assert!(Light::file_path(temp.as_path(), 70000).exists());
+	}
+
+	#[test]
+	fn removes_stale_datasets() {
+		let temp = RandomTempPath::create_dir();
+		let paths: Vec<_> = (0..4).map(|e| Full::file_path(temp.as_path(), e * ETHASH_EPOCH_LENGTH)).collect();
+		fs::create_dir_all(paths[0].parent().unwrap()).unwrap();
+		for path in &paths {
+			fs::File::create(path).unwrap();
+		}
+		fs::File::create(paths[3].with_extension("tmp")).unwrap();
+		fs::File::create(paths[0].with_extension("tmp")).unwrap();
+
+		remove_stale_datasets(temp.as_path(), &[2, 3]);
+		let exists: Vec<bool> = paths.iter().map(|p| p.exists()).collect();
+		assert_eq!(exists, vec![false, false, true, true]);
+		assert!(paths[3].with_extension("tmp").exists());
+		assert!(!paths[0].with_extension("tmp").exists());
+	}
 }
@@ -0,0 +1,68 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! benchmarking for the evm interpreter
+//! should be started with:
+//! ```bash
+//! multirust run nightly cargo bench
+//! ```
+
+#![feature(test)]
+
+extern crate test;
+extern crate ethcore_util;
+extern crate ethcore;
+
+use test::{Bencher, black_box};
+use ethcore_util::*;
+use ethcore::spec::Spec;
+use ethcore::evm_runner::{Runner, Call, VMType};
+
+// PUSH2 1000 JUMPDEST PUSH1 1 SWAP1 SUB DUP1 PUSH1 3 JUMPI STOP
+const LOOP_CODE: &'static str = "6103e85b600190038060035700";
+
+fn loop_call() -> Call {
+	Call {
+		code: LOOP_CODE.from_hex().unwrap(),
+		gas: U256::from(1_000_000),
+		address: Address::from(0x1000),
+		..Call::default()
+	}
+}
+
+#[bench]
+fn evm_loop(b: &mut Bencher) {
+	let runner = Runner::new(Spec::new_test(), VMType::Interpreter).unwrap();
+	let call = loop_call();
+	b.iter(|| {
+		black_box(runner.run(&call, false));
+	});
+}
+
+#[bench]
+fn evm_loop_analysed_every_run(b: &mut Bencher) {
+	let runner = Runner::new(Spec::new_test(), VMType::Interpreter).unwrap();
+	let mut call = loop_call();
+	let code_len = call.code.len();
+	let mut run = 0u32;
+	b.iter(|| {
+		// unreachable trailing bytes change the code hash, so cached analyses are never hit
+		run += 1;
+		call.code.truncate(code_len);
+		call.code.extend_from_slice(&[(run >> 24) as u8, (run >> 16) as u8, (run >> 8) as u8, run as u8]);
+		black_box(runner.run(&call, false));
+	});
+}
@@ -47,6 +47,8 @@ pub struct ActionParams {
 	pub value: ActionValue,
 	/// Code being executed.
 	pub code: Option<Bytes>,
+	/// Hash of the code being executed, if it is known. Analysis of such code is cached.
+	pub code_hash: Option<H256>,
 	/// Input data.
 	pub data: Option<Bytes>
 }
@@ -63,6 +65,7 @@ impl Default for ActionParams {
 			gas_price: U256::zero(),
 			value: ActionValue::Transfer(U256::zero()),
 			code: None,
+			code_hash: None,
 			data: None
 		}
 	}
@@ -0,0 +1,271 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+
+//! A proof-of-authority engine in which a fixed set of validators take turns to seal blocks.
+
+use time::get_time;
+use common::*;
+use util::crypto::ec;
+use util::keys::store::SecretStore;
+use block::*;
+use spec::Spec;
+use engine::Engine;
+use evm::Factory;
+
+/// Step duration in seconds used when the spec doesn't give one.
+const DEFAULT_STEP_DURATION: u64 = 4;
+
+/// Engine in which time is divided into steps of `stepDuration` seconds and each step has one
+/// proposer, taken in turn from the `validators` list of the spec's engine parameters.
+///
+/// The seal consists of the step and the proposer's signature of the bare header hash.
+pub struct AuthorityRound {
+	spec: Spec,
+	factory: Factory,
+	validators: Vec<Address>,
+	step_duration: u64,
+	proposed_step: Mutex<u64>,
+}
+
+impl AuthorityRound {
+	/// Create a new boxed instance of AuthorityRound engine
+	pub fn new_boxed(spec: Spec) -> Box<Engine> {
+		Box::new(AuthorityRound::new(spec))
+	}
+
+	fn new(spec: Spec) -> AuthorityRound {
+		let validators = spec.engine_params.get("validators").map_or_else(Vec::new, |v| decode(v));
+		let step_duration = spec.engine_params.get("stepDuration").map_or(DEFAULT_STEP_DURATION, |d| decode(d));
+		AuthorityRound {
+			spec: spec,
+			factory: Factory::default(),
+			validators: validators,
+			step_duration: max(step_duration, 1),
+			proposed_step: Mutex::new(0),
+		}
+	}
+
+	/// The step we're currently in according to the local clock.
+	fn step(&self) -> u64 {
+		get_time().sec as u64 / self.step_duration
+	}
+
+	/// The validator which is allowed to seal a block in `step`.
+	fn proposer(&self, step: u64) -> Option<&Address> {
+		match self.validators.len() {
+			0 => None,
+			n => Some(&self.validators[(step % n as u64) as usize]),
+		}
+	}
+
+	fn header_step(header: &Header) -> Result<u64, Error> {
+		Ok(try!(UntrustedRlp::new(&header.seal[0]).as_val::<u64>()))
+	}
+}
+
+impl Engine for AuthorityRound {
+	fn name(&self) -> &str { "AuthorityRound" }
+	fn version(&self) -> SemanticVersion { SemanticVersion::new(1, 0, 0) }
+	// Two fields - step and signature
+	fn seal_fields(&self) -> usize { 2 }
+
+	fn extra_info(&self, header: &Header) -> HashMap<String, String> {
+		let mut info = HashMap::new();
+		if let Ok(step) = AuthorityRound::header_step(header) {
+			info.insert("step".to_owned(), format!("{}", step));
+		}
+		info
+	}
+
+	fn spec(&self) -> &Spec { &self.spec }
+
+	fn vm_factory(&self) -> &Factory {
+		&self.factory
+	}
+
+	fn maximum_uncle_count(&self) -> usize { 0 }
+
+	fn seals_internally(&self) -> bool { true }
+
+	/// Sign the block if its author is the proposer for the current step.
+	fn generate_seal(&self, block: &ExecutedBlock, accounts: &SecretStore) -> Option<Vec<Bytes>> {
+		let header = block.header();
+		let step = header.timestamp() / self.step_duration;
+		if step != self.step() || self.proposer(step) != Some(header.author()) {
+			return None;
+		}
+		let mut proposed_step = self.proposed_step.lock().unwrap();
+		if *proposed_step >= step {
+			return None;
+		}
+		match accounts.sign(header.author(), &header.bare_hash()) {
+			Ok(signature) => {
+				*proposed_step = step;
+				Some(vec![encode(&step).to_vec(), encode(&signature).to_vec()])
+			},
+			Err(e) => {
+				warn!(target: "poa", "Cannot sign block #{} as {}: {:?}", header.number(), header.author(), e);
+				None
+			}
+		}
+	}
+
+	fn verify_block_basic(&self, header: &Header, _block: Option<&[u8]>) -> result::Result<(), Error> {
+		if header.seal.len() != self.seal_fields() {
+			return Err(From::from(BlockError::InvalidSealArity(
+				Mismatch { expected: self.seal_fields(), found: header.seal.len() }
+			)));
+		}
+		let step = try!(AuthorityRound::header_step(header));
+		let signature = try!(UntrustedRlp::new(&header.seal[1]).as_val::<H520>());
+
+		// the step must match the timestamp and may run at most one step ahead of our clock.
+		let expected_step = header.timestamp() / self.step_duration;
+		if step != expected_step {
+			return Err(From::from(BlockError::InvalidStep(OutOfBounds { min: Some(expected_step), max: Some(expected_step), found: step })));
+		}
+		let max_step = self.step() + 1;
+		if step > max_step {
+			return Err(From::from(BlockError::InvalidStep(OutOfBounds { min: None, max: Some(max_step), found: step })));
+		}
+
+		let signer = Address::from(try!(ec::recover(&signature, &header.bare_hash())).sha3());
+		if Some(&signer) != self.proposer(step) || &signer != header.author() {
+			return Err(From::from(BlockError::NotAuthorized(signer)));
+		}
+		Ok(())
+	}
+
+	fn verify_block_family(&self, header: &Header, parent: &Header, _block: Option<&[u8]>) -> result::Result<(), Error> {
+		if header.number() == 0 {
+			return Err(From::from(BlockError::RidiculousNumber(OutOfBounds { min: Some(1), max: None, found: header.number() })));
+		}
+		// genesis seal is not an authority seal.
+		let parent_step = if parent.number() == 0 { 0 } else { try!(AuthorityRound::header_step(parent)) };
+		let step = try!(AuthorityRound::header_step(header));
+		if step <= parent_step {
+			return Err(From::from(BlockError::InvalidStep(OutOfBounds { min: Some(parent_step + 1), max: None, found: step })));
+		}
+		Ok(())
+	}
+
+	fn verify_transaction(&self, t: &SignedTransaction, _header: &Header) -> Result<(), Error> {
+		t.sender().map(|_|()) // Perform EC recovery and cache sender
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use common::*;
+	use block::*;
+	use engine::*;
+	use spec::Spec;
+	use tests::helpers::*;
+	use devtools::RandomTempPath;
+	use util::keys::store::SecretStore;
+	use time::get_time;
+	use super::AuthorityRound;
+
+	const TEST_STEP_DURATION: u64 = 3600;
+
+	fn new_test_spec(validators: &[Address]) -> Spec {
+		let mut spec = Spec::new_test_round();
+		spec.engine_params.insert("validators".to_owned(), encode(&validators.to_vec()).to_vec());
+		spec.engine_params.insert("stepDuration".to_owned(), encode(&TEST_STEP_DURATION).to_vec());
+		spec
+	}
+
+	fn new_test_accounts(temp: &RandomTempPath) -> (SecretStore, Address, Address) {
+		let mut accounts = SecretStore::new_in(temp.as_path());
+		let first = accounts.new_account("first").unwrap();
+		let second = accounts.new_account("second").unwrap();
+		accounts.unlock_account(&first, "first").unwrap();
+		accounts.unlock_account(&second, "second").unwrap();
+		(accounts, first, second)
+	}
+
+	fn close_block(engine: &Engine, author: Address) -> ClosedBlock {
+		let genesis_header = engine.spec().genesis_header();
+		let mut db_result = get_temp_journal_db();
+		let mut db = db_result.take();
+		engine.spec().ensure_db_good(&mut db);
+		let last_hashes = vec![genesis_header.hash()];
+		OpenBlock::new(engine, db, &genesis_header, last_hashes, author, test_gas_range(), vec![]).close()
+	}
+
+	#[test]
+	fn has_valid_metadata() {
+		let engine = Spec::new_test_round().to_engine().unwrap();
+		assert!(!engine.name().is_empty());
+		assert!(engine.version().major >= 1);
+		assert_eq!(engine.seal_fields(), 2);
+	}
+
+	#[test]
+	fn rotates_proposer_per_step() {
+		let first = Address::from(1);
+		let second = Address::from(2);
+		let engine = AuthorityRound::new(new_test_spec(&[first.clone(), second.clone()]));
+		assert_eq!(engine.proposer(10), Some(&first));
+		assert_eq!(engine.proposer(11), Some(&second));
+		assert_eq!(engine.proposer(12), Some(&first));
+		assert_eq!(AuthorityRound::new(new_test_spec(&[])).proposer(10), None);
+	}
+
+	#[test]
+	fn seals_and_verifies_blocks() {
+		let temp = RandomTempPath::create_dir();
+		let (accounts, first, second) = new_test_accounts(&temp);
+		let engine = new_test_spec(&[first.clone(), second.clone()]).to_engine().unwrap();
+		let step = get_time().sec as u64 / TEST_STEP_DURATION;
+		let (proposer, other) = if step % 2 == 0 { (first, second) } else { (second, first) };
+
+		// not our turn.
+		let b = close_block(engine.deref(), other.clone());
+		assert!(engine.generate_seal(b.block(), &accounts).is_none());
+
+		let b = close_block(engine.deref(), proposer.clone());
+		let seal = engine.generate_seal(b.block(), &accounts).unwrap();
+		let sealed = b.try_seal(engine.deref(), seal.clone()).ok().expect("seal is valid");
+		let header = sealed.header().clone();
+		assert!(engine.verify_block_family(&header, &engine.spec().genesis_header(), None).is_ok());
+
+		// only one block is proposed per step.
+		let b = close_block(engine.deref(), proposer.clone());
+		assert!(engine.generate_seal(b.block(), &accounts).is_none());
+
+		// signed by a validator whose turn it isn't.
+		let mut forged = header.clone();
+		let signature = accounts.sign(&other, &forged.bare_hash()).unwrap();
+		forged.set_seal(vec![seal[0].clone(), encode(&signature).to_vec()]);
+		assert!(engine.verify_block_basic(&forged, None).is_err());
+
+		// a step in the future.
+		let mut future = header.clone();
+		future.set_timestamp(header.timestamp() + 2 * TEST_STEP_DURATION);
+		let future_step = future.timestamp() / TEST_STEP_DURATION;
+		let signature = accounts.sign(future.author(), &future.bare_hash()).unwrap();
+		future.set_seal(vec![encode(&future_step).to_vec(), encode(&signature).to_vec()]);
+		assert!(engine.verify_block_basic(&future, None).is_err());
+
+		// a second block in the same step.
+		let mut child = header.clone();
+		child.set_number(2);
+		child.set_parent_hash(header.hash());
+		assert!(engine.verify_block_family(&child, &header, None).is_err());
+	}
+}
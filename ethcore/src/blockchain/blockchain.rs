@@ -22,16 +22,31 @@ use extras::*;
 use transaction::*;
 use views::*;
 use receipt::Receipt;
+use filter::Filter;
 use chainfilter::{ChainFilter, BloomIndex, FilterDataSource};
 use blockchain::block_info::{BlockInfo, BlockLocation};
 use blockchain::best_block::BestBlock;
 use blockchain::bloom_indexer::BloomIndexer;
+use blockchain::log_indexer::LogIndexer;
 use blockchain::tree_route::TreeRoute;
 use blockchain::update::ExtrasUpdate;
 use blockchain::{CacheSize, ImportRoute};
+use blockchain::cache::LruCache;
 
 const BLOOM_INDEX_SIZE: usize = 16;
 const BLOOM_LEVELS: u8 = 3;
+const LOG_INDEX_GROUP_SIZE: BlockNumber = 64;
+// Shares, in sixteenths, of the cache size budget given to each of the caches.
+const BLOCKS_SHARE: usize = 6;
+const BLOCK_DETAILS_SHARE: usize = 3;
+const BLOCK_HASHES_SHARE: usize = 1;
+const TRANSACTION_ADDRESSES_SHARE: usize = 2;
+const BLOCK_LOGS_SHARE: usize = 1;
+const BLOCKS_BLOOMS_SHARE: usize = 1;
+const BLOCK_RECEIPTS_SHARE: usize = 2;
+// Most bodies dropped on a single block insertion, so that enabling pruning on a long chain
+// catches up gradually.
+const PRUNE_BATCH: BlockNumber = 256;
 
 /// Blockchain configuration.
 #[derive(Debug)]
@@ -40,6 +55,11 @@ pub struct BlockChainConfig {
 	pub pref_cache_size: usize,
 	/// Maximum cache size in bytes.
 	pub max_cache_size: usize,
+	/// Number of recent canonical blocks whose bodies and receipts are kept. Headers and details
+	/// of older blocks remain available. All bodies are kept if `None`.
+	pub body_history: Option<BlockNumber>,
+	/// Maintain an index of log positions by address and topic.
+	pub log_index: bool,
 }
 
 impl Default for BlockChainConfig {
@@ -47,10 +67,21 @@ impl Default for BlockChainConfig {
 		BlockChainConfig {
 			pref_cache_size: 1 << 14,
 			max_cache_size: 1 << 20,
+			body_history: None,
+			log_index: false,
 		}
 	}
 }
 
+/// Reason why data of a block is not available.
+#[derive(Debug, PartialEq, Clone)]
+pub enum BlockDataError {
+	/// Block is not known.
+	Unknown,
+	/// Block is known, but its body and receipts were pruned. Its header and details remain.
+	Pruned,
+}
+
 /// Interface for querying blocks by hash and by number.
 pub trait BlockProvider {
 	/// Returns true if the given block is known
@@ -72,6 +103,29 @@ pub trait BlockProvider {
 	/// Get receipts of block with given hash.
 	fn block_receipts(&self, hash: &H256) -> Option<BlockReceipts>;
 
+	/// Returns true if the body and receipts of the given known block were pruned.
+	fn is_pruned(&self, _hash: &H256) -> bool {
+		false
+	}
+
+	/// Get raw block data, or the reason it is not available.
+	fn try_block(&self, hash: &H256) -> Result<Bytes, BlockDataError> {
+		match self.block(hash) {
+			Some(bytes) => Ok(bytes),
+			None if self.is_pruned(hash) => Err(BlockDataError::Pruned),
+			None => Err(BlockDataError::Unknown),
+		}
+	}
+
+	/// Get receipts of block with given hash, or the reason they are not available.
+	fn try_block_receipts(&self, hash: &H256) -> Result<BlockReceipts, BlockDataError> {
+		match self.block_receipts(hash) {
+			Some(receipts) => Ok(receipts),
+			None if self.is_pruned(hash) => Err(BlockDataError::Pruned),
+			None => Err(BlockDataError::Unknown),
+		}
+	}
+
 	/// Get the partial-header of a block.
 	fn block_header(&self, hash: &H256) -> Option<Header> {
 		self.block(hash).map(|bytes| BlockView::new(&bytes).header())
@@ -119,44 +173,38 @@ pub trait BlockProvider {
 	fn blocks_with_bloom(&self, bloom: &H2048, from_block: BlockNumber, to_block: BlockNumber) -> Vec<BlockNumber>;
 }
 
-#[derive(Debug, Hash, Eq, PartialEq, Clone)]
-enum CacheID {
-	Block(H256),
-	Extras(ExtrasIndex, H256),
-}
-
-struct CacheManager {
-	cache_usage: VecDeque<HashSet<CacheID>>,
-	in_use: HashSet<CacheID>,
-}
-
 /// Structure providing fast access to blockchain data.
 ///
 /// **Does not do input data verification.**
 pub struct BlockChain {
 	pref_cache_size: usize,
 	max_cache_size: usize,
+	body_history: Option<BlockNumber>,
+	// first canonical block which may still have its body
+	first_body: RwLock<BlockNumber>,
 
 	best_block: RwLock<BestBlock>,
 
 	// block cache
-	blocks: RwLock<HashMap<H256, Bytes>>,
+	blocks: Mutex<LruCache<H256, Bytes>>,
 
 	// extra caches
-	block_details: RwLock<HashMap<H256, BlockDetails>>,
-	block_hashes: RwLock<HashMap<BlockNumber, H256>>,
-	transaction_addresses: RwLock<HashMap<H256, TransactionAddress>>,
-	block_logs: RwLock<HashMap<H256, BlockLogBlooms>>,
-	blocks_blooms: RwLock<HashMap<H256, BlocksBlooms>>,
-	block_receipts: RwLock<HashMap<H256, BlockReceipts>>,
-
-	extras_db: Database,
-	blocks_db: Database,
+	block_details: Mutex<LruCache<H256, BlockDetails>>,
+	block_hashes: Mutex<LruCache<BlockNumber, H256>>,
+	transaction_addresses: Mutex<LruCache<H256, TransactionAddress>>,
+	block_logs: Mutex<LruCache<H256, BlockLogBlooms>>,
+	blocks_blooms: Mutex<LruCache<H256, BlocksBlooms>>,
+	block_receipts: Mutex<LruCache<H256, BlockReceipts>>,
 
-	cache_man: RwLock<CacheManager>,
+	extras_db: Arc<KeyValueDB>,
+	blocks_db: Arc<KeyValueDB>,
 
 	// blooms indexing
 	bloom_indexer: BloomIndexer,
+
+	// log positions indexing, if enabled, with the first block it covers
+	log_indexer: LogIndexer,
+	log_index_from: Option<BlockNumber>,
 }
 
 impl FilterDataSource for BlockChain {
@@ -175,23 +223,17 @@ impl BlockProvider for BlockChain {
 
 	/// Get raw block data
 	fn block(&self, hash: &H256) -> Option<Bytes> {
-		{
-			let read = self.blocks.read().unwrap();
-			if let Some(v) = read.get(hash) {
-				return Some(v.clone());
-			}
+		if let Some(v) = self.blocks.lock().unwrap().get(hash) {
+			return Some(v);
 		}
 
 		let opt = self.blocks_db.get(hash)
 			.expect("Low level database error. Some issue with disk?");
 
-		self.note_used(CacheID::Block(hash.clone()));
-
 		match opt {
 			Some(b) => {
 				let bytes: Bytes = b.to_vec();
-				let mut write = self.blocks.write().unwrap();
-				write.insert(hash.clone(), bytes.clone());
+				self.blocks.lock().unwrap().insert(hash.clone(), bytes.clone());
 				Some(bytes)
 			},
 			None => None
@@ -218,6 +260,23 @@ impl BlockProvider for BlockChain {
 		self.query_extras(hash, &self.block_receipts)
 	}
 
+	fn is_pruned(&self, hash: &H256) -> bool {
+		self.extras_db.extras_exists::<_, Header>(hash)
+	}
+
+	/// Get the partial-header of a block. Headers of pruned blocks are read from extras.
+	fn block_header(&self, hash: &H256) -> Option<Header> {
+		match self.block(hash) {
+			Some(bytes) => Some(BlockView::new(&bytes).header()),
+			None => self.extras_db.get_extras(hash),
+		}
+	}
+
+	/// Get the number of given block's hash.
+	fn block_number(&self, hash: &H256) -> Option<BlockNumber> {
+		self.block_details(hash).map(|details| details.number)
+	}
+
 	/// Returns numbers of blocks containing given bloom.
 	fn blocks_with_bloom(&self, bloom: &H2048, from_block: BlockNumber, to_block: BlockNumber) -> Vec<BlockNumber> {
 		let filter = ChainFilter::new(self, self.bloom_indexer.index_size(), self.bloom_indexer.levels());
@@ -225,8 +284,6 @@ impl BlockProvider for BlockChain {
 	}
 }
 
-const COLLECTION_QUEUE_SIZE: usize = 8;
-
 pub struct AncestryIter<'a> {
 	current: H256,
 	chain: &'a BlockChain,
@@ -258,24 +315,29 @@ impl BlockChain {
 		blocks_path.push("blocks");
 		let blocks_db = Database::open_default(blocks_path.to_str().unwrap()).unwrap();
 
-		let mut cache_man = CacheManager{cache_usage: VecDeque::new(), in_use: HashSet::new()};
-		(0..COLLECTION_QUEUE_SIZE).foreach(|_| cache_man.cache_usage.push_back(HashSet::new()));
+		BlockChain::new_with_db(config, genesis, Arc::new(extras_db), Arc::new(blocks_db))
+	}
 
-		let bc = BlockChain {
+	/// Create new instance of blockchain from given Genesis, stored in given databases.
+	pub fn new_with_db(config: BlockChainConfig, genesis: &[u8], extras_db: Arc<KeyValueDB>, blocks_db: Arc<KeyValueDB>) -> BlockChain {
+		let mut bc = BlockChain {
 			pref_cache_size: config.pref_cache_size,
 			max_cache_size: config.max_cache_size,
+			body_history: config.body_history,
+			first_body: RwLock::new(1),
 			best_block: RwLock::new(BestBlock::default()),
-			blocks: RwLock::new(HashMap::new()),
-			block_details: RwLock::new(HashMap::new()),
-			block_hashes: RwLock::new(HashMap::new()),
-			transaction_addresses: RwLock::new(HashMap::new()),
-			block_logs: RwLock::new(HashMap::new()),
-			blocks_blooms: RwLock::new(HashMap::new()),
-			block_receipts: RwLock::new(HashMap::new()),
+			blocks: Mutex::new(LruCache::new()),
+			block_details: Mutex::new(LruCache::new()),
+			block_hashes: Mutex::new(LruCache::new()),
+			transaction_addresses: Mutex::new(LruCache::new()),
+			block_logs: Mutex::new(LruCache::new()),
+			blocks_blooms: Mutex::new(LruCache::new()),
+			block_receipts: Mutex::new(LruCache::new()),
 			extras_db: extras_db,
 			blocks_db: blocks_db,
-			cache_man: RwLock::new(cache_man),
-			bloom_indexer: BloomIndexer::new(BLOOM_INDEX_SIZE, BLOOM_LEVELS)
+			bloom_indexer: BloomIndexer::new(BLOOM_INDEX_SIZE, BLOOM_LEVELS),
+			log_indexer: LogIndexer::new(LOG_INDEX_GROUP_SIZE),
+			log_index_from: None,
 		};
 
 		// load best block
@@ -314,6 +376,25 @@ impl BlockChain {
 			best_block.hash = best_block_hash;
 		}
 
+		if let Some(first) = bc.extras_db.get(b"first_body").unwrap() {
+			*bc.first_body.write().unwrap() = decode(&first);
+		}
+
+		// the log index covers blocks imported since it was enabled
+		if config.log_index {
+			let from = match bc.extras_db.get(b"log_index_from").unwrap() {
+				Some(from) => decode(&from),
+				None => {
+					let from = bc.best_block_number() + 1;
+					bc.extras_db.put(b"log_index_from", &encode(&from)).unwrap();
+					from
+				}
+			};
+			bc.log_index_from = Some(from);
+		} else {
+			bc.extras_db.delete(b"log_index_from").unwrap();
+		}
+
 		bc
 	}
 
@@ -366,49 +447,7 @@ impl BlockChain {
 	///   { blocks: [B4, B3, A3, A4], ancestor: A2, index: 2 }
 	///   ```
 	pub fn tree_route(&self, from: H256, to: H256) -> TreeRoute {
-		let mut from_branch = vec![];
-		let mut to_branch = vec![];
-
-		let mut from_details = self.block_details(&from).expect(&format!("0. Expected to find details for block {:?}", from));
-		let mut to_details = self.block_details(&to).expect(&format!("1. Expected to find details for block {:?}", to));
-		let mut current_from = from;
-		let mut current_to = to;
-
-		// reset from && to to the same level
-		while from_details.number > to_details.number {
-			from_branch.push(current_from);
-			current_from = from_details.parent.clone();
-			from_details = self.block_details(&from_details.parent).expect(&format!("2. Expected to find details for block {:?}", from_details.parent));
-		}
-
-		while to_details.number > from_details.number {
-			to_branch.push(current_to);
-			current_to = to_details.parent.clone();
-			to_details = self.block_details(&to_details.parent).expect(&format!("3. Expected to find details for block {:?}", to_details.parent));
-		}
-
-		assert_eq!(from_details.number, to_details.number);
-
-		// move to shared parent
-		while current_from != current_to {
-			from_branch.push(current_from);
-			current_from = from_details.parent.clone();
-			from_details = self.block_details(&from_details.parent).expect(&format!("4. Expected to find details for block {:?}", from_details.parent));
-
-			to_branch.push(current_to);
-			current_to = to_details.parent.clone();
-			to_details = self.block_details(&to_details.parent).expect(&format!("5. Expected to find details for block {:?}", from_details.parent));
-		}
-
-		let index = from_branch.len();
-
-		from_branch.extend(to_branch.into_iter().rev());
-
-		TreeRoute {
-			blocks: from_branch,
-			ancestor: current_from,
-			index: index
-		}
+		TreeRoute::between(from, to, |hash| self.block_details(hash))
 	}
 
 	/// Inserts the block into backing cache database.
@@ -432,15 +471,71 @@ impl BlockChain {
 		self.apply_update(ExtrasUpdate {
 			block_hashes: self.prepare_block_hashes_update(bytes, &info),
 			block_details: self.prepare_block_details_update(bytes, &info),
+			log_positions: self.prepare_log_positions_update(&receipts, &info),
 			block_receipts: self.prepare_block_receipts_update(receipts, &info),
 			transactions_addresses: self.prepare_transaction_addresses_update(bytes, &info),
 			blocks_blooms: self.prepare_block_blooms_update(bytes, &info),
 			info: info.clone(),
 		});
 
+		match info.location {
+			BlockLocation::Branch => (),
+			_ => self.prune_ancient(),
+		}
+
 		ImportRoute::from(info)
 	}
 
+	/// Drops bodies, receipts and transaction addresses of canonical blocks which are older than
+	/// the configured body history, keeping their headers in the extras database.
+	fn prune_ancient(&self) {
+		let history = match self.body_history {
+			Some(history) => history,
+			None => return,
+		};
+		let best = self.best_block_number();
+		let mut first_body = self.first_body.write().unwrap();
+		if best < history + *first_body {
+			return;
+		}
+		let end = cmp::min(best - history + 1, *first_body + PRUNE_BATCH);
+
+		let mut pruned = Vec::new();
+		for number in *first_body..end {
+			if let Some(hash) = self.block_hash(number) {
+				if let Some(bytes) = self.block(&hash) {
+					let block = BlockView::new(&bytes);
+					pruned.push((hash, block.header(), block.transaction_hashes()));
+				}
+			}
+		}
+
+		let batch = DBTransaction::new();
+		let blocks_batch = DBTransaction::new();
+		{
+			let mut blocks = self.blocks.lock().unwrap();
+			let mut block_receipts = self.block_receipts.lock().unwrap();
+			let mut transaction_addresses = self.transaction_addresses.lock().unwrap();
+			for (hash, header, transaction_hashes) in pruned.into_iter() {
+				batch.put_extras(&hash, &header);
+				batch.delete(&hash.to_extras_slice(ExtrasIndex::BlockReceipts)).unwrap();
+				block_receipts.remove(&hash);
+				for tx_hash in transaction_hashes {
+					batch.delete(&tx_hash.to_extras_slice(ExtrasIndex::TransactionAddress)).unwrap();
+					transaction_addresses.remove(&tx_hash);
+				}
+				blocks_batch.delete(&hash).unwrap();
+				blocks.remove(&hash);
+			}
+		}
+		batch.put(b"first_body", &encode(&end)).unwrap();
+
+		// headers must be in extras before the blocks are gone
+		self.extras_db.write(batch).unwrap();
+		self.blocks_db.write(blocks_batch).unwrap();
+		*first_body = end;
+	}
+
 	/// Applies extras update.
 	fn apply_update(&self, update: ExtrasUpdate) {
 		let batch = DBTransaction::new();
@@ -459,37 +554,40 @@ impl BlockChain {
 			}
 		}
 
-		let mut write_hashes = self.block_hashes.write().unwrap();
+		let mut write_hashes = self.block_hashes.lock().unwrap();
 		for (number, hash) in &update.block_hashes {
 			batch.put_extras(number, hash);
 			write_hashes.remove(number);
 		}
 
-		let mut write_details = self.block_details.write().unwrap();
+		let mut write_details = self.block_details.lock().unwrap();
 		for (hash, details) in update.block_details.into_iter() {
 			batch.put_extras(&hash, &details);
-			write_details.insert(hash.clone(), details);
-			self.note_used(CacheID::Extras(ExtrasIndex::BlockDetails, hash));
+			write_details.insert(hash, details);
 		}
 
-		let mut write_receipts = self.block_receipts.write().unwrap();
+		let mut write_receipts = self.block_receipts.lock().unwrap();
 		for (hash, receipt) in &update.block_receipts {
 			batch.put_extras(hash, receipt);
 			write_receipts.remove(hash);
 		}
 
-		let mut write_txs = self.transaction_addresses.write().unwrap();
+		let mut write_txs = self.transaction_addresses.lock().unwrap();
 		for (hash, tx_address) in &update.transactions_addresses {
 			batch.put_extras(hash, tx_address);
 			write_txs.remove(hash);
 		}
 
-		let mut write_blocks_blooms = self.blocks_blooms.write().unwrap();
+		let mut write_blocks_blooms = self.blocks_blooms.lock().unwrap();
 		for (bloom_hash, blocks_bloom) in &update.blocks_blooms {
 			batch.put_extras(bloom_hash, blocks_bloom);
 			write_blocks_blooms.remove(bloom_hash);
 		}
 
+		for (key, positions) in &update.log_positions {
+			batch.put_extras(key, positions);
+		}
+
 		// update extras database
 		self.extras_db.write(batch).unwrap();
 	}
@@ -673,8 +771,7 @@ impl BlockChain {
 				let start_number = ancestor_number + 1;
 
 				let mut blooms: Vec<H2048> = enacted.iter()
-					.map(|hash| self.block(hash).unwrap())
-					.map(|bytes| BlockView::new(&bytes).header_view().log_bloom())
+					.map(|hash| self.block_header(hash).unwrap().log_bloom)
 					.collect();
 
 				blooms.push(header.log_bloom());
@@ -698,6 +795,113 @@ impl BlockChain {
 		})
 	}
 
+	/// This function returns modified log positions, if the log index is enabled.
+	///
+	/// Positions of logs of retracted blocks are removed before those of enacted blocks are added,
+	/// as both may be at the same block numbers.
+	fn prepare_log_positions_update(&self, receipts: &[Receipt], info: &BlockInfo) -> HashMap<H256, LogPositions> {
+		let mut update = HashMap::new();
+		let index_from = match self.log_index_from {
+			Some(from) => from,
+			None => return update,
+		};
+
+		match info.location {
+			BlockLocation::Branch => return update,
+			BlockLocation::CanonChain => {},
+			BlockLocation::BranchBecomingCanonChain { ref enacted, ref retracted, .. } => {
+				for hash in retracted {
+					if let (Some(number), Some(block_receipts)) = (self.block_number(hash), self.block_receipts(hash)) {
+						for key in self.log_indexer.positions(number, &block_receipts.receipts).keys() {
+							self.log_positions_entry(&mut update, key).positions.retain(|p| p.block_number != number);
+						}
+					}
+				}
+				for hash in enacted {
+					if let (Some(number), Some(block_receipts)) = (self.block_number(hash), self.block_receipts(hash)) {
+						if number >= index_from {
+							self.add_log_positions(&mut update, number, &block_receipts.receipts);
+						}
+					}
+				}
+			}
+		}
+
+		if info.number >= index_from {
+			self.add_log_positions(&mut update, info.number, receipts);
+		}
+		update
+	}
+
+	fn add_log_positions(&self, update: &mut HashMap<H256, LogPositions>, number: BlockNumber, receipts: &[Receipt]) {
+		for (key, positions) in self.log_indexer.positions(number, receipts).into_iter() {
+			self.log_positions_entry(update, &key).positions.extend(positions);
+		}
+	}
+
+	fn log_positions_entry<'a>(&self, update: &'a mut HashMap<H256, LogPositions>, key: &H256) -> &'a mut LogPositions {
+		update.entry(key.clone()).or_insert_with(|| self.extras_db.get_extras(key).unwrap_or_else(LogPositions::new))
+	}
+
+	/// Numbers of canonical blocks from `from` to `to` which contain logs of the addresses and
+	/// topics of `filter`, looked up in the log index, along with the first block the index
+	/// covers. Blocks before that are not included. Returns `None` if the index is disabled or
+	/// the filter has neither addresses nor topics.
+	pub fn indexed_log_blocks(&self, filter: &Filter, from: BlockNumber, to: BlockNumber) -> Option<(BlockNumber, Vec<BlockNumber>)> {
+		let index_from = match self.log_index_from {
+			Some(index_from) => index_from,
+			None => return None,
+		};
+		let from = cmp::max(from, index_from);
+		let groups = match from <= to {
+			true => self.log_indexer.groups(from, to),
+			false => vec![],
+		};
+
+		let lookup = |keys: Vec<H256>| -> HashSet<BlockNumber> {
+			keys.into_iter()
+				.filter_map(|key| self.extras_db.get_extras::<_, LogPositions>(&key))
+				.flat_map(|group| group.positions.into_iter())
+				.map(|position| position.block_number)
+				.filter(|number| *number >= from && *number <= to)
+				.collect()
+		};
+
+		let mut matches: Vec<HashSet<BlockNumber>> = Vec::new();
+		if let Some(ref addresses) = filter.address {
+			if !addresses.is_empty() {
+				let mut keys = Vec::new();
+				for address in addresses {
+					keys.extend(groups.iter().map(|group| self.log_indexer.address_key(address, *group)));
+				}
+				matches.push(lookup(keys));
+			}
+		}
+		for topics in filter.topics.iter() {
+			if let Some(ref topics) = *topics {
+				if !topics.is_empty() {
+					let mut keys = Vec::new();
+					for topic in topics {
+						keys.extend(groups.iter().map(|group| self.log_indexer.topic_key(topic, *group)));
+					}
+					matches.push(lookup(keys));
+				}
+			}
+		}
+
+		let mut matches = matches.into_iter();
+		let first = match matches.next() {
+			Some(first) => first,
+			None => return None,
+		};
+		let mut blocks: Vec<BlockNumber> = matches
+			.fold(first, |acc, set| acc.intersection(&set).cloned().collect())
+			.into_iter()
+			.collect();
+		blocks.sort();
+		Some((index_from, blocks))
+	}
+
 	/// Get best block hash.
 	pub fn best_block_hash(&self) -> H256 {
 		self.best_block.read().unwrap().hash.clone()
@@ -718,35 +922,24 @@ impl BlockChain {
 		self.query_extras(hash, &self.blocks_blooms)
 	}
 
-	fn query_extras<K, T>(&self, hash: &K, cache: &RwLock<HashMap<K, T>>) -> Option<T> where
-		T: Clone + Decodable + ExtrasIndexable,
+	fn query_extras<K, T>(&self, hash: &K, cache: &Mutex<LruCache<K, T>>) -> Option<T> where
+		T: Clone + Decodable + ExtrasIndexable + HeapSizeOf,
 		K: ExtrasSliceConvertable + Eq + Hash + Clone {
-		{
-			let read = cache.read().unwrap();
-			if let Some(v) = read.get(hash) {
-				return Some(v.clone());
-			}
-		}
-
-		if let Some(h) = hash.as_h256() {
-			self.note_used(CacheID::Extras(T::extras_index(), h.clone()));
+		if let Some(v) = cache.lock().unwrap().get(hash) {
+			return Some(v);
 		}
 
 		self.extras_db.get_extras(hash).map(| t: T | {
-			let mut write = cache.write().unwrap();
-			write.insert(hash.clone(), t.clone());
+			cache.lock().unwrap().insert(hash.clone(), t.clone());
 			t
 		})
 	}
 
-	fn query_extras_exist<K, T>(&self, hash: &K, cache: &RwLock<HashMap<K, T>>) -> bool where
+	fn query_extras_exist<K, T>(&self, hash: &K, cache: &Mutex<LruCache<K, T>>) -> bool where
 		K: ExtrasSliceConvertable + Eq + Hash + Clone,
-		T: ExtrasIndexable {
-		{
-			let read = cache.read().unwrap();
-			if let Some(_) = read.get(hash) {
-				return true;
-			}
+		T: Clone + ExtrasIndexable + HeapSizeOf {
+		if cache.lock().unwrap().contains_key(hash) {
+			return true;
 		}
 
 		self.extras_db.extras_exists::<_, T>(hash)
@@ -754,89 +947,67 @@ impl BlockChain {
 
 	/// Get current cache size.
 	pub fn cache_size(&self) -> CacheSize {
+		let blocks = self.blocks.lock().unwrap();
+		let block_details = self.block_details.lock().unwrap();
+		let block_hashes = self.block_hashes.lock().unwrap();
+		let transaction_addresses = self.transaction_addresses.lock().unwrap();
+		let block_logs = self.block_logs.lock().unwrap();
+		let blocks_blooms = self.blocks_blooms.lock().unwrap();
+		let block_receipts = self.block_receipts.lock().unwrap();
 		CacheSize {
-			blocks: self.blocks.read().unwrap().heap_size_of_children(),
-			block_details: self.block_details.read().unwrap().heap_size_of_children(),
-			transaction_addresses: self.transaction_addresses.read().unwrap().heap_size_of_children(),
-			block_logs: self.block_logs.read().unwrap().heap_size_of_children(),
-			blocks_blooms: self.blocks_blooms.read().unwrap().heap_size_of_children(),
-			block_receipts: self.block_receipts.read().unwrap().heap_size_of_children()
-		}
-	}
-
-	/// Let the cache system know that a cacheable item has been used.
-	fn note_used(&self, id: CacheID) {
-		let mut cache_man = self.cache_man.write().unwrap();
-		if !cache_man.cache_usage[0].contains(&id) {
-			cache_man.cache_usage[0].insert(id.clone());
-			if cache_man.in_use.contains(&id) {
-				if let Some(c) = cache_man.cache_usage.iter_mut().skip(1).find(|e|e.contains(&id)) {
-					c.remove(&id);
-				}
-			} else {
-				cache_man.in_use.insert(id);
-			}
+			blocks: blocks.size(),
+			block_details: block_details.size(),
+			block_hashes: block_hashes.size(),
+			transaction_addresses: transaction_addresses.size(),
+			block_logs: block_logs.size(),
+			blocks_blooms: blocks_blooms.size(),
+			block_receipts: block_receipts.size(),
+			hits: blocks.hits() + block_details.hits() + block_hashes.hits() + transaction_addresses.hits()
+				+ block_logs.hits() + blocks_blooms.hits() + block_receipts.hits(),
+			misses: blocks.misses() + block_details.misses() + block_hashes.misses() + transaction_addresses.misses()
+				+ block_logs.misses() + blocks_blooms.misses() + block_receipts.misses(),
 		}
 	}
 
-	/// Ticks our cache system and throws out any old data.
+	/// Evicts least recently used entries from every cache which went over its share of
+	/// `max_cache_size`, down to its share of `pref_cache_size`.
 	pub fn collect_garbage(&self) {
-		if self.cache_size().total() < self.pref_cache_size { return; }
-
-		for _ in 0..COLLECTION_QUEUE_SIZE {
-			{
-				let mut cache_man = self.cache_man.write().unwrap();
-				let mut blocks = self.blocks.write().unwrap();
-				let mut block_details = self.block_details.write().unwrap();
-				let mut block_hashes = self.block_hashes.write().unwrap();
-				let mut transaction_addresses = self.transaction_addresses.write().unwrap();
-				let mut block_logs = self.block_logs.write().unwrap();
-				let mut blocks_blooms = self.blocks_blooms.write().unwrap();
-				let mut block_receipts = self.block_receipts.write().unwrap();
-
-				for id in cache_man.cache_usage.pop_back().unwrap().into_iter() {
-					cache_man.in_use.remove(&id);
-					match id {
-						CacheID::Block(h) => { blocks.remove(&h); },
-						CacheID::Extras(ExtrasIndex::BlockDetails, h) => { block_details.remove(&h); },
-						CacheID::Extras(ExtrasIndex::TransactionAddress, h) => { transaction_addresses.remove(&h); },
-						CacheID::Extras(ExtrasIndex::BlockLogBlooms, h) => { block_logs.remove(&h); },
-						CacheID::Extras(ExtrasIndex::BlocksBlooms, h) => { blocks_blooms.remove(&h); },
-						CacheID::Extras(ExtrasIndex::BlockReceipts, h) => { block_receipts.remove(&h); },
-						_ => panic!(),
-					}
-				}
-				cache_man.cache_usage.push_front(HashSet::new());
-
-				// TODO: handle block_hashes properly.
-				block_hashes.clear();
-
-				blocks.shrink_to_fit();
-				block_details.shrink_to_fit();
- 				block_hashes.shrink_to_fit();
- 				transaction_addresses.shrink_to_fit();
- 				block_logs.shrink_to_fit();
- 				blocks_blooms.shrink_to_fit();
- 				block_receipts.shrink_to_fit();
+		fn shrink<K, V>(cache: &Mutex<LruCache<K, V>>, share: usize, max: usize, pref: usize) where K: Eq + Hash + Clone, V: Clone + HeapSizeOf {
+			let mut cache = cache.lock().unwrap();
+			if cache.size() > max / 16 * share {
+				cache.shrink_to(pref / 16 * share);
 			}
-			if self.cache_size().total() < self.max_cache_size { break; }
 		}
 
-		// TODO: m_lastCollection = chrono::system_clock::now();
+		let (max, pref) = (self.max_cache_size, self.pref_cache_size);
+		shrink(&self.blocks, BLOCKS_SHARE, max, pref);
+		shrink(&self.block_details, BLOCK_DETAILS_SHARE, max, pref);
+		shrink(&self.block_hashes, BLOCK_HASHES_SHARE, max, pref);
+		shrink(&self.transaction_addresses, TRANSACTION_ADDRESSES_SHARE, max, pref);
+		shrink(&self.block_logs, BLOCK_LOGS_SHARE, max, pref);
+		shrink(&self.blocks_blooms, BLOCKS_BLOOMS_SHARE, max, pref);
+		shrink(&self.block_receipts, BLOCK_RECEIPTS_SHARE, max, pref);
 	}
 }
 
 #[cfg(test)]
 mod tests {
 	use std::str::FromStr;
+	use std::sync::Arc;
+	use util::kvdb::in_memory;
 	use rustc_serialize::hex::FromHex;
 	use util::hash::*;
 	use util::sha3::Hashable;
-	use blockchain::{BlockProvider, BlockChain, BlockChainConfig, ImportRoute};
+	use blockchain::{BlockProvider, BlockChain, BlockChainConfig, BlockDataError, ImportRoute};
 	use tests::helpers::*;
 	use devtools::*;
 	use blockchain::generator::{ChainGenerator, ChainIterator, BlockFinalizer};
 	use views::BlockView;
+	use util::numbers::U256;
+	use client::BlockId;
+	use filter::Filter;
+	use log_entry::LogEntry;
+	use receipt::Receipt;
 
 	#[test]
 	fn basic_blockchain_insert() {
@@ -847,8 +1018,7 @@ mod tests {
 		let genesis_hash = BlockView::new(&genesis).header_view().sha3();
 		let first_hash = BlockView::new(&first).header_view().sha3();
 
-		let temp = RandomTempPath::new();
-		let bc = BlockChain::new(BlockChainConfig::default(), &genesis, temp.as_path());
+		let bc = BlockChain::new_with_db(BlockChainConfig::default(), &genesis, Arc::new(in_memory()), Arc::new(in_memory()));
 
 		assert_eq!(bc.genesis_hash(), genesis_hash.clone());
 		assert_eq!(bc.best_block_number(), 0);
@@ -1062,6 +1232,85 @@ mod tests {
 		}
 	}
 
+	#[test]
+	fn prunes_ancient_bodies() {
+		let mut canon_chain = ChainGenerator::default();
+		let mut finalizer = BlockFinalizer::default();
+		let genesis = canon_chain.generate(&mut finalizer).unwrap();
+		let genesis_hash = BlockView::new(&genesis).header_view().sha3();
+		let config = BlockChainConfig { body_history: Some(4), ..BlockChainConfig::default() };
+
+		let temp = RandomTempPath::new();
+		let mut hashes = vec![genesis_hash.clone()];
+		{
+			let bc = BlockChain::new(config, &genesis, temp.as_path());
+			for _ in 0..10 {
+				let block = canon_chain.generate(&mut finalizer).unwrap();
+				hashes.push(BlockView::new(&block).header_view().sha3());
+				bc.insert_block(&block, vec![]);
+			}
+		}
+
+		let bc = BlockChain::new(BlockChainConfig::default(), &genesis, temp.as_path());
+		assert!(bc.block(&genesis_hash).is_some());
+		for hash in &hashes[1..7] {
+			assert_eq!(bc.try_block(hash), Err(BlockDataError::Pruned));
+			assert!(bc.try_block_receipts(hash).is_err());
+			assert_eq!(bc.block_header(hash).unwrap().hash(), *hash);
+			assert!(bc.block_details(hash).is_some());
+		}
+		for hash in &hashes[7..] {
+			assert!(bc.block(hash).is_some());
+			assert!(bc.block_receipts(hash).is_some());
+		}
+		assert_eq!(bc.block_number(&hashes[3]), Some(3));
+		assert_eq!(bc.try_block(&H256::from(1)), Err(BlockDataError::Unknown));
+	}
+
+	#[test]
+	fn log_index_follows_reorganisations() {
+		let mut canon_chain = ChainGenerator::default();
+		let mut finalizer = BlockFinalizer::default();
+		let genesis = canon_chain.generate(&mut finalizer).unwrap();
+		let b1 = canon_chain.generate(&mut finalizer).unwrap();
+		let b2 = canon_chain.generate(&mut finalizer).unwrap();
+		let b3b = canon_chain.fork(1).generate(&mut finalizer.fork()).unwrap();
+		let b3a = canon_chain.generate(&mut finalizer).unwrap();
+
+		let a = Address::from(0xa);
+		let b = Address::from(0xb);
+		let topic = H256::from(0x70);
+		let receipts = |address: &Address| vec![Receipt::new(H256::new(), U256::zero(), vec![LogEntry {
+			address: address.clone(),
+			topics: vec![topic.clone(), topic.clone()],
+			data: vec![],
+		}])];
+		let filter = |address: &Address| Filter {
+			from_block: BlockId::Earliest,
+			to_block: BlockId::Latest,
+			address: Some(vec![address.clone()]),
+			topics: [None, None, None, None],
+			limit: None,
+		};
+
+		let temp = RandomTempPath::new();
+		let config = BlockChainConfig { log_index: true, ..BlockChainConfig::default() };
+		let bc = BlockChain::new(config, &genesis, temp.as_path());
+		bc.insert_block(&b1, vec![]);
+		bc.insert_block(&b2, vec![]);
+		bc.insert_block(&b3b, receipts(&a));
+		assert_eq!(bc.indexed_log_blocks(&filter(&a), 0, 3), Some((1, vec![3])));
+
+		bc.insert_block(&b3a, receipts(&b));
+		assert_eq!(bc.indexed_log_blocks(&filter(&a), 0, 3), Some((1, vec![])));
+		assert_eq!(bc.indexed_log_blocks(&filter(&b), 0, 3), Some((1, vec![3])));
+		assert_eq!(bc.indexed_log_blocks(&filter(&b), 0, 2), Some((1, vec![])));
+
+		let by_topic = Filter { address: None, topics: [Some(vec![topic.clone()]), None, None, None], ..filter(&a) };
+		assert_eq!(bc.indexed_log_blocks(&by_topic, 0, 3), Some((1, vec![3])));
+		assert_eq!(bc.indexed_log_blocks(&Filter { address: None, ..filter(&a) }, 0, 3), None);
+	}
+
 	#[test]
 	fn can_contain_arbitrary_block_sequence() {
 		let bc_result = generate_dummy_blockchain(50);
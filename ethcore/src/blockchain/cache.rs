@@ -14,6 +14,8 @@
 // You should have received a copy of the GNU General Public License
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
+use util::*;
+
 /// Represents blockchain's in-memory cache size in bytes.
 #[derive(Debug)]
 pub struct CacheSize {
@@ -21,6 +23,8 @@ pub struct CacheSize {
 	pub blocks: usize,
 	/// BlockDetails cache size.
 	pub block_details: usize,
+	/// Block hashes cache size.
+	pub block_hashes: usize,
 	/// Transaction addresses cache size.
 	pub transaction_addresses: usize,
 	/// Logs cache size.
@@ -29,9 +33,158 @@ pub struct CacheSize {
 	pub blocks_blooms: usize,
 	/// Block receipts size.
 	pub block_receipts: usize,
+	/// Number of lookups served from the caches.
+	pub hits: usize,
+	/// Number of lookups which had to go to the database.
+	pub misses: usize,
 }
 
 impl CacheSize {
 	/// Total amount used by the cache.
-	pub fn total(&self) -> usize { self.blocks + self.block_details + self.transaction_addresses + self.block_logs + self.blocks_blooms }
+	pub fn total(&self) -> usize {
+		self.blocks + self.block_details + self.block_hashes + self.transaction_addresses + self.block_logs + self.blocks_blooms + self.block_receipts
+	}
+}
+
+struct Entry<V> {
+	value: V,
+	size: usize,
+	used: u64,
+}
+
+/// Map of cached values which keeps track of the heap size of its entries and can evict
+/// the least recently used ones.
+pub struct LruCache<K, V> {
+	entries: HashMap<K, Entry<V>>,
+	usage: BTreeMap<u64, K>,
+	counter: u64,
+	size: usize,
+	hits: usize,
+	misses: usize,
+}
+
+impl<K, V> LruCache<K, V> where K: Eq + Hash + Clone, V: Clone + HeapSizeOf {
+	/// Create a new, empty cache.
+	pub fn new() -> Self {
+		LruCache {
+			entries: HashMap::new(),
+			usage: BTreeMap::new(),
+			counter: 0,
+			size: 0,
+			hits: 0,
+			misses: 0,
+		}
+	}
+
+	/// Get a copy of the value of `key` and mark it as the most recently used.
+	pub fn get(&mut self, key: &K) -> Option<V> {
+		self.counter += 1;
+		match self.entries.get_mut(key) {
+			Some(entry) => {
+				self.usage.remove(&entry.used);
+				self.usage.insert(self.counter, key.clone());
+				entry.used = self.counter;
+				self.hits += 1;
+				Some(entry.value.clone())
+			},
+			None => {
+				self.misses += 1;
+				None
+			}
+		}
+	}
+
+	/// Returns true if `key` is cached. Does not count as a use.
+	pub fn contains_key(&self, key: &K) -> bool {
+		self.entries.contains_key(key)
+	}
+
+	/// Insert `value` under `key` as the most recently used entry.
+	pub fn insert(&mut self, key: K, value: V) {
+		self.remove(&key);
+		self.counter += 1;
+		let size = mem::size_of::<K>() * 2 + mem::size_of::<Entry<V>>() + mem::size_of::<u64>() + value.heap_size_of_children();
+		self.usage.insert(self.counter, key.clone());
+		self.entries.insert(key, Entry { value: value, size: size, used: self.counter });
+		self.size += size;
+	}
+
+	/// Remove `key` from the cache.
+	pub fn remove(&mut self, key: &K) {
+		if let Some(entry) = self.entries.remove(key) {
+			self.usage.remove(&entry.used);
+			self.size -= entry.size;
+		}
+	}
+
+	/// Evict least recently used entries until the cache takes at most `size` bytes.
+	pub fn shrink_to(&mut self, size: usize) {
+		if self.size <= size {
+			return;
+		}
+		while self.size > size {
+			let oldest = match self.usage.keys().next() {
+				Some(used) => *used,
+				None => break,
+			};
+			let key = self.usage.remove(&oldest).expect("Key was just found in usage; qed");
+			if let Some(entry) = self.entries.remove(&key) {
+				self.size -= entry.size;
+			}
+		}
+		self.entries.shrink_to_fit();
+	}
+
+	/// Bytes taken by the cached entries.
+	pub fn size(&self) -> usize {
+		self.size
+	}
+
+	/// Number of successful lookups.
+	pub fn hits(&self) -> usize {
+		self.hits
+	}
+
+	/// Number of lookups of keys which were not cached.
+	pub fn misses(&self) -> usize {
+		self.misses
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use util::*;
+	use super::LruCache;
+
+	#[test]
+	fn evicts_least_recently_used() {
+		let mut cache = LruCache::new();
+		cache.insert(1u64, vec![0u8; 100]);
+		cache.insert(2u64, vec![0u8; 100]);
+		cache.insert(3u64, vec![0u8; 100]);
+		let full = cache.size();
+		assert!(full > 300);
+
+		assert!(cache.get(&1).is_some());
+		cache.shrink_to(full - 1);
+		assert!(cache.contains_key(&1));
+		assert!(!cache.contains_key(&2));
+		assert!(cache.contains_key(&3));
+
+		cache.shrink_to(0);
+		assert_eq!(cache.size(), 0);
+		assert!(cache.get(&3).is_none());
+		assert_eq!((cache.hits(), cache.misses()), (1, 1));
+	}
+
+	#[test]
+	fn accounts_for_replaced_values() {
+		let mut cache = LruCache::new();
+		cache.insert(H256::new(), vec![0u8; 1000]);
+		let big = cache.size();
+		cache.insert(H256::new(), vec![0u8; 10]);
+		assert!(big > cache.size() + 900);
+		cache.remove(&H256::new());
+		assert_eq!(cache.size(), 0);
+	}
 }
@@ -0,0 +1,128 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+
+//! Index of log positions by address and topic.
+
+use util::*;
+use header::BlockNumber;
+use receipt::Receipt;
+use extras::LogPosition;
+
+const ADDRESS: u8 = 0;
+const TOPIC: u8 = 1;
+
+/// Should be used to localize log positions of addresses and topics in extras database.
+///
+/// Positions of logs of one address or topic in `group_size` consecutive blocks are
+/// stored together, under a key derived from the address or topic and the group.
+pub struct LogIndexer {
+	group_size: BlockNumber,
+}
+
+impl LogIndexer {
+	/// Plain constructor.
+	pub fn new(group_size: BlockNumber) -> Self {
+		LogIndexer {
+			group_size: group_size
+		}
+	}
+
+	/// Key of positions of logs of `address` in the group of block `number`.
+	pub fn address_key(&self, address: &Address, number: BlockNumber) -> H256 {
+		self.key(ADDRESS, address, number)
+	}
+
+	/// Key of positions of logs with `topic` in the group of block `number`.
+	pub fn topic_key(&self, topic: &H256, number: BlockNumber) -> H256 {
+		self.key(TOPIC, topic, number)
+	}
+
+	/// Number of the first block of every group overlapping blocks `from` to `to`, inclusive.
+	pub fn groups(&self, from: BlockNumber, to: BlockNumber) -> Vec<BlockNumber> {
+		(from / self.group_size..to / self.group_size + 1).map(|group| group * self.group_size).collect()
+	}
+
+	/// Positions of all logs in `receipts` of block `number`, by the key they are indexed under.
+	pub fn positions(&self, number: BlockNumber, receipts: &[Receipt]) -> HashMap<H256, Vec<LogPosition>> {
+		let mut positions: HashMap<H256, Vec<LogPosition>> = HashMap::new();
+		for (log_index, log) in receipts.iter().flat_map(|r| r.logs.iter()).enumerate() {
+			let position = LogPosition {
+				block_number: number,
+				log_index: log_index,
+			};
+			let keys = Some(self.address_key(&log.address, number)).into_iter()
+				.chain(log.topics.iter().map(|topic| self.topic_key(topic, number)));
+			for key in keys {
+				let entry = positions.entry(key).or_insert_with(Vec::new);
+				// a topic may be repeated within a log
+				if entry.last() != Some(&position) {
+					entry.push(position.clone());
+				}
+			}
+		}
+		positions
+	}
+
+	fn key(&self, kind: u8, subject: &[u8], number: BlockNumber) -> H256 {
+		let group = number / self.group_size;
+		let mut bytes = vec![kind];
+		bytes.extend(subject.iter().cloned());
+		bytes.extend((0..8).map(|i| (group >> (56 - 8 * i)) as u8));
+		bytes.sha3()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use util::*;
+	use log_entry::LogEntry;
+	use receipt::Receipt;
+	use extras::LogPosition;
+	use super::LogIndexer;
+
+	#[test]
+	fn test_log_indexer_groups_and_keys() {
+		let indexer = LogIndexer::new(64);
+		assert_eq!(indexer.groups(10, 63), vec![0]);
+		assert_eq!(indexer.groups(63, 128), vec![0, 64, 128]);
+
+		let address = Address::from(1);
+		assert_eq!(indexer.address_key(&address, 0), indexer.address_key(&address, 63));
+		assert!(indexer.address_key(&address, 63) != indexer.address_key(&address, 64));
+		assert!(indexer.address_key(&address, 0) != indexer.topic_key(&H256::from(1), 0));
+	}
+
+	#[test]
+	fn test_log_indexer_positions() {
+		let indexer = LogIndexer::new(64);
+		let address = Address::from(1);
+		let topic = H256::from(2);
+		let log = |topics: Vec<H256>| LogEntry { address: address.clone(), topics: topics, data: vec![] };
+		let receipts = vec![
+			Receipt::new(H256::new(), U256::zero(), vec![log(vec![topic.clone(), topic.clone()])]),
+			Receipt::new(H256::new(), U256::zero(), vec![log(vec![])]),
+		];
+
+		let positions = indexer.positions(70, &receipts);
+		assert_eq!(positions.len(), 2);
+		assert_eq!(positions[&indexer.address_key(&address, 70)], vec![
+			LogPosition { block_number: 70, log_index: 0 },
+			LogPosition { block_number: 70, log_index: 1 },
+		]);
+		assert_eq!(positions[&indexer.topic_key(&topic, 70)], vec![LogPosition { block_number: 70, log_index: 0 }]);
+	}
+}
@@ -20,6 +20,7 @@ pub mod blockchain;
 mod best_block;
 mod block_info;
 mod bloom_indexer;
+mod log_indexer;
 mod cache;
 mod tree_route;
 mod update;
@@ -27,7 +28,7 @@ mod update;
 mod generator;
 mod import_route;
 
-pub use self::blockchain::{BlockProvider, BlockChain, BlockChainConfig};
+pub use self::blockchain::{BlockProvider, BlockChain, BlockChainConfig, BlockDataError};
 pub use self::cache::CacheSize;
 pub use self::tree_route::TreeRoute;
 pub use self::import_route::ImportRoute;
@@ -15,6 +15,7 @@
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
 use util::numbers::H256;
+use extras::BlockDetails;
 
 /// Represents a tree route between `from` block and `to` block:
 #[derive(Debug)]
@@ -27,3 +28,52 @@ pub struct TreeRoute {
 	pub index: usize,
 }
 
+impl TreeRoute {
+	/// Finds the route between `from` and `to`, walking parents using `details`.
+	/// Panics if details of any block on the route are missing.
+	pub fn between<F>(from: H256, to: H256, details: F) -> TreeRoute where F: Fn(&H256) -> Option<BlockDetails> {
+		let mut from_branch = vec![];
+		let mut to_branch = vec![];
+
+		let mut from_details = details(&from).expect(&format!("0. Expected to find details for block {:?}", from));
+		let mut to_details = details(&to).expect(&format!("1. Expected to find details for block {:?}", to));
+		let mut current_from = from;
+		let mut current_to = to;
+
+		// reset from && to to the same level
+		while from_details.number > to_details.number {
+			from_branch.push(current_from);
+			current_from = from_details.parent.clone();
+			from_details = details(&from_details.parent).expect(&format!("2. Expected to find details for block {:?}", from_details.parent));
+		}
+
+		while to_details.number > from_details.number {
+			to_branch.push(current_to);
+			current_to = to_details.parent.clone();
+			to_details = details(&to_details.parent).expect(&format!("3. Expected to find details for block {:?}", to_details.parent));
+		}
+
+		assert_eq!(from_details.number, to_details.number);
+
+		// move to shared parent
+		while current_from != current_to {
+			from_branch.push(current_from);
+			current_from = from_details.parent.clone();
+			from_details = details(&from_details.parent).expect(&format!("4. Expected to find details for block {:?}", from_details.parent));
+
+			to_branch.push(current_to);
+			current_to = to_details.parent.clone();
+			to_details = details(&to_details.parent).expect(&format!("5. Expected to find details for block {:?}", from_details.parent));
+		}
+
+		let index = from_branch.len();
+
+		from_branch.extend(to_branch.into_iter().rev());
+
+		TreeRoute {
+			blocks: from_branch,
+			ancestor: current_from,
+			index: index
+		}
+	}
+}
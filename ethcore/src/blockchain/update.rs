@@ -2,7 +2,7 @@ use std::collections::HashMap;
 use util::numbers::H256;
 use header::BlockNumber;
 use blockchain::block_info::BlockInfo;
-use extras::{BlockDetails, BlockReceipts, TransactionAddress, BlocksBlooms};
+use extras::{BlockDetails, BlockReceipts, TransactionAddress, BlocksBlooms, LogPositions};
 
 /// Block extras update info.
 pub struct ExtrasUpdate {
@@ -18,4 +18,6 @@ pub struct ExtrasUpdate {
 	pub transactions_addresses: HashMap<H256, TransactionAddress>,
 	/// Modified blocks blooms.
 	pub blocks_blooms: HashMap<H256, BlocksBlooms>,
+	/// Modified log positions.
+	pub log_positions: HashMap<H256, LogPositions>,
 }
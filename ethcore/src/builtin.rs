@@ -15,67 +15,177 @@
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
 use util::*;
-use crypto::sha2::Sha256;
-use crypto::ripemd160::Ripemd160;
+use crypto::sha2::Sha256 as Sha256Digest;
+use crypto::ripemd160::Ripemd160 as Ripemd160Digest;
 use crypto::digest::Digest;
+use num::{BigUint, Zero, One};
+use header::BlockNumber;
 
-/// Definition of a contract whose implementation is built-in. 
-pub struct Builtin {
-	/// The gas cost of running this built-in for the given size of input data.
-	pub cost: Box<Fn(usize) -> U256>,	// TODO: U256 should be bignum.
-	/// Run this built-in function with the input being the first argument and the output
-	/// being placed into the second.
-	pub execute: Box<Fn(&[u8], &mut [u8])>,
+/// Execution of a builtin contract failed because its input was malformed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BuiltinError(pub &'static str);
+
+/// Native implementation of a builtin contract.
+pub trait Impl: Send + Sync {
+	/// Run the contract on `input`, placing the result into `output`.
+	fn execute(&self, input: &[u8], output: &mut [u8]) -> Result<(), BuiltinError>;
+}
+
+/// Gas pricing scheme of a builtin contract.
+pub trait Pricer: Send + Sync {
+	/// The gas cost of running the contract on `input`.
+	fn cost(&self, input: &[u8]) -> U256;
+}
+
+/// Pricing of a base cost plus a cost for each 32-byte word of input.
+#[derive(Debug)]
+pub struct Linear {
+	/// Cost of any call.
+	pub base: usize,
+	/// Cost of each word of input, rounded up.
+	pub word: usize,
+}
+
+impl Pricer for Linear {
+	fn cost(&self, input: &[u8]) -> U256 {
+		U256::from(self.base) + U256::from(self.word) * U256::from((input.len() + 31) / 32)
+	}
 }
 
-// Rust does not mark closurer that do not capture as Sync
-// We promise that all builtins are thread safe since they only operate on given input.
-unsafe impl Sync for Builtin {}
-unsafe impl Send for Builtin {}
+/// Pricing of modular exponentiation by the size of its operands and exponent (EIP-198).
+#[derive(Debug)]
+pub struct ModexpPricer {
+	/// Divisor of the product of multiplication complexity and exponent length.
+	pub divisor: usize,
+}
+
+impl Pricer for ModexpPricer {
+	fn cost(&self, input: &[u8]) -> U256 {
+		let (base_len, exp_len, mod_len) = match modexp_lengths(input) {
+			Ok(lengths) => lengths,
+			Err(_) => return U256::max_value(),
+		};
+
+		// only the first 32 bytes of the exponent are taken into account for its length.
+		let mut exp_head = [0u8; 32];
+		let head_len = min(exp_len, 32);
+		read_padded(input, 96 + base_len, &mut exp_head[32 - head_len..]);
+		let exp_head_bits = U256::from(&exp_head[..]).bits();
+		let adjusted_exp_len = if exp_len <= 32 {
+			exp_head_bits.saturating_sub(1)
+		} else {
+			8 * (exp_len - 32) + exp_head_bits.saturating_sub(1)
+		};
+
+		let x = U256::from(max(base_len, mod_len));
+		let complexity = if x <= U256::from(64) {
+			x * x
+		} else if x <= U256::from(1024) {
+			x * x / U256::from(4) + U256::from(96) * x - U256::from(3072)
+		} else {
+			x * x / U256::from(16) + U256::from(480) * x - U256::from(199680)
+		};
+		complexity * U256::from(max(adjusted_exp_len, 1)) / U256::from(self.divisor)
+	}
+}
+
+/// Definition of a contract whose implementation is built-in.
+pub struct Builtin {
+	pricer: Box<Pricer>,
+	native: Arc<Impl>,
+	activate_at: BlockNumber,
+}
 
 impl fmt::Debug for Builtin {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-		write!(f, "<Builtin>")
+		write!(f, "<Builtin active from #{}>", self.activate_at)
 	}
 }
 
 impl Builtin {
 	/// Create a new object from components.
-	pub fn new(cost: Box<Fn(usize) -> U256>, execute: Box<Fn(&[u8], &mut [u8])>) -> Builtin {
-		Builtin {cost: cost, execute: execute}
+	pub fn new(pricer: Box<Pricer>, native: Arc<Impl>, activate_at: BlockNumber) -> Builtin {
+		Builtin {
+			pricer: pricer,
+			native: native,
+			activate_at: activate_at,
+		}
 	}
 
-	/// Create a new object from a builtin-function name with a linear cost associated with input size.
-	pub fn from_named_linear(name: &str, base_cost: usize, word_cost: usize) -> Option<Builtin> {
-		new_builtin_exec(name).map(|b| {
-			let cost = Box::new(move|s: usize| -> U256 {
-				U256::from(base_cost) + U256::from(word_cost) * U256::from((s + 31) / 32)
-			});
-			Self::new(cost, b)
-		})
+	/// The gas cost of running this builtin on `input`.
+	pub fn cost(&self, input: &[u8]) -> U256 { self.pricer.cost(input) }
+
+	/// Run this builtin with the input being the first argument and the output
+	/// being placed into the second.
+	pub fn execute(&self, input: &[u8], output: &mut [u8]) -> Result<(), BuiltinError> { self.native.execute(input, output) }
+
+	/// First block at which the builtin may be called.
+	pub fn activate_at(&self) -> BlockNumber { self.activate_at }
+
+	/// Whether the builtin may be called at block `number`.
+	pub fn is_active(&self, number: BlockNumber) -> bool { number >= self.activate_at }
+}
+
+/// Named builtin implementations which chain specs may refer to.
+pub struct Registry {
+	natives: HashMap<String, Arc<Impl>>,
+}
+
+impl Default for Registry {
+	/// The registry of all builtins known to Parity.
+	fn default() -> Registry {
+		let mut registry = Registry::new();
+		registry.register("identity", Arc::new(Identity));
+		registry.register("ecrecover", Arc::new(EcRecover));
+		registry.register("sha256", Arc::new(Sha256));
+		registry.register("ripemd160", Arc::new(Ripemd160));
+		registry.register("modexp", Arc::new(Modexp));
+		registry
 	}
+}
 
-	/// Simple forwarder for cost.
-	pub fn cost(&self, s: usize) -> U256 { (*self.cost)(s) }
+impl Registry {
+	/// Create an empty registry.
+	pub fn new() -> Registry {
+		Registry { natives: HashMap::new() }
+	}
 
-	/// Simple forwarder for execute.
-	pub fn execute(&self, input: &[u8], output: &mut[u8]) { (*self.execute)(input, output); }
+	/// Register the implementation of the builtin called `name`, replacing any previous one.
+	pub fn register(&mut self, name: &str, native: Arc<Impl>) {
+		self.natives.insert(name.to_owned(), native);
+	}
+
+	/// Get the implementation of the builtin called `name`.
+	pub fn native(&self, name: &str) -> Option<Arc<Impl>> {
+		self.natives.get(name).cloned()
+	}
 
 	/// Create a builtin from JSON.
 	///
-	/// JSON must be of the form `{ "name": "identity", "linear": {"base": 10, "word": 20} }`.
-	pub fn from_json(json: &Json) -> Option<Builtin> {
-		// NICE: figure out a more convenient means of handing errors here.
-		if let Json::String(ref name) = json["name"] {
-			if let Json::Object(ref o) = json["linear"] {
-				if let Json::U64(ref word) = o["word"] {
-					if let Json::U64(ref base) = o["base"] {
-						return Self::from_named_linear(&name[..], *base as usize, *word as usize);
-					}
-				}
+	/// JSON must be of the form `{ "name": "identity", "activateAt": 0, "pricing": { "linear": {"base": 10, "word": 20} } }`,
+	/// where `activateAt` is optional and the pricing may also be `{ "modexp": { "divisor": 20 } }`.
+	/// The legacy form with `"linear"` in place of `"pricing"` is accepted too.
+	pub fn builtin_from_json(&self, json: &Json) -> Option<Builtin> {
+		let native = match json.find("name").and_then(Json::as_string).and_then(|name| self.native(name)) {
+			Some(native) => native,
+			None => return None,
+		};
+		let pricing = json.find("pricing");
+		let pricer: Box<Pricer> = if let Some(linear) = pricing.and_then(|p| p.find("linear")).or_else(|| json.find("linear")) {
+			match (linear.find("base"), linear.find("word")) {
+				(Some(base), Some(word)) => Box::new(Linear { base: u64::from_json(base) as usize, word: u64::from_json(word) as usize }),
+				_ => return None,
 			}
-		}
-		None
+		} else if let Some(modexp) = pricing.and_then(|p| p.find("modexp")) {
+			match modexp.find("divisor").map(u64::from_json) {
+				Some(divisor) if divisor > 0 => Box::new(ModexpPricer { divisor: divisor as usize }),
+				_ => return None,
+			}
+		} else {
+			return None;
+		};
+		let activate_at = json.find("activateAt").map_or(0, u64::from_json);
+		Some(Builtin::new(pricer, native, activate_at))
 	}
 }
 
@@ -88,76 +198,179 @@ pub fn copy_to(src: &[u8], dest: &mut[u8]) {
 	}
 }
 
-/// Create a new builtin executor according to `name`.
-/// TODO: turn in to a factory with dynamic registration.
-pub fn new_builtin_exec(name: &str) -> Option<Box<Fn(&[u8], &mut [u8])>> {
-	match name {
-		"identity" => Some(Box::new(move|input: &[u8], output: &mut[u8]| {
-			for i in 0..min(input.len(), output.len()) {
-				output[i] = input[i];
-			}
-		})),
-		"ecrecover" => Some(Box::new(move|input: &[u8], output: &mut[u8]| {
-			#[repr(packed)]
-			#[derive(Debug)]
-			struct InType {
-				hash: H256,
-				v: H256,
-				r: H256,
-				s: H256,
-			}
-			let mut it: InType = InType { hash: H256::new(), v: H256::new(), r: H256::new(), s: H256::new() };
-			it.copy_raw(input);
-			if it.v == H256::from(&U256::from(27)) || it.v == H256::from(&U256::from(28)) {
-				let s = Signature::from_rsv(&it.r, &it.s, it.v[31] - 27);
-				if ec::is_valid(&s) {
-					if let Ok(p) = ec::recover(&s, &it.hash) {
-						let r = p.as_slice().sha3();
-						// NICE: optimise and separate out into populate-like function
-						for i in 0..min(32, output.len()) {
-							output[i] = if i < 12 {0} else {r[i]};
-						}
+/// Fill `dest` with the bytes of `src` from `offset` on, padding with zeroes past the end of `src`.
+fn read_padded(src: &[u8], offset: usize, dest: &mut [u8]) {
+	for b in dest.iter_mut() {
+		*b = 0;
+	}
+	if offset < src.len() {
+		copy_to(&src[offset..], dest);
+	}
+}
+
+/// Identity function; copies the input to the output.
+#[derive(Debug)]
+pub struct Identity;
+
+impl Impl for Identity {
+	fn execute(&self, input: &[u8], output: &mut [u8]) -> Result<(), BuiltinError> {
+		copy_to(input, output);
+		Ok(())
+	}
+}
+
+/// Recovery of the address which signed a message hash.
+#[derive(Debug)]
+pub struct EcRecover;
+
+impl Impl for EcRecover {
+	fn execute(&self, input: &[u8], output: &mut [u8]) -> Result<(), BuiltinError> {
+		#[repr(packed)]
+		#[derive(Debug)]
+		struct InType {
+			hash: H256,
+			v: H256,
+			r: H256,
+			s: H256,
+		}
+		let mut it: InType = InType { hash: H256::new(), v: H256::new(), r: H256::new(), s: H256::new() };
+		it.copy_raw(input);
+		// an invalid signature is not a failure; the call succeeds with no output.
+		if it.v == H256::from(&U256::from(27)) || it.v == H256::from(&U256::from(28)) {
+			let s = Signature::from_rsv(&it.r, &it.s, it.v[31] - 27);
+			if ec::is_valid(&s) {
+				if let Ok(p) = ec::recover(&s, &it.hash) {
+					let r = p.as_slice().sha3();
+					// NICE: optimise and separate out into populate-like function
+					for i in 0..min(32, output.len()) {
+						output[i] = if i < 12 {0} else {r[i]};
 					}
 				}
 			}
-		})),
-		"sha256" => Some(Box::new(move|input: &[u8], output: &mut[u8]| {
-			let mut sha = Sha256::new();
-			sha.input(input);
-			if output.len() >= 32 {
-				sha.result(output);
-			} else {
-				let mut ret = H256::new();
-				sha.result(ret.as_slice_mut());
-				copy_to(&ret, output);
-			}
-		})),
-		"ripemd160" => Some(Box::new(move|input: &[u8], output: &mut[u8]| {
-			let mut sha = Ripemd160::new();
-			sha.input(input);
+		}
+		Ok(())
+	}
+}
+
+/// SHA2-256 hash of the input.
+#[derive(Debug)]
+pub struct Sha256;
+
+impl Impl for Sha256 {
+	fn execute(&self, input: &[u8], output: &mut [u8]) -> Result<(), BuiltinError> {
+		let mut sha = Sha256Digest::new();
+		sha.input(input);
+		if output.len() >= 32 {
+			sha.result(output);
+		} else {
 			let mut ret = H256::new();
-			sha.result(&mut ret.as_slice_mut()[12..32]);
+			sha.result(ret.as_slice_mut());
 			copy_to(&ret, output);
-		})),
-		_ => None
+		}
+		Ok(())
+	}
+}
+
+/// RIPEMD-160 hash of the input, left-padded to 32 bytes.
+#[derive(Debug)]
+pub struct Ripemd160;
+
+impl Impl for Ripemd160 {
+	fn execute(&self, input: &[u8], output: &mut [u8]) -> Result<(), BuiltinError> {
+		let mut sha = Ripemd160Digest::new();
+		sha.input(input);
+		let mut ret = H256::new();
+		sha.result(&mut ret.as_slice_mut()[12..32]);
+		copy_to(&ret, output);
+		Ok(())
+	}
+}
+
+/// Big-integer modular exponentiation (EIP-198). The input is the lengths of the base, exponent
+/// and modulus as 32-byte words followed by the numbers themselves, all big-endian.
+#[derive(Debug)]
+pub struct Modexp;
+
+// Operands longer than this can't be paid for with any sensible amount of gas.
+const MAX_MODEXP_OPERAND_LEN: u64 = 0xffff_ffff;
+
+/// Read the lengths of the base, exponent and modulus from modexp input.
+fn modexp_lengths(input: &[u8]) -> Result<(usize, usize, usize), BuiltinError> {
+	let mut word = [0u8; 32];
+	let mut read_len = |offset: usize| {
+		read_padded(input, offset, &mut word);
+		let len = U256::from(&word[..]);
+		if len > U256::from(MAX_MODEXP_OPERAND_LEN) {
+			Err(BuiltinError("modexp operand too long"))
+		} else {
+			Ok(len.low_u64() as usize)
+		}
+	};
+	let base_len = try!(read_len(0));
+	let exp_len = try!(read_len(32));
+	let mod_len = try!(read_len(64));
+	Ok((base_len, exp_len, mod_len))
+}
+
+impl Impl for Modexp {
+	fn execute(&self, input: &[u8], output: &mut [u8]) -> Result<(), BuiltinError> {
+		let (base_len, exp_len, mod_len) = try!(modexp_lengths(input));
+		if mod_len == 0 {
+			return Ok(());
+		}
+
+		let read_number = |offset: usize, len: usize| {
+			let mut bytes = vec![0u8; len];
+			read_padded(input, offset, &mut bytes);
+			bytes
+		};
+		let base = BigUint::from_bytes_be(&read_number(96, base_len));
+		let exp = read_number(96 + base_len, exp_len);
+		let modulus = BigUint::from_bytes_be(&read_number(96 + base_len + exp_len, mod_len));
+
+		let result = if modulus.is_zero() {
+			BigUint::zero()
+		} else {
+			// square and multiply, from the most significant bit of the exponent.
+			let base = base % &modulus;
+			let mut result = BigUint::one() % &modulus;
+			for byte in exp {
+				for bit in (0..8).rev() {
+					result = (&result * &result) % &modulus;
+					if byte & (1 << bit) != 0 {
+						result = (&result * &base) % &modulus;
+					}
+				}
+			}
+			result
+		};
+
+		// the result is left-padded to the length of the modulus.
+		let bytes = result.to_bytes_be();
+		let mut padded = vec![0u8; mod_len];
+		if !result.is_zero() {
+			copy_to(&bytes, &mut padded[mod_len - bytes.len()..]);
+		}
+		copy_to(&padded, output);
+		Ok(())
 	}
 }
 
 #[test]
 fn identity() {
-	let f = new_builtin_exec("identity").unwrap();
+	let f = Registry::default().native("identity").unwrap();
 	let i = [0u8, 1, 2, 3];
 
 	let mut o2 = [255u8; 2];
-	f(&i[..], &mut o2[..]);
+	f.execute(&i[..], &mut o2[..]).unwrap();
 	assert_eq!(i[0..2], o2);
 
 	let mut o4 = [255u8; 4];
-	f(&i[..], &mut o4[..]);
+	f.execute(&i[..], &mut o4[..]).unwrap();
 	assert_eq!(i, o4);
 
 	let mut o8 = [255u8; 8];
-	f(&i[..], &mut o8[..]);
+	f.execute(&i[..], &mut o8[..]).unwrap();
 	assert_eq!(i, o8[..4]);
 	assert_eq!([255u8; 4], o8[4..]);
 }
@@ -165,38 +378,38 @@ fn identity() {
 #[test]
 fn sha256() {
 	use rustc_serialize::hex::FromHex;
-	let f = new_builtin_exec("sha256").unwrap();
+	let f = Registry::default().native("sha256").unwrap();
 	let i = [0u8; 0];
 
 	let mut o = [255u8; 32];
-	f(&i[..], &mut o[..]);
+	f.execute(&i[..], &mut o[..]).unwrap();
 	assert_eq!(&o[..], &(FromHex::from_hex("e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855").unwrap())[..]);
 
 	let mut o8 = [255u8; 8];
-	f(&i[..], &mut o8[..]);
+	f.execute(&i[..], &mut o8[..]).unwrap();
 	assert_eq!(&o8[..], &(FromHex::from_hex("e3b0c44298fc1c14").unwrap())[..]);
 
 	let mut o34 = [255u8; 34];
-	f(&i[..], &mut o34[..]);
+	f.execute(&i[..], &mut o34[..]).unwrap();
 	assert_eq!(&o34[..], &(FromHex::from_hex("e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855ffff").unwrap())[..]);
 }
 
 #[test]
 fn ripemd160() {
 	use rustc_serialize::hex::FromHex;
-	let f = new_builtin_exec("ripemd160").unwrap();
+	let f = Registry::default().native("ripemd160").unwrap();
 	let i = [0u8; 0];
 
 	let mut o = [255u8; 32];
-	f(&i[..], &mut o[..]);
+	f.execute(&i[..], &mut o[..]).unwrap();
 	assert_eq!(&o[..], &(FromHex::from_hex("0000000000000000000000009c1185a5c5e9fc54612808977ee8f548b2258d31").unwrap())[..]);
 
 	let mut o8 = [255u8; 8];
-	f(&i[..], &mut o8[..]);
+	f.execute(&i[..], &mut o8[..]).unwrap();
 	assert_eq!(&o8[..], &(FromHex::from_hex("0000000000000000").unwrap())[..]);
 
 	let mut o34 = [255u8; 34];
-	f(&i[..], &mut o34[..]);
+	f.execute(&i[..], &mut o34[..]).unwrap();
 	assert_eq!(&o34[..], &(FromHex::from_hex("0000000000000000000000009c1185a5c5e9fc54612808977ee8f548b2258d31ffff").unwrap())[..]);
 }
 
@@ -211,64 +424,64 @@ fn ecrecover() {
 	let s = k.sign(&m).unwrap();
 	println!("Signed: {}", s);*/
 
-	let f = new_builtin_exec("ecrecover").unwrap();
+	let f = Registry::default().native("ecrecover").unwrap();
 	let i = FromHex::from_hex("47173285a8d7341e5e972fc677286384f802f8ef42a5ec5f03bbfa254cb01fad000000000000000000000000000000000000000000000000000000000000001b650acf9d3f5f0a2c799776a1254355d5f4061762a237396a99a0e0e3fc2bcd6729514a0dacb2e623ac4abd157cb18163ff942280db4d5caad66ddf941ba12e03").unwrap();
 
 	let mut o = [255u8; 32];
-	f(&i[..], &mut o[..]);
+	f.execute(&i[..], &mut o[..]).unwrap();
 	assert_eq!(&o[..], &(FromHex::from_hex("000000000000000000000000c08b5542d177ac6686946920409741463a15dddb").unwrap())[..]);
 
 	let mut o8 = [255u8; 8];
-	f(&i[..], &mut o8[..]);
+	f.execute(&i[..], &mut o8[..]).unwrap();
 	assert_eq!(&o8[..], &(FromHex::from_hex("0000000000000000").unwrap())[..]);
 
 	let mut o34 = [255u8; 34];
-	f(&i[..], &mut o34[..]);
+	f.execute(&i[..], &mut o34[..]).unwrap();
 	assert_eq!(&o34[..], &(FromHex::from_hex("000000000000000000000000c08b5542d177ac6686946920409741463a15dddbffff").unwrap())[..]);
 
 	let i_bad = FromHex::from_hex("47173285a8d7341e5e972fc677286384f802f8ef42a5ec5f03bbfa254cb01fad000000000000000000000000000000000000000000000000000000000000001a650acf9d3f5f0a2c799776a1254355d5f4061762a237396a99a0e0e3fc2bcd6729514a0dacb2e623ac4abd157cb18163ff942280db4d5caad66ddf941ba12e03").unwrap();
 	let mut o = [255u8; 32];
-	f(&i_bad[..], &mut o[..]);
+	f.execute(&i_bad[..], &mut o[..]).unwrap();
 	assert_eq!(&o[..], &(FromHex::from_hex("ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff").unwrap())[..]);
 
 	let i_bad = FromHex::from_hex("47173285a8d7341e5e972fc677286384f802f8ef42a5ec5f03bbfa254cb01fad000000000000000000000000000000000000000000000000000000000000001b000000000000000000000000000000000000000000000000000000000000001b0000000000000000000000000000000000000000000000000000000000000000").unwrap();
 	let mut o = [255u8; 32];
-	f(&i_bad[..], &mut o[..]);
+	f.execute(&i_bad[..], &mut o[..]).unwrap();
 	assert_eq!(&o[..], &(FromHex::from_hex("ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff").unwrap())[..]);
 
 	let i_bad = FromHex::from_hex("47173285a8d7341e5e972fc677286384f802f8ef42a5ec5f03bbfa254cb01fad000000000000000000000000000000000000000000000000000000000000001b0000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000001b").unwrap();
 	let mut o = [255u8; 32];
-	f(&i_bad[..], &mut o[..]);
+	f.execute(&i_bad[..], &mut o[..]).unwrap();
 	assert_eq!(&o[..], &(FromHex::from_hex("ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff").unwrap())[..]);
 
 	let i_bad = FromHex::from_hex("47173285a8d7341e5e972fc677286384f802f8ef42a5ec5f03bbfa254cb01fad000000000000000000000000000000000000000000000000000000000000001bffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff000000000000000000000000000000000000000000000000000000000000001b").unwrap();
 	let mut o = [255u8; 32];
-	f(&i_bad[..], &mut o[..]);
+	f.execute(&i_bad[..], &mut o[..]).unwrap();
 	assert_eq!(&o[..], &(FromHex::from_hex("ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff").unwrap())[..]);
 
 	let i_bad = FromHex::from_hex("47173285a8d7341e5e972fc677286384f802f8ef42a5ec5f03bbfa254cb01fad000000000000000000000000000000000000000000000000000000000000001b000000000000000000000000000000000000000000000000000000000000001bffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff").unwrap();
 	let mut o = [255u8; 32];
-	f(&i_bad[..], &mut o[..]);
+	f.execute(&i_bad[..], &mut o[..]).unwrap();
 	assert_eq!(&o[..], &(FromHex::from_hex("ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff").unwrap())[..]);
 
 	// TODO: Should this (corrupted version of the above) fail rather than returning some address?
 /*	let i_bad = FromHex::from_hex("48173285a8d7341e5e972fc677286384f802f8ef42a5ec5f03bbfa254cb01fad000000000000000000000000000000000000000000000000000000000000001b650acf9d3f5f0a2c799776a1254355d5f4061762a237396a99a0e0e3fc2bcd6729514a0dacb2e623ac4abd157cb18163ff942280db4d5caad66ddf941ba12e03").unwrap();
 	let mut o = [255u8; 32];
-	f(&i_bad[..], &mut o[..]);
+	f.execute(&i_bad[..], &mut o[..]).unwrap();
 	assert_eq!(&o[..], &(FromHex::from_hex("ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff").unwrap())[..]);*/
 }
 
 #[test]
-fn from_named_linear() {
-	let b = Builtin::from_named_linear("identity", 10, 20).unwrap();
-	assert_eq!((*b.cost)(0), U256::from(10));
-	assert_eq!((*b.cost)(1), U256::from(30));
-	assert_eq!((*b.cost)(32), U256::from(30));
-	assert_eq!((*b.cost)(33), U256::from(50));
+fn linear_pricing() {
+	let b = Builtin::new(Box::new(Linear { base: 10, word: 20 }), Arc::new(Identity), 0);
+	assert_eq!(b.cost(&[0u8; 0]), U256::from(10));
+	assert_eq!(b.cost(&[0u8; 1]), U256::from(30));
+	assert_eq!(b.cost(&[0u8; 32]), U256::from(30));
+	assert_eq!(b.cost(&[0u8; 33]), U256::from(50));
 
 	let i = [0u8, 1, 2, 3];
 	let mut o = [255u8; 4];
-	(*b.execute)(&i[..], &mut o[..]);
+	b.execute(&i[..], &mut o[..]).unwrap();
 	assert_eq!(i, o);
 }
 
@@ -276,14 +489,76 @@ fn from_named_linear() {
 fn from_json() {
 	let text = "{ \"name\": \"identity\", \"linear\": {\"base\": 10, \"word\": 20} }";
 	let json = Json::from_str(text).unwrap();
-	let b = Builtin::from_json(&json).unwrap();
-	assert_eq!((*b.cost)(0), U256::from(10));
-	assert_eq!((*b.cost)(1), U256::from(30));
-	assert_eq!((*b.cost)(32), U256::from(30));
-	assert_eq!((*b.cost)(33), U256::from(50));
+	let b = Registry::default().builtin_from_json(&json).unwrap();
+	assert_eq!(b.cost(&[0u8; 0]), U256::from(10));
+	assert_eq!(b.cost(&[0u8; 1]), U256::from(30));
+	assert_eq!(b.cost(&[0u8; 32]), U256::from(30));
+	assert_eq!(b.cost(&[0u8; 33]), U256::from(50));
+	assert!(b.is_active(0));
 
 	let i = [0u8, 1, 2, 3];
 	let mut o = [255u8; 4];
-	(*b.execute)(&i[..], &mut o[..]);
+	b.execute(&i[..], &mut o[..]).unwrap();
 	assert_eq!(i, o);
 }
+
+#[test]
+fn from_json_with_activation() {
+	let text = "{ \"name\": \"modexp\", \"activateAt\": \"0x64\", \"pricing\": { \"modexp\": { \"divisor\": 20 } } }";
+	let json = Json::from_str(text).unwrap();
+	let b = Registry::default().builtin_from_json(&json).unwrap();
+	assert_eq!(b.activate_at(), 100);
+	assert!(!b.is_active(99));
+	assert!(b.is_active(100));
+
+	let unknown = Json::from_str("{ \"name\": \"unknown\", \"linear\": {\"base\": 10, \"word\": 20} }").unwrap();
+	assert!(Registry::default().builtin_from_json(&unknown).is_none());
+}
+
+#[test]
+fn modexp() {
+	use rustc_serialize::hex::FromHex;
+	let b = Builtin::new(Box::new(ModexpPricer { divisor: 20 }), Registry::default().native("modexp").unwrap(), 0);
+
+	// Fermat's little theorem: 3 ^ (p - 1) mod p == 1
+	let i = FromHex::from_hex("\
+		0000000000000000000000000000000000000000000000000000000000000001\
+		0000000000000000000000000000000000000000000000000000000000000020\
+		0000000000000000000000000000000000000000000000000000000000000020\
+		03\
+		fffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc2e\
+		fffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc2f").unwrap();
+	let mut o = [255u8; 32];
+	b.execute(&i[..], &mut o[..]).unwrap();
+	assert_eq!(&o[..], &(FromHex::from_hex("0000000000000000000000000000000000000000000000000000000000000001").unwrap())[..]);
+	assert_eq!(b.cost(&i[..]), U256::from(13056));
+
+	// zero modulus; the input is padded with zeroes.
+	let i = FromHex::from_hex("\
+		0000000000000000000000000000000000000000000000000000000000000001\
+		0000000000000000000000000000000000000000000000000000000000000001\
+		0000000000000000000000000000000000000000000000000000000000000002\
+		0305").unwrap();
+	let mut o = [255u8; 4];
+	b.execute(&i[..], &mut o[..]).unwrap();
+	assert_eq!(o, [0u8, 0, 255, 255]);
+
+	// 2 ^ 5 mod 7 == 4
+	let i = FromHex::from_hex("\
+		0000000000000000000000000000000000000000000000000000000000000001\
+		0000000000000000000000000000000000000000000000000000000000000001\
+		0000000000000000000000000000000000000000000000000000000000000001\
+		020507").unwrap();
+	let mut o = [255u8; 1];
+	b.execute(&i[..], &mut o[..]).unwrap();
+	assert_eq!(o, [4u8]);
+
+	// an operand too long to ever be paid for.
+	let i = FromHex::from_hex("\
+		0000000000000000000000000000000000000000000000000000000100000000\
+		0000000000000000000000000000000000000000000000000000000000000001\
+		0000000000000000000000000000000000000000000000000000000000000001").unwrap();
+	let mut o = [255u8; 1];
+	assert_eq!(b.execute(&i[..], &mut o[..]), Err(BuiltinError("modexp operand too long")));
+	assert_eq!(b.cost(&i[..]), U256::max_value());
+}
@@ -17,13 +17,15 @@
 //! Blockchain database client.
 
 use std::marker::PhantomData;
+use std::path::PathBuf;
 use std::sync::atomic::AtomicBool;
 use util::*;
 use util::panics::*;
-use blockchain::{BlockChain, BlockProvider};
+use blockchain::{BlockChain, BlockProvider, BlockDataError, ImportRoute};
+use basic_types::Seal;
 use views::BlockView;
 use error::*;
-use header::{BlockNumber};
+use header::{BlockNumber, Header};
 use state::State;
 use spec::Spec;
 use engine::Engine;
@@ -33,11 +35,14 @@ use service::{NetSyncMessage, SyncMessage};
 use env_info::LastHashes;
 use verification::*;
 use block::*;
-use transaction::LocalizedTransaction;
-use extras::TransactionAddress;
+use transaction::{LocalizedTransaction, SignedTransaction};
+use extras::{TransactionAddress, ExtrasIndex};
 use filter::Filter;
 use log_entry::LocalizedLogEntry;
+use receipt::Receipt;
 use util::keys::store::SecretStore;
+use sealing::SealingConfig;
+use migrations;
 pub use block_queue::{BlockQueueConfig, BlockQueueInfo};
 pub use blockchain::{TreeRoute, BlockChainConfig, CacheSize as BlockChainCacheSize};
 
@@ -85,10 +90,205 @@ pub struct ClientConfig {
 	pub queue: BlockQueueConfig,
 	/// Blockchain configuration.
 	pub blockchain: BlockChainConfig,
-	/// Prefer journal rather than archive.
-	pub prefer_journal: bool,
+	/// State pruning algorithm.
+	pub pruning: journaldb::Algorithm,
+	/// Number of recent blocks whose state is kept by the pruning algorithm.
+	pub history: u64,
+	/// Total block cache size of all databases in megabytes. RocksDB default if `None`.
+	pub db_cache_size: Option<usize>,
+	/// Compaction profile of the databases.
+	pub db_compaction: CompactionProfile,
+	/// Write to the write-ahead log of the databases.
+	pub db_wal: bool,
 	/// The name of the client instance.
 	pub name: String,
+	/// Policy of authoring blocks.
+	pub sealing: SealingConfig,
+	/// Directory of proof-of-work light caches and datasets. The engine's default if `None`.
+	pub pow_cache_dir: Option<PathBuf>,
+}
+
+/// Databases the client keeps its data in.
+pub struct ClientDatabases {
+	/// State trie. Prefix lookups should use `journaldb::DB_PREFIX_LEN` bytes.
+	pub state: Arc<KeyValueDB>,
+	/// Block details, hashes, receipts and other extras.
+	pub extras: Arc<KeyValueDB>,
+	/// Block bodies.
+	pub blocks: Arc<KeyValueDB>,
+}
+
+/// Number and total size of database entries.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct EntryStats {
+	/// Number of entries.
+	pub count: u64,
+	/// Total size of keys in bytes.
+	pub key_bytes: u64,
+	/// Total size of values in bytes.
+	pub value_bytes: u64,
+}
+
+impl EntryStats {
+	fn add(&mut self, key: &[u8], value: &[u8]) {
+		self.count += 1;
+		self.key_bytes += key.len() as u64;
+		self.value_bytes += value.len() as u64;
+	}
+}
+
+/// Contents of the client databases.
+#[derive(Debug, Default)]
+pub struct DatabaseStats {
+	/// State database entries.
+	pub state: EntryStats,
+	/// Blocks database entries.
+	pub blocks: EntryStats,
+	/// Extras database entries by `ExtrasIndex` name. Keys not belonging to any index are counted as `Other`.
+	pub extras: BTreeMap<String, EntryStats>,
+}
+
+impl ClientDatabases {
+	/// Directory the databases of the chain given by `spec` are kept in under `path`.
+	pub fn dir(config: &ClientConfig, spec: &Spec, path: &Path) -> PathBuf {
+		Self::pruning_dir(config.pruning, spec, path)
+	}
+
+	fn pruning_dir(pruning: journaldb::Algorithm, spec: &Spec, path: &Path) -> PathBuf {
+		let mut dir = Self::chain_dir(spec, path);
+		//TODO: sec/fat: pruned/full versioning
+		dir.push(format!("v{}-sec-{}", CLIENT_DB_VER_STR, match pruning {
+			journaldb::Algorithm::Archive => "archive",
+			journaldb::Algorithm::RefCounted => "pruned",
+			journaldb::Algorithm::OverlayRecent => "overlayrecent",
+		}));
+		dir
+	}
+
+	/// Directory the databases of all versions and pruning algorithms of the chain given by `spec` are kept in.
+	pub fn chain_dir(spec: &Spec, path: &Path) -> PathBuf {
+		let mut dir = path.to_path_buf();
+		dir.push(H64::from(spec.genesis_header().hash()).hex());
+		dir
+	}
+
+	/// Open RocksDB databases of the chain given by `spec` under `path`, migrating them
+	/// to the current schema first.
+	///
+	/// Fails if the databases of the chain were created with another pruning algorithm.
+	pub fn open(config: &ClientConfig, spec: &Spec, path: &Path) -> Result<ClientDatabases, Error> {
+		let dir = Self::dir(config, spec, path);
+		if !dir.exists() {
+			// each algorithm has its own directory, so the state database can't tell about the mismatch.
+			let algorithms = [journaldb::Algorithm::Archive, journaldb::Algorithm::RefCounted, journaldb::Algorithm::OverlayRecent];
+			if let Some(other) = algorithms.iter().find(|&&a| a != config.pruning && Self::pruning_dir(a, spec, path).exists()) {
+				return Err(From::from(UtilError::SimpleString(format!(
+					"Database was created with `--pruning {}` and can't be opened with `--pruning {}`. \
+					Run with `--pruning {}` or remove the database to resync.", other, config.pruning, other))));
+			}
+		}
+
+		// state is read the most; the rest is split between extras and blocks.
+		let cache_share = |percent: usize| config.db_cache_size.map(|total| max(1, total * percent / 100));
+		let open = |name: &str, prefix_size: Option<usize>, cache_size: Option<usize>, manager: migration::Manager| -> Result<Arc<KeyValueDB>, UtilError> {
+			let mut db_path = dir.clone();
+			db_path.push(name);
+			let db_config = DatabaseConfig {
+				prefix_size: prefix_size,
+				cache_size: cache_size,
+				compaction: config.db_compaction,
+				wal: config.db_wal,
+				..DatabaseConfig::default()
+			};
+			try!(manager.migrate(&db_path, &db_config).map_err(|e| format!("Error migrating {} db: {}", name, e)));
+			let db: Arc<KeyValueDB> = Arc::new(try!(Database::open(&db_config, db_path.to_str().unwrap()).map_err(|e| format!("Error opening {} db: {}", name, e))));
+			Ok(db)
+		};
+		Ok(ClientDatabases {
+			state: try!(open("state", Some(journaldb::DB_PREFIX_LEN), cache_share(50), migrations::state())),
+			extras: try!(open("extras", None, cache_share(25), migrations::extras())),
+			blocks: try!(open("blocks", None, cache_share(25), migrations::blocks())),
+		})
+	}
+
+	/// Create empty in-memory databases.
+	pub fn in_memory() -> ClientDatabases {
+		ClientDatabases {
+			state: Arc::new(in_memory()),
+			extras: Arc::new(in_memory()),
+			blocks: Arc::new(in_memory()),
+		}
+	}
+
+	/// Count entries of all databases.
+	pub fn stats(&self) -> DatabaseStats {
+		let mut stats = DatabaseStats::default();
+		for (key, value) in self.state.iter() {
+			stats.state.add(&key, &value);
+		}
+		for (key, value) in self.blocks.iter() {
+			stats.blocks.add(&key, &value);
+		}
+		for (key, value) in self.extras.iter() {
+			let index = match key.len() {
+				33 => ExtrasIndex::from_u8(key[32]).map(|i| format!("{:?}", i)),
+				_ => None,
+			};
+			stats.extras.entry(index.unwrap_or_else(|| "Other".to_owned())).or_insert_with(EntryStats::default).add(&key, &value);
+		}
+		stats
+	}
+
+	/// Compact all databases.
+	pub fn compact(&self) {
+		self.state.compact();
+		self.extras.compact();
+		self.blocks.compact();
+	}
+}
+
+/// Logs of block `hash` matching `filter`, given its receipts and transaction hashes.
+pub fn localized_block_logs(number: BlockNumber, hash: H256, receipts: Vec<Receipt>, hashes: Vec<H256>, filter: &Filter) -> Vec<LocalizedLogEntry> {
+	let mut log_index = 0;
+	receipts.into_iter()
+		.enumerate()
+		.flat_map(|(index, receipt)| {
+			log_index += receipt.logs.len();
+			receipt.logs.into_iter()
+				.enumerate()
+				.filter(|tuple| filter.matches(&tuple.1))
+				.map(|(i, log)| LocalizedLogEntry {
+					entry: log,
+					block_hash: hash.clone(),
+					block_number: number as usize,
+					transaction_hash: hashes.get(index).cloned().unwrap_or_else(H256::new),
+					transaction_index: index,
+					log_index: log_index + i
+				})
+				.collect::<Vec<LocalizedLogEntry>>()
+		})
+		.collect()
+}
+
+/// Inconsistency of the client databases found by `Client::verify_integrity`.
+#[derive(Debug, PartialEq)]
+pub enum IntegrityError {
+	/// No canonical block hash stored for the number.
+	MissingBlockHash(BlockNumber),
+	/// Block body is missing.
+	MissingBlock(H256),
+	/// Block details are missing.
+	MissingDetails(H256),
+	/// Block details carry a wrong number.
+	WrongNumber(H256),
+	/// Parent in block details is not the previous canonical block.
+	WrongParent(H256),
+	/// Parent's details don't list the block as a child.
+	NotAChild(H256),
+	/// Transaction address of a canonical transaction is missing or points elsewhere.
+	WrongTransactionAddress(H256),
+	/// State root of the block is not in the state database.
+	MissingState(H256),
 }
 
 impl Default for ClientConfig {
@@ -96,8 +296,14 @@ impl Default for ClientConfig {
 		ClientConfig {
 			queue: Default::default(),
 			blockchain: Default::default(),
-			prefer_journal: false,
+			pruning: journaldb::Algorithm::Archive,
+			history: 1000,
+			db_cache_size: None,
+			db_compaction: CompactionProfile::default(),
+			db_wal: true,
 			name: Default::default(),
+			sealing: SealingConfig::default(),
+			pow_cache_dir: None,
 		}
 	}
 }
@@ -123,6 +329,35 @@ impl fmt::Display for BlockChainInfo {
 	}
 }
 
+/// Merkle proof of a storage slot of an account.
+#[derive(Debug, PartialEq, Clone)]
+pub struct StorageProof {
+	/// Storage key.
+	pub key: H256,
+	/// Value stored under the key; zero if there is none.
+	pub value: H256,
+	/// RLP of the storage trie nodes from the account's storage root to the value.
+	pub proof: Vec<Bytes>,
+}
+
+/// Merkle proof of an account and some of its storage against a block's state root.
+/// The fields of an account which does not exist are those of an empty account.
+#[derive(Debug, PartialEq, Clone)]
+pub struct AccountProof {
+	/// Account balance.
+	pub balance: U256,
+	/// Account nonce.
+	pub nonce: U256,
+	/// Hash of the account code.
+	pub code_hash: H256,
+	/// Root of the account storage trie.
+	pub storage_root: H256,
+	/// RLP of the state trie nodes from the state root to the account.
+	pub proof: Vec<Bytes>,
+	/// Proofs of the requested storage slots.
+	pub storage: Vec<StorageProof>,
+}
+
 /// Blockchain database client. Owns and manages a blockchain and a block queue.
 pub trait BlockChainClient : Sync + Send {
 	/// Get raw block header data by block id.
@@ -141,8 +376,8 @@ pub trait BlockChainClient : Sync + Send {
 	/// Get block total difficulty.
 	fn block_total_difficulty(&self, id: BlockId) -> Option<U256>;
 
-	/// Get address nonce.
-	fn nonce(&self, address: &Address) -> U256;
+	/// Get address nonce. Returns `None` if the state could not be retrieved.
+	fn nonce(&self, address: &Address) -> Option<U256>;
 
 	/// Get block hash.
 	fn block_hash(&self, id: BlockId) -> Option<H256>;
@@ -163,6 +398,10 @@ pub trait BlockChainClient : Sync + Send {
 	/// Get raw block receipts data by block header hash.
 	fn block_receipts(&self, hash: &H256) -> Option<Bytes>;
 
+	/// Get `address` with a Merkle proof of it and of each of `storage_keys`, checked against the
+	/// state root of block `id`. Returns `None` if the block or its state is not available.
+	fn prove_account(&self, address: &Address, storage_keys: &[H256], id: BlockId) -> Option<AccountProof>;
+
 	/// Import a block into the blockchain.
 	fn import_block(&self, bytes: Bytes) -> ImportResult;
 
@@ -186,12 +425,36 @@ pub trait BlockChainClient : Sync + Send {
 	/// Returns logs matching given filter.
 	fn logs(&self, filter: Filter) -> Vec<LocalizedLogEntry>;
 
+	/// Returns logs of the block with given hash matching given filter. The block does not need to be
+	/// a part of the canon chain. Block range of the filter is ignored.
+	fn block_logs(&self, hash: &H256, filter: &Filter) -> Vec<LocalizedLogEntry>;
+
 	/// Grab the `ClosedBlock` that we want to be sealed. Comes as a mutex that you have to lock.
 	fn sealing_block(&self) -> &Mutex<Option<ClosedBlock>>;
 
 	/// Submit `seal` as a valid solution for the header of `pow_hash`.
 	/// Will check the seal, but not actually insert the block into the chain.
 	fn submit_seal(&self, pow_hash: H256, seal: Vec<Bytes>) -> Result<(), Error>;
+
+	/// Returns true if the engine seals blocks itself rather than relying on external miners.
+	fn seals_internally(&self) -> bool;
+
+	/// Returns true if a block is being prepared for external sealing.
+	fn is_sealing_enabled(&self) -> bool;
+
+	/// Seal a block with `transactions` on top of the best block and import it, if the engine
+	/// seals blocks itself and is able to do so now. Returns the hash of the imported block.
+	fn seal_transactions(&self, transactions: Vec<SignedTransaction>) -> Option<H256>;
+
+	/// Policy of authoring blocks.
+	fn sealing_config(&self) -> SealingConfig;
+
+	/// Set the pending transactions to include in the block prepared for external sealing, and
+	/// prepare it again if sealing is enabled.
+	fn set_transactions_to_seal(&self, transactions: Vec<SignedTransaction>);
+
+	/// Check that the chain `t` is signed for, if any, is accepted by the next block.
+	fn verify_transaction_chain_id(&self, t: &SignedTransaction) -> Result<(), Error>;
 }
 
 #[derive(Default, Clone, Debug, Eq, PartialEq)]
@@ -222,6 +485,7 @@ pub struct Client<V = CanonVerifier> where V: Verifier {
 	chain: Arc<RwLock<BlockChain>>,
 	engine: Arc<Box<Engine>>,
 	state_db: Mutex<JournalDB>,
+	history: u64,
 	block_queue: RwLock<BlockQueue>,
 	report: RwLock<ClientReport>,
 	import_lock: Mutex<()>,
@@ -232,40 +496,41 @@ pub struct Client<V = CanonVerifier> where V: Verifier {
 	sealing_block: Mutex<Option<ClosedBlock>>,
 	author: RwLock<Address>,
 	extra_data: RwLock<Bytes>,
+	sealing_config: SealingConfig,
+	transactions_to_seal: Mutex<Vec<SignedTransaction>>,
 	verifier: PhantomData<V>,
 	secret_store: Arc<RwLock<SecretStore>>,
+	message_channel: IoChannel<NetSyncMessage>,
 }
 
-const HISTORY: u64 = 1000;
 const CLIENT_DB_VER_STR: &'static str = "5.1";
 
 impl Client<CanonVerifier> {
 	/// Create a new client with given spec and DB path.
 	pub fn new(config: ClientConfig, spec: Spec, path: &Path, message_channel: IoChannel<NetSyncMessage> ) -> Result<Arc<Client>, Error> {
-		Client::<CanonVerifier>::new_with_verifier(config, spec, path, message_channel)
+		let dbs = try!(ClientDatabases::open(&config, &spec, path));
+		Client::<CanonVerifier>::new_with_verifier(config, spec, dbs, message_channel)
+	}
+
+	/// Create a new client with given spec keeping all data in memory.
+	pub fn new_in_memory(config: ClientConfig, spec: Spec, message_channel: IoChannel<NetSyncMessage> ) -> Result<Arc<Client>, Error> {
+		Client::<CanonVerifier>::new_with_verifier(config, spec, ClientDatabases::in_memory(), message_channel)
 	}
 }
 
 impl<V> Client<V> where V: Verifier {
-	///  Create a new client with given spec and DB path and custom verifier.
-	pub fn new_with_verifier(config: ClientConfig, spec: Spec, path: &Path, message_channel: IoChannel<NetSyncMessage> ) -> Result<Arc<Client<V>>, Error> {
-		let mut dir = path.to_path_buf();
-		dir.push(H64::from(spec.genesis_header().hash()).hex());
-		//TODO: sec/fat: pruned/full versioning
-		dir.push(format!("v{}-sec-{}", CLIENT_DB_VER_STR, if config.prefer_journal { "pruned" } else { "archive" }));
-		let path = dir.as_path();
+	///  Create a new client with given spec, databases and custom verifier.
+	pub fn new_with_verifier(config: ClientConfig, spec: Spec, dbs: ClientDatabases, message_channel: IoChannel<NetSyncMessage> ) -> Result<Arc<Client<V>>, Error> {
 		let gb = spec.genesis_block();
-		let chain = Arc::new(RwLock::new(BlockChain::new(config.blockchain, &gb, path)));
-		let mut state_path = path.to_path_buf();
-		state_path.push("state");
+		let chain = Arc::new(RwLock::new(BlockChain::new_with_db(config.blockchain, &gb, dbs.extras, dbs.blocks)));
 
-		let engine = Arc::new(try!(spec.to_engine()));
-		let mut state_db = JournalDB::from_prefs(state_path.to_str().unwrap(), config.prefer_journal);
+		let engine = Arc::new(try!(spec.to_engine_with_cache_dir(config.pow_cache_dir.as_ref().map(PathBuf::as_path))));
+		let mut state_db = try!(JournalDB::new_with_db(dbs.state, config.pruning));
 		if state_db.is_empty() && engine.spec().ensure_db_good(&mut state_db) {
 			state_db.commit(0, &engine.spec().genesis_header().hash(), None).expect("Error commiting genesis state to state DB");
 		}
 
-		let block_queue = BlockQueue::new(config.queue, engine.clone(), message_channel);
+		let block_queue = BlockQueue::new(config.queue, engine.clone(), message_channel.clone());
 		let panic_handler = PanicHandler::new_in_arc();
 		panic_handler.forward_from(&block_queue);
 
@@ -276,6 +541,7 @@ impl<V> Client<V> where V: Verifier {
 			chain: chain,
 			engine: engine,
 			state_db: Mutex::new(state_db),
+			history: config.history,
 			block_queue: RwLock::new(block_queue),
 			report: RwLock::new(Default::default()),
 			import_lock: Mutex::new(()),
@@ -284,8 +550,11 @@ impl<V> Client<V> where V: Verifier {
 			sealing_block: Mutex::new(None),
 			author: RwLock::new(Address::new()),
 			extra_data: RwLock::new(Vec::new()),
+			sealing_config: config.sealing,
+			transactions_to_seal: Mutex::new(Vec::new()),
 			verifier: PhantomData,
 			secret_store: secret_store,
+			message_channel: message_channel,
 		}))
 	}
 
@@ -321,7 +590,7 @@ impl<V> Client<V> where V: Verifier {
 
 		// Check the block isn't so old we won't be able to enact it.
 		let best_block_number = self.chain.read().unwrap().best_block_number();
-		if best_block_number >= HISTORY && header.number() <= best_block_number - HISTORY {
+		if best_block_number >= self.history && header.number() <= best_block_number - self.history {
 			warn!(target: "client", "Block import failed for #{} ({})\nBlock is ancient (current best block: #{}).", header.number(), header.hash(), best_block_number);
 			return Err(());
 		}
@@ -372,6 +641,7 @@ impl<V> Client<V> where V: Verifier {
 		let blocks = self.block_queue.write().unwrap().drain(max_blocks_to_import);
 
 		let original_best = self.chain_info().best_block_hash;
+		let mut route = ImportRoute::none();
 
 		for block in blocks {
 			let header = &block.header;
@@ -388,8 +658,8 @@ impl<V> Client<V> where V: Verifier {
 			good_blocks.push(header.hash());
 
 			// Are we committing an era?
-			let ancient = if header.number() >= HISTORY {
-				let n = header.number() - HISTORY;
+			let ancient = if header.number() >= self.history {
+				let n = header.number() - self.history;
 				let chain = self.chain.read().unwrap();
 				Some((n, chain.block_hash(n).unwrap()))
 			} else {
@@ -405,8 +675,9 @@ impl<V> Client<V> where V: Verifier {
 
 			// And update the chain after commit to prevent race conditions
 			// (when something is in chain but you are not able to fetch details)
-			self.chain.write().unwrap()
+			let block_route = self.chain.write().unwrap()
 				.insert_block(&block.bytes, receipts);
+			Self::merge_import_route(&mut route, block_route);
 
 			self.report.write().unwrap().accrue_block(&block);
 			trace!(target: "client", "Imported #{} ({})", header.number(), header.hash());
@@ -431,8 +702,8 @@ impl<V> Client<V> where V: Verifier {
 				io.send(NetworkIoMessage::User(SyncMessage::NewChainBlocks {
 					good: good_blocks,
 					bad: bad_blocks,
-					// TODO [todr] were to take those from?
-					retracted: vec![],
+					retracted: route.retracted,
+					enacted: route.enacted,
 				})).unwrap();
 			}
 		}
@@ -444,11 +715,115 @@ impl<V> Client<V> where V: Verifier {
 		imported
 	}
 
+	/// Folds the route of a single imported block into the route of the whole import round,
+	/// so that blocks which were both enacted and retracted within the round cancel out.
+	fn merge_import_route(route: &mut ImportRoute, block_route: ImportRoute) {
+		for hash in block_route.retracted {
+			match route.enacted.iter().position(|h| *h == hash) {
+				Some(index) => { route.enacted.remove(index); },
+				None => route.retracted.push(hash),
+			}
+		}
+		for hash in block_route.enacted {
+			if let Some(index) = route.retracted.iter().position(|h| *h == hash) {
+				route.retracted.remove(index);
+			}
+			route.enacted.push(hash);
+		}
+	}
+
+	fn block_logs_at(chain: &BlockChain, number: BlockNumber, hash: H256, filter: &Filter) -> Vec<LocalizedLogEntry> {
+		let receipts = match chain.block_receipts(&hash) {
+			Some(r) => r.receipts,
+			None => return vec![],
+		};
+		let hashes = match chain.block(&hash) {
+			Some(ref b) => BlockView::new(b).transaction_hashes(),
+			None => return vec![],
+		};
+		localized_block_logs(number, hash, receipts, hashes, filter)
+	}
+
 	/// Get a copy of the best block's state.
 	pub fn state(&self) -> State {
 		State::from_existing(self.state_db.lock().unwrap().clone(), HeaderView::new(&self.best_block_header()).state_root(), self.engine.account_start_nonce())
 	}
 
+	/// Get a copy of the state at block `id`, if it has not been pruned.
+	pub fn state_at(&self, id: BlockId) -> Option<State> {
+		let root = match self.block_header(id) {
+			Some(header) => HeaderView::new(&header).state_root(),
+			None => return None,
+		};
+		let state_db = self.state_db.lock().unwrap().clone();
+		match state_db.exists(&root) {
+			true => Some(State::from_existing(state_db, root, self.engine.account_start_nonce())),
+			false => None,
+		}
+	}
+
+	/// Walk the canonical chain checking block details, transaction addresses and, for blocks
+	/// whose state has not been pruned, presence of the state root. `progress` is called with
+	/// every checked block number.
+	pub fn verify_integrity<F>(&self, mut progress: F) -> Vec<IntegrityError> where F: FnMut(BlockNumber) {
+		let mut errors = Vec::new();
+		let chain = self.chain.read().unwrap();
+		let state_db = self.state_db.lock().unwrap().clone();
+		let best = chain.best_block_number();
+		let first_with_state = match state_db.algorithm() {
+			journaldb::Algorithm::Archive => 0,
+			_ => if best >= self.history { best - self.history + 1 } else { 0 },
+		};
+
+		let mut parent: Option<H256> = None;
+		for number in 0..best + 1 {
+			progress(number);
+			let hash = match chain.block_hash(number) {
+				Some(hash) => hash,
+				None => {
+					errors.push(IntegrityError::MissingBlockHash(number));
+					parent = None;
+					continue;
+				}
+			};
+
+			match chain.block_details(&hash) {
+				Some(details) => {
+					if details.number != number {
+						errors.push(IntegrityError::WrongNumber(hash.clone()));
+					}
+					if let Some(ref parent) = parent {
+						if details.parent != *parent {
+							errors.push(IntegrityError::WrongParent(hash.clone()));
+						} else if !chain.block_details(parent).map_or(false, |d| d.children.contains(&hash)) {
+							errors.push(IntegrityError::NotAChild(hash.clone()));
+						}
+					}
+				},
+				None => errors.push(IntegrityError::MissingDetails(hash.clone())),
+			}
+
+			match chain.try_block(&hash) {
+				Ok(bytes) => {
+					let view = BlockView::new(&bytes);
+					for (index, tx_hash) in view.transaction_hashes().into_iter().enumerate() {
+						match chain.transaction_address(&tx_hash) {
+							Some(ref address) if address.block_hash == hash && address.index == index => {},
+							_ => errors.push(IntegrityError::WrongTransactionAddress(tx_hash)),
+						}
+					}
+					if number >= first_with_state && !state_db.exists(&view.header_view().state_root()) {
+						errors.push(IntegrityError::MissingState(hash.clone()));
+					}
+				},
+				Err(BlockDataError::Pruned) => {},
+				Err(BlockDataError::Unknown) => errors.push(IntegrityError::MissingBlock(hash.clone())),
+			}
+			parent = Some(hash);
+		}
+		errors
+	}
+
 	/// Get info on the cache.
 	pub fn blockchain_cache_info(&self) -> BlockChainCacheSize {
 		self.chain.read().unwrap().cache_size()
@@ -463,10 +838,14 @@ impl<V> Client<V> where V: Verifier {
 
 	/// Tick the client.
 	pub fn tick(&self) {
-		self.chain.read().unwrap().collect_garbage();
 		self.block_queue.read().unwrap().collect_garbage();
 	}
 
+	/// Evict least recently used entries from the blockchain caches which are over budget.
+	pub fn collect_garbage(&self) {
+		self.chain.read().unwrap().collect_garbage();
+	}
+
 	/// Set up the cache behaviour.
 	pub fn configure_cache(&self, pref_cache_size: usize, max_cache_size: usize) {
 		self.chain.write().unwrap().configure_cache(pref_cache_size, max_cache_size);
@@ -481,6 +860,18 @@ impl<V> Client<V> where V: Verifier {
 		}
 	}
 
+	/// Sorted numbers of the blocks in `from..to` whose blooms may contain logs matching `filter`.
+	fn bloom_blocks(chain: &BlockChain, filter: &Filter, from: BlockNumber, to: BlockNumber) -> Vec<BlockNumber> {
+		let mut blocks = filter.bloom_possibilities().iter()
+			.flat_map(|bloom| chain.blocks_with_bloom(bloom, from, to))
+			// remove duplicate elements
+			.collect::<HashSet<u64>>()
+			.into_iter()
+			.collect::<Vec<u64>>();
+		blocks.sort();
+		blocks
+	}
+
 	fn block_number(&self, id: BlockId) -> Option<BlockNumber> {
 		match id {
 			BlockId::Number(number) => Some(number),
@@ -512,23 +903,82 @@ impl<V> Client<V> where V: Verifier {
 
 	/// New chain head event. Restart mining operation.
 	pub fn prepare_sealing(&self) {
+		let mut b = match self.open_sealing_block() {
+			Some(b) => b,
+			None => return,
+		};
+
+		let transactions = self.transactions_to_seal.lock().unwrap().clone();
+		self.push_transactions(&mut b, transactions);
+
+		let b = b.close();
+		trace!("Sealing: number={}, hash={}, diff={}", b.hash(), b.block().header().difficulty(), b.block().header().number());
+		*self.sealing_block.lock().unwrap() = Some(b);
+	}
+
+	/// Open a new block on top of the best block, with uncles but no transactions.
+	fn open_sealing_block(&self) -> Option<OpenBlock> {
 		let h = self.chain.read().unwrap().best_block_hash();
 		let mut b = OpenBlock::new(
 			self.engine.deref().deref(),
 			self.state_db.lock().unwrap().clone(),
-			match self.chain.read().unwrap().block_header(&h) { Some(ref x) => x, None => {return;} },
+			match self.chain.read().unwrap().block_header(&h) { Some(ref x) => x, None => { return None; } },
 			self.build_last_hashes(h.clone()),
 			self.author(),
+			(self.sealing_config.gas_floor_target, self.sealing_config.gas_ceil_target),
 			self.extra_data()
 		);
 
-		self.chain.read().unwrap().find_uncle_headers(&h, self.engine.deref().deref().maximum_uncle_age()).unwrap().into_iter().take(self.engine.deref().deref().maximum_uncle_count()).foreach(|h| { b.push_uncle(h).unwrap(); });
+		let max_uncles = self.sealing_config.uncles.limit(self.engine.deref().deref().maximum_uncle_count());
+		self.chain.read().unwrap().find_uncle_headers(&h, self.engine.deref().deref().maximum_uncle_age()).unwrap().into_iter().take(max_uncles).foreach(|h| { b.push_uncle(h).unwrap(); });
+		Some(b)
+	}
 
-		// TODO: push transactions.
+	/// Push those of `transactions` the sealing policy admits into `b`. Transactions sent from an account
+	/// in the secret store count as local.
+	fn push_transactions(&self, b: &mut OpenBlock, transactions: Vec<SignedTransaction>) {
+		let secret_store = self.secret_store.read().unwrap();
+		for t in transactions {
+			let hash = t.hash();
+			let local = t.sender().map(|sender| secret_store.account(&sender).is_some()).unwrap_or(false);
+			let env_info = b.env_info();
+			if !self.sealing_config.admits(&t, local, &env_info.gas_used, &env_info.gas_limit) {
+				trace!(target: "client", "Transaction {} not admitted by sealing policy", hash);
+				continue;
+			}
+			if let Err(e) = self.engine.verify_transaction_chain_id(&t, b.header()) {
+				debug!(target: "client", "Transaction {} left out of sealed block: {:?}", hash, e);
+				continue;
+			}
+			if let Err(e) = b.push_transaction(t, None) {
+				debug!(target: "client", "Transaction {} left out of sealed block: {:?}", hash, e);
+			}
+		}
+	}
 
-		let b = b.close();
-		trace!("Sealing: number={}, hash={}, diff={}", b.hash(), b.block().header().difficulty(), b.block().header().number());
-		*self.sealing_block.lock().unwrap() = Some(b);
+	/// Seal and import a new block if the engine seals blocks itself and may do so now.
+	pub fn update_sealing(&self) {
+		let engine = self.engine.deref().deref();
+		if !engine.seals_internally() {
+			return;
+		}
+		self.prepare_sealing();
+		let sealed = {
+			let mut maybe_b = self.sealing_block.lock().unwrap();
+			let seal = match *maybe_b {
+				Some(ref b) => engine.generate_seal(b.block(), self.secret_store.read().unwrap().deref()),
+				None => None,
+			};
+			match seal {
+				Some(seal) => maybe_b.take().unwrap().try_seal(engine, seal).ok(),
+				None => None,
+			}
+		};
+		if let Some(sealed) = sealed {
+			if let Err(e) = self.import_block(sealed.rlp_bytes()) {
+				warn!(target: "client", "Failed to import sealed block: {:?}", e);
+			}
+		}
 	}
 }
 
@@ -537,7 +987,7 @@ impl<V> Client<V> where V: Verifier {
 impl<V> BlockChainClient for Client<V> where V: Verifier {
 	fn block_header(&self, id: BlockId) -> Option<Bytes> {
 		let chain = self.chain.read().unwrap();
-		Self::block_hash(&chain, id).and_then(|hash| chain.block(&hash).map(|bytes| BlockView::new(&bytes).rlp().at(0).as_raw().to_vec()))
+		Self::block_hash(&chain, id).and_then(|hash| chain.block_header(&hash).map(|header| header.rlp(Seal::With)))
 	}
 
 	fn block_body(&self, id: BlockId) -> Option<Bytes> {
@@ -574,8 +1024,8 @@ impl<V> BlockChainClient for Client<V> where V: Verifier {
 		Self::block_hash(&chain, id).and_then(|hash| chain.block_details(&hash)).map(|d| d.total_difficulty)
 	}
 
-	fn nonce(&self, address: &Address) -> U256 {
-		self.state().nonce(address)
+	fn nonce(&self, address: &Address) -> Option<U256> {
+		Some(self.state().nonce(address))
 	}
 
 	fn block_hash(&self, id: BlockId) -> Option<H256> {
@@ -614,6 +1064,24 @@ impl<V> BlockChainClient for Client<V> where V: Verifier {
 		None
 	}
 
+	fn prove_account(&self, address: &Address, storage_keys: &[H256], id: BlockId) -> Option<AccountProof> {
+		self.state_at(id).map(|state| {
+			let (account, proof) = state.prove_account(address);
+			let storage = storage_keys.iter().map(|key| {
+				let (value, proof) = state.prove_storage(address, key);
+				StorageProof { key: key.clone(), value: value, proof: proof }
+			}).collect();
+			AccountProof {
+				balance: account.as_ref().map_or(U256::zero(), |a| a.balance().clone()),
+				nonce: account.as_ref().map_or(U256::zero(), |a| a.nonce().clone()),
+				code_hash: account.as_ref().map_or(SHA3_EMPTY, |a| a.code_hash()),
+				storage_root: account.as_ref().and_then(|a| a.storage_root().cloned()).unwrap_or(SHA3_NULL_RLP),
+				proof: proof,
+				storage: storage,
+			}
+		})
+	}
+
 	fn import_block(&self, bytes: Bytes) -> ImportResult {
 		{
 			let header = BlockView::new(&bytes).header_view();
@@ -654,43 +1122,39 @@ impl<V> BlockChainClient for Client<V> where V: Verifier {
 	}
 
 	fn logs(&self, filter: Filter) -> Vec<LocalizedLogEntry> {
-		let mut blocks = filter.bloom_possibilities().iter()
-			.filter_map(|bloom| self.blocks_with_bloom(bloom, filter.from_block.clone(), filter.to_block.clone()))
-			.flat_map(|m| m)
-			// remove duplicate elements
-			.collect::<HashSet<u64>>()
-			.into_iter()
-			.collect::<Vec<u64>>();
+		let (from, to) = match (self.block_number(filter.from_block.clone()), self.block_number(filter.to_block.clone())) {
+			(Some(from), Some(to)) => (from, to),
+			_ => return vec![],
+		};
 
-		blocks.sort();
+		let chain = self.chain.read().unwrap();
+		let blocks = match chain.indexed_log_blocks(&filter, from, to) {
+			// blocks imported before the index was enabled still go through the blooms
+			Some((index_from, indexed)) => {
+				let mut blocks = if from < index_from {
+					Self::bloom_blocks(&chain, &filter, from, cmp::min(to, index_from - 1))
+				} else {
+					vec![]
+				};
+				blocks.extend(indexed);
+				blocks
+			},
+			None => Self::bloom_blocks(&chain, &filter, from, to),
+		};
 
-		blocks.into_iter()
-			.filter_map(|number| self.chain.read().unwrap().block_hash(number).map(|hash| (number, hash)))
-			.filter_map(|(number, hash)| self.chain.read().unwrap().block_receipts(&hash).map(|r| (number, hash, r.receipts)))
-			.filter_map(|(number, hash, receipts)| self.chain.read().unwrap().block(&hash).map(|ref b| (number, hash, receipts, BlockView::new(b).transaction_hashes())))
-			.flat_map(|(number, hash, receipts, hashes)| {
-				let mut log_index = 0;
-				receipts.into_iter()
-					.enumerate()
-					.flat_map(|(index, receipt)| {
-						log_index += receipt.logs.len();
-						receipt.logs.into_iter()
-							.enumerate()
-							.filter(|tuple| filter.matches(&tuple.1))
-						 	.map(|(i, log)| LocalizedLogEntry {
-							 	entry: log,
-								block_hash: hash.clone(),
-								block_number: number as usize,
-								transaction_hash: hashes.get(index).cloned().unwrap_or_else(H256::new),
-								transaction_index: index,
-								log_index: log_index + i
-							})
-							.collect::<Vec<LocalizedLogEntry>>()
-					})
-					.collect::<Vec<LocalizedLogEntry>>()
+		let logs = blocks.into_iter()
+			.filter_map(|number| chain.block_hash(number).map(|hash| (number, hash)))
+			.flat_map(|(number, hash)| Self::block_logs_at(&chain, number, hash, &filter))
+			.collect();
+		filter.apply_limit(logs)
+	}
 
-			})
-			.collect()
+	fn block_logs(&self, hash: &H256, filter: &Filter) -> Vec<LocalizedLogEntry> {
+		let chain = self.chain.read().unwrap();
+		match chain.block_details(hash) {
+			Some(details) => Self::block_logs_at(&chain, details.number, hash.clone(), filter),
+			None => vec![],
+		}
 	}
 
 	/// Grab the `ClosedBlock` that we want to be sealed. Comes as a mutex that you have to lock.
@@ -725,6 +1189,64 @@ impl<V> BlockChainClient for Client<V> where V: Verifier {
 			}
 		}
 	}
+
+	fn seals_internally(&self) -> bool {
+		self.engine.seals_internally()
+	}
+
+	fn sealing_config(&self) -> SealingConfig {
+		self.sealing_config.clone()
+	}
+
+	fn is_sealing_enabled(&self) -> bool {
+		self.sealing_enabled.load(atomic::Ordering::Relaxed)
+	}
+
+	fn set_transactions_to_seal(&self, transactions: Vec<SignedTransaction>) {
+		*self.transactions_to_seal.lock().unwrap() = transactions;
+		if self.sealing_enabled.load(atomic::Ordering::Relaxed) {
+			self.prepare_sealing();
+		}
+	}
+
+	fn verify_transaction_chain_id(&self, t: &SignedTransaction) -> Result<(), Error> {
+		let mut header = Header::new();
+		header.set_number(self.chain.read().unwrap().best_block_number() + 1);
+		self.engine.verify_transaction_chain_id(t, &header)
+	}
+
+	fn seal_transactions(&self, transactions: Vec<SignedTransaction>) -> Option<H256> {
+		let engine = self.engine.deref().deref();
+		if !engine.seals_internally() {
+			return None;
+		}
+		let mut b = match self.open_sealing_block() {
+			Some(b) => b,
+			None => return None,
+		};
+		self.push_transactions(&mut b, transactions);
+		let b = b.close();
+		let seal = match engine.generate_seal(b.block(), self.secret_store.read().unwrap().deref()) {
+			Some(seal) => seal,
+			None => return None,
+		};
+		let sealed = match b.try_seal(engine, seal) {
+			Ok(sealed) => sealed,
+			Err(_) => return None,
+		};
+		match self.import_block(sealed.rlp_bytes()) {
+			Ok(hash) => {
+				// import right away so that the next block is sealed on top of this one.
+				self.flush_queue();
+				self.import_verified_blocks(&self.message_channel);
+				Some(hash)
+			},
+			Err(e) => {
+				warn!(target: "client", "Failed to import sealed block: {:?}", e);
+				None
+			}
+		}
+	}
 }
 
 impl MayPanic for Client {
@@ -732,3 +1254,56 @@ impl MayPanic for Client {
 		self.panic_handler.on_panic(closure);
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use util::*;
+	use devtools::RandomTempPath;
+	use blockchain::ImportRoute;
+	use spec::Spec;
+	use super::{Client, ClientConfig, ClientDatabases};
+
+	fn route(retracted: &[u64], enacted: &[u64]) -> ImportRoute {
+		ImportRoute {
+			retracted: retracted.iter().map(|&n| H256::from(n)).collect(),
+			enacted: enacted.iter().map(|&n| H256::from(n)).collect(),
+		}
+	}
+
+	#[test]
+	fn merged_import_route_cancels_out_blocks_retracted_within_the_round() {
+		// 1 and 2 are imported on top of 0, then a fork from before 0 with 5 and 3 takes over, then 4 follows.
+		let mut merged = ImportRoute::none();
+		<Client>::merge_import_route(&mut merged, route(&[], &[1]));
+		<Client>::merge_import_route(&mut merged, route(&[], &[2]));
+		<Client>::merge_import_route(&mut merged, route(&[2, 1, 0], &[5, 3]));
+		<Client>::merge_import_route(&mut merged, route(&[], &[4]));
+		assert_eq!(merged, route(&[0], &[5, 3, 4]));
+	}
+
+	#[test]
+	fn merged_import_route_keeps_blocks_reenacted_within_the_round() {
+		let mut merged = ImportRoute::none();
+		<Client>::merge_import_route(&mut merged, route(&[1], &[2]));
+		<Client>::merge_import_route(&mut merged, route(&[2], &[1]));
+		assert_eq!(merged, route(&[], &[1]));
+	}
+
+	#[test]
+	fn should_refuse_databases_of_another_pruning_algorithm() {
+		let temp = RandomTempPath::new();
+		let spec = Spec::new_test();
+		let mut config = ClientConfig::default();
+		config.pruning = journaldb::Algorithm::Archive;
+		ClientDatabases::open(&config, &spec, temp.as_path()).unwrap();
+
+		config.pruning = journaldb::Algorithm::OverlayRecent;
+		match ClientDatabases::open(&config, &spec, temp.as_path()) {
+			Err(e) => assert!(format!("{:?}", e).contains("created with `--pruning archive`")),
+			Ok(_) => panic!("databases of another pruning algorithm opened"),
+		}
+
+		config.pruning = journaldb::Algorithm::Archive;
+		assert!(ClientDatabases::open(&config, &spec, temp.as_path()).is_ok());
+	}
+}
@@ -19,6 +19,9 @@ use block::ExecutedBlock;
 use spec::Spec;
 use evm::Schedule;
 use evm::Factory;
+use util::keys::store::SecretStore;
+use transitions::{RuleSet, ScheduleVariant};
+use sealing::target_gas_limit;
 
 /// A consensus mechanism for the chain. Generally either proof-of-work or proof-of-stake-based.
 /// Provides hooks into each of the major parts of block import.
@@ -40,8 +43,16 @@ pub trait Engine : Sync + Send {
 	/// Get current EVM factory
 	fn vm_factory(&self) -> &Factory;
 
-	/// Get the EVM schedule for the given `env_info`.
-	fn schedule(&self, env_info: &EnvInfo) -> Schedule;
+	/// Get the EVM schedule for the given `env_info`; by default the one the spec's rules set for its block.
+	fn schedule(&self, env_info: &EnvInfo) -> Schedule {
+		match self.rules(env_info.number).schedule {
+			ScheduleVariant::Frontier => Schedule::new_frontier(),
+			ScheduleVariant::Homestead => Schedule::new_homestead(),
+		}
+	}
+
+	/// The rules in force at block `number`, as set by the spec's transitions.
+	fn rules(&self, number: BlockNumber) -> &RuleSet { self.spec().transitions.rules(number) }
 
 	/// Some intrinsic operation parameters; by default they take their value from the `spec()`'s `engine_params`.
 	fn maximum_extra_data_size(&self) -> usize { decode(&self.spec().engine_params.get("maximumExtraDataSize").unwrap()) }
@@ -57,6 +68,12 @@ pub trait Engine : Sync + Send {
 	/// Block transformation functions, after the transactions.
 	fn on_close_block(&self, _block: &mut ExecutedBlock) {}
 
+	/// Whether blocks are sealed by the engine itself rather than by external miners.
+	fn seals_internally(&self) -> bool { false }
+	/// Attempt to seal the closed `block` using keys from `accounts`. Returns `None` if the engine
+	/// doesn't seal internally or the block can't be sealed right now.
+	fn generate_seal(&self, _block: &ExecutedBlock, _accounts: &SecretStore) -> Option<Vec<Bytes>> { None }
+
 	/// Phase 1 quick block verification. Only does checks that are cheap. `block` (the header's full block) 
 	/// may be provided for additional checks. Returns either a null `Ok` or a general error detailing the problem with import.
 	fn verify_block_basic(&self, _header: &Header,  _block: Option<&[u8]>) -> Result<(), Error> { Ok(()) }
@@ -73,6 +90,15 @@ pub trait Engine : Sync + Send {
 	// TODO: Add flags for which bits of the transaction to check.
 	// TODO: consider including State in the params.
 	fn verify_transaction_basic(&self, _t: &SignedTransaction, _header: &Header) -> Result<(), Error> { Ok(()) }
+	/// Verify a chain-bound transaction signature names our chain and is allowed at this block.
+	/// Signatures which aren't bound to a chain are always accepted.
+	fn verify_transaction_chain_id(&self, t: &SignedTransaction, header: &Header) -> Result<(), Error> {
+		match t.chain_id() {
+			Some(id) if !self.rules(header.number()).replay_protection || self.spec().chain_id != Some(id) =>
+				Err(From::from(TransactionError::InvalidChainId(id))),
+			_ => Ok(()),
+		}
+	}
 	/// Verify a particular transaction is valid.
 	fn verify_transaction(&self, _t: &SignedTransaction, _header: &Header) -> Result<(), Error> { Ok(()) }
 
@@ -84,23 +110,32 @@ pub trait Engine : Sync + Send {
 	}
 
 	/// Don't forget to call Super::populate_from_parent when subclassing & overriding.
+	/// The gas limit moves towards the range between `gas_floor_target` and `gas_ceil_target` if the spec
+	/// has a `gasLimitBoundDivisor`, otherwise the parent's is kept.
 	// TODO: consider including State in the params.
-	fn populate_from_parent(&self, header: &mut Header, parent: &Header) {
+	fn populate_from_parent(&self, header: &mut Header, parent: &Header, gas_floor_target: U256, gas_ceil_target: U256) {
 		header.difficulty = parent.difficulty;
-		header.gas_limit = parent.gas_limit;
+		header.gas_limit = match self.spec().engine_params.get("gasLimitBoundDivisor") {
+			Some(bound_divisor) => target_gas_limit(parent, &decode(bound_divisor), &gas_floor_target, &gas_ceil_target),
+			None => parent.gas_limit,
+		};
 		header.note_dirty();
 	}
 
 	// TODO: builtin contract routing - to do this properly, it will require removing the built-in configuration-reading logic
 	// from Spec into here and removing the Spec::builtins field.
-	/// Determine whether a particular address is a builtin contract.
-	fn is_builtin(&self, a: &Address) -> bool { self.spec().builtins.contains_key(a) }
+	/// Determine whether a particular address is a builtin contract active at block `number`.
+	fn is_builtin(&self, a: &Address, number: BlockNumber) -> bool {
+		self.spec().builtins.get(a).map_or(false, |b| b.is_active(number))
+	}
 	/// Determine the code execution cost of the builtin contract with address `a`.
-	/// Panics if `is_builtin(a)` is not true.
-	fn cost_of_builtin(&self, a: &Address, input: &[u8]) -> U256 { self.spec().builtins.get(a).unwrap().cost(input.len()) }
-	/// Execution the builtin contract `a` on `input` and return `output`.
-	/// Panics if `is_builtin(a)` is not true.
-	fn execute_builtin(&self, a: &Address, input: &[u8], output: &mut [u8]) { self.spec().builtins.get(a).unwrap().execute(input, output); }
+	/// Panics if `a` is not a builtin.
+	fn cost_of_builtin(&self, a: &Address, input: &[u8]) -> U256 { self.spec().builtins.get(a).unwrap().cost(input) }
+	/// Execution the builtin contract `a` on `input` and return `output`. Fails if the input is malformed.
+	/// Panics if `a` is not a builtin.
+	fn execute_builtin(&self, a: &Address, input: &[u8], output: &mut [u8]) -> Result<(), BuiltinError> {
+		self.spec().builtins.get(a).unwrap().execute(input, output)
+	}
 
 	// TODO: sealing stuff - though might want to leave this for later.
 }
@@ -62,11 +62,42 @@ pub enum ExecutionError {
 	Internal
 }
 
+#[derive(Debug, PartialEq, Eq)]
+/// Errors concerning chain specifications. Paths name the offending field, e.g. `genesis.gasLimit`.
+pub enum SpecError {
+	/// The specification is not valid UTF-8 JSON.
+	InvalidJson(String),
+	/// A required field is missing.
+	MissingField(String),
+	/// A field's value is of the wrong type or out of range.
+	InvalidValue {
+		/// Path of the field.
+		path: String,
+		/// Description of a valid value.
+		expected: &'static str,
+	},
+	/// The engine named by `engineName` is not known.
+	UnknownEngine(String),
+}
+
+impl fmt::Display for SpecError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			SpecError::InvalidJson(ref e) => write!(f, "Invalid JSON: {}", e),
+			SpecError::MissingField(ref path) => write!(f, "Missing field `{}`", path),
+			SpecError::InvalidValue { ref path, expected } => write!(f, "Invalid value of `{}`; expected {}", path, expected),
+			SpecError::UnknownEngine(ref name) => write!(f, "Unknown engine `{}`", name),
+		}
+	}
+}
+
 #[derive(Debug)]
 /// Errors concerning transaction proessing.
 pub enum TransactionError {
 	/// Transaction's gas limit (aka gas) is invalid.
 	InvalidGasLimit(OutOfBounds<U256>),
+	/// Transaction is signed for a chain other than ours, or before chain-bound signatures are accepted.
+	InvalidChainId(u64),
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -126,6 +157,10 @@ pub enum BlockError {
 	UnknownParent(H256),
 	/// Uncle parent given is unknown.
 	UnknownUncleParent(H256),
+	/// Block was signed by an account which is not the proposer for its step.
+	NotAuthorized(Address),
+	/// Step of the seal is not after the parent's step or is too far in the future.
+	InvalidStep(OutOfBounds<u64>),
 }
 
 #[derive(Debug)]
@@ -148,6 +183,8 @@ pub enum Error {
 	Block(BlockError),
 	/// Unknown engine given.
 	UnknownEngineName(String),
+	/// Error concerning a chain specification.
+	Spec(SpecError),
 	/// Error concerning EVM code execution.
 	Execution(ExecutionError),
 	/// Error concerning transaction processing.
@@ -169,6 +206,12 @@ impl From<TransactionError> for Error {
 	}
 }
 
+impl From<SpecError> for Error {
+	fn from(err: SpecError) -> Error {
+		Error::Spec(err)
+	}
+}
+
 impl From<ImportError> for Error {
 	fn from(err: ImportError) -> Error {
 		Error::Import(err)
@@ -16,13 +16,14 @@
 
 extern crate ethash;
 
-use self::ethash::{quick_get_difficulty, EthashManager, H256 as EH256};
+use self::ethash::{quick_get_difficulty, default_cache_dir, EthashManager, H256 as EH256};
 use common::*;
 use block::*;
 use spec::*;
 use engine::*;
-use evm::Schedule;
+use transitions::{DifficultyRule, TransactionRules};
 use evm::Factory;
+use sealing::target_gas_limit;
 
 /// Engine using Ethash proof-of-work consensus algorithm, suitable for Ethereum
 /// mainnet chains in the Olympic, Frontier and Homestead eras.
@@ -35,11 +36,12 @@ pub struct Ethash {
 }
 
 impl Ethash {
-	/// Create a new boxed instance of Ethash engine
-	pub fn new_boxed(spec: Spec) -> Box<Engine> {
+	/// Create a new boxed instance of Ethash engine keeping light caches and datasets in `cache_dir`,
+	/// or `~/.ethash` if `None`.
+	pub fn new_boxed(spec: Spec, cache_dir: Option<&Path>) -> Box<Engine> {
 		Box::new(Ethash {
 			spec: spec,
-			pow: EthashManager::new(),
+			pow: EthashManager::new(&cache_dir.map_or_else(default_cache_dir, Path::to_path_buf)),
 			// TODO [todr] should this return any specific factory?
 			factory: Factory::default(),
 			u64_params: RwLock::new(HashMap::new()),
@@ -51,7 +53,7 @@ impl Ethash {
 	fn new_test(spec: Spec) -> Ethash {
 		Ethash {
 			spec: spec,
-			pow: EthashManager::new(),
+			pow: EthashManager::new(&default_cache_dir()),
 			factory: Factory::default(),
 			u64_params: RwLock::new(HashMap::new()),
 			u256_params: RwLock::new(HashMap::new())
@@ -83,27 +85,9 @@ impl Engine for Ethash {
 		&self.factory
 	}
 
-	fn schedule(&self, env_info: &EnvInfo) -> Schedule {
-		trace!(target: "client", "Creating schedule. param={:?}, fCML={}", self.spec().engine_params.get("frontierCompatibilityModeLimit"), self.u64_param("frontierCompatibilityModeLimit"));
-		if env_info.number < self.u64_param("frontierCompatibilityModeLimit") {
-			Schedule::new_frontier()
-		} else {
-			Schedule::new_homestead()
-		}
-	}
-
-	fn populate_from_parent(&self, header: &mut Header, parent: &Header) {
+	fn populate_from_parent(&self, header: &mut Header, parent: &Header, gas_floor_target: U256, gas_ceil_target: U256) {
 		header.difficulty = self.calculate_difficuty(header, parent);
-		header.gas_limit = {
-			let gas_floor_target: U256 = x!(3141562);
-			let gas_limit = parent.gas_limit;
-			let bound_divisor = self.u256_param("gasLimitBoundDivisor");
-			if gas_limit < gas_floor_target {
-				min(gas_floor_target, gas_limit + gas_limit / bound_divisor - x!(1))
-			} else {
-				max(gas_floor_target, gas_limit - gas_limit / bound_divisor + x!(1) + (header.gas_used * x!(6) / x!(5)) / bound_divisor)
-			}
-		};
+		header.gas_limit = target_gas_limit(parent, &self.u256_param("gasLimitBoundDivisor"), &gas_floor_target, &gas_ceil_target);
 		header.note_dirty();
 //		info!("ethash: populate_from_parent #{}: difficulty={} and gas_limit={}", header.number, header.difficulty, header.gas_limit);
 	}
@@ -136,7 +120,7 @@ impl Engine for Ethash {
 		try!(UntrustedRlp::new(&header.seal[1]).as_val::<H64>());
 
 		// TODO: consider removing these lines.
-		let min_difficulty = decode(self.spec().engine_params.get("minimumDifficulty").unwrap());
+		let min_difficulty = self.u256_param("minimumDifficulty");
 		if header.difficulty < min_difficulty {
 			return Err(From::from(BlockError::DifficultyOutOfBounds(OutOfBounds { min: Some(min_difficulty), max: None, found: header.difficulty })))
 		}
@@ -181,7 +165,7 @@ impl Engine for Ethash {
 		if header.difficulty != expected_difficulty {
 			return Err(From::from(BlockError::InvalidDifficulty(Mismatch { expected: expected_difficulty, found: header.difficulty })))
 		}
-		let gas_limit_divisor = decode(self.spec().engine_params.get("gasLimitBoundDivisor").unwrap());
+		let gas_limit_divisor = self.u256_param("gasLimitBoundDivisor");
 		let min_gas = parent.gas_limit - parent.gas_limit / gas_limit_divisor;
 		let max_gas = parent.gas_limit + parent.gas_limit / gas_limit_divisor;
 		if header.gas_limit <= min_gas || header.gas_limit >= max_gas {
@@ -191,7 +175,7 @@ impl Engine for Ethash {
 	}
 
 	fn verify_transaction_basic(&self, t: &SignedTransaction, header: &Header) -> result::Result<(), Error> {
-		if header.number() >= self.u64_param("frontierCompatibilityModeLimit") {
+		if self.rules(header.number()).transaction_rules == TransactionRules::Homestead {
 			try!(t.check_low_s());
 		}
 		Ok(())
@@ -213,8 +197,8 @@ impl Ethash {
 		let min_difficulty = self.u256_param("minimumDifficulty");
 		let difficulty_bound_divisor = self.u256_param("difficultyBoundDivisor");
 		let duration_limit = self.u64_param("durationLimit");
-		let frontier_limit = self.u64_param("frontierCompatibilityModeLimit");
-		let mut target = if header.number < frontier_limit {
+		let rules = self.rules(header.number);
+		let mut target = if rules.difficulty == DifficultyRule::Frontier {
 			if header.timestamp >= parent.timestamp + duration_limit {
 				parent.difficulty - (parent.difficulty / difficulty_bound_divisor)
 			}
@@ -233,7 +217,7 @@ impl Ethash {
 			}
 		};
 		target = max(min_difficulty, target);
-		let period = ((parent.number + 1) / EXP_DIFF_PERIOD) as usize;
+		let period = ((parent.number + 1).saturating_sub(rules.bomb_delay) / EXP_DIFF_PERIOD) as usize;
 		if period > 1 {
 			target = max(min_difficulty, target + (U256::from(1) << (period - 2)));
 		}
@@ -290,7 +274,8 @@ mod tests {
 	use engine::*;
 	use tests::helpers::*;
 	use super::{Ethash};
-	use super::super::new_morden;
+	use super::super::{new_morden, new_homestead_test};
+	use transitions::{Transitions, Transition, DifficultyRule};
 
 	#[test]
 	fn on_close_block() {
@@ -300,7 +285,7 @@ mod tests {
 		let mut db = db_result.take();
 		engine.spec().ensure_db_good(&mut db);
 		let last_hashes = vec![genesis_header.hash()];
-		let b = OpenBlock::new(engine.deref(), db, &genesis_header, last_hashes, Address::zero(), vec![]);
+		let b = OpenBlock::new(engine.deref(), db, &genesis_header, last_hashes, Address::zero(), test_gas_range(), vec![]);
 		let b = b.close();
 		assert_eq!(b.state().balance(&Address::zero()), U256::from_str("4563918244f40000").unwrap());
 	}
@@ -313,7 +298,7 @@ mod tests {
 		let mut db = db_result.take();
 		engine.spec().ensure_db_good(&mut db);
 		let last_hashes = vec![genesis_header.hash()];
-		let mut b = OpenBlock::new(engine.deref(), db, &genesis_header, last_hashes, Address::zero(), vec![]);
+		let mut b = OpenBlock::new(engine.deref(), db, &genesis_header, last_hashes, Address::zero(), test_gas_range(), vec![]);
 		let mut uncle = Header::new();
 		let uncle_author = address_from_hex("ef2d6d194084c2de36e0dabfce45d046b37d1106");
 		uncle.author = uncle_author.clone();
@@ -326,7 +311,7 @@ mod tests {
 
 	#[test]
 	fn has_valid_metadata() {
-		let engine = Ethash::new_boxed(new_morden());
+		let engine = Ethash::new_boxed(new_morden(), None);
 		assert!(!engine.name().is_empty());
 		assert!(engine.version().major >= 1);
 	}
@@ -338,6 +323,54 @@ mod tests {
 		assert!(engine.u256_param("minimumDifficulty") > U256::zero());
 	}
 
+	#[test]
+	fn delays_difficulty_bomb() {
+		let engine = Ethash::new_test(new_homestead_test());
+		let mut spec = new_homestead_test();
+		spec.transitions = Transitions::new(vec![Transition {
+			difficulty: Some(DifficultyRule::Homestead),
+			bomb_delay: Some(3_000_000),
+			..Transition::new("Delay", 0)
+		}]);
+		let delayed = Ethash::new_test(spec);
+
+		let mut parent = Header::new();
+		parent.number = 3_000_000;
+		parent.timestamp = 1000;
+		parent.difficulty = U256::from(1_000_000_000u64);
+		let mut header = Header::new();
+		header.number = 3_000_001;
+		header.timestamp = 1010;
+
+		let difference = engine.calculate_difficuty(&header, &parent) - delayed.calculate_difficuty(&header, &parent);
+		assert_eq!(difference, U256::one() << 28);
+	}
+
+	#[test]
+	fn verifies_transaction_chain_id() {
+		let mut spec = new_morden();
+		spec.chain_id = Some(62);
+		spec.transitions = Transitions::new(vec![Transition { replay_protection: Some(true), ..Transition::new("Replay", 10) }]);
+		let engine = Ethash::new_test(spec);
+		let keypair = KeyPair::create().unwrap();
+		let legacy = Transaction::default().sign(&keypair.secret());
+		let bound = Transaction::default().sign_with_chain_id(&keypair.secret(), Some(62));
+		let foreign = Transaction::default().sign_with_chain_id(&keypair.secret(), Some(61));
+
+		let mut header = Header::new();
+		header.number = 9;
+		assert!(engine.verify_transaction_chain_id(&legacy, &header).is_ok());
+		assert!(engine.verify_transaction_chain_id(&bound, &header).is_err());
+
+		header.number = 10;
+		assert!(engine.verify_transaction_chain_id(&legacy, &header).is_ok());
+		assert!(engine.verify_transaction_chain_id(&bound, &header).is_ok());
+		match engine.verify_transaction_chain_id(&foreign, &header) {
+			Err(Error::Transaction(TransactionError::InvalidChainId(61))) => {},
+			r => panic!("should be chain id error (got {:?})", r),
+		}
+	}
+
 	#[test]
 	fn can_return_factory() {
 		let engine = Ethash::new_test(new_morden());
@@ -0,0 +1,223 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+
+//! Analysis of code ahead of its execution: valid jump destinations and basic blocks with their static
+//! gas costs. Analyses of code with a known hash are shared between executions.
+
+use common::*;
+use evm::Schedule;
+use super::instructions::{self, Instruction, InstructionInfo};
+
+/// Number of analysed codes kept by a `SharedCache`.
+const MAX_CACHED_CODES: usize = 4096;
+/// Number of gas price tiers with a cost in the schedule.
+const TIERS: usize = 8;
+
+/// Set of code positions.
+pub struct BitSet {
+	words: Vec<u64>,
+}
+
+impl BitSet {
+	fn with_len(len: usize) -> BitSet {
+		BitSet { words: vec![0; (len + 63) / 64] }
+	}
+
+	fn insert(&mut self, position: usize) {
+		self.words[position / 64] |= 1u64 << (position % 64);
+	}
+
+	/// Whether `position` is in the set.
+	pub fn contains(&self, position: usize) -> bool {
+		self.words.get(position / 64).map_or(false, |w| w & (1u64 << (position % 64)) != 0)
+	}
+}
+
+/// Run of instructions which is only entered at its start and after which gas may be observed, so
+/// that the static costs of all its instructions can be charged at once.
+pub struct BasicBlock {
+	/// Code position after the last instruction of the block.
+	pub end: usize,
+	// number of instructions with a static cost per gas price tier
+	tier_counts: [usize; TIERS],
+}
+
+impl BasicBlock {
+	/// Static cost of the instructions of the block.
+	pub fn gas(&self, schedule: &Schedule) -> U256 {
+		U256::from(self.tier_counts.iter().zip(schedule.tier_step_gas.iter()).fold(0, |gas, (count, step)| gas + count * step))
+	}
+}
+
+/// Jump destinations and basic blocks of some code.
+pub struct CodeInfo {
+	/// Positions of `JUMPDEST` instructions.
+	pub jump_destinations: BitSet,
+	// keyed by start position
+	blocks: HashMap<usize, BasicBlock>,
+}
+
+impl CodeInfo {
+	/// Analyse `code`.
+	pub fn new(code: &[u8]) -> CodeInfo {
+		let mut jump_destinations = BitSet::with_len(code.len());
+		let mut blocks = HashMap::new();
+		let mut start = 0;
+		let mut tier_counts = [0; TIERS];
+		let mut position = 0;
+
+		while position < code.len() {
+			let instruction = code[position];
+			if instruction == instructions::JUMPDEST {
+				jump_destinations.insert(position);
+				if position > start {
+					blocks.insert(start, BasicBlock { end: position, tier_counts: tier_counts });
+					tier_counts = [0; TIERS];
+					start = position;
+				}
+			}
+
+			let info = instructions::get_info(instruction);
+			if has_static_cost(instruction, &info) {
+				tier_counts[instructions::get_tier_idx(info.tier)] += 1;
+			}
+			position += 1;
+			if instructions::is_push(instruction) {
+				position += instructions::get_push_bytes(instruction);
+			}
+
+			if ends_block(instruction) {
+				blocks.insert(start, BasicBlock { end: position, tier_counts: tier_counts });
+				tier_counts = [0; TIERS];
+				start = position;
+			}
+		}
+		if start < code.len() {
+			blocks.insert(start, BasicBlock { end: position, tier_counts: tier_counts });
+		}
+
+		CodeInfo {
+			jump_destinations: jump_destinations,
+			blocks: blocks,
+		}
+	}
+
+	/// The basic block starting at `position`, if any. Blocks start at 0, at jump destinations and
+	/// where other blocks end.
+	pub fn block(&self, position: usize) -> Option<&BasicBlock> {
+		self.blocks.get(&position)
+	}
+}
+
+/// Whether the cost of `instruction` is the step gas of its tier alone. Such costs are charged
+/// per basic block rather than per instruction.
+pub fn has_static_cost(instruction: Instruction, info: &InstructionInfo) -> bool {
+	match instruction {
+		instructions::SSTORE | instructions::SLOAD | instructions::MSTORE | instructions::MLOAD |
+		instructions::MSTORE8 | instructions::RETURN | instructions::SHA3 | instructions::CALLDATACOPY |
+		instructions::CODECOPY | instructions::EXTCODECOPY | instructions::JUMPDEST | instructions::LOG0...instructions::LOG4 |
+		instructions::CALL | instructions::CALLCODE | instructions::DELEGATECALL | instructions::CREATE | instructions::EXP => false,
+		_ => info.tier != instructions::GasPriceTier::Invalid,
+	}
+}
+
+/// Whether execution may leave sequential order after `instruction`, or gas left may be observed or
+/// returned by it.
+fn ends_block(instruction: Instruction) -> bool {
+	match instruction {
+		instructions::JUMP | instructions::JUMPI | instructions::STOP | instructions::RETURN | instructions::SUICIDE |
+		instructions::GAS | instructions::CALL | instructions::CALLCODE | instructions::DELEGATECALL | instructions::CREATE => true,
+		_ => false,
+	}
+}
+
+/// Code analyses shared between executions, keyed by code hash.
+#[derive(Default)]
+pub struct SharedCache {
+	// analyses and their hashes in order of insertion
+	codes: Mutex<(HashMap<H256, Arc<CodeInfo>>, VecDeque<H256>)>,
+}
+
+impl SharedCache {
+	/// Analysis of `code`, taken from the cache if `code_hash` is known.
+	pub fn code_info(&self, code_hash: Option<&H256>, code: &[u8]) -> Arc<CodeInfo> {
+		let code_hash = match code_hash {
+			Some(hash) => hash,
+			None => return Arc::new(CodeInfo::new(code)),
+		};
+		if let Some(info) = self.codes.lock().unwrap().0.get(code_hash) {
+			return info.clone();
+		}
+
+		let info = Arc::new(CodeInfo::new(code));
+		let mut codes = self.codes.lock().unwrap();
+		let (ref mut infos, ref mut order) = *codes;
+		if !infos.contains_key(code_hash) {
+			if infos.len() == MAX_CACHED_CODES {
+				let oldest = order.pop_front().expect("cache is full; qed");
+				infos.remove(&oldest);
+			}
+			infos.insert(code_hash.clone(), info.clone());
+			order.push_back(code_hash.clone());
+		}
+		info
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use common::*;
+	use evm::Schedule;
+	use super::*;
+
+	#[test]
+	fn finds_jump_destinations() {
+		let code = "7fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff7fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff5b01600055".from_hex().unwrap();
+		let info = CodeInfo::new(&code);
+		assert!(info.jump_destinations.contains(66));
+		assert!(!info.jump_destinations.contains(31));
+		assert!(!info.jump_destinations.contains(1000));
+	}
+
+	#[test]
+	fn splits_code_into_basic_blocks() {
+		// PUSH1 1 PUSH1 8 JUMPI ADD GAS STOP JUMPDEST POP PUSH1 0 SSTORE
+		let code = "6001600857015a005b50600055".from_hex().unwrap();
+		let info = CodeInfo::new(&code);
+		let schedule = Schedule::new_homestead();
+
+		let first = info.block(0).unwrap();
+		assert_eq!(first.end, 5);
+		assert_eq!(first.gas(&schedule), U256::from(3 + 3 + 10));
+		assert_eq!(info.block(5).unwrap().end, 7);
+		assert_eq!(info.block(5).unwrap().gas(&schedule), U256::from(3 + 2));
+		assert_eq!(info.block(7).unwrap().end, 8);
+		// JUMPDEST and SSTORE are charged per instruction.
+		assert_eq!(info.block(8).unwrap().gas(&schedule), U256::from(2 + 3));
+		assert!(info.block(1).is_none());
+	}
+
+	#[test]
+	fn shares_analyses_of_known_code() {
+		let cache = SharedCache::default();
+		let code = vec![0x5b, 0x00];
+		let hash = code.sha3();
+		let first = cache.code_info(Some(&hash), &code);
+		assert!(&*first as *const CodeInfo == &*cache.code_info(Some(&hash), &code) as *const CodeInfo);
+		assert!(&*first as *const CodeInfo != &*cache.code_info(None, &code) as *const CodeInfo);
+	}
+}
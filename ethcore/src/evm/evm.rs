@@ -57,6 +57,8 @@ pub enum Error {
 		/// What was the stack limit
 		limit: usize
 	},
+	/// Builtin contract failed because of malformed input.
+	BuiltinFailed(&'static str),
 	/// Returned on evm internal error. Should never be ignored during development.
 	/// Likely to cause consensus issues.
 	#[allow(dead_code)] // created only by jit
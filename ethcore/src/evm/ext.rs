@@ -104,4 +104,12 @@ pub trait Ext {
 
 	/// Increments sstore refunds count by 1.
 	fn inc_sstore_clears(&mut self);
+
+	/// Returns true if `trace_instruction` should be called for every executed instruction. Read once per
+	/// execution; VMs may charge gas in larger steps when tracing is off.
+	fn is_tracing(&self) -> bool { false }
+
+	/// Called before `instruction` at `pc` is executed with `gas` left, of which `gas_cost` is charged for it,
+	/// and the given stack, bottom first. Does nothing unless execution is traced.
+	fn trace_instruction(&mut self, _pc: usize, _instruction: u8, _gas: &U256, _gas_cost: &U256, _stack: &[U256]) {}
 }
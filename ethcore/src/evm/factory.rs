@@ -17,9 +17,10 @@
 //! Evm factory.
 //!
 //! TODO: consider spliting it into two separate files.
-#[cfg(test)]
 use std::fmt;
+use std::sync::Arc;
 use evm::Evm;
+use super::analysis::SharedCache;
 
 #[derive(Clone)]
 /// Type of EVM to use.
@@ -31,7 +32,6 @@ pub enum VMType {
 	Interpreter
 }
 
-#[cfg(test)]
 impl fmt::Display for VMType {
 	#[cfg(feature="jit")]
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -48,8 +48,6 @@ impl fmt::Display for VMType {
 	}
 }
 
-#[cfg(test)]
-#[cfg(feature = "json-tests")]
 impl VMType {
 	/// Return all possible VMs (JIT, Interpreter)
 	#[cfg(feature="jit")]
@@ -66,7 +64,9 @@ impl VMType {
 
 /// Evm factory. Creates appropriate Evm.
 pub struct Factory {
-	evm : VMType
+	evm : VMType,
+	// code analyses shared by created interpreters
+	evm_cache: Arc<SharedCache>,
 }
 
 impl Factory {
@@ -78,7 +78,7 @@ impl Factory {
 				Box::new(super::jit::JitEvm)
 			},
 			VMType::Interpreter => {
-				Box::new(super::interpreter::Interpreter)
+				Box::new(super::interpreter::Interpreter::new(self.evm_cache.clone()))
 			}
 		}	
 	}
@@ -88,16 +88,16 @@ impl Factory {
 	pub fn create(&self) -> Box<Evm> {
 		match self.evm {
 			VMType::Interpreter => {
-				Box::new(super::interpreter::Interpreter)
+				Box::new(super::interpreter::Interpreter::new(self.evm_cache.clone()))
 			}
 		}	
 	}
 
 	/// Create new instance of specific `VMType` factory
-	#[cfg(test)]
 	pub fn new(evm: VMType) -> Factory {
 		Factory {
-			evm: evm
+			evm: evm,
+			evm_cache: Arc::new(SharedCache::default()),
 		}
 	}
 }
@@ -105,17 +105,13 @@ impl Default for Factory {
 	/// Returns jitvm factory
 	#[cfg(feature = "jit")]
 	fn default() -> Factory {
-		Factory {
-			evm: VMType::Jit
-		}
+		Factory::new(VMType::Jit)
 	}
 
 	/// Returns native rust evm factory
 	#[cfg(not(feature = "jit"))]
 	fn default() -> Factory {
-		Factory {
-			evm: VMType::Interpreter
-		}
+		Factory::new(VMType::Interpreter)
 	}
 }
 
@@ -21,6 +21,7 @@ use super::instructions as instructions;
 use super::instructions::Instruction;
 use std::marker::Copy;
 use evm::{self, MessageCallResult, ContractCreateResult};
+use super::analysis::{self, BitSet, SharedCache};
 
 #[cfg(not(feature = "evm-debug"))]
 macro_rules! evm_debug {
@@ -49,7 +50,6 @@ macro_rules! overflowing {
 	}}
 }
 
-type CodePosition = usize;
 type Gas = U256;
 type ProgramCounter = usize;
 
@@ -276,12 +276,24 @@ enum InstructionResult {
 }
 
 /// Intepreter EVM implementation
-pub struct Interpreter;
+#[derive(Default)]
+pub struct Interpreter {
+	cache: Arc<SharedCache>,
+}
+
+impl Interpreter {
+	/// Create an interpreter which shares code analyses through `cache`.
+	pub fn new(cache: Arc<SharedCache>) -> Interpreter {
+		Interpreter {
+			cache: cache
+		}
+	}
+}
 
 impl evm::Evm for Interpreter {
 	fn exec(&self, params: ActionParams, ext: &mut evm::Ext) -> evm::Result {
 		let code = &params.code.as_ref().unwrap();
-		let valid_jump_destinations = self.find_jump_destinations(&code);
+		let code_info = self.cache.code_info(params.code_hash.as_ref(), &code);
 
 		let mut current_gas = params.gas;
 		let mut stack = VecStack::with_capacity(ext.schedule().stack_limit, U256::zero());
@@ -291,13 +303,35 @@ impl evm::Evm for Interpreter {
 			code: &code
 		};
 
+		// When tracing every instruction is charged on its own, so that its cost can be reported.
+		let tracing = ext.is_tracing();
+		let mut block_end = 0;
 		while reader.position < code.len() {
 			let instruction = code[reader.position];
+
+			// Charge static costs of the basic block starting here
+			if !tracing && reader.position == block_end {
+				if let Some(block) = code_info.block(reader.position) {
+					let block_gas = block.gas(ext.schedule());
+					try!(self.verify_gas(&current_gas, &block_gas));
+					current_gas = current_gas - block_gas;
+					block_end = block.end;
+				}
+			}
 			reader.position += 1;
 
-			// Calculate gas cost
-			let (gas_cost, mem_size) = try!(self.get_gas_cost_mem(ext, instruction, &mut mem, &stack));
+			// Calculate remaining gas cost
+			let info = instructions::get_info(instruction);
+			let (gas_cost, mem_size) = if !tracing && analysis::has_static_cost(instruction, &info) {
+				try!(self.verify_instructions_requirements(&info, ext.schedule().stack_limit, &stack));
+				(U256::zero(), 0)
+			} else {
+				try!(self.get_gas_cost_mem(ext, instruction, &mut mem, &stack))
+			};
 			try!(self.verify_gas(&current_gas, &gas_cost));
+			if tracing {
+				ext.trace_instruction(reader.position - 1, instruction, &current_gas, &gas_cost, &stack.stack);
+			}
 			mem.expand(mem_size);
 			current_gas = current_gas - gas_cost; //TODO: use operator -=
 
@@ -329,8 +363,9 @@ impl evm::Evm for Interpreter {
 					current_gas = gas_left;
 				},
 				InstructionResult::JumpToPosition(position) => {
-					let pos = try!(self.verify_jump(position, &valid_jump_destinations));
+					let pos = try!(self.verify_jump(position, &code_info.jump_destinations));
 					reader.position = pos;
+					block_end = pos;
 				},
 				InstructionResult::StopExecutionWithGasLeft(gas_left) => {
 					current_gas = gas_left;
@@ -858,10 +893,10 @@ impl Interpreter {
 		}
 	}
 
-	fn verify_jump(&self, jump_u: U256, valid_jump_destinations: &HashSet<usize>) -> Result<usize, evm::Error> {
+	fn verify_jump(&self, jump_u: U256, valid_jump_destinations: &BitSet) -> Result<usize, evm::Error> {
 		let jump = jump_u.low_u64() as usize;
 
-		if valid_jump_destinations.contains(&jump) && jump_u < U256::from(!0 as usize) {
+		if valid_jump_destinations.contains(jump) && jump_u < U256::from(!0 as usize) {
 			Ok(jump)
 		} else {
 			Err(evm::Error::BadJumpDestination {
@@ -1082,24 +1117,6 @@ impl Interpreter {
 		}
 		Ok(())
 	}
-
-	fn find_jump_destinations(&self, code: &[u8]) -> HashSet<CodePosition> {
-		let mut jump_dests = HashSet::new();
-		let mut position = 0;
-
-		while position < code.len() {
-			let instruction = code[position];
-
-			if instruction == instructions::JUMPDEST {
-				jump_dests.insert(position);
-			} else if instructions::is_push(instruction) {
-				position += instructions::get_push_bytes(instruction);
-			}
-			position += 1;
-		}
-
-		jump_dests
-	}
 }
 
 fn get_and_reset_sign(value: U256) -> (U256, bool) {
@@ -1133,7 +1150,7 @@ fn address_to_u256(value: Address) -> U256 {
 #[test]
 fn test_mem_gas_cost() {
 	// given
-	let interpreter = Interpreter;
+	let interpreter = Interpreter::default();
 	let schedule = evm::Schedule::default();
 	let current_mem_size = 5;
 	let mem_size = !U256::zero();
@@ -1153,23 +1170,10 @@ mod tests {
 	use super::*;
 	use evm;
 
-	#[test]
-	fn test_find_jump_destinations() {
-		// given
-		let interpreter = Interpreter;
-		let code = "7fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff7fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff5b01600055".from_hex().unwrap();
-
-		// when
-		let valid_jump_destinations = interpreter.find_jump_destinations(&code);
-
-		// then
-		assert!(valid_jump_destinations.contains(&66));
-	}
-
 	#[test]
 	fn test_calculate_mem_cost() {
 		// given
-		let interpreter = Interpreter;
+		let interpreter = Interpreter::default();
 		let schedule = evm::Schedule::default();
 		let current_mem_size = 0;
 		let mem_size = U256::from(5);
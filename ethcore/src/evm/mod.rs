@@ -22,7 +22,8 @@ pub mod interpreter;
 #[macro_use]
 pub mod factory;
 pub mod schedule;
-mod instructions;
+pub mod instructions;
+mod analysis;
 #[cfg(feature = "jit" )]
 mod jit;
 
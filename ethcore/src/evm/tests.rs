@@ -173,7 +173,7 @@ fn test_stack_underflow() {
 	let mut ext = FakeExt::new();
 
 	let err = {
-		let vm : Box<evm::Evm> = Box::new(super::interpreter::Interpreter);
+		let vm : Box<evm::Evm> = Box::new(super::interpreter::Interpreter::default());
 		vm.exec(params, &mut ext).unwrap_err()
 	};
 
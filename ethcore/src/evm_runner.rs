@@ -0,0 +1,246 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+
+//! Standalone execution of EVM code on an in-memory state, for debugging and benchmarking contracts.
+
+use common::*;
+use engine::Engine;
+use spec::Spec;
+use state::State;
+use pod_state::PodState;
+use substate::Substate;
+use externalities::{Externalities, OriginInfo, OutputPolicy};
+use evm::{Ext, Factory, Schedule, ContractCreateResult, MessageCallResult};
+use evm::instructions;
+
+pub use evm::VMType;
+pub use evm::Error as VmError;
+
+/// Code to run and the message it runs for.
+#[derive(Debug, Clone, Default)]
+pub struct Call {
+	/// Code to execute.
+	pub code: Bytes,
+	/// Call data.
+	pub data: Bytes,
+	/// Gas available.
+	pub gas: U256,
+	/// Gas price.
+	pub gas_price: U256,
+	/// Sender, also the origin.
+	pub sender: Address,
+	/// Address the code runs at.
+	pub address: Address,
+	/// Value sent.
+	pub value: U256,
+}
+
+/// An instruction about to be executed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Step {
+	/// Position in the code.
+	pub pc: usize,
+	/// Name of the instruction.
+	pub instruction: &'static str,
+	/// Gas left before executing the instruction.
+	pub gas: U256,
+	/// Gas charged for the instruction. Static costs of a basic block are charged at its first instruction.
+	pub gas_cost: U256,
+	/// Stack, bottom first.
+	pub stack: Vec<U256>,
+}
+
+/// Outcome of running code.
+#[derive(Debug)]
+pub struct Outcome {
+	/// Gas left, or why execution failed.
+	pub result: Result<U256, VmError>,
+	/// Gas used.
+	pub gas_used: U256,
+	/// Data returned.
+	pub output: Bytes,
+	/// Logs created.
+	pub logs: Vec<LogEntry>,
+	/// Storage of the called address afterwards.
+	pub storage: BTreeMap<H256, H256>,
+	/// Executed instructions, if traced.
+	pub trace: Vec<Step>,
+}
+
+/// Runs code with a chosen VM against a fresh in-memory copy of a pre-state.
+pub struct Runner {
+	engine: Box<Engine>,
+	factory: Factory,
+	pre_state: PodState,
+	env_info: EnvInfo,
+}
+
+impl Runner {
+	/// Create a runner using the rules of `spec` at block 0 and an empty pre-state.
+	pub fn new(spec: Spec, vm_type: VMType) -> Result<Runner, Error> {
+		Ok(Runner {
+			engine: try!(spec.to_engine()),
+			factory: Factory::new(vm_type),
+			pre_state: PodState::new(),
+			env_info: EnvInfo::default(),
+		})
+	}
+
+	/// Seed the state from a JSON map of addresses to `{ "balance", "nonce", "code", "storage" }`.
+	pub fn set_pre_state(&mut self, json: &Json) {
+		self.pre_state = PodState::from_json(json);
+	}
+
+	/// Switch to another VM.
+	pub fn set_vm_type(&mut self, vm_type: VMType) {
+		self.factory = Factory::new(vm_type);
+	}
+
+	/// Execute `call`, recording every instruction if `trace` is set.
+	pub fn run(&self, call: &Call, trace: bool) -> Outcome {
+		let db = JournalDB::new_with_db(Arc::new(in_memory()), Algorithm::Archive).expect("in-memory database opens");
+		let mut state = State::new(db, self.engine.account_start_nonce());
+		state.populate_from(self.pre_state.clone());
+
+		let params = ActionParams {
+			code_address: call.address.clone(),
+			address: call.address.clone(),
+			sender: call.sender.clone(),
+			origin: call.sender.clone(),
+			gas: call.gas,
+			gas_price: call.gas_price,
+			value: ActionValue::Transfer(call.value),
+			code: Some(call.code.clone()),
+			code_hash: Some(call.code.sha3()),
+			data: Some(call.data.clone()),
+		};
+		let mut env_info = self.env_info.clone();
+		env_info.gas_limit = call.gas;
+
+		let mut substate = Substate::new();
+		let mut output = vec![];
+		let mut steps = vec![];
+		let result = {
+			let ext = Externalities::new(&mut state, &env_info, self.engine.deref(), 0, OriginInfo::from(&params), &mut substate, OutputPolicy::Return(BytesRef::Flexible(&mut output)));
+			let vm = self.factory.create();
+			match trace {
+				true => {
+					let mut ext = TracingExt { ext: ext, steps: &mut steps };
+					vm.exec(params, &mut ext)
+				},
+				false => {
+					let mut ext = ext;
+					vm.exec(params, &mut ext)
+				},
+			}
+		};
+
+		let storage = state.to_pod().get().get(&call.address).map_or_else(BTreeMap::new, |a| a.storage.clone());
+		Outcome {
+			gas_used: match result {
+				Ok(gas_left) => call.gas - gas_left,
+				Err(_) => call.gas,
+			},
+			result: result,
+			output: output,
+			logs: substate.logs,
+			storage: storage,
+			trace: steps,
+		}
+	}
+}
+
+/// Externalities recording each executed instruction.
+struct TracingExt<'a, 'b> {
+	ext: Externalities<'a>,
+	steps: &'b mut Vec<Step>,
+}
+
+impl<'a, 'b> Ext for TracingExt<'a, 'b> {
+	fn storage_at(&self, key: &H256) -> H256 { self.ext.storage_at(key) }
+	fn set_storage(&mut self, key: H256, value: H256) { self.ext.set_storage(key, value) }
+	fn exists(&self, address: &Address) -> bool { self.ext.exists(address) }
+	fn balance(&self, address: &Address) -> U256 { self.ext.balance(address) }
+	fn blockhash(&self, number: &U256) -> H256 { self.ext.blockhash(number) }
+	fn create(&mut self, gas: &U256, value: &U256, code: &[u8]) -> ContractCreateResult { self.ext.create(gas, value, code) }
+	fn call(&mut self, gas: &U256, sender_address: &Address, receive_address: &Address, value: Option<U256>, data: &[u8], code_address: &Address, output: &mut [u8]) -> MessageCallResult {
+		self.ext.call(gas, sender_address, receive_address, value, data, code_address, output)
+	}
+	fn extcode(&self, address: &Address) -> Bytes { self.ext.extcode(address) }
+	fn log(&mut self, topics: Vec<H256>, data: &[u8]) { self.ext.log(topics, data) }
+	fn ret(&mut self, gas: &U256, data: &[u8]) -> Result<U256, VmError> { self.ext.ret(gas, data) }
+	fn suicide(&mut self, refund_address: &Address) { self.ext.suicide(refund_address) }
+	fn schedule(&self) -> &Schedule { self.ext.schedule() }
+	fn env_info(&self) -> &EnvInfo { self.ext.env_info() }
+	fn depth(&self) -> usize { self.ext.depth() }
+	fn inc_sstore_clears(&mut self) { self.ext.inc_sstore_clears() }
+
+	fn is_tracing(&self) -> bool { true }
+	fn trace_instruction(&mut self, pc: usize, instruction: u8, gas: &U256, gas_cost: &U256, stack: &[U256]) {
+		self.steps.push(Step {
+			pc: pc,
+			instruction: instructions::get_info(instruction).name,
+			gas: *gas,
+			gas_cost: *gas_cost,
+			stack: stack.to_vec(),
+		});
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use common::*;
+	use tests::helpers::get_test_spec;
+	use super::*;
+
+	#[test]
+	fn runs_code_with_trace() {
+		let runner = Runner::new(get_test_spec(), VMType::Interpreter).unwrap();
+		// SSTORE(0, CALLDATALOAD(0)); LOG0(0, 0); RETURN(0, 32) with memory set by MSTORE(0, 42)
+		let call = Call {
+			code: "600035600055600060006000a0602a60005260206000f3".from_hex().unwrap(),
+			data: "0000000000000000000000000000000000000000000000000000000000000007".from_hex().unwrap(),
+			gas: U256::from(100_000),
+			address: Address::from(0x1000),
+			..Call::default()
+		};
+		let outcome = runner.run(&call, true);
+
+		assert!(outcome.result.is_ok());
+		assert_eq!(outcome.output, H256::from(42).to_vec());
+		assert_eq!(outcome.storage.get(&H256::new()), Some(&H256::from(7)));
+		assert_eq!(outcome.logs.len(), 1);
+		assert_eq!(outcome.trace[0].instruction, "PUSH1");
+		assert_eq!(outcome.trace[1].stack, vec![U256::zero()]);
+		assert_eq!(outcome.trace.last().unwrap().instruction, "RETURN");
+		// every instruction is charged on its own when tracing.
+		assert_eq!(outcome.trace[0].gas_cost, U256::from(3));
+		assert_eq!(outcome.trace[1].gas_cost, U256::from(3));
+		assert_eq!(outcome.trace[1].gas, outcome.trace[0].gas - U256::from(3));
+		assert_eq!(outcome.gas_used, call.gas - outcome.result.unwrap());
+		assert!(runner.run(&call, false).trace.is_empty());
+	}
+
+	#[test]
+	fn reports_failure() {
+		let runner = Runner::new(get_test_spec(), VMType::Interpreter).unwrap();
+		let call = Call { code: vec![0x01], gas: U256::from(100), ..Call::default() };
+		let outcome = runner.run(&call, false);
+		assert!(outcome.result.is_err());
+		assert_eq!(outcome.gas_used, U256::from(100));
+	}
+}
@@ -157,6 +157,7 @@ impl<'a> Executive<'a> {
 					gas_price: t.gas_price,
 					value: ActionValue::Transfer(t.value),
 					code: Some(t.data.clone()),
+					code_hash: None,
 					data: None,
 				};
 				self.create(params, &mut substate)
@@ -171,6 +172,7 @@ impl<'a> Executive<'a> {
 					gas_price: t.gas_price,
 					value: ActionValue::Transfer(t.value),
 					code: self.state.code(address),
+					code_hash: self.state.code_hash(address),
 					data: Some(t.data.clone()),
 				};
 				// TODO: move output upstream
@@ -218,7 +220,7 @@ impl<'a> Executive<'a> {
 		}
 		trace!("Executive::call(params={:?}) self.env_info={:?}", params, self.info);
 
-		if self.engine.is_builtin(&params.code_address) {
+		if self.engine.is_builtin(&params.code_address, self.info.number) {
 			// if destination is builtin, try to execute it
 
 			let default = [];
@@ -226,10 +228,16 @@ impl<'a> Executive<'a> {
 
 			let cost = self.engine.cost_of_builtin(&params.code_address, data);
 			match cost <= params.gas {
-				true => {
-					self.engine.execute_builtin(&params.code_address, data, &mut output);
-					self.state.clear_snapshot();
-					Ok(params.gas - cost)
+				true => match self.engine.execute_builtin(&params.code_address, data, &mut output) {
+					Ok(()) => {
+						self.state.clear_snapshot();
+						Ok(params.gas - cost)
+					},
+					// bad input; fail and drain the whole gas
+					Err(BuiltinError(reason)) => {
+						self.state.revert_snapshot();
+						Err(evm::Error::BuiltinFailed(reason))
+					}
 				},
 				// just drain the whole gas
 				false => {
@@ -348,7 +356,8 @@ impl<'a> Executive<'a> {
 				| Err(evm::Error::BadJumpDestination {..})
 				| Err(evm::Error::BadInstruction {.. })
 				| Err(evm::Error::StackUnderflow {..})
-				| Err(evm::Error::OutOfStack {..}) => {
+				| Err(evm::Error::OutOfStack {..})
+				| Err(evm::Error::BuiltinFailed(_)) => {
 				self.state.revert_snapshot();
 			},
 			Ok(_) | Err(evm::Error::Internal) => {
@@ -135,6 +135,7 @@ impl<'a> Ext for Externalities<'a> {
 			gas_price: self.origin_info.gas_price,
 			value: ActionValue::Transfer(*value),
 			code: Some(code.to_vec()),
+			code_hash: None,
 			data: None,
 		};
 
@@ -169,6 +170,7 @@ impl<'a> Ext for Externalities<'a> {
 			gas: *gas,
 			gas_price: self.origin_info.gas_price,
 			code: self.state.code(code_address),
+			code_hash: self.state.code_hash(code_address),
 			data: Some(data.to_vec()),
 		};
 
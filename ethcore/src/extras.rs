@@ -17,7 +17,7 @@
 //! Blockchain DB extras.
 
 use util::*;
-use header::BlockNumber;
+use header::{BlockNumber, Header};
 use receipt::Receipt;
 
 /// Represents index of extra data in database
@@ -35,8 +35,29 @@ pub enum ExtrasIndex {
 	BlocksBlooms = 4,
 	/// Block receipts index
 	BlockReceipts = 5,
+	/// Header of a block whose body was pruned index
+	BlockHeader = 6,
+	/// Log positions index
+	LogPositions = 7,
 } 
 
+impl ExtrasIndex {
+	/// Returns index stored as the last byte of extras keys.
+	pub fn from_u8(i: u8) -> Option<ExtrasIndex> {
+		match i {
+			0 => Some(ExtrasIndex::BlockDetails),
+			1 => Some(ExtrasIndex::BlockHash),
+			2 => Some(ExtrasIndex::TransactionAddress),
+			3 => Some(ExtrasIndex::BlockLogBlooms),
+			4 => Some(ExtrasIndex::BlocksBlooms),
+			5 => Some(ExtrasIndex::BlockReceipts),
+			6 => Some(ExtrasIndex::BlockHeader),
+			7 => Some(ExtrasIndex::LogPositions),
+			_ => None,
+		}
+	}
+}
+
 /// trait used to write Extras data to db
 pub trait ExtrasWritable {
 	/// Write extra data to db
@@ -67,7 +88,7 @@ impl ExtrasWritable for DBTransaction {
 	}
 }
 
-impl ExtrasReadable for Database {
+impl ExtrasReadable for KeyValueDB {
 	fn get_extras<K, T>(&self, hash: &K) -> Option<T> where
 		T: ExtrasIndexable + Decodable,
 		K: ExtrasSliceConvertable {
@@ -120,6 +141,12 @@ pub trait ExtrasIndexable {
 	fn extras_index() -> ExtrasIndex;
 }
 
+impl ExtrasIndexable for Header {
+	fn extras_index() -> ExtrasIndex {
+		ExtrasIndex::BlockHeader
+	}
+}
+
 impl ExtrasIndexable for H256 {
 	fn extras_index() -> ExtrasIndex {
 		ExtrasIndex::BlockHash
@@ -340,3 +367,70 @@ impl ExtrasIndexable for BlockReceipts {
 		ExtrasIndex::BlockReceipts
 	}
 }
+
+/// Position of a log in the canonical chain.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct LogPosition {
+	/// Number of the block containing the log
+	pub block_number: BlockNumber,
+	/// Index of the log among all logs of the block
+	pub log_index: usize,
+}
+
+impl Decodable for LogPosition {
+	fn decode<D>(decoder: &D) -> Result<Self, DecoderError> where D: Decoder {
+		let d = decoder.as_rlp();
+		Ok(LogPosition {
+			block_number: try!(d.val_at(0)),
+			log_index: try!(d.val_at(1)),
+		})
+	}
+}
+
+impl Encodable for LogPosition {
+	fn rlp_append(&self, s: &mut RlpStream) {
+		s.begin_list(2);
+		s.append(&self.block_number);
+		s.append(&self.log_index);
+	}
+}
+
+/// Positions of logs of a single address or topic within a group of consecutive blocks.
+#[derive(Clone)]
+pub struct LogPositions {
+	pub positions: Vec<LogPosition>,
+}
+
+impl LogPositions {
+	pub fn new() -> Self {
+		LogPositions {
+			positions: vec![]
+		}
+	}
+}
+
+impl Decodable for LogPositions {
+	fn decode<D>(decoder: &D) -> Result<Self, DecoderError> where D: Decoder {
+		Ok(LogPositions {
+			positions: try!(Decodable::decode(decoder))
+		})
+	}
+}
+
+impl Encodable for LogPositions {
+	fn rlp_append(&self, s: &mut RlpStream) {
+		s.append(&self.positions);
+	}
+}
+
+impl HeapSizeOf for LogPositions {
+	fn heap_size_of_children(&self) -> usize {
+		self.positions.capacity() * mem::size_of::<LogPosition>()
+	}
+}
+
+impl ExtrasIndexable for LogPositions {
+	fn extras_index() -> ExtrasIndex {
+		ExtrasIndex::LogPositions
+	}
+}
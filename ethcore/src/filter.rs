@@ -19,7 +19,7 @@
 use util::hash::*;
 use util::sha3::*;
 use client::BlockId;
-use log_entry::LogEntry;
+use log_entry::{LogEntry, LocalizedLogEntry};
 
 /// Blockchain Filter.
 pub struct Filter {
@@ -40,6 +40,12 @@ pub struct Filter {
 	/// If None, match all.
 	/// If specified, log must contain one of these topics.
 	pub topics: [Option<Vec<H256>>; 4],
+
+	/// Logs limit.
+	///
+	/// If None, return all logs.
+	/// If specified, only the most recent `limit` logs are returned.
+	pub limit: Option<usize>,
 }
 
 impl Clone for Filter {
@@ -53,7 +59,8 @@ impl Clone for Filter {
 			from_block: self.from_block.clone(),
 			to_block: self.to_block.clone(),
 			address: self.address.clone(),
-			topics: topics
+			topics: topics,
+			limit: self.limit,
 		}
 	}
 }
@@ -83,6 +90,17 @@ impl Filter {
 		})
 	}
 
+	/// Returns the most recent `limit` of `logs`, which must be ordered oldest first.
+	pub fn apply_limit(&self, mut logs: Vec<LocalizedLogEntry>) -> Vec<LocalizedLogEntry> {
+		match self.limit {
+			Some(limit) if logs.len() > limit => {
+				let len = logs.len();
+				logs.split_off(len - limit)
+			},
+			_ => logs,
+		}
+	}
+
 	/// Returns true if given log entry matches filter.
 	pub fn matches(&self, log: &LogEntry) -> bool {
 		let matches = match self.address {
@@ -103,7 +121,7 @@ mod tests {
 	use util::hash::*;
 	use filter::Filter;
 	use client::BlockId;
-	use log_entry::LogEntry;
+	use log_entry::{LogEntry, LocalizedLogEntry};
 
 	#[test]
 	fn test_bloom_possibilities_none() {
@@ -111,7 +129,8 @@ mod tests {
 			from_block: BlockId::Earliest,
 			to_block: BlockId::Latest,
 			address: None,
-			topics: [None, None, None, None]
+			topics: [None, None, None, None],
+			limit: None,
 		};
 
 		let possibilities = none_filter.bloom_possibilities();
@@ -129,7 +148,8 @@ mod tests {
 			topics: [
 				Some(vec![H256::from_str("ff74e91598aed6ae5d2fdcf8b24cd2c7be49a0808112a305069355b7160f23f9").unwrap()]),
 				None, None, None
-			]
+			],
+			limit: None,
 		};
 
 		let possibilities = filter.bloom_possibilities();
@@ -146,7 +166,8 @@ mod tests {
 				Some(vec![H256::from_str("ff74e91598aed6ae5d2fdcf8b24cd2c7be49a0808112a305069355b7160f23f9").unwrap()]),
 				Some(vec![H256::from_str("ff74e91598aed6ae5d2fdcf8b24cd2c7be49a0808112a305069355b7160f23f9").unwrap()]),
 				None, None
-			]
+			],
+			limit: None,
 		};
 
 		let possibilities = filter.bloom_possibilities();
@@ -173,7 +194,8 @@ mod tests {
 				]),
 				Some(vec![H256::from_str("ff74e91598aed6ae5d2fdcf8b24cd2c7be49a0808112a305069355b7160f23f9").unwrap()]),
 				None
-			]
+			],
+			limit: None,
 		};
 
 		// number of possibilites should be equal 2 * 2 * 2 * 1 = 8
@@ -192,7 +214,8 @@ mod tests {
 				Some(vec![H256::from_str("ff74e91598aed6ae5d2fdcf8b24cd2c7be49a0808112a305069355b7160f23f9").unwrap()]),
 				Some(vec![H256::from_str("ff74e91598aed6ae5d2fdcf8b24cd2c7be49a0808112a305069355b7160f23fa").unwrap()]),
 				None, None
-			]
+			],
+			limit: None,
 		};
 
 		let entry0 = LogEntry {
@@ -227,4 +250,28 @@ mod tests {
 		assert_eq!(filter.matches(&entry1), false);
 		assert_eq!(filter.matches(&entry2), false);
 	}
+
+	#[test]
+	fn test_apply_limit() {
+		let logs = || (0..5).map(|i| LocalizedLogEntry {
+			entry: LogEntry { address: Address::new(), topics: vec![], data: vec![] },
+			block_hash: H256::new(),
+			block_number: i,
+			transaction_hash: H256::new(),
+			transaction_index: 0,
+			log_index: 0,
+		}).collect::<Vec<_>>();
+
+		let filter = Filter {
+			from_block: BlockId::Earliest,
+			to_block: BlockId::Latest,
+			address: None,
+			topics: [None, None, None, None],
+			limit: Some(2),
+		};
+
+		let limited = filter.apply_limit(logs());
+		assert_eq!(limited.iter().map(|l| l.block_number).collect::<Vec<_>>(), vec![3, 4]);
+		assert_eq!(Filter { limit: None, ..filter }.apply_limit(logs()), logs());
+	}
 }
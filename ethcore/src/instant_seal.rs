@@ -0,0 +1,85 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+
+use common::*;
+use util::keys::store::SecretStore;
+use block::*;
+use spec::Spec;
+use engine::Engine;
+use evm::Factory;
+
+/// An engine for development chains which seals a block as soon as it has transactions.
+/// Blocks have no seal fields; like `NullEngine` it provides no consensus mechanism.
+pub struct InstantSeal {
+	spec: Spec,
+	factory: Factory,
+}
+
+impl InstantSeal {
+	/// Returns new instance of InstantSeal with default VM Factory
+	pub fn new_boxed(spec: Spec) -> Box<Engine> {
+		Box::new(InstantSeal {
+			spec: spec,
+			factory: Factory::default(),
+		})
+	}
+}
+
+impl Engine for InstantSeal {
+	fn vm_factory(&self) -> &Factory {
+		&self.factory
+	}
+	fn name(&self) -> &str { "InstantSeal" }
+	fn spec(&self) -> &Spec { &self.spec }
+	fn maximum_uncle_count(&self) -> usize { 0 }
+
+	fn seals_internally(&self) -> bool { true }
+
+	fn generate_seal(&self, block: &ExecutedBlock, _accounts: &SecretStore) -> Option<Vec<Bytes>> {
+		match block.transactions().is_empty() {
+			true => None,
+			false => Some(Vec::new()),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use common::*;
+	use block::*;
+	use spec::Spec;
+	use tests::helpers::*;
+	use devtools::RandomTempPath;
+	use util::keys::store::SecretStore;
+
+	#[test]
+	fn seals_blocks_with_transactions_only() {
+		let engine = Spec::new_dev(&[]).to_engine().unwrap();
+		let temp = RandomTempPath::create_dir();
+		let accounts = SecretStore::new_in(temp.as_path());
+		let genesis_header = engine.spec().genesis_header();
+		let mut db_result = get_temp_journal_db();
+		let mut db = db_result.take();
+		engine.spec().ensure_db_good(&mut db);
+		let b = OpenBlock::new(engine.deref(), db, &genesis_header, vec![genesis_header.hash()], Address::zero(), test_gas_range(), vec![]).close();
+
+		assert_eq!(engine.seal_fields(), 0);
+		assert!(engine.seals_internally());
+		assert!(engine.generate_seal(b.block(), &accounts).is_none());
+		assert!(b.seal(engine.deref(), vec![]).is_ok());
+	}
+}
@@ -81,6 +81,7 @@ extern crate time;
 extern crate env_logger;
 extern crate num_cpus;
 extern crate crossbeam;
+extern crate num;
 
 #[cfg(test)] extern crate ethcore_devtools as devtools;
 #[cfg(feature = "jit" )] extern crate evmjit;
@@ -94,9 +95,13 @@ pub mod header;
 pub mod service;
 pub mod log_entry;
 pub mod spec;
+pub mod sealing;
+pub mod evm_runner;
+pub mod transitions;
 pub mod transaction;
 pub mod views;
 pub mod receipt;
+pub mod light;
 
 mod common;
 mod basic_types;
@@ -112,6 +117,8 @@ mod account;
 mod account_db;
 mod action_params;
 mod null_engine;
+mod authority_round;
+mod instant_seal;
 mod builtin;
 mod chainfilter;
 mod extras;
@@ -121,6 +128,7 @@ mod externalities;
 mod verification;
 mod block_queue;
 mod blockchain;
+mod migrations;
 
 #[cfg(test)]
 mod tests;
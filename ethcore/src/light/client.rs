@@ -0,0 +1,432 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Light client implementation of `BlockChainClient`.
+
+use std::path::{Path, PathBuf};
+use util::*;
+use util::trie::proof;
+use basic_types::Seal;
+use header::{Header, BlockNumber};
+use views::{BlockView, HeaderView};
+use error::*;
+use spec::Spec;
+use engine::Engine;
+use account::Account;
+use block::ClosedBlock;
+use receipt::Receipt;
+use transaction::{LocalizedTransaction, SignedTransaction};
+use filter::Filter;
+use log_entry::LocalizedLogEntry;
+use verification::verify_header_only;
+use sealing::SealingConfig;
+use client::{BlockChainClient, BlockChainInfo, BlockStatus, BlockId, TransactionId, TreeRoute, BlockQueueInfo, ClientConfig, ClientDatabases, AccountProof, StorageProof, localized_block_logs};
+use super::{HeaderChain, OnDemand, Request, FetchError};
+
+/// Client keeping the header chain only. Everything else is fetched through `OnDemand`
+/// and verified against the headers.
+pub struct LightClient {
+	chain: HeaderChain,
+	engine: Arc<Box<Engine>>,
+	on_demand: Arc<OnDemand>,
+	import_lock: Mutex<()>,
+	sealing_block: Mutex<Option<ClosedBlock>>,
+}
+
+impl LightClient {
+	/// Create a light client keeping headers of the chain given by `spec` under `path`.
+	pub fn new(config: &ClientConfig, spec: Spec, path: &Path, on_demand: Arc<OnDemand>) -> Result<Arc<LightClient>, Error> {
+		let mut db_path = ClientDatabases::chain_dir(&spec, path);
+		db_path.push("light");
+		db_path.push("headers");
+		try!(fs::create_dir_all(&db_path).map_err(UtilError::StdIo));
+		let db_config = DatabaseConfig {
+			cache_size: config.db_cache_size,
+			compaction: config.db_compaction,
+			wal: config.db_wal,
+			..DatabaseConfig::default()
+		};
+		let db = try!(Database::open(&db_config, db_path.to_str().unwrap()).map_err(|e| UtilError::SimpleString(format!("Error opening headers db: {}", e))));
+		LightClient::new_with_db(spec, Arc::new(db), config.pow_cache_dir.as_ref().map(PathBuf::as_path), on_demand)
+	}
+
+	/// Create a light client keeping headers in given database. Proof-of-work caches are kept
+	/// under `cache_dir`, or the engine's default location.
+	pub fn new_with_db(spec: Spec, db: Arc<KeyValueDB>, cache_dir: Option<&Path>, on_demand: Arc<OnDemand>) -> Result<Arc<LightClient>, Error> {
+		let chain = HeaderChain::new(&spec.genesis_header().rlp(Seal::With), db);
+		let engine = Arc::new(try!(spec.to_engine_with_cache_dir(cache_dir)));
+		Ok(Arc::new(LightClient {
+			chain: chain,
+			engine: engine,
+			on_demand: on_demand,
+			import_lock: Mutex::new(()),
+			sealing_block: Mutex::new(None),
+		}))
+	}
+
+	/// Get the underlying header chain.
+	pub fn chain(&self) -> &HeaderChain {
+		&self.chain
+	}
+
+	fn block_id_hash(&self, id: BlockId) -> Option<H256> {
+		match id {
+			BlockId::Hash(hash) => Some(hash),
+			BlockId::Number(number) => self.chain.block_hash(number),
+			BlockId::Earliest => Some(self.chain.genesis_hash()),
+			BlockId::Latest => Some(self.chain.best_block_hash()),
+		}
+	}
+
+	fn block_number(&self, id: BlockId) -> Option<BlockNumber> {
+		match id {
+			BlockId::Number(number) => Some(number),
+			BlockId::Hash(ref hash) => self.chain.block_details(hash).map(|d| d.number),
+			BlockId::Earliest => Some(0),
+			BlockId::Latest => Some(self.chain.best_block_number()),
+		}
+	}
+
+	fn header(&self, hash: &H256) -> Option<Header> {
+		self.chain.header(hash).map(|bytes| decode(&bytes))
+	}
+
+	/// Body of the block `hash`. Bodies of empty blocks are built locally,
+	/// others are fetched and checked against `transactions_root` and `uncles_hash`.
+	fn body(&self, hash: &H256) -> Option<Bytes> {
+		let header = match self.header(hash) {
+			Some(header) => header,
+			None => return None,
+		};
+		if header.transactions_root == SHA3_NULL_RLP && header.uncles_hash == SHA3_EMPTY_LIST_RLP {
+			let mut body = RlpStream::new_list(2);
+			body.begin_list(0);
+			body.begin_list(0);
+			return Some(body.out());
+		}
+		let verify = |body: &[u8]| {
+			let rlp = UntrustedRlp::new(body);
+			match (rlp.at(0), rlp.at(1)) {
+				(Ok(transactions), Ok(uncles)) => {
+					ordered_trie_root(transactions.iter().map(|t| t.as_raw().to_vec()).collect()) == header.transactions_root
+						&& uncles.as_raw().sha3() == header.uncles_hash
+				},
+				_ => false,
+			}
+		};
+		self.fetch(Request::Body(hash.clone()), &verify)
+	}
+
+	/// RLP list of receipts of the block `hash`, checked against its `receipts_root`.
+	fn receipts(&self, hash: &H256) -> Option<Bytes> {
+		let header = match self.header(hash) {
+			Some(header) => header,
+			None => return None,
+		};
+		let verify = |receipts: &[u8]| {
+			let rlp = UntrustedRlp::new(receipts);
+			rlp.is_list() && ordered_trie_root(rlp.iter().map(|r| r.as_raw().to_vec()).collect()) == header.receipts_root
+		};
+		self.fetch(Request::Receipts(hash.clone()), &verify)
+	}
+
+	/// State trie node or code with given hash.
+	fn node(&self, hash: &H256) -> Option<Bytes> {
+		self.fetch(Request::Node(hash.clone()), &|node: &[u8]| &node.sha3() == hash)
+	}
+
+	fn fetch(&self, request: Request, verify: &Fn(&[u8]) -> bool) -> Option<Bytes> {
+		match self.on_demand.fetch(request.clone(), verify) {
+			Ok(data) => Some(data),
+			Err(FetchError::Timeout) => {
+				warn!(target: "light", "Timed out fetching {:?}", request);
+				None
+			},
+			Err(FetchError::BadResponse) => {
+				warn!(target: "light", "No peer gave valid data for {:?}", request);
+				None
+			},
+		}
+	}
+
+	/// Value stored under `key` in the trie with `root`. Nodes are fetched as the path is walked,
+	/// each one checked against the hash its parent refers to it by.
+	/// Returns `Err` if a node could not be fetched.
+	fn trie_get(&self, root: &H256, key: &[u8]) -> Result<Option<Bytes>, ()> {
+		self.trie_get_with_proof(root, key).map(|(value, _)| value)
+	}
+
+	/// As `trie_get`, also returning the fetched nodes which prove the value.
+	fn trie_get_with_proof(&self, root: &H256, key: &[u8]) -> Result<(Option<Bytes>, Vec<Bytes>), ()> {
+		let mut nodes = Vec::new();
+		let value = try!(proof::lookup(root, key, |hash| {
+			let node = self.node(hash);
+			if let Some(ref node) = node {
+				nodes.push(node.clone());
+			}
+			node
+		}).map_err(|_| ()));
+		Ok((value, nodes))
+	}
+
+	/// Account at the state of the best block.
+	fn account(&self, address: &Address) -> Result<Option<Account>, ()> {
+		let header = self.header(&self.chain.best_block_hash()).expect("Best block header is always stored");
+		let value = try!(self.trie_get(&header.state_root, &address.sha3()));
+		Ok(value.map(|rlp| Account::from_rlp(&rlp)))
+	}
+
+	fn block_logs_at(&self, number: BlockNumber, hash: &H256, filter: &Filter) -> Vec<LocalizedLogEntry> {
+		let receipts: Vec<Receipt> = match self.receipts(hash) {
+			Some(receipts) => decode(&receipts),
+			None => return vec![],
+		};
+		let body = match self.body(hash) {
+			Some(body) => body,
+			None => return vec![],
+		};
+		let hashes = Rlp::new(&body).at(0).iter().map(|t| t.as_raw().sha3()).collect();
+		localized_block_logs(number, hash.clone(), receipts, hashes, filter)
+	}
+}
+
+impl BlockChainClient for LightClient {
+	fn block_header(&self, id: BlockId) -> Option<Bytes> {
+		self.block_id_hash(id).and_then(|hash| self.chain.header(&hash))
+	}
+
+	fn block_body(&self, id: BlockId) -> Option<Bytes> {
+		self.block_id_hash(id).and_then(|hash| self.body(&hash))
+	}
+
+	fn block(&self, id: BlockId) -> Option<Bytes> {
+		self.block_id_hash(id).and_then(|hash| {
+			let header = match self.chain.header(&hash) {
+				Some(header) => header,
+				None => return None,
+			};
+			self.body(&hash).map(|body| {
+				let body = Rlp::new(&body);
+				let mut block = RlpStream::new_list(3);
+				block.append_raw(&header, 1);
+				block.append_raw(body.at(0).as_raw(), 1);
+				block.append_raw(body.at(1).as_raw(), 1);
+				block.out()
+			})
+		})
+	}
+
+	fn block_status(&self, id: BlockId) -> BlockStatus {
+		match self.block_id_hash(id) {
+			Some(ref hash) if self.chain.is_known(hash) => BlockStatus::InChain,
+			_ => BlockStatus::Unknown,
+		}
+	}
+
+	fn block_total_difficulty(&self, id: BlockId) -> Option<U256> {
+		self.block_id_hash(id).and_then(|hash| self.chain.block_details(&hash)).map(|d| d.total_difficulty)
+	}
+
+	fn nonce(&self, address: &Address) -> Option<U256> {
+		match self.account(address) {
+			Ok(Some(account)) => Some(account.nonce().clone()),
+			Ok(None) => Some(U256::zero()),
+			Err(()) => None,
+		}
+	}
+
+	fn block_hash(&self, id: BlockId) -> Option<H256> {
+		self.block_id_hash(id)
+	}
+
+	fn code(&self, address: &Address) -> Option<Bytes> {
+		match self.account(address) {
+			Ok(Some(account)) => match account.code_hash() {
+				ref hash if hash == &SHA3_EMPTY => Some(vec![]),
+				hash => self.node(&hash),
+			},
+			_ => None,
+		}
+	}
+
+	fn transaction(&self, id: TransactionId) -> Option<LocalizedTransaction> {
+		match id {
+			// there is no transaction index without block bodies
+			TransactionId::Hash(_) => None,
+			TransactionId::Location(id, index) => self.block(id).and_then(|block| BlockView::new(&block).localized_transaction_at(index)),
+		}
+	}
+
+	fn tree_route(&self, from: &H256, to: &H256) -> Option<TreeRoute> {
+		match self.chain.is_known(from) && self.chain.is_known(to) {
+			true => Some(self.chain.tree_route(from.clone(), to.clone())),
+			false => None
+		}
+	}
+
+	fn state_data(&self, _hash: &H256) -> Option<Bytes> {
+		None
+	}
+
+	fn block_receipts(&self, hash: &H256) -> Option<Bytes> {
+		self.receipts(hash)
+	}
+
+	fn prove_account(&self, address: &Address, storage_keys: &[H256], id: BlockId) -> Option<AccountProof> {
+		let header = match self.block_id_hash(id).and_then(|hash| self.header(&hash)) {
+			Some(header) => header,
+			None => return None,
+		};
+		let (account, proof) = match self.trie_get_with_proof(&header.state_root, &address.sha3()) {
+			Ok((account, proof)) => (account.map(|rlp| Account::from_rlp(&rlp)), proof),
+			Err(_) => return None,
+		};
+		let storage_root = account.as_ref().and_then(|a| a.storage_root().cloned()).unwrap_or(SHA3_NULL_RLP);
+		let mut storage = Vec::with_capacity(storage_keys.len());
+		for key in storage_keys {
+			match self.trie_get_with_proof(&storage_root, &key.sha3()) {
+				Ok((value, proof)) => storage.push(StorageProof {
+					key: key.clone(),
+					value: value.map_or(H256::new(), |v| H256::from(decode::<U256>(&v))),
+					proof: proof,
+				}),
+				Err(_) => return None,
+			}
+		}
+		Some(AccountProof {
+			balance: account.as_ref().map_or(U256::zero(), |a| a.balance().clone()),
+			nonce: account.as_ref().map_or(U256::zero(), |a| a.nonce().clone()),
+			code_hash: account.as_ref().map_or(SHA3_EMPTY, |a| a.code_hash()),
+			storage_root: storage_root,
+			proof: proof,
+			storage: storage,
+		})
+	}
+
+	fn import_block(&self, bytes: Bytes) -> ImportResult {
+		let header = BlockView::new(&bytes).header();
+		let hash = header.hash();
+		let _import_lock = self.import_lock.lock();
+		if self.chain.is_known(&hash) {
+			return Err(x!(ImportError::AlreadyInChain));
+		}
+		let parent = match self.header(&header.parent_hash) {
+			Some(parent) => parent,
+			None => return Err(x!(BlockError::UnknownParent(header.parent_hash.clone()))),
+		};
+		try!(verify_header_only(&header, &parent, self.engine.deref().deref()));
+
+		let route = self.chain.insert(&header.rlp(Seal::With));
+		if !route.enacted.is_empty() {
+			trace!(target: "light", "Imported header #{} ({})", header.number, hash);
+		}
+		Ok(hash)
+	}
+
+	fn queue_info(&self) -> BlockQueueInfo {
+		// headers are imported synchronously
+		BlockQueueInfo {
+			unverified_queue_size: 0,
+			verified_queue_size: 0,
+			verifying_queue_size: 0,
+			max_queue_size: 0,
+			max_mem_use: 0,
+			mem_used: 0,
+		}
+	}
+
+	fn clear_queue(&self) {}
+
+	fn chain_info(&self) -> BlockChainInfo {
+		BlockChainInfo {
+			total_difficulty: self.chain.best_block_total_difficulty(),
+			pending_total_difficulty: self.chain.best_block_total_difficulty(),
+			genesis_hash: self.chain.genesis_hash(),
+			best_block_hash: self.chain.best_block_hash(),
+			best_block_number: self.chain.best_block_number(),
+		}
+	}
+
+	fn blocks_with_bloom(&self, bloom: &H2048, from_block: BlockId, to_block: BlockId) -> Option<Vec<BlockNumber>> {
+		match (self.block_number(from_block), self.block_number(to_block)) {
+			(Some(from), Some(to)) => Some((from..to + 1)
+				.filter(|number| self.chain.block_hash(*number)
+					.and_then(|hash| self.chain.header(&hash))
+					.map_or(false, |header| HeaderView::new(&header).log_bloom().contains_bloomed(bloom)))
+				.collect()),
+			_ => None
+		}
+	}
+
+	fn logs(&self, filter: Filter) -> Vec<LocalizedLogEntry> {
+		let mut blocks = filter.bloom_possibilities().iter()
+			.filter_map(|bloom| self.blocks_with_bloom(bloom, filter.from_block.clone(), filter.to_block.clone()))
+			.flat_map(|m| m)
+			// remove duplicate elements
+			.collect::<HashSet<u64>>()
+			.into_iter()
+			.collect::<Vec<u64>>();
+
+		blocks.sort();
+
+		let logs = blocks.into_iter()
+			.filter_map(|number| self.chain.block_hash(number).map(|hash| (number, hash)))
+			.flat_map(|(number, hash)| self.block_logs_at(number, &hash, &filter))
+			.collect();
+		filter.apply_limit(logs)
+	}
+
+	fn block_logs(&self, hash: &H256, filter: &Filter) -> Vec<LocalizedLogEntry> {
+		match self.chain.block_details(hash) {
+			Some(details) => self.block_logs_at(details.number, hash, filter),
+			None => vec![],
+		}
+	}
+
+	/// Light client can't seal blocks; there is never a block to be sealed.
+	fn sealing_block(&self) -> &Mutex<Option<ClosedBlock>> {
+		&self.sealing_block
+	}
+
+	fn submit_seal(&self, _pow_hash: H256, _seal: Vec<Bytes>) -> Result<(), Error> {
+		Err(Error::PowHashInvalid)
+	}
+
+	fn seals_internally(&self) -> bool {
+		false
+	}
+
+	fn is_sealing_enabled(&self) -> bool {
+		false
+	}
+
+	fn seal_transactions(&self, _transactions: Vec<SignedTransaction>) -> Option<H256> {
+		// blocks can't be sealed without state.
+		None
+	}
+
+	fn sealing_config(&self) -> SealingConfig {
+		SealingConfig::default()
+	}
+
+	fn set_transactions_to_seal(&self, _transactions: Vec<SignedTransaction>) {}
+
+	fn verify_transaction_chain_id(&self, t: &SignedTransaction) -> Result<(), Error> {
+		let mut header = Header::new();
+		header.set_number(self.chain.best_block_number() + 1);
+		self.engine.verify_transaction_chain_id(t, &header)
+	}
+}
@@ -0,0 +1,239 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Verified header chain of the light client.
+
+use util::*;
+use header::BlockNumber;
+use views::HeaderView;
+use extras::{BlockDetails, ExtrasIndex, ExtrasReadable, ExtrasWritable, ExtrasSliceConvertable};
+use blockchain::{TreeRoute, ImportRoute};
+
+const BEST_KEY: &'static [u8] = b"best";
+
+struct BestHeader {
+	hash: H256,
+	number: BlockNumber,
+	total_difficulty: U256,
+}
+
+/// Header chain kept in a single database. Headers are stored by hash, block details and
+/// canonical hashes use the same keys as the full client's extras database.
+pub struct HeaderChain {
+	db: Arc<KeyValueDB>,
+	best: RwLock<BestHeader>,
+	genesis_hash: H256,
+}
+
+impl HeaderChain {
+	/// Opens the header chain stored in `db`, inserting the `genesis` header if it's empty.
+	pub fn new(genesis: &[u8], db: Arc<KeyValueDB>) -> HeaderChain {
+		let genesis_hash = genesis.sha3();
+		let best_hash = match db.get(BEST_KEY).unwrap() {
+			Some(best) => H256::from_slice(&best),
+			None => {
+				let header = HeaderView::new(genesis);
+				let batch = DBTransaction::new();
+				batch.put(&genesis_hash, genesis).unwrap();
+				batch.put_extras(&genesis_hash, &BlockDetails {
+					number: header.number(),
+					total_difficulty: header.difficulty(),
+					parent: header.parent_hash(),
+					children: vec![],
+				});
+				batch.put_extras(&header.number(), &genesis_hash);
+				batch.put(BEST_KEY, &genesis_hash).unwrap();
+				db.write(batch).unwrap();
+				genesis_hash.clone()
+			}
+		};
+
+		let details: BlockDetails = db.get_extras(&best_hash).expect("Best header details are written together with the best hash");
+		HeaderChain {
+			db: db,
+			best: RwLock::new(BestHeader {
+				hash: best_hash,
+				number: details.number,
+				total_difficulty: details.total_difficulty,
+			}),
+			genesis_hash: genesis_hash,
+		}
+	}
+
+	/// RLP of the header with given hash.
+	pub fn header(&self, hash: &H256) -> Option<Bytes> {
+		self.db.get(hash).unwrap()
+	}
+
+	/// Details of the block with given hash.
+	pub fn block_details(&self, hash: &H256) -> Option<BlockDetails> {
+		self.db.get_extras(hash)
+	}
+
+	/// Hash of the canonical block with given number.
+	pub fn block_hash(&self, number: BlockNumber) -> Option<H256> {
+		self.db.get_extras(&number)
+	}
+
+	/// Returns true if the header with given hash was imported.
+	pub fn is_known(&self, hash: &H256) -> bool {
+		self.db.extras_exists::<H256, BlockDetails>(hash)
+	}
+
+	/// Hash of the genesis block.
+	pub fn genesis_hash(&self) -> H256 {
+		self.genesis_hash.clone()
+	}
+
+	/// Hash of the best block.
+	pub fn best_block_hash(&self) -> H256 {
+		self.best.read().unwrap().hash.clone()
+	}
+
+	/// Number of the best block.
+	pub fn best_block_number(&self) -> BlockNumber {
+		self.best.read().unwrap().number
+	}
+
+	/// Total difficulty of the best block.
+	pub fn best_block_total_difficulty(&self) -> U256 {
+		self.best.read().unwrap().total_difficulty.clone()
+	}
+
+	/// Route between two known blocks. See `BlockChain::tree_route`.
+	pub fn tree_route(&self, from: H256, to: H256) -> TreeRoute {
+		TreeRoute::between(from, to, |hash| self.block_details(hash))
+	}
+
+	/// Inserts a header whose parent is already in the chain. The header must be verified.
+	/// Returns blocks which became canonical and those which stopped being canonical.
+	pub fn insert(&self, bytes: &[u8]) -> ImportRoute {
+		let header = HeaderView::new(bytes);
+		let hash = header.sha3();
+		if self.is_known(&hash) {
+			return ImportRoute::none();
+		}
+
+		let parent_hash = header.parent_hash();
+		let mut parent_details = self.block_details(&parent_hash).expect("Parent of inserted header must be in the chain");
+		let total_difficulty = parent_details.total_difficulty + header.difficulty();
+
+		let batch = DBTransaction::new();
+		batch.put(&hash, bytes).unwrap();
+		parent_details.children.push(hash.clone());
+		batch.put_extras(&parent_hash, &parent_details);
+		batch.put_extras(&hash, &BlockDetails {
+			number: header.number(),
+			total_difficulty: total_difficulty,
+			parent: parent_hash.clone(),
+			children: vec![],
+		});
+
+		let mut best = self.best.write().unwrap();
+		if total_difficulty <= best.total_difficulty {
+			self.db.write(batch).unwrap();
+			return ImportRoute::none();
+		}
+
+		let route = self.tree_route(best.hash.clone(), parent_hash);
+		let retracted = route.blocks[..route.index].to_vec();
+		let mut enacted = route.blocks[route.index..].to_vec();
+		enacted.push(hash.clone());
+
+		let ancestor_number = header.number() - enacted.len() as BlockNumber;
+		for (i, h) in enacted.iter().enumerate() {
+			batch.put_extras(&(ancestor_number + 1 + i as BlockNumber), h);
+		}
+		// the new best chain may be shorter than the old one
+		for number in (header.number() + 1)..(best.number + 1) {
+			batch.delete(&number.to_extras_slice(ExtrasIndex::BlockHash)).unwrap();
+		}
+		batch.put(BEST_KEY, &hash).unwrap();
+		self.db.write(batch).unwrap();
+
+		*best = BestHeader {
+			hash: hash,
+			number: header.number(),
+			total_difficulty: total_difficulty,
+		};
+		ImportRoute {
+			retracted: retracted,
+			enacted: enacted,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use util::*;
+	use header::Header;
+	use basic_types::Seal;
+	use super::*;
+
+	fn child(parent: &Header, difficulty: u64) -> Header {
+		let mut header = Header::new();
+		header.parent_hash = parent.hash();
+		header.number = parent.number + 1;
+		header.difficulty = U256::from(difficulty);
+		header.timestamp = parent.timestamp + difficulty;
+		header
+	}
+
+	#[test]
+	fn should_follow_heaviest_branch() {
+		let genesis = Header::new();
+		let chain = HeaderChain::new(&genesis.rlp(Seal::With), Arc::new(in_memory()));
+
+		let a1 = child(&genesis, 10);
+		let a2 = child(&a1, 10);
+		let a3 = child(&a2, 10);
+		for h in &[&a1, &a2, &a3] {
+			chain.insert(&h.rlp(Seal::With));
+		}
+		assert_eq!(chain.best_block_hash(), a3.hash());
+		assert_eq!(chain.block_hash(3), Some(a3.hash()));
+
+		// shorter, but heavier branch
+		let b2 = child(&a1, 100);
+		let route = chain.insert(&b2.rlp(Seal::With));
+		assert_eq!(route.enacted, vec![b2.hash()]);
+		assert_eq!(route.retracted, vec![a3.hash(), a2.hash()]);
+		assert_eq!(chain.best_block_number(), 2);
+		assert_eq!(chain.block_hash(2), Some(b2.hash()));
+		assert_eq!(chain.block_hash(3), None);
+		assert_eq!(chain.best_block_total_difficulty(), U256::from(110));
+
+		// lighter fork doesn't change anything
+		let c2 = child(&a1, 1);
+		assert_eq!(chain.insert(&c2.rlp(Seal::With)).enacted, vec![]);
+		assert_eq!(chain.best_block_hash(), b2.hash());
+		assert!(chain.is_known(&c2.hash()));
+	}
+
+	#[test]
+	fn should_reopen_at_best_header() {
+		let genesis = Header::new();
+		let db: Arc<KeyValueDB> = Arc::new(in_memory());
+		let a1 = child(&genesis, 10);
+		{
+			let chain = HeaderChain::new(&genesis.rlp(Seal::With), db.clone());
+			chain.insert(&a1.rlp(Seal::With));
+		}
+		let chain = HeaderChain::new(&genesis.rlp(Seal::With), db);
+		assert_eq!(chain.best_block_hash(), a1.hash());
+		assert_eq!(chain.header(&a1.hash()), Some(a1.rlp(Seal::With)));
+	}
+}
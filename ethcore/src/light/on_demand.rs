@@ -0,0 +1,46 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Retrieval of chain data from full peers.
+
+use util::*;
+
+/// Data the light client asks full peers for.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Request {
+	/// Body of the block with given hash.
+	Body(H256),
+	/// Receipts of the block with given hash.
+	Receipts(H256),
+	/// State trie node or contract code with given hash.
+	Node(H256),
+}
+
+/// Reason on-demand retrieval failed.
+#[derive(Debug, PartialEq, Clone)]
+pub enum FetchError {
+	/// No peer answered in time.
+	Timeout,
+	/// All peers asked gave data which failed verification.
+	BadResponse,
+}
+
+/// Fetches data from the network on behalf of the light client.
+pub trait OnDemand : Send + Sync {
+	/// Fetch the answer to `request`, blocking until it arrives. `verify` is called with each
+	/// response; peers giving data it rejects are disabled and the request is sent elsewhere.
+	fn fetch(&self, request: Request, verify: &Fn(&[u8]) -> bool) -> Result<Bytes, FetchError>;
+}
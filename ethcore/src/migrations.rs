@@ -0,0 +1,37 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Schema migrations of the client databases.
+//!
+//! Any change of the on-disk format of a database must come with a migration added
+//! (in version order) to the database's manager below.
+
+use util::migration::{Manager, Config};
+
+/// Migrations of the extras database.
+pub fn extras() -> Manager {
+	Manager::new(Config::default())
+}
+
+/// Migrations of the blocks database.
+pub fn blocks() -> Manager {
+	Manager::new(Config::default())
+}
+
+/// Migrations of the state database.
+pub fn state() -> Manager {
+	Manager::new(Config::default())
+}
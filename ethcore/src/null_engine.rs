@@ -16,9 +16,7 @@
 
 use engine::Engine;
 use spec::Spec;
-use evm::Schedule;
 use evm::Factory;
-use env_info::EnvInfo;
 
 /// An engine which does not provide any consensus mechanism.
 pub struct NullEngine {
@@ -43,5 +41,4 @@ impl Engine for NullEngine {
 	}
 	fn name(&self) -> &str { "NullEngine" }
 	fn spec(&self) -> &Spec { &self.spec }
-	fn schedule(&self, _env_info: &EnvInfo) -> Schedule { Schedule::new_frontier() }
 }
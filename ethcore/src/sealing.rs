@@ -0,0 +1,170 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+
+//! Policy of authoring blocks: the gas limit to aim for and which transactions and uncles to include.
+
+use common::*;
+
+/// Which uncles to include in authored blocks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnclePolicy {
+	/// As many as the engine allows.
+	All,
+	/// At most the given number, and no more than the engine allows.
+	AtMost(usize),
+}
+
+impl FromStr for UnclePolicy {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<UnclePolicy, String> {
+		match s {
+			"all" => Ok(UnclePolicy::All),
+			"none" => Ok(UnclePolicy::AtMost(0)),
+			_ => usize::from_str(s).map(UnclePolicy::AtMost).map_err(|_| format!("{}: expected all, none or a number of uncles", s)),
+		}
+	}
+}
+
+impl UnclePolicy {
+	/// The number of uncles to include when the engine allows at most `engine_max`.
+	pub fn limit(&self, engine_max: usize) -> usize {
+		match *self {
+			UnclePolicy::All => engine_max,
+			UnclePolicy::AtMost(n) => min(n, engine_max),
+		}
+	}
+}
+
+/// Configuration of block authoring.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SealingConfig {
+	/// Authored blocks raise the gas limit while it's below this target.
+	pub gas_floor_target: U256,
+	/// Authored blocks lower the gas limit while it's above this target.
+	pub gas_ceil_target: U256,
+	/// Minimum gas price of included transactions.
+	pub min_gas_price: U256,
+	/// Maximum gas of an included transaction. Unlimited if `None`.
+	pub max_gas_per_tx: Option<U256>,
+	/// Uncles to include.
+	pub uncles: UnclePolicy,
+	/// Gas at the end of each block which only local transactions, i.e. those sent from an account in
+	/// the node's secret store, may use.
+	pub reserved_local_gas: U256,
+}
+
+impl Default for SealingConfig {
+	fn default() -> SealingConfig {
+		SealingConfig {
+			gas_floor_target: U256::from(3_141_562),
+			gas_ceil_target: U256::from(6_283_184),
+			min_gas_price: U256::zero(),
+			max_gas_per_tx: None,
+			uncles: UnclePolicy::All,
+			reserved_local_gas: U256::zero(),
+		}
+	}
+}
+
+impl SealingConfig {
+	/// Whether transaction `t` may be included in a block with a gas limit of `gas_limit` of which
+	/// `gas_used` is already used.
+	pub fn admits(&self, t: &SignedTransaction, local: bool, gas_used: &U256, gas_limit: &U256) -> bool {
+		if t.gas_price < self.min_gas_price || self.max_gas_per_tx.map_or(false, |max| t.gas > max) {
+			return false;
+		}
+		let available = match local {
+			true => *gas_limit,
+			false if *gas_limit > self.reserved_local_gas => *gas_limit - self.reserved_local_gas,
+			false => U256::zero(),
+		};
+		*gas_used + t.gas <= available
+	}
+}
+
+/// The gas limit of a block authored on top of `parent`. It moves towards the range between
+/// `floor` and `ceil` and within that follows the parent's gas usage, changing by less than
+/// `1/bound_divisor` of the parent's gas limit.
+pub fn target_gas_limit(parent: &Header, bound_divisor: &U256, floor: &U256, ceil: &U256) -> U256 {
+	let gas_limit = parent.gas_limit;
+	let step = gas_limit / *bound_divisor;
+	if gas_limit < *floor {
+		min(*floor, gas_limit + step - U256::one())
+	} else if gas_limit > *ceil {
+		max(*ceil, gas_limit - step + U256::one())
+	} else {
+		let by_usage = gas_limit - step + U256::one() + (parent.gas_used * U256::from(6) / U256::from(5)) / *bound_divisor;
+		max(*floor, min(*ceil, by_usage))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use common::*;
+	use super::*;
+
+	fn parent(gas_limit: u64, gas_used: u64) -> Header {
+		let mut header = Header::new();
+		header.gas_limit = U256::from(gas_limit);
+		header.gas_used = U256::from(gas_used);
+		header
+	}
+
+	#[test]
+	fn moves_gas_limit_towards_targets() {
+		let divisor = U256::from(1024);
+		let floor = U256::from(3_000_000);
+		let ceil = U256::from(4_000_000);
+
+		assert_eq!(target_gas_limit(&parent(2_048_000, 0), &divisor, &floor, &ceil), U256::from(2_049_999));
+		assert_eq!(target_gas_limit(&parent(2_999_999, 0), &divisor, &floor, &ceil), floor);
+		assert_eq!(target_gas_limit(&parent(5_120_000, 0), &divisor, &floor, &ceil), U256::from(5_115_001));
+		assert_eq!(target_gas_limit(&parent(4_000_001, 4_000_001), &divisor, &floor, &ceil), ceil);
+		assert_eq!(target_gas_limit(&parent(3_072_000, 0), &divisor, &floor, &ceil), U256::from(3_069_001));
+		assert_eq!(target_gas_limit(&parent(3_001_000, 0), &divisor, &floor, &ceil), floor);
+		assert_eq!(target_gas_limit(&parent(3_072_000, 3_072_000), &divisor, &floor, &ceil), U256::from(3_072_601));
+	}
+
+	#[test]
+	fn admits_transactions_by_policy() {
+		let config = SealingConfig {
+			min_gas_price: U256::from(10),
+			max_gas_per_tx: Some(U256::from(100_000)),
+			reserved_local_gas: U256::from(50_000),
+			..SealingConfig::default()
+		};
+		let transaction = |gas: u64, gas_price: u64| Transaction { gas: U256::from(gas), gas_price: U256::from(gas_price), ..Transaction::default() }.fake_sign();
+		let limit = U256::from(200_000);
+
+		assert!(config.admits(&transaction(21_000, 10), false, &U256::zero(), &limit));
+		assert!(!config.admits(&transaction(21_000, 9), false, &U256::zero(), &limit));
+		assert!(!config.admits(&transaction(100_001, 10), true, &U256::zero(), &limit));
+		assert!(!config.admits(&transaction(21_000, 10), false, &U256::from(130_000), &limit));
+		assert!(config.admits(&transaction(21_000, 10), true, &U256::from(130_000), &limit));
+		assert!(!config.admits(&transaction(21_000, 10), true, &U256::from(180_000), &limit));
+	}
+
+	#[test]
+	fn parses_uncle_policy() {
+		assert_eq!("all".parse::<UnclePolicy>().unwrap().limit(2), 2);
+		assert_eq!("none".parse::<UnclePolicy>().unwrap().limit(2), 0);
+		assert_eq!("1".parse::<UnclePolicy>().unwrap().limit(2), 1);
+		assert_eq!("5".parse::<UnclePolicy>().unwrap().limit(2), 2);
+		assert!("some".parse::<UnclePolicy>().is_err());
+	}
+}
@@ -33,9 +33,13 @@ pub enum SyncMessage {
 		bad: Vec<H256>,
 		/// Hashes of blocks that were removed from canonical chain
 		retracted: Vec<H256>,
+		/// Hashes of blocks that became part of canonical chain, oldest first
+		enacted: Vec<H256>,
 	},
 	/// A block is ready
 	BlockVerified,
+	/// New transactions have been added to the transaction queue
+	NewTransactions(Vec<H256>),
 }
 
 /// IO Message type used for Network service
@@ -105,15 +109,24 @@ struct ClientIoHandler {
 
 const CLIENT_TICK_TIMER: TimerToken = 0;
 const CLIENT_TICK_MS: u64 = 5000;
+const CACHE_COLLECTION_TIMER: TimerToken = 1;
+const CACHE_COLLECTION_MS: u64 = 1000;
+const SEALING_TIMER: TimerToken = 2;
+const SEALING_MS: u64 = 1000;
 
 impl IoHandler<NetSyncMessage> for ClientIoHandler {
 	fn initialize(&self, io: &IoContext<NetSyncMessage>) {
 		io.register_timer(CLIENT_TICK_TIMER, CLIENT_TICK_MS).expect("Error registering client timer");
+		io.register_timer(CACHE_COLLECTION_TIMER, CACHE_COLLECTION_MS).expect("Error registering cache collection timer");
+		io.register_timer(SEALING_TIMER, SEALING_MS).expect("Error registering sealing timer");
 	}
 
 	fn timeout(&self, _io: &IoContext<NetSyncMessage>, timer: TimerToken) {
-		if timer == CLIENT_TICK_TIMER {
-			self.client.tick();
+		match timer {
+			CLIENT_TICK_TIMER => self.client.tick(),
+			CACHE_COLLECTION_TIMER => self.client.collect_garbage(),
+			SEALING_TIMER => self.client.update_sealing(),
+			_ => {},
 		}
 	}
 
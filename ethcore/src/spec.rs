@@ -20,12 +20,16 @@ use common::*;
 use engine::*;
 use pod_state::*;
 use null_engine::*;
+use authority_round::*;
+use instant_seal::*;
+use pod_account::*;
+use util::crypto::KeyPair;
+use transitions::*;
 use account_db::*;
 
-/// Convert JSON value to equivalent RLP representation.
-// TODO: handle container types.
-fn json_to_rlp(json: &Json) -> Bytes {
-	match *json {
+/// Convert JSON value at `path` to equivalent RLP representation.
+fn json_to_rlp(json: &Json, path: &str) -> Result<Bytes, SpecError> {
+	Ok(match *json {
 		Json::Boolean(o) => encode(&(if o {1u64} else {0})).to_vec(),
 		Json::I64(o) => encode(&(o as u64)).to_vec(),
 		Json::U64(o) => encode(&o).to_vec(),
@@ -35,16 +39,247 @@ fn json_to_rlp(json: &Json) -> Bytes {
 		Json::String(ref s) => {
 			encode(s).to_vec()
 		},
-		_ => panic!()
+		Json::Array(ref items) => {
+			let mut s = RlpStream::new_list(items.len());
+			for (i, item) in items.iter().enumerate() {
+				match *item {
+					// keep fixed-size data such as addresses at full length
+					Json::String(ref h) if h.len() >= 2 && &h[0..2] == "0x" && h[2..].from_hex().is_ok() => {
+						s.append(&h[2..].from_hex().unwrap());
+					},
+					_ => {
+						s.append_raw(&try!(json_to_rlp(item, &format!("{}.{}", path, i))), 1);
+					},
+				}
+			}
+			s.out()
+		},
+		_ => return Err(SpecError::InvalidValue { path: path.to_owned(), expected: "a number, string, boolean or list" }),
+	})
+}
+
+/// Convert JSON object at `path` to a string->RLP map.
+fn json_to_rlp_map(json: &Json, path: &str) -> Result<HashMap<String, Bytes>, SpecError> {
+	let object = try!(json.as_object().ok_or_else(|| SpecError::InvalidValue { path: path.to_owned(), expected: "an object" }));
+	let mut map = HashMap::new();
+	for (k, v) in object {
+		map.insert(k.clone(), try!(json_to_rlp(v, &format!("{}.{}", path, k))));
 	}
+	Ok(map)
 }
 
-/// Convert JSON to a string->RLP map.
-fn json_to_rlp_map(json: &Json) -> HashMap<String, Bytes> {
-	json.as_object().unwrap().iter().map(|(k, v)| (k, json_to_rlp(v))).fold(HashMap::new(), |mut acc, kv| {
-		acc.insert(kv.0.clone(), kv.1);
-		acc
-	})
+/// A value which may be read from a chain specification.
+trait SpecValue: Sized {
+	/// Description of a valid value, for error messages.
+	fn expected() -> &'static str;
+	/// Read the value from `json`, or `None` if it isn't valid.
+	fn from_spec(json: &Json) -> Option<Self>;
+}
+
+impl SpecValue for U256 {
+	fn expected() -> &'static str { "a number or a 0x-prefixed hex string" }
+	fn from_spec(json: &Json) -> Option<U256> {
+		match *json {
+			Json::U64(n) => Some(U256::from(n)),
+			Json::String(ref s) if s.starts_with("0x") => U256::from_str(&s[2..]).ok(),
+			Json::String(ref s) => U256::from_dec_str(s).ok(),
+			_ => None,
+		}
+	}
+}
+
+impl SpecValue for u64 {
+	fn expected() -> &'static str { "a 64-bit number or a 0x-prefixed hex string" }
+	fn from_spec(json: &Json) -> Option<u64> {
+		U256::from_spec(json).and_then(|n| if n > U256::from(u64::max_value()) { None } else { Some(n.low_u64()) })
+	}
+}
+
+impl SpecValue for Bytes {
+	fn expected() -> &'static str { "a 0x-prefixed hex string" }
+	fn from_spec(json: &Json) -> Option<Bytes> {
+		json.as_string().and_then(|s| if s.starts_with("0x") { s[2..].from_hex().ok() } else { None })
+	}
+}
+
+impl SpecValue for String {
+	fn expected() -> &'static str { "a string" }
+	fn from_spec(json: &Json) -> Option<String> {
+		json.as_string().map(ToOwned::to_owned)
+	}
+}
+
+impl SpecValue for bool {
+	fn expected() -> &'static str { "a boolean" }
+	fn from_spec(json: &Json) -> Option<bool> {
+		json.as_boolean()
+	}
+}
+
+macro_rules! impl_hash_spec_value {
+	($hash: ident, $expected: expr) => {
+		impl SpecValue for $hash {
+			fn expected() -> &'static str { $expected }
+			fn from_spec(json: &Json) -> Option<$hash> {
+				json.as_string().and_then(|s| if s.starts_with("0x") { $hash::from_str(&s[2..]).ok() } else { None })
+			}
+		}
+	}
+}
+
+impl_hash_spec_value!(H64, "a 0x-prefixed 8-byte hex string");
+impl_hash_spec_value!(Address, "a 0x-prefixed 20-byte hex string");
+impl_hash_spec_value!(H256, "a 0x-prefixed 32-byte hex string");
+
+fn field_path(parent: &str, key: &str) -> String {
+	if parent.is_empty() { key.to_owned() } else { format!("{}.{}", parent, key) }
+}
+
+/// Read the optional field `key` of the object at `parent`.
+fn optional_field<T: SpecValue>(json: &Json, parent: &str, key: &str) -> Result<Option<T>, SpecError> {
+	match json.find(key) {
+		Some(value) => T::from_spec(value)
+			.map(Some)
+			.ok_or_else(|| SpecError::InvalidValue { path: field_path(parent, key), expected: T::expected() }),
+		None => Ok(None),
+	}
+}
+
+/// Read the field `key` of the object at `parent`.
+fn field<T: SpecValue>(json: &Json, parent: &str, key: &str) -> Result<T, SpecError> {
+	optional_field(json, parent, key).and_then(|v| v.ok_or_else(|| SpecError::MissingField(field_path(parent, key))))
+}
+
+/// Get the object `key` of the object at `parent`.
+fn object_field<'a>(json: &'a Json, parent: &str, key: &str) -> Result<&'a Json, SpecError> {
+	match json.find(key) {
+		Some(value) if value.is_object() => Ok(value),
+		Some(_) => Err(SpecError::InvalidValue { path: field_path(parent, key), expected: "an object" }),
+		None => Err(SpecError::MissingField(field_path(parent, key))),
+	}
+}
+
+/// Read account storage from an object mapping slots to values, both numbers.
+fn storage_from_json(json: &Json, path: &str) -> Result<BTreeMap<H256, H256>, SpecError> {
+	let object = try!(json.as_object().ok_or_else(|| SpecError::InvalidValue { path: path.to_owned(), expected: "an object" }));
+	let mut storage = BTreeMap::new();
+	for (key, value) in object {
+		let slot = try!(U256::from_spec(&Json::String(key.clone())).ok_or_else(|| SpecError::InvalidValue {
+			path: field_path(path, key),
+			expected: "storage keyed by numbers or 0x-prefixed hex strings",
+		}));
+		let value: U256 = try!(field(json, path, key));
+		storage.insert(H256::from(slot), H256::from(value));
+	}
+	Ok(storage)
+}
+
+/// Read whether the optional rules `key` of the transition at `parent` are `"homestead"` rather
+/// than `"frontier"` ones.
+fn homestead_field(json: &Json, parent: &str, key: &str) -> Result<Option<bool>, SpecError> {
+	match try!(optional_field::<String>(json, parent, key)).map(|s| s.to_lowercase()) {
+		Some(ref s) if s == "frontier" => Ok(Some(false)),
+		Some(ref s) if s == "homestead" => Ok(Some(true)),
+		Some(_) => Err(SpecError::InvalidValue { path: field_path(parent, key), expected: "\"frontier\" or \"homestead\"" }),
+		None => Ok(None),
+	}
+}
+
+/// Read transitions from a list of `{ "name", "block", "schedule", "difficulty", "bombDelay",
+/// "transactions", "replayProtection" }` objects.
+fn transitions_from_json(json: &Json) -> Result<Transitions, SpecError> {
+	let list = try!(json.as_array().ok_or_else(|| SpecError::InvalidValue { path: "transitions".to_owned(), expected: "a list of transitions" }));
+	let mut transitions = Vec::with_capacity(list.len());
+	for (i, t) in list.iter().enumerate() {
+		let path = format!("transitions.{}", i);
+		if !t.is_object() {
+			return Err(SpecError::InvalidValue { path: path, expected: "an object" });
+		}
+		transitions.push(Transition {
+			name: try!(optional_field(t, &path, "name")).unwrap_or_else(|| "unnamed".to_owned()),
+			block: try!(field(t, &path, "block")),
+			schedule: try!(homestead_field(t, &path, "schedule")).map(|h| if h { ScheduleVariant::Homestead } else { ScheduleVariant::Frontier }),
+			difficulty: try!(homestead_field(t, &path, "difficulty")).map(|h| if h { DifficultyRule::Homestead } else { DifficultyRule::Frontier }),
+			bomb_delay: try!(optional_field(t, &path, "bombDelay")),
+			transaction_rules: try!(homestead_field(t, &path, "transactions")).map(|h| if h { TransactionRules::Homestead } else { TransactionRules::Frontier }),
+			replay_protection: try!(optional_field(t, &path, "replayProtection")),
+		});
+	}
+	Ok(Transitions::new(transitions))
+}
+
+/// Engine params read by every engine.
+const COMMON_ENGINE_PARAMS: &'static [&'static str] = &["accountStartNonce", "maximumExtraDataSize", "minGasLimit"];
+/// Engine params read by `Ethash`.
+const ETHASH_ENGINE_PARAMS: &'static [&'static str] = &["minimumDifficulty", "difficultyBoundDivisor", "durationLimit", "gasLimitBoundDivisor"];
+/// Engine params used as divisors.
+const NON_ZERO_ENGINE_PARAMS: &'static [&'static str] = &["difficultyBoundDivisor", "gasLimitBoundDivisor"];
+
+/// Engines a spec may name in `engineName`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum EngineKind {
+	Null,
+	Ethash,
+	AuthorityRound,
+	InstantSeal,
+}
+
+impl EngineKind {
+	fn from_name(name: &str) -> Option<EngineKind> {
+		match name {
+			"NullEngine" => Some(EngineKind::Null),
+			"Ethash" => Some(EngineKind::Ethash),
+			"AuthorityRound" => Some(EngineKind::AuthorityRound),
+			"InstantSeal" => Some(EngineKind::InstantSeal),
+			_ => None,
+		}
+	}
+}
+
+/// Check that the engine `engine_name` is known and that `params` has what it requires.
+fn check_engine_params(engine_name: &str, params: &HashMap<String, Bytes>) -> Result<(), SpecError> {
+	let kind = try!(EngineKind::from_name(engine_name).ok_or_else(|| SpecError::UnknownEngine(engine_name.to_owned())));
+	let engine_params: &[&str] = match kind {
+		EngineKind::Ethash => ETHASH_ENGINE_PARAMS,
+		_ => &[],
+	};
+	for name in COMMON_ENGINE_PARAMS.iter().chain(engine_params) {
+		let path = field_path("params", name);
+		let value: U256 = match params.get(*name).map(|rlp| UntrustedRlp::new(rlp).as_val()) {
+			Some(Ok(value)) => value,
+			Some(Err(_)) => return Err(SpecError::InvalidValue { path: path, expected: "a number" }),
+			None => return Err(SpecError::MissingField(path)),
+		};
+		if value.is_zero() && NON_ZERO_ENGINE_PARAMS.contains(name) {
+			return Err(SpecError::InvalidValue { path: path, expected: "a non-zero number" });
+		}
+	}
+	if kind == EngineKind::AuthorityRound {
+		if let Some(Err(_)) = params.get("stepDuration").map(|rlp| UntrustedRlp::new(rlp).as_val::<u64>()) {
+			return Err(SpecError::InvalidValue { path: field_path("params", "stepDuration"), expected: "a 64-bit number" });
+		}
+		let path = field_path("params", "validators");
+		let validators = match params.get("validators").map(|rlp| UntrustedRlp::new(rlp).as_val::<Vec<Address>>()) {
+			Some(Ok(validators)) => validators,
+			Some(Err(_)) => return Err(SpecError::InvalidValue { path: path, expected: "a list of addresses" }),
+			None => return Err(SpecError::MissingField(path)),
+		};
+		if validators.is_empty() {
+			return Err(SpecError::InvalidValue { path: path, expected: "a non-empty list of addresses" });
+		}
+	}
+	Ok(())
+}
+
+/// Number of prefunded accounts on the development chain.
+const DEV_ACCOUNTS: usize = 5;
+
+/// Key pairs of the prefunded accounts of the development chain. They are derived from fixed
+/// phrases so that the genesis is the same on every run; never use them on a public chain.
+pub fn dev_key_pairs() -> Vec<KeyPair> {
+	(0..DEV_ACCOUNTS)
+		.map(|i| KeyPair::from_secret(format!("parity dev account {}", i).sha3()).expect("secret is a valid key"))
+		.collect()
 }
 
 /// Parameters for a block chain; includes both those intrinsic to the design of the
@@ -60,11 +295,16 @@ pub struct Spec {
 	pub nodes: Vec<String>,
 	/// Network ID
 	pub network_id: U256,
+	/// Chain ID that transaction signatures may be bound to once replay protection is in force.
+	pub chain_id: Option<u64>,
 
 	/// Parameters concerning operation of the specific engine we're using.
 	/// Maps the parameter name to an RLP-encoded value.
 	pub engine_params: HashMap<String, Bytes>,
 
+	/// Hard forks of the chain and the rules they bring in.
+	pub transitions: Transitions,
+
 	/// Builtin-contracts we would like to see in the chain.
 	/// (In principle these are just hints for the engine since that has the last word on them.)
 	pub builtins: BTreeMap<Address, Builtin>,
@@ -104,10 +344,18 @@ impl Spec {
 	/// Convert this object into a boxed Engine of the right underlying type.
 	// TODO avoid this hard-coded nastiness - use dynamic-linked plugin framework instead.
 	pub fn to_engine(self) -> Result<Box<Engine>, Error> {
-		match self.engine_name.as_ref() {
-			"NullEngine" => Ok(NullEngine::new_boxed(self)),
-			"Ethash" => Ok(super::ethereum::Ethash::new_boxed(self)),
-			_ => Err(Error::UnknownEngineName(self.engine_name.clone()))
+		self.to_engine_with_cache_dir(None)
+	}
+
+	/// Like `to_engine`, with proof-of-work caches and datasets kept in `cache_dir` instead of the
+	/// engine's default location.
+	pub fn to_engine_with_cache_dir(self, cache_dir: Option<&Path>) -> Result<Box<Engine>, Error> {
+		match EngineKind::from_name(&self.engine_name) {
+			Some(EngineKind::Null) => Ok(NullEngine::new_boxed(self)),
+			Some(EngineKind::Ethash) => Ok(super::ethereum::Ethash::new_boxed(self, cache_dir)),
+			Some(EngineKind::AuthorityRound) => Ok(AuthorityRound::new_boxed(self)),
+			Some(EngineKind::InstantSeal) => Ok(InstantSeal::new_boxed(self)),
+			None => Err(Error::UnknownEngineName(self.engine_name.clone()))
 		}
 	}
 
@@ -212,65 +460,7 @@ impl Spec {
 impl FromJson for Spec {
 	/// Loads a chain-specification from a json data structure
 	fn from_json(json: &Json) -> Spec {
-		// once we commit ourselves to some json parsing library (serde?)
-		// move it to proper data structure
-		let mut builtins = BTreeMap::new();
-		let mut state = PodState::new();
-
-		if let Some(&Json::Object(ref accounts)) = json.find("accounts") {
-			for (address, acc) in accounts.iter() {
-				let addr = Address::from_str(address).unwrap();
-				if let Some(ref builtin_json) = acc.find("builtin") {
-					if let Some(builtin) = Builtin::from_json(builtin_json) {
-						builtins.insert(addr.clone(), builtin);
-					}
-				}
-			}
-			state = xjson!(&json["accounts"]);
-		}
-
-		let nodes = if let Some(&Json::Array(ref ns)) = json.find("nodes") {
-			ns.iter().filter_map(|n| if let Json::String(ref s) = *n { Some(s.clone()) } else {None}).collect()
-		} else { Vec::new() };
-
-		let genesis = &json["genesis"];//.as_object().expect("No genesis object in JSON");
-
-		let (seal_fields, seal_rlp) = {
-			if genesis.find("mixHash").is_some() && genesis.find("nonce").is_some() {
-				let mut s = RlpStream::new();
-				s.append(&H256::from_str(&genesis["mixHash"].as_string().expect("mixHash not a string.")[2..]).expect("Invalid mixHash string value"));
-				s.append(&H64::from_str(&genesis["nonce"].as_string().expect("nonce not a string.")[2..]).expect("Invalid nonce string value"));
-				(2, s.out())
-			} else {
-				// backup algo that will work with sealFields/sealRlp (and without).
-				(
-					usize::from_str(&genesis["sealFields"].as_string().unwrap_or("0x")[2..]).expect("Invalid sealFields integer data"),
-					genesis["sealRlp"].as_string().unwrap_or("0x")[2..].from_hex().expect("Invalid sealRlp hex data")
-				)
-			}
-		};
-
-		Spec {
-			name: json.find("name").map_or("unknown", |j| j.as_string().unwrap()).to_owned(),
-			engine_name: json["engineName"].as_string().unwrap().to_owned(),
-			engine_params: json_to_rlp_map(&json["params"]),
-			nodes: nodes,
-			network_id: U256::from_str(&json["params"]["networkID"].as_string().unwrap()[2..]).unwrap(),
-			builtins: builtins,
-			parent_hash: H256::from_str(&genesis["parentHash"].as_string().unwrap()[2..]).unwrap(),
-			author: Address::from_str(&genesis["author"].as_string().unwrap()[2..]).unwrap(),
-			difficulty: U256::from_str(&genesis["difficulty"].as_string().unwrap()[2..]).unwrap(),
-			gas_limit: U256::from_str(&genesis["gasLimit"].as_string().unwrap()[2..]).unwrap(),
-			gas_used: U256::from(0u8),
-			timestamp: u64::from_str(&genesis["timestamp"].as_string().unwrap()[2..]).unwrap(),
-			transactions_root: SHA3_NULL_RLP.clone(),
-			receipts_root: SHA3_NULL_RLP.clone(),
-			extra_data: genesis["extraData"].as_string().unwrap()[2..].from_hex().unwrap(),
-			genesis_state: state,
-			seal_fields: seal_fields,
-			seal_rlp: seal_rlp,
-			state_root_memo: RwLock::new(genesis.find("stateRoot").and_then(|_| genesis["stateRoot"].as_string()).map(|s| H256::from_str(&s[2..]).unwrap())),
-		}
+		Spec::load_json(json).unwrap_or_else(|e| panic!("Invalid chain specification: {}", e))
 	}
 }
 
@@ -293,26 +483,158 @@ impl Spec {
 		} else { false }
 	}
 
-	/// Create a new Spec from a JSON UTF-8 data resource `data`.
+	/// Create a new Spec from a JSON UTF-8 data resource `data`. Panics if it isn't valid; use `load`
+	/// for specifications which don't come with Parity.
 	pub fn from_json_utf8(data: &[u8]) -> Spec {
-		Self::from_json_str(::std::str::from_utf8(data).unwrap())
+		Self::load(data).unwrap_or_else(|e| panic!("Invalid chain specification: {}", e))
 	}
 
-	/// Create a new Spec from a JSON string.
+	/// Create a new Spec from a JSON string. Panics if it isn't valid.
 	pub fn from_json_str(s: &str) -> Spec {
-		Self::from_json(&Json::from_str(s).expect("Json is invalid"))
+		Self::from_json_utf8(s.as_bytes())
+	}
+
+	/// Load a Spec from JSON UTF-8 data `data`, checking that all fields are present and valid.
+	pub fn load(data: &[u8]) -> Result<Spec, SpecError> {
+		let s = try!(::std::str::from_utf8(data).map_err(|e| SpecError::InvalidJson(format!("{}", e))));
+		let json = try!(Json::from_str(s).map_err(|e| SpecError::InvalidJson(format!("{}", e))));
+		Self::load_json(&json)
+	}
+
+	/// Load a Spec from a JSON data structure, checking that all fields are present and valid.
+	pub fn load_json(json: &Json) -> Result<Spec, SpecError> {
+		if !json.is_object() {
+			return Err(SpecError::InvalidValue { path: "".to_owned(), expected: "an object" });
+		}
+		let engine_name: String = try!(field(json, "", "engineName"));
+		let engine_params = try!(json_to_rlp_map(try!(object_field(json, "", "params")), "params"));
+		try!(check_engine_params(&engine_name, &engine_params));
+		let params = &json["params"];
+
+		let mut builtins = BTreeMap::new();
+		let builtin_registry = Registry::default();
+		let mut state = BTreeMap::new();
+		if json.find("accounts").is_some() {
+			let accounts = try!(object_field(json, "", "accounts"));
+			for (address, acc) in accounts.as_object().expect("checked by object_field; qed") {
+				let path = field_path("accounts", address);
+				let addr = try!(Address::from_str(address).map_err(|_| SpecError::InvalidValue {
+					path: path.clone(),
+					expected: "accounts keyed by 20-byte hex addresses",
+				}));
+				if !acc.is_object() {
+					return Err(SpecError::InvalidValue { path: path, expected: "an object" });
+				}
+				let balance = try!(optional_field::<U256>(acc, &path, "balance"));
+				let nonce = try!(optional_field::<U256>(acc, &path, "nonce"));
+				let code = try!(optional_field::<Bytes>(acc, &path, "code"));
+				let storage = match acc.find("storage") {
+					Some(storage) => Some(try!(storage_from_json(storage, &field_path(&path, "storage")))),
+					None => None,
+				};
+				if balance.is_some() || nonce.is_some() || storage.is_some() || code.is_some() {
+					state.insert(addr.clone(), PodAccount::new(
+						balance.unwrap_or_else(U256::zero),
+						nonce.unwrap_or_else(U256::zero),
+						code.unwrap_or_else(Vec::new),
+						storage.unwrap_or_else(BTreeMap::new),
+					));
+				}
+				if let Some(builtin_json) = acc.find("builtin") {
+					let builtin = try!(builtin_registry.builtin_from_json(builtin_json).ok_or_else(|| SpecError::InvalidValue {
+						path: field_path(&path, "builtin"),
+						expected: "a known builtin name with linear or modexp pricing",
+					}));
+					builtins.insert(addr, builtin);
+				}
+			}
+		}
+
+		let nodes = match json.find("nodes") {
+			Some(&Json::Array(ref ns)) => try!(ns.iter().enumerate().map(|(i, n)| String::from_spec(n).ok_or_else(|| SpecError::InvalidValue {
+				path: format!("nodes.{}", i),
+				expected: "an enode URL",
+			})).collect::<Result<Vec<_>, _>>()),
+			Some(_) => return Err(SpecError::InvalidValue { path: "nodes".to_owned(), expected: "a list of enode URLs" }),
+			None => Vec::new(),
+		};
+
+		let transitions = match (json.find("transitions"), params.find("frontierCompatibilityModeLimit")) {
+			(Some(transitions), _) => try!(transitions_from_json(transitions)),
+			(None, Some(_)) => Transitions::new_homestead_at(try!(field(params, "params", "frontierCompatibilityModeLimit"))),
+			(None, None) => Transitions::new(vec![]),
+		};
+
+		let genesis = try!(object_field(json, "", "genesis"));
+		let (seal_fields, seal_rlp) = {
+			if genesis.find("mixHash").is_some() && genesis.find("nonce").is_some() {
+				let mut s = RlpStream::new();
+				s.append(&try!(field::<H256>(genesis, "genesis", "mixHash")));
+				s.append(&try!(field::<H64>(genesis, "genesis", "nonce")));
+				(2, s.out())
+			} else {
+				// backup algo that will work with sealFields/sealRlp (and without).
+				(
+					try!(optional_field::<u64>(genesis, "genesis", "sealFields")).unwrap_or(0) as usize,
+					try!(optional_field::<Bytes>(genesis, "genesis", "sealRlp")).unwrap_or_else(Vec::new),
+				)
+			}
+		};
+
+		Ok(Spec {
+			name: try!(optional_field(json, "", "name")).unwrap_or_else(|| "unknown".to_owned()),
+			engine_name: engine_name,
+			engine_params: engine_params,
+			nodes: nodes,
+			transitions: transitions,
+			network_id: try!(field(params, "params", "networkID")),
+			chain_id: try!(optional_field(params, "params", "chainID")),
+			builtins: builtins,
+			parent_hash: try!(field(genesis, "genesis", "parentHash")),
+			author: try!(field(genesis, "genesis", "author")),
+			difficulty: try!(field(genesis, "genesis", "difficulty")),
+			gas_limit: try!(field(genesis, "genesis", "gasLimit")),
+			gas_used: U256::from(0u8),
+			timestamp: try!(field(genesis, "genesis", "timestamp")),
+			transactions_root: SHA3_NULL_RLP.clone(),
+			receipts_root: SHA3_NULL_RLP.clone(),
+			extra_data: try!(field(genesis, "genesis", "extraData")),
+			genesis_state: PodState::from(state),
+			seal_fields: seal_fields,
+			seal_rlp: seal_rlp,
+			state_root_memo: RwLock::new(try!(optional_field(genesis, "genesis", "stateRoot"))),
+		})
 	}
 
 	/// Create a new Spec which conforms to the Morden chain except that it's a NullEngine consensus.
 	pub fn new_test() -> Spec { Self::from_json_utf8(include_bytes!("../res/null_morden.json")) }
+
+	/// Create a new Spec for a development chain sealed by `InstantSeal` on which each of
+	/// `accounts` starts with a million ether.
+	pub fn new_dev(accounts: &[Address]) -> Spec {
+		let mut spec = Self::from_json_utf8(include_bytes!("../res/instant_seal.json"));
+		let mut state = spec.genesis_state.get().clone();
+		for address in accounts {
+			state.insert(address.clone(), PodAccount::new(U256::exp10(24), U256::zero(), vec![], BTreeMap::new()));
+		}
+		spec.set_genesis_state(PodState::from(state));
+		spec
+	}
+
+	/// Create a new Spec with an `AuthorityRound` consensus and a single test validator.
+	pub fn new_test_round() -> Spec { Self::from_json_utf8(include_bytes!("../res/authority_round.json")) }
 }
 
 #[cfg(test)]
 mod tests {
+	use common::*;
 	use std::str::FromStr;
 	use util::hash::*;
 	use util::sha3::*;
+	use util::rlp::decode;
+	use util::numbers::U256;
 	use views::*;
+	use transitions::*;
 	use super::*;
 
 	#[test]
@@ -325,4 +647,148 @@ mod tests {
 
 		let _ = test_spec.to_engine();
 	}
+
+	#[test]
+	fn encodes_array_params() {
+		let spec = Spec::new_test_round();
+		let validators: Vec<Address> = decode(spec.engine_params.get("validators").unwrap());
+		assert_eq!(validators, vec![Address::from_str("0000000000000000000000000000000000000001").unwrap()]);
+		assert_eq!(spec.to_engine().unwrap().name(), "AuthorityRound");
+	}
+
+	#[test]
+	fn prefunds_dev_accounts() {
+		let accounts: Vec<Address> = dev_key_pairs().iter().map(|pair| Address::from(pair.public().sha3())).collect();
+
+		let spec = Spec::new_dev(&accounts);
+		assert_eq!(spec.genesis_state.get()[&accounts[0]].balance, U256::exp10(24));
+		assert!(spec.genesis_state.get().contains_key(&Address::from(1)));
+		assert!(spec.state_root() != Spec::new_dev(&[]).state_root());
+		assert_eq!(spec.to_engine().unwrap().name(), "InstantSeal");
+	}
+
+	fn load_modified<F>(f: F) -> Result<Spec, SpecError> where F: FnOnce(&mut BTreeMap<String, Json>) {
+		let mut json = Json::from_str(::std::str::from_utf8(include_bytes!("../res/ethereum/morden.json")).unwrap()).unwrap();
+		f(json.as_object_mut().unwrap());
+		Spec::load_json(&json)
+	}
+
+	#[test]
+	fn loads_valid_spec() {
+		let spec = load_modified(|_| {}).unwrap();
+		assert_eq!(spec.genesis_header().hash(), ::ethereum::new_morden().genesis_header().hash());
+		assert!(Spec::load(b"{ \"name\": ").is_err());
+	}
+
+	#[test]
+	fn reports_path_of_invalid_fields() {
+		assert_eq!(load_modified(|j| { j.remove("genesis"); }).unwrap_err(), SpecError::MissingField("genesis".to_owned()));
+
+		let err = load_modified(|j| {
+			j.get_mut("genesis").unwrap().as_object_mut().unwrap().insert("gasLimit".to_owned(), Json::String("0xnope".to_owned()));
+		}).unwrap_err();
+		assert_eq!(err, SpecError::InvalidValue { path: "genesis.gasLimit".to_owned(), expected: "a number or a 0x-prefixed hex string" });
+
+		let err = load_modified(|j| { j.get_mut("params").unwrap().as_object_mut().unwrap().insert("validators".to_owned(), Json::Null); }).unwrap_err();
+		assert_eq!(err, SpecError::InvalidValue { path: "params.validators".to_owned(), expected: "a number, string, boolean or list" });
+
+		let err = load_modified(|j| { j.insert("engineName".to_owned(), Json::String("Ethsah".to_owned())); }).unwrap_err();
+		assert_eq!(err, SpecError::UnknownEngine("Ethsah".to_owned()));
+	}
+
+	fn load_with_transitions(transitions: &str) -> Result<Spec, SpecError> {
+		load_modified(|j| { j.insert("transitions".to_owned(), Json::from_str(transitions).unwrap()); })
+	}
+
+	#[test]
+	fn loads_transitions() {
+		let spec = load_with_transitions(r#"[
+			{ "name": "Homestead", "block": "0x10", "schedule": "homestead", "difficulty": "Homestead", "transactions": "homestead" },
+			{ "name": "Delay", "block": 32, "bombDelay": "0x2dc6c0" },
+			{ "name": "Replay", "block": 48, "replayProtection": true }
+		]"#).unwrap();
+		let transitions = &spec.transitions;
+
+		assert_eq!(transitions.rules(15), &RuleSet::default());
+		assert_eq!(transitions.rules(16).difficulty, DifficultyRule::Homestead);
+		assert_eq!(transitions.rules(32).bomb_delay, 3_000_000);
+		assert!(!transitions.rules(47).replay_protection);
+		assert!(transitions.rules(48).replay_protection);
+
+		let legacy = Transitions::new_homestead_at(16);
+		assert_eq!(legacy.rules(15), &RuleSet::default());
+		assert_eq!(legacy.rules(16).schedule, ScheduleVariant::Homestead);
+		assert_eq!(legacy.rules(16).difficulty, DifficultyRule::Homestead);
+		assert_eq!(legacy.rules(16).transaction_rules, TransactionRules::Homestead);
+	}
+
+	#[test]
+	fn reports_invalid_transitions() {
+		assert_eq!(load_with_transitions(r#"[{ "name": "Homestead" }]"#).unwrap_err(), SpecError::MissingField("transitions.0.block".to_owned()));
+		assert_eq!(load_with_transitions(r#"[{ "block": 1 }, { "block": 2, "schedule": "metropolis" }]"#).unwrap_err(),
+			SpecError::InvalidValue { path: "transitions.1.schedule".to_owned(), expected: "\"frontier\" or \"homestead\"" });
+		assert_eq!(load_with_transitions(r#"[{ "block": 1, "replayProtection": "yes" }]"#).unwrap_err(),
+			SpecError::InvalidValue { path: "transitions.0.replayProtection".to_owned(), expected: "a boolean" });
+		assert_eq!(load_with_transitions(r#"{}"#).unwrap_err(),
+			SpecError::InvalidValue { path: "transitions".to_owned(), expected: "a list of transitions" });
+	}
+
+	#[test]
+	fn checks_engine_params() {
+		let err = load_modified(|j| { j.get_mut("params").unwrap().as_object_mut().unwrap().remove("minimumDifficulty"); }).unwrap_err();
+		assert_eq!(err, SpecError::MissingField("params.minimumDifficulty".to_owned()));
+
+		let err = load_modified(|j| {
+			j.get_mut("params").unwrap().as_object_mut().unwrap().insert("gasLimitBoundDivisor".to_owned(), Json::String("0x0".to_owned()));
+		}).unwrap_err();
+		assert_eq!(err, SpecError::InvalidValue { path: "params.gasLimitBoundDivisor".to_owned(), expected: "a non-zero number" });
+
+		let err = load_modified(|j| { j.insert("engineName".to_owned(), Json::String("AuthorityRound".to_owned())); }).unwrap_err();
+		assert_eq!(err, SpecError::MissingField("params.validators".to_owned()));
+
+		let err = load_modified(|j| {
+			j.insert("engineName".to_owned(), Json::String("AuthorityRound".to_owned()));
+			let params = j.get_mut("params").unwrap().as_object_mut().unwrap();
+			params.insert("validators".to_owned(), Json::from_str(r#"["0x0000000000000000000000000000000000000001"]"#).unwrap());
+			params.insert("stepDuration".to_owned(), Json::String("0x10000000000000000".to_owned()));
+		}).unwrap_err();
+		assert_eq!(err, SpecError::InvalidValue { path: "params.stepDuration".to_owned(), expected: "a 64-bit number" });
+	}
+
+	fn load_with_account(account: &str) -> Result<Spec, SpecError> {
+		load_modified(|j| {
+			let accounts = j.get_mut("accounts").unwrap().as_object_mut().unwrap();
+			accounts.insert("0000000000000000000000000000000000000042".to_owned(), Json::from_str(account).unwrap());
+		})
+	}
+
+	#[test]
+	fn loads_genesis_accounts() {
+		let spec = load_with_account(r#"{ "balance": "0x10", "code": "0x6000", "storage": { "0x01": "2" } }"#).unwrap();
+		let account = &spec.genesis_state.get()[&Address::from(0x42)];
+		assert_eq!(account.balance, U256::from(16));
+		assert_eq!(account.nonce, U256::zero());
+		assert_eq!(account.code, vec![0x60, 0x00]);
+		assert_eq!(account.storage[&H256::from(U256::from(1))], H256::from(U256::from(2)));
+	}
+
+	#[test]
+	fn reports_invalid_genesis_accounts() {
+		let path = "accounts.0000000000000000000000000000000000000042";
+		assert_eq!(load_with_account(r#"[]"#).unwrap_err(), SpecError::InvalidValue { path: path.to_owned(), expected: "an object" });
+		assert_eq!(load_with_account(r#"{ "code": "6000" }"#).unwrap_err(),
+			SpecError::InvalidValue { path: format!("{}.code", path), expected: "a 0x-prefixed hex string" });
+		assert_eq!(load_with_account(r#"{ "storage": [] }"#).unwrap_err(),
+			SpecError::InvalidValue { path: format!("{}.storage", path), expected: "an object" });
+		assert_eq!(load_with_account(r#"{ "storage": { "0x01": "two" } }"#).unwrap_err(),
+			SpecError::InvalidValue { path: format!("{}.storage.0x01", path), expected: "a number or a 0x-prefixed hex string" });
+		assert_eq!(load_with_account(r#"{ "storage": { "slot": "2" } }"#).unwrap_err(),
+			SpecError::InvalidValue { path: format!("{}.storage.slot", path), expected: "storage keyed by numbers or 0x-prefixed hex strings" });
+
+		let err = load_modified(|j| {
+			let accounts = j.get_mut("accounts").unwrap().as_object_mut().unwrap();
+			accounts.insert("0x42".to_owned(), Json::from_str("{}").unwrap());
+		}).unwrap_err();
+		assert_eq!(err, SpecError::InvalidValue { path: "accounts.0x42".to_owned(), expected: "accounts keyed by 20-byte hex addresses" });
+	}
 }
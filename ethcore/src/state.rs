@@ -171,6 +171,32 @@ impl State {
 		self.get(a, true).as_ref().map_or(None, |a|a.code().map(|x|x.to_vec()))
 	}
 
+	/// Get the hash of the code of account `a`, if it was committed.
+	pub fn code_hash(&self, a: &Address) -> Option<H256> {
+		self.get(a, false).as_ref().and_then(|a| a.committed_code_hash())
+	}
+
+	/// Get account `a` from the committed state along with a Merkle proof of it against `root()`.
+	/// Changes which have not been committed are not reflected.
+	pub fn prove_account(&self, a: &Address) -> (Option<Account>, Vec<Bytes>) {
+		let (value, proof) = SecTrieDB::new(&self.db, &self.root).get_with_proof(&a);
+		(value.map(|rlp| Account::from_rlp(&rlp)), proof)
+	}
+
+	/// Get storage `key` of account `a` from the committed state along with a Merkle proof of it
+	/// against the account's storage root. The proof is empty if the account does not exist.
+	pub fn prove_storage(&self, a: &Address, key: &H256) -> (H256, Vec<Bytes>) {
+		match self.prove_account(a).0 {
+			Some(account) => {
+				let db = AccountDB::new(&self.db, a);
+				let root = account.storage_root().expect("Account decoded from the trie has no pending storage").clone();
+				let (value, proof) = SecTrieDB::new(&db, &root).get_with_proof(key);
+				(value.map_or(H256::new(), |v| H256::from(decode::<U256>(&v))), proof)
+			},
+			None => (H256::new(), vec![]),
+		}
+	}
+
 	/// Add `incr` to the balance of account `a`.
 	pub fn add_balance(&mut self, a: &Address, incr: &U256) {
 		let old = self.balance(a);
@@ -256,8 +282,6 @@ impl State {
 		Self::commit_into(&mut self.db, &mut self.root, self.cache.borrow_mut().deref_mut());
 	}
 
-	#[cfg(test)]
-	#[cfg(feature = "json-tests")]
 	/// Populate the state from `accounts`.
 	pub fn populate_from(&mut self, accounts: PodState) {
 		assert!(self.snapshots.borrow().is_empty());
@@ -266,8 +290,6 @@ impl State {
 		}
 	}
 
-	#[cfg(test)]
-	#[cfg(feature = "json-tests")]
 	/// Populate a PodAccount map from this state.
 	pub fn to_pod(&self) -> PodState {
 		assert!(self.snapshots.borrow().is_empty());
@@ -375,6 +397,31 @@ fn storage_at_from_database() {
 	assert_eq!(s.storage_at(&a, &H256::from(&U256::from(01u64))), H256::from(&U256::from(69u64)));
 }
 
+#[test]
+fn proves_account_and_storage() {
+	let a = Address::zero();
+	let key = H256::from(&U256::from(01u64));
+	let mut state_result = get_temp_state();
+	let mut state = state_result.reference_mut();
+	state.add_balance(&a, &U256::from(69u64));
+	state.set_storage(&a, key.clone(), H256::from(&U256::from(42u64)));
+	state.commit();
+
+	let (account, proof) = state.prove_account(&a);
+	let account = account.unwrap();
+	assert_eq!(account.balance(), &U256::from(69u64));
+	assert_eq!(verify_sec_proof(state.root(), &a, &proof).unwrap(), Some(account.rlp()));
+
+	let (value, proof) = state.prove_storage(&a, &key);
+	assert_eq!(value, H256::from(&U256::from(42u64)));
+	assert_eq!(verify_sec_proof(account.storage_root().unwrap(), &key, &proof).unwrap(), Some(encode(&U256::from(42u64)).to_vec()));
+
+	let (value, proof) = state.prove_storage(&a, &H256::from(&U256::from(02u64)));
+	assert_eq!(value, H256::new());
+	assert_eq!(verify_sec_proof(account.storage_root().unwrap(), &H256::from(&U256::from(02u64)), &proof).unwrap(), None);
+	assert!(state.prove_account(&Address::random()).0.is_none());
+}
+
 #[test]
 fn get_from_database() {
 	let a = Address::zero();
@@ -19,6 +19,8 @@ use block::IsBlock;
 use tests::helpers::*;
 use common::*;
 use devtools::*;
+use spec::{Spec, dev_key_pairs};
+use transaction::{Transaction, Action};
 
 #[test]
 fn created() {
@@ -50,6 +52,20 @@ fn imports_good_block() {
 	assert!(!block.is_empty());
 }
 
+#[test]
+fn imports_good_block_in_memory() {
+	let client = Client::new_in_memory(ClientConfig::default(), get_test_spec(), IoChannel::disconnected()).unwrap();
+	let good_block = get_good_dummy_block();
+	if let Err(_) = client.import_block(good_block) {
+		panic!("error importing block being good by definition");
+	}
+	client.flush_queue();
+	client.import_verified_blocks(&IoChannel::disconnected());
+
+	let block = client.block_header(BlockId::Number(1)).unwrap();
+	assert!(!block.is_empty());
+}
+
 #[test]
 fn query_none_block() {
 	let dir = RandomTempPath::new();
@@ -104,7 +120,7 @@ fn imports_block_sequence() {
 fn can_collect_garbage() {
 	let client_result = generate_dummy_client(100);
 	let client = client_result.reference();
-	client.tick();
+	client.collect_garbage();
 	assert!(client.blockchain_cache_info().blocks < 100 * 1024);
 }
 
@@ -145,3 +161,53 @@ fn can_mine() {
 	};
 	assert!(client.submit_seal(pow_hash, vec![]).is_ok());
 }
+
+#[test]
+fn verifies_integrity() {
+	let client_result = generate_dummy_client(8);
+	let client = client_result.reference();
+	let mut checked = 0;
+	assert!(client.verify_integrity(|_| checked += 1).is_empty());
+	assert_eq!(checked, 9);
+}
+
+#[test]
+fn seals_transactions_instantly() {
+	let pairs = dev_key_pairs();
+	let sender = Address::from(pairs[0].public().sha3());
+	let client = Client::new_in_memory(ClientConfig::default(), Spec::new_dev(&[sender.clone()]), IoChannel::disconnected()).unwrap();
+	assert_eq!(client.seal_transactions(vec![]), None);
+
+	let t = Transaction {
+		nonce: U256::zero(),
+		gas_price: U256::zero(),
+		gas: U256::from(21000),
+		action: Action::Call(Address::from(0x1234)),
+		value: U256::from(1000),
+		data: vec![],
+	}.sign(pairs[0].secret());
+	let hash = client.seal_transactions(vec![t]).unwrap();
+
+	assert_eq!(client.chain_info().best_block_hash, hash);
+	assert_eq!(client.chain_info().best_block_number, 1);
+	assert_eq!(client.nonce(&sender), Some(U256::one()));
+}
+
+#[test]
+fn leaves_out_transactions_of_other_chains() {
+	let pairs = dev_key_pairs();
+	let sender = Address::from(pairs[0].public().sha3());
+	let client = Client::new_in_memory(ClientConfig::default(), Spec::new_dev(&[sender.clone()]), IoChannel::disconnected()).unwrap();
+
+	let t = Transaction {
+		nonce: U256::zero(),
+		gas_price: U256::zero(),
+		gas: U256::from(21000),
+		action: Action::Call(Address::from(0x1234)),
+		value: U256::from(1000),
+		data: vec![],
+	}.sign_with_chain_id(pairs[0].secret(), Some(61));
+	assert!(client.verify_transaction_chain_id(&t).is_err());
+	assert_eq!(client.seal_transactions(vec![t]), None);
+	assert_eq!(client.nonce(&sender), Some(U256::zero()));
+}
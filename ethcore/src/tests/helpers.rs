@@ -83,6 +83,11 @@ pub fn get_test_spec() -> Spec {
 	Spec::new_test()
 }
 
+/// Gas floor and ceiling targets of blocks opened in tests.
+pub fn test_gas_range() -> (U256, U256) {
+	(U256::from(3_141_562), U256::from(31_415_620))
+}
+
 pub fn create_test_block(header: &Header) -> Bytes {
 	let mut rlp = RlpStream::new_list(3);
 	rlp.append(header);
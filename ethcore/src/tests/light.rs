@@ -0,0 +1,109 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+use client::{BlockChainClient, BlockId};
+use light::{LightClient, OnDemand, Request, FetchError};
+use tests::helpers::*;
+use common::*;
+
+/// Serves state nodes from memory. Bodies and receipts are never available.
+struct TestOnDemand {
+	state: MemoryDB,
+	corrupt: bool,
+}
+
+impl OnDemand for TestOnDemand {
+	fn fetch(&self, request: Request, verify: &Fn(&[u8]) -> bool) -> Result<Bytes, FetchError> {
+		let data = match request {
+			Request::Node(ref hash) => match self.state.raw(hash) {
+				Some(&(ref data, _)) if self.corrupt => data.iter().rev().cloned().collect(),
+				Some(&(ref data, _)) => data.clone(),
+				None => return Err(FetchError::Timeout),
+			},
+			_ => return Err(FetchError::Timeout),
+		};
+		match verify(&data) {
+			true => Ok(data),
+			false => Err(FetchError::BadResponse),
+		}
+	}
+}
+
+fn light_client(corrupt: bool) -> Arc<LightClient> {
+	let mut state = MemoryDB::new();
+	get_test_spec().ensure_db_good(&mut state);
+	let on_demand = Arc::new(TestOnDemand { state: state, corrupt: corrupt });
+	LightClient::new_with_db(get_test_spec(), Arc::new(in_memory()), None, on_demand).unwrap()
+}
+
+#[test]
+fn imports_headers_of_full_chain() {
+	let client_result = generate_dummy_client(6);
+	let client = client_result.reference();
+	let light = light_client(false);
+
+	for n in 1..7 {
+		light.import_block(client.block(BlockId::Number(n)).unwrap()).unwrap();
+	}
+
+	assert_eq!(light.chain_info().best_block_hash, client.chain_info().best_block_hash);
+	assert_eq!(light.chain_info().total_difficulty, client.chain_info().total_difficulty);
+	// empty bodies don't need to be fetched
+	assert_eq!(light.block(BlockId::Number(3)), client.block(BlockId::Number(3)));
+	assert!(light.import_block(client.block(BlockId::Number(6)).unwrap()).is_err());
+}
+
+#[test]
+fn rejects_header_with_unknown_parent() {
+	let client_result = generate_dummy_client(2);
+	let client = client_result.reference();
+	let light = light_client(false);
+
+	assert!(light.import_block(client.block(BlockId::Number(2)).unwrap()).is_err());
+	assert_eq!(light.chain_info().best_block_number, 0);
+}
+
+#[test]
+fn reads_verified_state() {
+	let light = light_client(false);
+	let address = Address::from_str("102e61f5d8f9bc71d0ad4a084df4e65e05ce0e1c").unwrap();
+
+	assert_eq!(light.nonce(&address), Some(U256::from(1048576)));
+	assert_eq!(light.code(&address), Some(vec![]));
+	assert_eq!(light.code(&Address::from_str("0000000000000000000000000000000000001234").unwrap()), None);
+}
+
+#[test]
+fn proves_account_from_fetched_nodes() {
+	let light = light_client(false);
+	let address = Address::from_str("102e61f5d8f9bc71d0ad4a084df4e65e05ce0e1c").unwrap();
+	let header: Header = decode(&light.best_block_header());
+
+	let proof = light.prove_account(&address, &[H256::new()], BlockId::Latest).unwrap();
+	assert_eq!(proof.nonce, U256::from(1048576));
+	assert_eq!(proof.storage_root, SHA3_NULL_RLP);
+	assert!(verify_sec_proof(&header.state_root, &address, &proof.proof).unwrap().is_some());
+	assert_eq!(proof.storage[0].value, H256::new());
+	assert!(proof.storage[0].proof.is_empty());
+}
+
+#[test]
+fn ignores_state_failing_verification() {
+	let light = light_client(true);
+	let address = Address::from_str("102e61f5d8f9bc71d0ad4a084df4e65e05ce0e1c").unwrap();
+
+	assert_eq!(light.nonce(&address), None);
+}
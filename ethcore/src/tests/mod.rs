@@ -15,4 +15,6 @@
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
 pub mod helpers;
-mod client;
\ No newline at end of file
+mod client;
+mod light;
+
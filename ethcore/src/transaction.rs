@@ -66,7 +66,13 @@ pub struct Transaction {
 impl Transaction {
 	/// Append object with a without signature into RLP stream
 	pub fn rlp_append_unsigned_transaction(&self, s: &mut RlpStream) {
-		s.begin_list(6);
+		self.rlp_append_unsigned_transaction_with_chain_id(s, None);
+	}
+
+	/// Append object without signature into RLP stream. A `chain_id` is appended along with two empty
+	/// fields, binding the signature to that chain.
+	pub fn rlp_append_unsigned_transaction_with_chain_id(&self, s: &mut RlpStream, chain_id: Option<u64>) {
+		s.begin_list(if chain_id.is_some() { 9 } else { 6 });
 		s.append(&self.nonce);
 		s.append(&self.gas_price);
 		s.append(&self.gas);
@@ -76,6 +82,11 @@ impl Transaction {
 		};
 		s.append(&self.value);
 		s.append(&self.data);
+		if let Some(chain_id) = chain_id {
+			s.append(&chain_id);
+			s.append_empty_data();
+			s.append_empty_data();
+		}
 	}
 }
 
@@ -97,7 +108,7 @@ impl FromJson for SignedTransaction {
 			Some(&Json::String(ref secret_key)) => t.sign(&h256_from_hex(clean(secret_key))),
 			_ => SignedTransaction {
 				unsigned: t,
-				v: match json.find("v") { Some(ref j) => u16::from_json(j) as u8, None => 0 },
+				v: match json.find("v") { Some(ref j) => u64::from_json(j), None => 0 },
 				r: match json.find("r") { Some(j) => xjson!(j), None => x!(0) },
 				s: match json.find("s") { Some(j) => xjson!(j), None => x!(0) },
 				hash: Cell::new(None),
@@ -113,20 +124,34 @@ impl FromJson for SignedTransaction {
 impl Transaction {
 	/// The message hash of the transaction.
 	pub fn hash(&self) -> H256 {
+		self.hash_with_chain_id(None)
+	}
+
+	/// The message hash of the transaction when signed for the chain `chain_id`, if any.
+	pub fn hash_with_chain_id(&self, chain_id: Option<u64>) -> H256 {
 		let mut stream = RlpStream::new();
-		self.rlp_append_unsigned_transaction(&mut stream);
+		self.rlp_append_unsigned_transaction_with_chain_id(&mut stream, chain_id);
 		stream.out().sha3()
 	}
 
 	/// Signs the transaction as coming from `sender`.
 	pub fn sign(self, secret: &Secret) -> SignedTransaction {
-		let sig = ec::sign(secret, &self.hash());
+		self.sign_with_chain_id(secret, None)
+	}
+
+	/// Signs the transaction as coming from `sender`. If `chain_id` is given the signature is only
+	/// valid on that chain and `v` is encoded as `chain_id * 2 + 35` or `36`.
+	pub fn sign_with_chain_id(self, secret: &Secret, chain_id: Option<u64>) -> SignedTransaction {
+		let sig = ec::sign(secret, &self.hash_with_chain_id(chain_id));
 		let (r, s, v) = sig.unwrap().to_rsv();
 		SignedTransaction {
 			unsigned: self,
 			r: r,
 			s: s,
-			v: v + 27,
+			v: match chain_id {
+				Some(chain_id) => chain_id * 2 + 35 + v as u64,
+				None => v as u64 + 27,
+			},
 			hash: Cell::new(None),
 			sender: Cell::new(None),
 		}
@@ -164,8 +189,9 @@ impl Transaction {
 pub struct SignedTransaction {
 	/// Plain Transaction.
 	unsigned: Transaction,
-	/// The V field of the signature, either 27 or 28; helps describe the point on the curve.
-	v: u8,
+	/// The V field of the signature; 27 or 28, or `chain_id * 2 + 35` or `36` for a chain-bound
+	/// signature. Helps describe the point on the curve.
+	v: u64,
 	/// The R field of the signature; helps describe the point on the curve.
 	r: U256,
 	/// The S field of the signature; helps describe the point on the curve.
@@ -249,8 +275,23 @@ impl SignedTransaction {
 		}
 	}
 
-	/// 0 is `v` is 27, 1 if 28, and 4 otherwise.
-	pub fn standard_v(&self) -> u8 { match self.v { 27 => 0, 28 => 1, _ => 4 } }
+	/// 0 is `v` is 27, 1 if 28, the recovery id of a chain-bound signature, and 4 otherwise.
+	pub fn standard_v(&self) -> u8 {
+		match self.v {
+			27 => 0,
+			28 => 1,
+			v if v >= 35 => ((v - 35) % 2) as u8,
+			_ => 4,
+		}
+	}
+
+	/// The chain the signature is bound to, or `None` for a signature valid on any chain.
+	pub fn chain_id(&self) -> Option<u64> {
+		match self.v {
+			v if v >= 35 => Some((v - 35) / 2),
+			_ => None,
+		}
+	}
 
 	/// Construct a signature object from the sig.
 	pub fn signature(&self) -> Signature { Signature::from_rsv(&From::from(&self.r), &From::from(&self.s), self.standard_v()) }
@@ -270,7 +311,7 @@ impl SignedTransaction {
 		match sender {
 			Some(s) => Ok(s),
 			None => {
-				let s = Address::from(try!(ec::recover(&self.signature(), &self.unsigned.hash())).sha3());
+				let s = Address::from(try!(ec::recover(&self.signature(), &self.unsigned.hash_with_chain_id(self.chain_id()))).sha3());
 				self.sender.set(Some(s));
 				Ok(s)
 			}
@@ -342,3 +383,32 @@ fn signing() {
 	}.sign(&key.secret());
 	assert_eq!(Address::from(key.public().sha3()), t.sender().unwrap());
 }
+
+#[test]
+fn signing_with_chain_id() {
+	let key = KeyPair::create().unwrap();
+	let t = Transaction {
+		action: Action::Call(Address::from(0x69)),
+		nonce: U256::from(9),
+		gas_price: U256::from(20_000_000_000u64),
+		gas: U256::from(21_000),
+		value: U256::from(1),
+		data: vec![]
+	}.sign_with_chain_id(&key.secret(), Some(62));
+	assert_eq!(t.chain_id(), Some(62));
+	assert!(t.v == 159 || t.v == 160);
+	assert_eq!(Address::from(key.public().sha3()), t.sender().unwrap());
+
+	let decoded: SignedTransaction = decode(&encode(&t));
+	assert_eq!(decoded, t);
+	assert_eq!(decoded.sender().unwrap(), t.sender().unwrap());
+	assert!(decoded.unsigned.hash() != decoded.unsigned.hash_with_chain_id(Some(62)));
+}
+
+#[test]
+fn legacy_signature_has_no_chain_id() {
+	let key = KeyPair::create().unwrap();
+	let t = Transaction::default().sign(&key.secret());
+	assert_eq!(t.chain_id(), None);
+	assert!(t.v == 27 || t.v == 28);
+}
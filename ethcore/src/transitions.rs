@@ -0,0 +1,200 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+
+//! Hard-fork transitions of a chain, each activating a set of rule changes at a block number.
+
+use header::BlockNumber;
+
+/// Gas schedule variant in force.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScheduleVariant {
+	/// Frontier schedule.
+	Frontier,
+	/// Homestead schedule.
+	Homestead,
+}
+
+/// Difficulty adjustment algorithm in force.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DifficultyRule {
+	/// Frontier algorithm, based on `durationLimit`.
+	Frontier,
+	/// Homestead algorithm (EIP-2).
+	Homestead,
+}
+
+/// Transaction validation rules in force.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransactionRules {
+	/// Any valid signature is accepted.
+	Frontier,
+	/// Signatures must have a low `s` value (EIP-2).
+	Homestead,
+}
+
+/// The set of rules in force at some block.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuleSet {
+	/// Gas schedule.
+	pub schedule: ScheduleVariant,
+	/// Difficulty algorithm.
+	pub difficulty: DifficultyRule,
+	/// Number of blocks by which the difficulty bomb is delayed.
+	pub bomb_delay: u64,
+	/// Transaction validation rules.
+	pub transaction_rules: TransactionRules,
+	/// Whether transactions signed for the spec's chain id are accepted (EIP-155).
+	pub replay_protection: bool,
+}
+
+impl Default for RuleSet {
+	fn default() -> RuleSet {
+		RuleSet {
+			schedule: ScheduleVariant::Frontier,
+			difficulty: DifficultyRule::Frontier,
+			bomb_delay: 0,
+			transaction_rules: TransactionRules::Frontier,
+			replay_protection: false,
+		}
+	}
+}
+
+/// A named change of rules which comes into force at `block`. Rules left as `None` are kept.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Transition {
+	/// Name of the hard fork.
+	pub name: String,
+	/// First block the rules apply to.
+	pub block: BlockNumber,
+	/// New gas schedule.
+	pub schedule: Option<ScheduleVariant>,
+	/// New difficulty algorithm.
+	pub difficulty: Option<DifficultyRule>,
+	/// New difficulty bomb delay.
+	pub bomb_delay: Option<u64>,
+	/// New transaction validation rules.
+	pub transaction_rules: Option<TransactionRules>,
+	/// Whether chain-bound transaction signatures become accepted.
+	pub replay_protection: Option<bool>,
+}
+
+impl Transition {
+	/// Create a transition at `block` which changes nothing.
+	pub fn new(name: &str, block: BlockNumber) -> Transition {
+		Transition {
+			name: name.to_owned(),
+			block: block,
+			schedule: None,
+			difficulty: None,
+			bomb_delay: None,
+			transaction_rules: None,
+			replay_protection: None,
+		}
+	}
+
+	fn apply(&self, rules: &RuleSet) -> RuleSet {
+		let mut rules = rules.clone();
+		rules.schedule = self.schedule.unwrap_or(rules.schedule);
+		rules.difficulty = self.difficulty.unwrap_or(rules.difficulty);
+		rules.bomb_delay = self.bomb_delay.unwrap_or(rules.bomb_delay);
+		rules.transaction_rules = self.transaction_rules.unwrap_or(rules.transaction_rules);
+		rules.replay_protection = self.replay_protection.unwrap_or(rules.replay_protection);
+		rules
+	}
+}
+
+/// The transitions of a chain along with the rule sets they result in.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Transitions {
+	transitions: Vec<Transition>,
+	// first block and rules in force from it, ascending; the first entry is for block 0.
+	rules: Vec<(BlockNumber, RuleSet)>,
+}
+
+impl Transitions {
+	/// Create from a list of transitions in any order.
+	pub fn new(mut transitions: Vec<Transition>) -> Transitions {
+		transitions.sort_by(|a, b| a.block.cmp(&b.block));
+		let mut rules = vec![(0, RuleSet::default())];
+		for transition in &transitions {
+			let next = transition.apply(&rules.last().expect("rules start with genesis rules").1);
+			if rules.last().expect("rules start with genesis rules").0 == transition.block {
+				rules.pop();
+			}
+			rules.push((transition.block, next));
+		}
+
+		Transitions {
+			transitions: transitions,
+			rules: rules,
+		}
+	}
+
+	/// Transitions for a chain which switches from Frontier to Homestead rules at `block`, as
+	/// configured by the legacy `frontierCompatibilityModeLimit` parameter.
+	pub fn new_homestead_at(block: BlockNumber) -> Transitions {
+		Transitions::new(vec![Transition {
+			schedule: Some(ScheduleVariant::Homestead),
+			difficulty: Some(DifficultyRule::Homestead),
+			transaction_rules: Some(TransactionRules::Homestead),
+			..Transition::new("Homestead", block)
+		}])
+	}
+
+	/// The rules in force at block `number`.
+	pub fn rules(&self, number: BlockNumber) -> &RuleSet {
+		&self.rules.iter().rev().find(|&&(block, _)| block <= number).expect("rules start at block 0").1
+	}
+
+	/// All transitions, ordered by block.
+	pub fn transitions(&self) -> &[Transition] {
+		&self.transitions
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn resolves_rules_per_block() {
+		let transitions = Transitions::new(vec![
+			Transition { bomb_delay: Some(3_000_000), ..Transition::new("Bomb", 200) },
+			Transition { replay_protection: Some(true), ..Transition::new("Replay", 300) },
+			Transition {
+				schedule: Some(ScheduleVariant::Homestead),
+				transaction_rules: Some(TransactionRules::Homestead),
+				..Transition::new("Homestead", 100)
+			},
+		]);
+
+		assert_eq!(transitions.rules(0), &RuleSet::default());
+		assert_eq!(transitions.rules(99), &RuleSet::default());
+
+		let homestead = transitions.rules(100);
+		assert_eq!(homestead.schedule, ScheduleVariant::Homestead);
+		assert_eq!(homestead.transaction_rules, TransactionRules::Homestead);
+		assert_eq!(homestead.difficulty, DifficultyRule::Frontier);
+		assert_eq!(homestead.bomb_delay, 0);
+
+		assert_eq!(transitions.rules(200).bomb_delay, 3_000_000);
+		assert_eq!(transitions.rules(200).schedule, ScheduleVariant::Homestead);
+		assert!(!transitions.rules(299).replay_protection);
+		assert!(transitions.rules(u64::max_value()).replay_protection);
+		assert_eq!(transitions.transitions()[1].name, "Bomb");
+	}
+}
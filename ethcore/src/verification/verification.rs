@@ -48,6 +48,7 @@ pub fn verify_block_basic(header: &Header, bytes: &[u8], engine: &Engine) -> Res
 	// TODO: either use transaction views or cache the decoded transactions.
 	let v = BlockView::new(bytes);
 	for t in v.transactions() {
+		try!(engine.verify_transaction_chain_id(&t, &header));
 		try!(engine.verify_transaction_basic(&t, &header));
 	}
 	Ok(())
@@ -158,6 +159,17 @@ pub fn verify_block_family(header: &Header, bytes: &[u8], engine: &Engine, bc: &
 	Ok(())
 }
 
+/// Verification of a header without its block body, as done by the light client.
+/// Runs the header checks of phases 1-3, including the engine's seal and difficulty checks.
+pub fn verify_header_only(header: &Header, parent: &Header, engine: &Engine) -> Result<(), Error> {
+	try!(verify_header(header, engine));
+	try!(engine.verify_block_basic(header, None));
+	try!(engine.verify_block_unordered(header, None));
+	try!(verify_parent(header, parent));
+	try!(engine.verify_block_family(header, parent, None));
+	Ok(())
+}
+
 /// Phase 4 verification. Check block information against transaction enactment results,
 pub fn verify_block_final(expected: &Header, got: &Header) -> Result<(), Error> {
 	if expected.gas_used != got.gas_used {
@@ -37,23 +37,42 @@ extern crate rpassword;
 #[cfg(feature = "rpc")]
 extern crate ethcore_rpc as rpc;
 
-use std::net::{SocketAddr};
+#[cfg(feature = "rpc")]
+use rpc::RpcStats;
+
+use std::net::{SocketAddr, IpAddr};
 use std::env;
+use std::io::{self, Write};
 use std::process::exit;
 use std::path::PathBuf;
 use env_logger::LogBuilder;
 use ctrlc::CtrlC;
 use util::*;
 use util::panics::{MayPanic, ForwardPanic, PanicHandler};
+use util::keys::store::SecretStore;
 use ethcore::spec::*;
 use ethcore::client::*;
+use ethcore::sealing::SealingConfig;
+use ethcore::evm_runner::{Runner as EvmRunner, Call, VMType};
 use ethcore::service::{ClientService, NetSyncMessage};
 use ethcore::ethereum;
-use ethsync::{EthSync, SyncConfig, SyncProvider};
+use ethcore::light::LightClient;
+use ethsync::{EthSync, SyncConfig, SyncProvider, OnDemandService};
 use docopt::Docopt;
 use daemonize::Daemonize;
 use number_prefix::{binary_prefix, Standalone, Prefixed};
 
+/// Statistics of the JSON-RPC server; there are none when the server is not compiled in.
+#[cfg(not(feature = "rpc"))]
+struct RpcStats;
+
+#[cfg(not(feature = "rpc"))]
+impl RpcStats {
+	fn totals(&self) -> (u64, u64) {
+		(0, 0)
+	}
+}
+
 fn die_with_message(msg: &str) -> ! {
 	println!("ERROR: {}", msg);
 	exit(1);
@@ -64,6 +83,9 @@ macro_rules! die {
 	($($arg:tt)*) => (die_with_message(&format!("{}", format_args!($($arg)*))));
 }
 
+// Bodies of recent blocks are needed to verify uncles and to reorganise the chain.
+const MIN_BODY_HISTORY: u64 = 256;
+
 const USAGE: &'static str = r#"
 Parity. Ethereum Client.
   By Wood/Paronyan/Kotewicz/Drwięga/Volf.
@@ -72,14 +94,28 @@ Parity. Ethereum Client.
 Usage:
   parity daemon <pid-file> [options] [ --no-bootstrap | <enode>... ]
   parity account (new | list)
+  parity db (kill | compact | stats | verify) [options]
+  parity spec check <file>
+  parity evm <code> [options]
   parity [options] [ --no-bootstrap | <enode>... ]
 
 Protocol Options:
   --chain CHAIN            Specify the blockchain type. CHAIN may be either a JSON chain specification file
-                           or olympic, frontier, homestead, mainnet, morden, or testnet [default: homestead].
+                           or olympic, frontier, homestead, mainnet, morden, testnet or dev [default: homestead].
+                           dev is a local chain which seals a block for each new transaction and whose
+                           prefunded accounts are unlocked at start.
   --testnet                Equivalent to --chain testnet (geth-compatible).
   --networkid INDEX        Override the network identifier from the chain we are on.
-  --pruning                Client should prune the state/storage trie.
+  --pruning METHOD         Configure pruning of the state/storage trie. METHOD may be one of: archive (keep all
+                           state), basic (reference-counted journal on disk), fast (recent state kept in memory,
+                           only canonical state written) [default: archive].
+  --pruning-history NUM    Number of recent blocks whose state is kept when pruning [default: 1000].
+  --body-history NUM       Keep bodies and receipts of only the NUM most recent blocks. Headers of older
+                           blocks are kept. All bodies are kept if not given.
+  --log-index              Index logs by address and topic for blocks imported from now on, speeding
+                           up log queries which name an address or a topic.
+  --light                  Keep block headers only. Block bodies, receipts and state are fetched from
+                           peers when requested over RPC.
   -d --datadir PATH        Specify the database & configuration directory path [default: $HOME/.parity]
   --keys-path PATH         Specify the path for JSON key files to be found [default: $HOME/.web3/keys]
   --identity NAME          Specify your node's name.
@@ -100,7 +136,30 @@ API and Console Options:
   --jsonrpc-port PORT      Specify the port portion of the JSONRPC API server [default: 8545].
   --jsonrpc-cors URL       Specify CORS header for JSON-RPC API responses [default: null].
   --jsonrpc-apis APIS      Specify the APIs available through the JSONRPC interface. APIS is a comma-delimited
-                           list of API name. Possible name are web3, eth and net. [default: web3,eth,net].
+                           list of API name. Possible name are web3, eth, net, parity and personal.
+                           [default: web3,eth,net,parity].
+  --jsonrpc-threads NUM    Specify the number of threads serving JSON-RPC API HTTP requests [default: 4].
+  --jsonrpc-hosts HOSTS    Specify the hostnames accepted in the Host header of HTTP and WebSockets requests,
+                           in addition to localhost and the listen addresses. HOSTS is a comma-delimited list
+                           or all to accept any host. When listening on all interfaces (0.0.0.0 or ::), list
+                           the names remote clients use to reach the node [default: none].
+  --jsonrpc-allow-ips IPS  Specify the IP addresses allowed to connect over HTTP and WebSockets. IPS is a
+                           comma-delimited list or all [default: all].
+  --jsonrpc-auth CREDS     Require the Authorization header on HTTP and WebSockets requests. CREDS is either
+                           bearer:TOKEN or basic:USER:PASSWORD.
+  --jsonrpc-unsafe         Allow the personal API over HTTP and WebSockets. It is always allowed over IPC.
+  --filter-lifetime SECS   Specify the number of seconds after which an unpolled filter is uninstalled [default: 60].
+  --max-filters NUM        Specify the maximal number of filters each client may have installed at once
+                           [default: 1000].
+  --no-ipc                 Disable the JSON-RPC API over IPC (unix domain socket).
+  --ipc-path PATH          Specify the path of the JSON-RPC IPC socket [default: $DATADIR/jsonrpc.ipc].
+  --ipc-apis APIS          Specify the APIs available through the IPC interface
+                           [default: web3,eth,net,parity,personal].
+  --ws                     Enable the JSON-RPC API over WebSockets.
+  --ws-addr HOST           Specify the hostname portion of the WebSockets JSON-RPC server [default: 127.0.0.1].
+  --ws-port PORT           Specify the port portion of the WebSockets JSON-RPC server [default: 8546].
+  --ws-apis APIS           Specify the APIs available through the WebSockets interface
+                           [default: web3,eth,net,parity].
   --rpc                    Equivalent to --jsonrpc (geth-compatible).
   --rpcaddr HOST           Equivalent to --jsonrpc-addr HOST (geth-compatible).
   --rpcport PORT           Equivalent to --jsonrpc-port PORT (geth-compatible).
@@ -111,15 +170,43 @@ Sealing/Mining Options:
   --author ADDRESS         Specify the block author (aka "coinbase") address for sending block rewards
                            from sealed blocks [default: 0037a6b811ffeb6e072da21179d11b1406371c63].
   --extradata STRING      Specify a custom extra-data for authored blocks, no more than 32 characters.
+  --gas-floor-target GAS   Raise the gas limit of authored blocks while it is below GAS [default: 3141562].
+  --gas-cap GAS            Lower the gas limit of authored blocks while it is above GAS [default: 6283184].
+  --min-gas-price WEI      Minimum gas price of transactions included in authored blocks [default: 0].
+  --tx-gas-limit GAS       Don't include transactions using more than GAS in authored blocks.
+  --uncles POLICY          Uncles to include in authored blocks; all, none or a maximum number
+                           [default: all].
+  --reserve-local-gas GAS  Reserve the last GAS of each authored block for transactions sent from local
+                           accounts [default: 0].
 
 Memory Footprint Options:
   --cache-pref-size BYTES  Specify the prefered size of the blockchain cache in bytes [default: 16384].
   --cache-max-size BYTES   Specify the maximum size of the blockchain cache in bytes [default: 262144].
   --queue-max-size BYTES   Specify the maximum size of memory to use for block queue [default: 52428800].
   --cache MEGABYTES        Set total amount of cache to use for the entire system, mutually exclusive with
-                           other cache options (geth-compatible).
+                           other cache options (geth-compatible). A quarter goes to the blockchain cache, the
+                           rest to the database caches.
+  --db-cache-size MB       Override the total database cache size in megabytes. Half of it goes to the state
+                           database, the rest is split between the blocks and extras databases.
+  --db-compaction TYPE     Database compaction profile. TYPE may be one of: ssd (solid state drive), hdd
+                           (rotational drive) [default: ssd].
+  --fast-and-loose         Disable the database write-ahead log. Faster, but an unclean shutdown may
+                           corrupt the database.
+
+EVM Options:
+  --input DATA             Call data for `parity evm` as hex.
+  --gas GAS                Gas available to the code [default: 10000000].
+  --gas-price WEI          Gas price of the call [default: 0].
+  --from ADDRESS           Sender and origin of the call.
+  --to ADDRESS             Address the code runs at.
+  --value WEI              Value sent with the call [default: 0].
+  --state FILE             Seed the state with the accounts of a JSON file.
+  --trace                  Print every executed instruction with its gas and the stack.
+  --bench                  Execute the code repeatedly with each VM and report timings.
+  --repeat NUM             Number of executions per VM with --bench [default: 1000].
 
 Miscellaneous Options:
+  -y --yes                 Don't ask for confirmation before `parity db kill` removes the databases.
   -l --logging LOGGING     Specify the logging level.
   -v --version             Show information about version.
   -h --help                Show this screen.
@@ -131,7 +218,17 @@ struct Args {
 	cmd_account: bool,
 	cmd_new: bool,
 	cmd_list: bool,
+	cmd_db: bool,
+	cmd_kill: bool,
+	cmd_compact: bool,
+	cmd_stats: bool,
+	cmd_verify: bool,
+	cmd_spec: bool,
+	cmd_check: bool,
+	cmd_evm: bool,
 	arg_pid_file: String,
+	arg_file: String,
+	arg_code: String,
 	arg_enode: Vec<String>,
 	flag_chain: String,
 	flag_testnet: bool,
@@ -140,7 +237,11 @@ struct Args {
 	flag_identity: String,
 	flag_cache: Option<usize>,
 	flag_keys_path: String,
-	flag_pruning: bool,
+	flag_pruning: String,
+	flag_pruning_history: u64,
+	flag_body_history: Option<u64>,
+	flag_log_index: bool,
+	flag_light: bool,
 	flag_no_bootstrap: bool,
 	flag_listen_address: String,
 	flag_public_address: Option<String>,
@@ -152,11 +253,28 @@ struct Args {
 	flag_cache_pref_size: usize,
 	flag_cache_max_size: usize,
 	flag_queue_max_size: usize,
+	flag_db_cache_size: Option<usize>,
+	flag_db_compaction: String,
+	flag_fast_and_loose: bool,
 	flag_jsonrpc: bool,
 	flag_jsonrpc_addr: String,
 	flag_jsonrpc_port: u16,
 	flag_jsonrpc_cors: String,
 	flag_jsonrpc_apis: String,
+	flag_jsonrpc_threads: usize,
+	flag_jsonrpc_hosts: String,
+	flag_jsonrpc_allow_ips: String,
+	flag_jsonrpc_auth: Option<String>,
+	flag_jsonrpc_unsafe: bool,
+	flag_filter_lifetime: u64,
+	flag_max_filters: usize,
+	flag_no_ipc: bool,
+	flag_ipc_path: String,
+	flag_ipc_apis: String,
+	flag_ws: bool,
+	flag_ws_addr: String,
+	flag_ws_port: u16,
+	flag_ws_apis: String,
 	flag_rpc: bool,
 	flag_rpcaddr: Option<String>,
 	flag_rpcport: Option<u16>,
@@ -166,6 +284,23 @@ struct Args {
 	flag_version: bool,
 	flag_author: String,
 	flag_extra_data: Option<String>,
+	flag_gas_floor_target: String,
+	flag_gas_cap: String,
+	flag_min_gas_price: String,
+	flag_tx_gas_limit: Option<String>,
+	flag_uncles: String,
+	flag_reserve_local_gas: String,
+	flag_input: Option<String>,
+	flag_gas: String,
+	flag_gas_price: String,
+	flag_from: Option<String>,
+	flag_to: Option<String>,
+	flag_value: String,
+	flag_state: Option<String>,
+	flag_trace: bool,
+	flag_bench: bool,
+	flag_repeat: u32,
+	flag_yes: bool,
 }
 
 fn setup_log(init: &Option<String>) {
@@ -194,30 +329,124 @@ fn setup_log(init: &Option<String>) {
 	builder.init().unwrap();
 }
 
+/// Transports the JSON-RPC server should be started on.
+struct RpcTransports {
+	/// Address, CORS domain, number of threads and APIs.
+	http: Option<(String, String, usize, Vec<String>)>,
+	/// Socket path and APIs.
+	ipc: Option<(String, Vec<String>)>,
+	/// Address and APIs.
+	ws: Option<(String, Vec<String>)>,
+	/// Accepted Host headers of network transports, `None` for any.
+	hosts: Option<Vec<String>>,
+	/// Addresses allowed to use network transports, `None` for any.
+	allowed_ips: Option<Vec<IpAddr>>,
+	/// Credentials required by network transports.
+	auth: Option<String>,
+	/// Whether network transports expose unsafe APIs.
+	allow_unsafe: bool,
+}
+
+impl RpcTransports {
+	fn is_empty(&self) -> bool {
+		self.http.is_none() && self.ipc.is_none() && self.ws.is_none()
+	}
+
+	/// APIs enabled on any of the transports.
+	fn apis(&self) -> Vec<String> {
+		let mut apis = Vec::new();
+		{
+			let lists = self.http.iter().map(|t| &t.3)
+				.chain(self.ipc.iter().map(|t| &t.1))
+				.chain(self.ws.iter().map(|t| &t.1));
+			for api in lists.flat_map(|list| list.iter()) {
+				if !apis.contains(api) {
+					apis.push(api.clone());
+				}
+			}
+		}
+		apis
+	}
+}
+
+fn api_list(apis: &str) -> Vec<String> {
+	apis.split(',').map(|api| api.trim().to_owned()).filter(|api| !api.is_empty()).collect()
+}
+
+/// Host header (without port) of requests addressed to `addr`. None for unspecified addresses,
+/// which no client sends.
+fn host_header_of(addr: &SocketAddr) -> Option<String> {
+	match *addr {
+		SocketAddr::V4(ref addr) if addr.ip().octets() == [0, 0, 0, 0] => None,
+		SocketAddr::V4(ref addr) => Some(addr.ip().to_string()),
+		SocketAddr::V6(ref addr) if addr.ip().segments() == [0; 8] => None,
+		SocketAddr::V6(ref addr) => Some(format!("[{}]", addr.ip())),
+	}
+}
+
 #[cfg(feature = "rpc")]
-fn setup_rpc_server(client: Arc<Client>, sync: Arc<EthSync>, url: &str, cors_domain: &str, apis: Vec<&str>) -> Option<Arc<PanicHandler>> {
+fn setup_rpc_server<C>(client: Arc<C>, sync: Arc<EthSync>, secret_store: Arc<RwLock<SecretStore>>, io: &mut IoService<NetSyncMessage>, transports: &RpcTransports, filter_lifetime: u64, max_filters: usize) -> (Vec<Arc<PanicHandler>>, Option<Arc<RpcStats>>) where C: BlockChainClient + 'static {
 	use rpc::v1::*;
 
 	let server = rpc::RpcServer::new();
-	for api in apis.into_iter() {
-		match api {
+	for api in transports.apis() {
+		match api.as_ref() {
 			"web3" => server.add_delegate(Web3Client::new().to_delegate()),
 			"net" => server.add_delegate(NetClient::new(&sync).to_delegate()),
 			"eth" => {
 				server.add_delegate(EthClient::new(&client, &sync).to_delegate());
-				server.add_delegate(EthFilterClient::new(&client).to_delegate());
-			}
+				server.add_delegate(EthFilterClient::new(&client, filter_lifetime, max_filters).to_delegate());
+				let pubsub = Arc::new(EthPubSubClient::new(&client, &sync));
+				io.register_handler(pubsub.clone()).expect("Error registering pubsub IO handler");
+				server.set_pubsub(pubsub);
+			},
+			"personal" => server.add_delegate(PersonalClient::new(&secret_store).to_delegate()),
+			"parity" => server.add_delegate(ParityClient::new(&server.stats()).to_delegate()),
 			_ => {
 				die!("{}: Invalid API name to be enabled.", api);
 			}
 		}
 	}
-	Some(server.start_http(url, cors_domain, 1))
+
+	let authorization = match transports.auth {
+		None => rpc::Authorization::None,
+		Some(ref auth) => {
+			let mut parts = auth.splitn(2, ':');
+			let (scheme, credentials) = (parts.next(), parts.next().unwrap_or(""));
+			let mut credentials_parts = credentials.splitn(2, ':');
+			match (scheme, credentials_parts.next(), credentials_parts.next()) {
+				(Some("bearer"), Some(_), _) if !credentials.is_empty() => rpc::Authorization::Bearer(credentials.to_owned()),
+				(Some("basic"), Some(username), Some(password)) => rpc::Authorization::Basic { username: username.to_owned(), password: password.to_owned() },
+				_ => die!("{}: Invalid JSONRPC credentials. Use bearer:TOKEN or basic:USER:PASSWORD.", auth),
+			}
+		}
+	};
+	let access = |apis: &[String]| {
+		let apis = rpc::ApiSet::list(apis);
+		rpc::AccessControl {
+			apis: if transports.allow_unsafe { apis.allow_unsafe() } else { apis },
+			authorization: authorization.clone(),
+			allowed_hosts: transports.hosts.clone(),
+			allowed_ips: transports.allowed_ips.clone(),
+		}
+	};
+
+	let mut handlers = Vec::new();
+	if let Some((ref url, ref cors_domain, threads, ref apis)) = transports.http {
+		handlers.push(server.start_http(url, cors_domain, threads, access(&apis[..])));
+	}
+	if let Some((ref path, ref apis)) = transports.ipc {
+		handlers.push(server.start_ipc(path, rpc::ApiSet::list(apis)).unwrap_or_else(|e| die!("{}: Couldn't start JSONRPC IPC server: {}", path, e)));
+	}
+	if let Some((ref url, ref apis)) = transports.ws {
+		handlers.push(server.start_ws(url, access(&apis[..])));
+	}
+	(handlers, Some(server.stats()))
 }
 
 #[cfg(not(feature = "rpc"))]
-fn setup_rpc_server(_client: Arc<Client>, _sync: Arc<EthSync>, _url: &str) -> Option<Arc<PanicHandler>> {
-	None
+fn setup_rpc_server<C>(_client: Arc<C>, _sync: Arc<EthSync>, _secret_store: Arc<RwLock<SecretStore>>, _io: &mut IoService<NetSyncMessage>, _transports: &RpcTransports, _filter_lifetime: u64, _max_filters: usize) -> (Vec<Arc<PanicHandler>>, Option<Arc<RpcStats>>) where C: BlockChainClient + 'static {
+	(Vec::new(), None)
 }
 
 fn print_version() {
@@ -260,6 +489,62 @@ impl Configuration {
 		}
 	}
 
+	fn rpc_transports(&self) -> RpcTransports {
+		let http = if self.args.flag_jsonrpc || self.args.flag_rpc {
+			let url = format!("{}:{}",
+				self.args.flag_rpcaddr.as_ref().unwrap_or(&self.args.flag_jsonrpc_addr),
+				self.args.flag_rpcport.unwrap_or(self.args.flag_jsonrpc_port)
+			);
+			SocketAddr::from_str(&url).unwrap_or_else(|_|die!("{}: Invalid JSONRPC listen host/port given.", url));
+			let cors = self.args.flag_rpccorsdomain.as_ref().unwrap_or(&self.args.flag_jsonrpc_cors).clone();
+			let apis = api_list(self.args.flag_rpcapi.as_ref().unwrap_or(&self.args.flag_jsonrpc_apis));
+			Some((url, cors, self.args.flag_jsonrpc_threads, apis))
+		} else {
+			None
+		};
+		let ipc = if self.args.flag_no_ipc {
+			None
+		} else {
+			Some((self.args.flag_ipc_path.replace("$DATADIR", &self.path()), api_list(&self.args.flag_ipc_apis)))
+		};
+		let ws = if self.args.flag_ws {
+			let url = format!("{}:{}", self.args.flag_ws_addr, self.args.flag_ws_port);
+			SocketAddr::from_str(&url).unwrap_or_else(|_|die!("{}: Invalid WebSockets JSONRPC listen host/port given.", url));
+			Some((url, api_list(&self.args.flag_ws_apis)))
+		} else {
+			None
+		};
+		let hosts = match &self.args.flag_jsonrpc_hosts[..] {
+			"all" => None,
+			hosts => {
+				let mut allowed = vec!["localhost".to_owned(), "127.0.0.1".to_owned(), "[::1]".to_owned()];
+				// accept requests addressed to the listen addresses.
+				for url in http.iter().map(|t| &t.0).chain(ws.iter().map(|t| &t.0)) {
+					allowed.extend(host_header_of(&SocketAddr::from_str(url).expect("Address validated above")));
+				}
+				if hosts != "none" {
+					allowed.extend(api_list(hosts));
+				}
+				Some(allowed)
+			}
+		};
+		let allowed_ips: Option<Vec<IpAddr>> = match &self.args.flag_jsonrpc_allow_ips[..] {
+			"all" => None,
+			ips => Some(api_list(ips).iter()
+				.map(|ip| IpAddr::from_str(ip).unwrap_or_else(|_| die!("{}: Invalid IP address given.", ip)))
+				.collect()),
+		};
+		RpcTransports {
+			http: http,
+			ipc: ipc,
+			ws: ws,
+			hosts: hosts,
+			allowed_ips: allowed_ips,
+			auth: self.args.flag_jsonrpc_auth.clone(),
+			allow_unsafe: self.args.flag_jsonrpc_unsafe,
+		}
+	}
+
 	fn _keys_path(&self) -> String {
 		self.args.flag_keys_path.replace("$HOME", env::home_dir().unwrap().to_str().unwrap())
 	}
@@ -272,7 +557,34 @@ impl Configuration {
 			"frontier" | "homestead" | "mainnet" => ethereum::new_frontier(),
 			"morden" | "testnet" => ethereum::new_morden(),
 			"olympic" => ethereum::new_olympic(),
-			f => Spec::from_json_utf8(contents(f).unwrap_or_else(|_| die!("{}: Couldn't read chain specification file. Sure it exists?", f)).as_ref()),
+			"dev" => Spec::new_dev(&dev_key_pairs().iter().map(|pair| Address::from(pair.public().sha3())).collect::<Vec<_>>()),
+			f => Self::load_spec(f),
+		}
+	}
+
+	fn load_spec(path: &str) -> Spec {
+		let data = contents(path).unwrap_or_else(|_| die!("{}: Couldn't read chain specification file. Sure it exists?", path));
+		Spec::load(&data).unwrap_or_else(|e| die!("{}: Invalid chain specification. {}", path, e))
+	}
+
+	/// Secret store holding the development accounts. It's kept in the directory of the dev chain,
+	/// so the publicly known keys never end up among the user's own accounts.
+	fn dev_secret_store(spec: &Spec, path: &Path) -> SecretStore {
+		let keys_path = ClientDatabases::chain_dir(spec, path).join("keys");
+		fs::create_dir_all(&keys_path).unwrap_or_else(|e| die!("Couldn't create development keys directory {}: {}", keys_path.display(), e));
+		let mut secret_store = SecretStore::new_in(&keys_path);
+		Configuration::unlock_dev_accounts(&mut secret_store);
+		secret_store
+	}
+
+	fn unlock_dev_accounts(secret_store: &mut SecretStore) {
+		for pair in dev_key_pairs() {
+			let address = Address::from(pair.public().sha3());
+			if secret_store.account(&address).is_none() {
+				secret_store.insert_account(pair.secret().clone(), "").unwrap_or_else(|e| die!("Couldn't store development account {}: {}", address, e));
+			}
+			secret_store.unlock_account(&address, "").unwrap_or_else(|e| die!("Couldn't unlock development account {}: {:?}", address, e));
+			info!("Development account {} unlocked", address);
 		}
 	}
 
@@ -346,11 +658,83 @@ impl Configuration {
 			self.execute_account_cli();
 			return;
 		}
+		if self.args.cmd_db {
+			self.execute_db_cli();
+			return;
+		}
+		if self.args.cmd_spec {
+			self.execute_spec_cli();
+			return;
+		}
+		if self.args.cmd_evm {
+			self.execute_evm_cli();
+			return;
+		}
+		if self.args.flag_light {
+			self.execute_light_client();
+			return;
+		}
 		self.execute_client();
 	}
 
+	fn client_config(&self) -> ClientConfig {
+		let mut client_config = ClientConfig::default();
+		match self.args.flag_cache {
+			Some(mb) => {
+				client_config.blockchain.max_cache_size = mb * 1024 * 1024 / 4;
+				client_config.blockchain.pref_cache_size = client_config.blockchain.max_cache_size / 2;
+				client_config.db_cache_size = Some(mb - mb / 4);
+			}
+			None => {
+				client_config.blockchain.pref_cache_size = self.args.flag_cache_pref_size;
+				client_config.blockchain.max_cache_size = self.args.flag_cache_max_size;
+			}
+		}
+		client_config.pruning = self.args.flag_pruning.parse().unwrap_or_else(|_| die!("{}: Invalid pruning method given.", self.args.flag_pruning));
+		if self.args.flag_pruning_history == 0 {
+			die!("--pruning-history must be at least 1.");
+		}
+		client_config.history = self.args.flag_pruning_history;
+		if let Some(history) = self.args.flag_body_history {
+			if history < MIN_BODY_HISTORY {
+				die!("--body-history must be at least {}.", MIN_BODY_HISTORY);
+			}
+			client_config.blockchain.body_history = Some(history);
+		}
+		client_config.blockchain.log_index = self.args.flag_log_index;
+		if let Some(mb) = self.args.flag_db_cache_size {
+			client_config.db_cache_size = Some(mb);
+		}
+		client_config.db_compaction = match &self.args.flag_db_compaction[..] {
+			"ssd" => CompactionProfile::ssd(),
+			"hdd" => CompactionProfile::hdd(),
+			_ => die!("{}: Invalid compaction profile given. Use ssd or hdd.", self.args.flag_db_compaction),
+		};
+		client_config.db_wal = !self.args.flag_fast_and_loose;
+		client_config.name = self.args.flag_identity.clone();
+		client_config.queue.max_mem_use = self.args.flag_queue_max_size;
+		client_config.sealing = self.sealing_config();
+		client_config.pow_cache_dir = Some(PathBuf::from(self.path()).join("ethash"));
+		client_config
+	}
+
+	fn sealing_config(&self) -> SealingConfig {
+		let gas = |flag: &str, value: &str| U256::from_dec_str(value).unwrap_or_else(|_| die!("{}: Invalid amount given with --{}.", value, flag));
+		let config = SealingConfig {
+			gas_floor_target: gas("gas-floor-target", &self.args.flag_gas_floor_target),
+			gas_ceil_target: gas("gas-cap", &self.args.flag_gas_cap),
+			min_gas_price: gas("min-gas-price", &self.args.flag_min_gas_price),
+			max_gas_per_tx: self.args.flag_tx_gas_limit.as_ref().map(|g| gas("tx-gas-limit", g)),
+			uncles: self.args.flag_uncles.parse().unwrap_or_else(|e| die!("Invalid --uncles policy. {}", e)),
+			reserved_local_gas: gas("reserve-local-gas", &self.args.flag_reserve_local_gas),
+		};
+		if config.gas_floor_target > config.gas_ceil_target {
+			die!("--gas-floor-target must not be greater than --gas-cap.");
+		}
+		config
+	}
+
 	fn execute_account_cli(&self) {
-		use util::keys::store::SecretStore;
 		use rpassword::read_password;
 		let mut secret_store = SecretStore::new();
 		if self.args.cmd_new {
@@ -376,6 +760,188 @@ impl Configuration {
 		}
 	}
 
+	fn execute_spec_cli(&self) {
+		if self.args.cmd_check {
+			let spec = Self::load_spec(&self.args.arg_file);
+			let summary = format!("{} ({}), genesis {}", spec.name, spec.engine_name, spec.genesis_header().hash());
+			spec.to_engine().unwrap_or_else(|e| die!("{}: Couldn't create the engine: {:?}", self.args.arg_file, e));
+			println!("{}: valid specification of {}", self.args.arg_file, summary);
+		}
+	}
+
+	fn execute_evm_cli(&self) {
+		let hex = |flag: &str, value: &str| value.trim_left_matches("0x").from_hex().unwrap_or_else(|_| die!("{}: Invalid hex given for {}.", value, flag));
+		let amount = |flag: &str, value: &str| U256::from_dec_str(value).unwrap_or_else(|_| die!("{}: Invalid amount given with --{}.", value, flag));
+		let address = |flag: &str, value: &str| Address::from_str(value.trim_left_matches("0x")).unwrap_or_else(|_| die!("{}: Invalid address given with --{}.", value, flag));
+		let call = Call {
+			code: hex("<code>", &self.args.arg_code),
+			data: self.args.flag_input.as_ref().map_or_else(Vec::new, |d| hex("--input", d)),
+			gas: amount("gas", &self.args.flag_gas),
+			gas_price: amount("gas-price", &self.args.flag_gas_price),
+			sender: self.args.flag_from.as_ref().map_or_else(Address::new, |a| address("from", a)),
+			address: self.args.flag_to.as_ref().map_or_else(Address::new, |a| address("to", a)),
+			value: amount("value", &self.args.flag_value),
+		};
+
+		let mut runner = EvmRunner::new(self.spec(), VMType::Interpreter).unwrap_or_else(|e| die!("Couldn't create the engine: {:?}", e));
+		if let Some(ref path) = self.args.flag_state {
+			let data = contents(path).unwrap_or_else(|_| die!("{}: Couldn't read state file.", path));
+			let json = ::std::str::from_utf8(&data).ok().and_then(|s| Json::from_str(s).ok()).unwrap_or_else(|| die!("{}: Invalid JSON in state file.", path));
+			runner.set_pre_state(&json);
+		}
+
+		if self.args.flag_bench {
+			for vm_type in VMType::all() {
+				let name = format!("{}", vm_type);
+				runner.set_vm_type(vm_type);
+				let start = time::precise_time_ns();
+				for _ in 0..self.args.flag_repeat {
+					runner.run(&call, false);
+				}
+				let elapsed = (time::precise_time_ns() - start) as f64;
+				println!("{}: {} runs in {:.3} ms, {:.3} us per run", name, self.args.flag_repeat, elapsed / 1e6, elapsed / 1e3 / self.args.flag_repeat as f64);
+			}
+			return;
+		}
+
+		let outcome = runner.run(&call, self.args.flag_trace);
+		for step in &outcome.trace {
+			let stack: Vec<String> = step.stack.iter().map(|v| format!("{:x}", v)).collect();
+			println!("{:>6} {:<14} gas {:<10} cost {:<6} stack [{}]", step.pc, step.instruction, step.gas, step.gas_cost, stack.join(", "));
+		}
+		match outcome.result {
+			Ok(_) => println!("Success"),
+			Err(ref e) => println!("Failed: {:?}", e),
+		}
+		println!("Output: 0x{}", outcome.output.to_hex());
+		println!("Gas used: {}", outcome.gas_used);
+		for log in &outcome.logs {
+			let topics: Vec<String> = log.topics.iter().map(|t| format!("{:?}", t)).collect();
+			println!("Log: address {:?}, topics [{}], data 0x{}", log.address, topics.join(", "), log.data.to_hex());
+		}
+		println!("Storage:");
+		for (key, value) in &outcome.storage {
+			println!("  {:?}: {:?}", key, value);
+		}
+	}
+
+	fn execute_db_cli(&self) {
+		setup_log(&self.args.flag_logging);
+		let spec = self.spec();
+		let path = self.path();
+		let path = Path::new(&path);
+		let client_config = self.client_config();
+
+		if self.args.cmd_kill {
+			let chain_dir = ClientDatabases::chain_dir(&spec, path);
+			let entries = match fs::read_dir(&chain_dir) {
+				Ok(entries) => entries,
+				Err(_) => {
+					println!("No databases found in {}", chain_dir.display());
+					return;
+				}
+			};
+			let dirs: Vec<PathBuf> = entries
+				.map(|entry| entry.unwrap_or_else(|e| die!("Error reading {}: {}", chain_dir.display(), e)).path())
+				.filter(|dir| dir.is_dir() && dir.file_name().and_then(|n| n.to_str()).map_or(false, |n| n.starts_with('v') && n.contains("-sec-")))
+				.collect();
+			if dirs.is_empty() {
+				println!("No databases found in {}", chain_dir.display());
+				return;
+			}
+			println!("The databases of all versions and pruning methods of this chain will be removed:");
+			for dir in &dirs {
+				println!("  {}", dir.display());
+			}
+			if !self.args.flag_yes {
+				print!("Remove them? [y/N] ");
+				io::stdout().flush().unwrap_or_else(|e| die!("Error writing to stdout: {}", e));
+				let mut answer = String::new();
+				io::stdin().read_line(&mut answer).unwrap_or_else(|e| die!("Error reading answer: {}", e));
+				match answer.trim().to_lowercase().as_ref() {
+					"y" | "yes" => {},
+					_ => {
+						println!("Nothing removed.");
+						return;
+					}
+				}
+			}
+			for dir in &dirs {
+				fs::remove_dir_all(dir).unwrap_or_else(|e| die!("Error removing {}: {}", dir.display(), e));
+				println!("Removed {}", dir.display());
+			}
+			return;
+		}
+
+		if self.args.cmd_verify {
+			let client = Client::new(client_config, spec, path, IoChannel::disconnected()).unwrap_or_else(|e| die!("Error opening databases: {:?}", e));
+			let best = client.chain_info().best_block_number;
+			let errors = client.verify_integrity(|n| if n % 10000 == 0 { println!("Verifying #{} of {}", n, best); });
+			for e in &errors {
+				println!("{:?}", e);
+			}
+			if !errors.is_empty() {
+				die!("Found {} inconsistencies. Run `parity db kill` and resync.", errors.len());
+			}
+			println!("Verified {} blocks, no inconsistencies found.", best + 1);
+			return;
+		}
+
+		let dbs = ClientDatabases::open(&client_config, &spec, path).unwrap_or_else(|e| die!("Error opening databases: {:?}", e));
+		if self.args.cmd_compact {
+			println!("Compacting databases in {}", ClientDatabases::dir(&client_config, &spec, path).display());
+			dbs.compact();
+			println!("Done.");
+		}
+		if self.args.cmd_stats {
+			let stats = dbs.stats();
+			let format = |s: &EntryStats| format!("{} entries, {} keys, {} values", s.count,
+				Informant::format_bytes(s.key_bytes as usize), Informant::format_bytes(s.value_bytes as usize));
+			println!("state: {}", format(&stats.state));
+			println!("blocks: {}", format(&stats.blocks));
+			println!("extras:");
+			for (index, s) in &stats.extras {
+				println!("  {}: {}", index, format(s));
+			}
+		}
+	}
+
+	fn sync_config(&self, spec: &Spec) -> SyncConfig {
+		let mut sync_config = SyncConfig::default();
+		sync_config.network_id = self.args.flag_networkid.as_ref().map(|id| U256::from_str(id).unwrap_or_else(|_| die!("{}: Invalid index given with --networkid", id))).unwrap_or(spec.network_id());
+		sync_config
+	}
+
+	fn execute_light_client(&self) {
+		let panic_handler = PanicHandler::new_in_arc();
+		setup_log(&self.args.flag_logging);
+		unsafe { ::fdlimit::raise_fd_limit(); }
+
+		let spec = self.spec();
+		let net_settings = self.net_settings(&spec);
+		let sync_config = self.sync_config(&spec);
+		let client_config = self.client_config();
+
+		let mut network = NetworkService::start(net_settings).unwrap_or_else(|e| die!("Error starting network: {:?}", e));
+		panic_handler.forward_from(&network);
+		info!("Starting {}", network.host_info());
+		info!("Configured for {} using {} engine (light client)", spec.name, spec.engine_name);
+		let on_demand = Arc::new(OnDemandService::new());
+		let client = LightClient::new(&client_config, spec, &Path::new(&self.path()), on_demand.clone()).unwrap_or_else(|e| die!("Error opening header chain: {:?}", e));
+		let sync = EthSync::register_light(&mut network, sync_config, client.clone(), on_demand);
+
+		let transports = self.rpc_transports();
+		let secret_store = Arc::new(RwLock::new(SecretStore::new()));
+		if !transports.is_empty() {
+			let (handlers, _) = setup_rpc_server(client, sync, secret_store.clone(), network.io(), &transports, self.args.flag_filter_lifetime, self.args.flag_max_filters);
+			for handler in handlers {
+				panic_handler.forward_from(handler.deref());
+			}
+		}
+
+		wait_for_exit(panic_handler);
+	}
+
 	fn execute_client(&self) {
 		// Setup panic handler
 		let panic_handler = PanicHandler::new_in_arc();
@@ -387,54 +953,48 @@ impl Configuration {
 
 		let spec = self.spec();
 		let net_settings = self.net_settings(&spec);
-		let mut sync_config = SyncConfig::default();
-		sync_config.network_id = self.args.flag_networkid.as_ref().map(|id| U256::from_str(id).unwrap_or_else(|_| die!("{}: Invalid index given with --networkid", id))).unwrap_or(spec.network_id());
+		let sync_config = self.sync_config(&spec);
+		let dev_secret_store = if self.args.flag_chain == "dev" && !self.args.flag_testnet {
+			Some(Configuration::dev_secret_store(&spec, &Path::new(&self.path())))
+		} else {
+			None
+		};
 
 		// Build client
-		let mut client_config = ClientConfig::default();
-		match self.args.flag_cache {
-			Some(mb) => {
-				client_config.blockchain.max_cache_size = mb * 1024 * 1024;
-				client_config.blockchain.pref_cache_size = client_config.blockchain.max_cache_size / 2;
-			}
-			None => {
-				client_config.blockchain.pref_cache_size = self.args.flag_cache_pref_size;
-				client_config.blockchain.max_cache_size = self.args.flag_cache_max_size;
-			}
-		}
-		client_config.prefer_journal = self.args.flag_pruning;
-		client_config.name = self.args.flag_identity.clone();
-		client_config.queue.max_mem_use = self.args.flag_queue_max_size;
-		let mut service = ClientService::start(client_config, spec, net_settings, &Path::new(&self.path())).unwrap();
+		let client_config = self.client_config();
+		let mut service = match ClientService::start(client_config, spec, net_settings, &Path::new(&self.path())) {
+			Ok(service) => service,
+			Err(ethcore::error::Error::Util(UtilError::SimpleString(message))) => die!("{}", message),
+			Err(e) => die!("Error starting client: {:?}", e),
+		};
 		panic_handler.forward_from(&service);
 		let client = service.client().clone();
 		client.set_author(self.author());
 		client.set_extra_data(self.extra_data());
+		if let Some(secret_store) = dev_secret_store {
+			*client.secret_store().write().unwrap() = secret_store;
+		}
 
 		// Sync
 		let sync = EthSync::register(service.network(), sync_config, client);
 
 		// Setup rpc
-		if self.args.flag_jsonrpc || self.args.flag_rpc {
-			let url = format!("{}:{}",
-				self.args.flag_rpcaddr.as_ref().unwrap_or(&self.args.flag_jsonrpc_addr),
-				self.args.flag_rpcport.unwrap_or(self.args.flag_jsonrpc_port)
-			);
-			SocketAddr::from_str(&url).unwrap_or_else(|_|die!("{}: Invalid JSONRPC listen host/port given.", url));
-			let cors = self.args.flag_rpccorsdomain.as_ref().unwrap_or(&self.args.flag_jsonrpc_cors);
-			// TODO: use this as the API list.
-			let apis = self.args.flag_rpcapi.as_ref().unwrap_or(&self.args.flag_jsonrpc_apis);
-			let server_handler = setup_rpc_server(service.client(), sync.clone(), &url, cors, apis.split(",").collect());
-			if let Some(handler) = server_handler {
+		let transports = self.rpc_transports();
+		// shared with the client so that accounts unlocked over RPC can seal blocks.
+		let secret_store = service.client().secret_store().clone();
+		let mut rpc_stats = None;
+		if !transports.is_empty() {
+			let (handlers, stats) = setup_rpc_server(service.client(), sync.clone(), secret_store.clone(), service.io(), &transports, self.args.flag_filter_lifetime, self.args.flag_max_filters);
+			for handler in handlers {
 				panic_handler.forward_from(handler.deref());
 			}
-
+			rpc_stats = stats;
 		}
 
 		// Register IO handler
 		let io_handler  = Arc::new(ClientIoHandler {
 			client: service.client(),
-			info: Default::default(),
+			info: Informant::new(rpc_stats),
 			sync: sync.clone(),
 		});
 		service.io().register_handler(io_handler).expect("Error registering IO handler");
@@ -468,19 +1028,21 @@ struct Informant {
 	chain_info: RwLock<Option<BlockChainInfo>>,
 	cache_info: RwLock<Option<BlockChainCacheSize>>,
 	report: RwLock<Option<ClientReport>>,
+	rpc_stats: Option<Arc<RpcStats>>,
+	rpc_totals: RwLock<Option<(u64, u64)>>,
 }
 
-impl Default for Informant {
-	fn default() -> Self {
+impl Informant {
+	fn new(rpc_stats: Option<Arc<RpcStats>>) -> Self {
 		Informant {
 			chain_info: RwLock::new(None),
 			cache_info: RwLock::new(None),
 			report: RwLock::new(None),
+			rpc_stats: rpc_stats,
+			rpc_totals: RwLock::new(None),
 		}
 	}
-}
 
-impl Informant {
 	fn format_bytes(b: usize) -> String {
 		match binary_prefix(b as f64) {
 			Standalone(bytes)   => format!("{} bytes", bytes),
@@ -497,14 +1059,26 @@ impl Informant {
 		let cache_info = client.blockchain_cache_info();
 		let report = client.report();
 		let sync_info = sync.status();
-
-		if let (_, _, &Some(ref last_report)) = (self.chain_info.read().unwrap().deref(), self.cache_info.read().unwrap().deref(), self.report.read().unwrap().deref()) {
-			println!("[ #{} {} ]---[ {} blk/s | {} tx/s | {} gas/s  //··· {}/{} peers, #{}, {}+{} queued ···// mem: {} db, {} chain, {} queue, {} sync ]",
+		let rpc_totals = self.rpc_stats.as_ref().map(|stats| stats.totals());
+
+		if let (_, &Some(ref last_cache_info), &Some(ref last_report)) = (self.chain_info.read().unwrap().deref(), self.cache_info.read().unwrap().deref(), self.report.read().unwrap().deref()) {
+			let lookups = (cache_info.hits + cache_info.misses) - (last_cache_info.hits + last_cache_info.misses);
+			let hit_rate = match lookups {
+				0 => 100,
+				_ => (cache_info.hits - last_cache_info.hits) * 100 / lookups,
+			};
+			let rpc_info = match (rpc_totals, *self.rpc_totals.read().unwrap().deref()) {
+				(Some((calls, errors)), Some((last_calls, last_errors))) =>
+					format!(" | {} rpc/s, {} failed", (calls - last_calls) / dur as u64, errors - last_errors),
+				_ => String::new(),
+			};
+			println!("[ #{} {} ]---[ {} blk/s | {} tx/s | {} gas/s{}  //··· {}/{} peers, #{}, {}+{} queued ···// mem: {} db, {} chain ({}% hits), {} queue, {} sync ]",
 				chain_info.best_block_number,
 				chain_info.best_block_hash,
 				(report.blocks_imported - last_report.blocks_imported) / dur,
 				(report.transactions_applied - last_report.transactions_applied) / dur,
 				(report.gas_processed - last_report.gas_processed) / From::from(dur),
+				rpc_info,
 
 				sync_info.num_active_peers,
 				sync_info.num_peers,
@@ -514,6 +1088,7 @@ impl Informant {
 
 				Informant::format_bytes(report.state_db_mem),
 				Informant::format_bytes(cache_info.total()),
+				hit_rate,
 				Informant::format_bytes(queue_info.mem_used),
 				Informant::format_bytes(sync_info.mem_used),
 			);
@@ -522,6 +1097,7 @@ impl Informant {
 		*self.chain_info.write().unwrap().deref_mut() = Some(chain_info);
 		*self.cache_info.write().unwrap().deref_mut() = Some(cache_info);
 		*self.report.write().unwrap().deref_mut() = Some(report);
+		*self.rpc_totals.write().unwrap().deref_mut() = rpc_totals;
 	}
 }
 
@@ -549,3 +1125,12 @@ impl IoHandler<NetSyncMessage> for ClientIoHandler {
 #[test]
 fn if_works() {
 }
+
+#[test]
+fn host_headers_of_listen_addresses() {
+	let host = |addr: &str| host_header_of(&SocketAddr::from_str(addr).unwrap());
+	assert_eq!(host("127.0.0.1:8545"), Some("127.0.0.1".to_owned()));
+	assert_eq!(host("[::1]:8545"), Some("[::1]".to_owned()));
+	assert_eq!(host("0.0.0.0:8545"), None);
+	assert_eq!(host("[::]:8545"), None);
+}
@@ -0,0 +1,230 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Access control of the JSON-RPC transports.
+
+use std::ascii::AsciiExt;
+use std::collections::HashSet;
+use std::net::IpAddr;
+use rustc_serialize::base64::{ToBase64, STANDARD};
+
+/// Namespaces giving access to accounts. Only exposed over non-local transports if explicitly allowed.
+const UNSAFE_APIS: &'static [&'static str] = &["personal"];
+
+/// Set of API namespaces (method name prefixes, e.g. `eth` for `eth_getBalance`) available over a transport.
+#[derive(Debug, Clone)]
+pub struct ApiSet {
+	apis: Option<HashSet<String>>,
+	allow_unsafe: bool,
+}
+
+impl ApiSet {
+	/// All namespaces except the unsafe ones.
+	pub fn all() -> Self {
+		ApiSet {
+			apis: None,
+			allow_unsafe: false,
+		}
+	}
+
+	/// Only listed namespaces. Unsafe namespaces are still refused unless `allow_unsafe` is called.
+	pub fn list<T>(apis: &[T]) -> Self where T: AsRef<str> {
+		ApiSet {
+			apis: Some(apis.iter().map(|api| api.as_ref().to_owned()).collect()),
+			allow_unsafe: false,
+		}
+	}
+
+	/// Allows unsafe namespaces present in the set.
+	pub fn allow_unsafe(mut self) -> Self {
+		self.allow_unsafe = true;
+		self
+	}
+
+	/// Returns true if `method` may be called.
+	pub fn is_allowed(&self, method: &str) -> bool {
+		let namespace = method.split('_').next().unwrap_or("");
+		if !self.allow_unsafe && UNSAFE_APIS.contains(&namespace) {
+			return false;
+		}
+		match self.apis {
+			Some(ref apis) => apis.contains(namespace),
+			None => true,
+		}
+	}
+}
+
+impl Default for ApiSet {
+	fn default() -> Self {
+		ApiSet::all()
+	}
+}
+
+/// Credentials required in the `Authorization` header.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Authorization {
+	/// No authorization.
+	None,
+	/// `Authorization: Bearer <token>`.
+	Bearer(String),
+	/// HTTP basic authorization.
+	Basic {
+		/// User name.
+		username: String,
+		/// Password.
+		password: String,
+	},
+}
+
+impl Authorization {
+	fn header(&self) -> Option<String> {
+		match *self {
+			Authorization::None => None,
+			Authorization::Bearer(ref token) => Some(format!("Bearer {}", token)),
+			Authorization::Basic { ref username, ref password } =>
+				Some(format!("Basic {}", format!("{}:{}", username, password).as_bytes().to_base64(STANDARD))),
+		}
+	}
+}
+
+impl Default for Authorization {
+	fn default() -> Self {
+		Authorization::None
+	}
+}
+
+/// Access control of a network transport.
+#[derive(Debug, Clone, Default)]
+pub struct AccessControl {
+	/// Namespaces available over the transport.
+	pub apis: ApiSet,
+	/// Required credentials.
+	pub authorization: Authorization,
+	/// Accepted values of the `Host` header (without port). `None` accepts any host.
+	pub allowed_hosts: Option<Vec<String>>,
+	/// Addresses allowed to connect. `None` allows any address.
+	pub allowed_ips: Option<Vec<IpAddr>>,
+}
+
+impl AccessControl {
+	/// Returns true if peer at `ip` may connect.
+	pub fn is_ip_allowed(&self, ip: &IpAddr) -> bool {
+		match self.allowed_ips {
+			Some(ref ips) => ips.contains(ip),
+			None => true,
+		}
+	}
+
+	/// Returns true if request with given `Host` header should be served.
+	///
+	/// Protects against DNS rebinding: a page served from a malicious domain resolving to
+	/// a local address sends its own domain in the header.
+	pub fn is_host_allowed(&self, host: Option<&str>) -> bool {
+		match (self.allowed_hosts.as_ref(), host) {
+			(None, _) => true,
+			// non-browser clients don't always send the header.
+			(Some(_), None) => true,
+			(Some(hosts), Some(host)) => {
+				let hostname = strip_port(host);
+				hosts.iter().any(|allowed| allowed.eq_ignore_ascii_case(hostname))
+			},
+		}
+	}
+
+	/// Returns true if the `Authorization` header carries required credentials.
+	pub fn is_authorized(&self, header: Option<&str>) -> bool {
+		match (self.authorization.header(), header) {
+			(None, _) => true,
+			(Some(expected), Some(header)) => constant_time_eq(expected.as_bytes(), header.trim().as_bytes()),
+			(Some(_), None) => false,
+		}
+	}
+}
+
+fn strip_port(host: &str) -> &str {
+	// IPv6 literal, e.g. `[::1]:8545`
+	if host.starts_with('[') {
+		return match host.find(']') {
+			Some(end) => &host[..end + 1],
+			None => host,
+		};
+	}
+	host.split(':').next().unwrap_or(host)
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+	a.len() == b.len() && a.iter().zip(b.iter()).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+	use std::net::IpAddr;
+	use std::str::FromStr;
+	use super::*;
+
+	#[test]
+	fn should_filter_apis() {
+		let apis = ApiSet::list(&["eth", "personal"]);
+		assert!(apis.is_allowed("eth_getBalance"));
+		assert!(!apis.is_allowed("net_version"));
+		assert!(!apis.is_allowed("personal_listAccounts"));
+		assert!(apis.allow_unsafe().is_allowed("personal_listAccounts"));
+		assert!(!ApiSet::all().is_allowed("personal_listAccounts"));
+		assert!(ApiSet::all().is_allowed("web3_clientVersion"));
+	}
+
+	#[test]
+	fn should_check_hosts() {
+		let access = AccessControl {
+			allowed_hosts: Some(vec!["localhost".to_owned(), "[::1]".to_owned()]),
+			..Default::default()
+		};
+		assert!(access.is_host_allowed(Some("localhost:8545")));
+		assert!(access.is_host_allowed(Some("[::1]:8545")));
+		assert!(access.is_host_allowed(None));
+		assert!(!access.is_host_allowed(Some("evil.com")));
+	}
+
+	#[test]
+	fn should_check_ips() {
+		let access = AccessControl {
+			allowed_ips: Some(vec![IpAddr::from_str("127.0.0.1").unwrap()]),
+			..Default::default()
+		};
+		assert!(access.is_ip_allowed(&IpAddr::from_str("127.0.0.1").unwrap()));
+		assert!(!access.is_ip_allowed(&IpAddr::from_str("10.0.0.1").unwrap()));
+	}
+
+	#[test]
+	fn should_check_authorization() {
+		let bearer = AccessControl {
+			authorization: Authorization::Bearer("secret".to_owned()),
+			..Default::default()
+		};
+		assert!(bearer.is_authorized(Some("Bearer secret")));
+		assert!(!bearer.is_authorized(Some("Bearer secreT")));
+		assert!(!bearer.is_authorized(None));
+
+		let basic = AccessControl {
+			authorization: Authorization::Basic { username: "Aladdin".to_owned(), password: "open sesame".to_owned() },
+			..Default::default()
+		};
+		assert!(basic.is_authorized(Some("Basic QWxhZGRpbjpvcGVuIHNlc2FtZQ==")));
+		assert!(!basic.is_authorized(Some("Basic QWxhZGRpbjpvcGVuIHNlc2FtZR==")));
+
+		assert!(AccessControl::default().is_authorized(None));
+	}
+}
@@ -0,0 +1,121 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! HTTP transport.
+//!
+//! Every `POST` body is a single JSON-RPC request (or batch). Requests pass through
+//! `AccessMiddleware`, which checks them against the transport's `AccessControl`, before
+//! they reach the JSON-RPC handler.
+
+use std::io::Read;
+use std::str;
+use std::sync::Arc;
+use hyper;
+use hyper::header::{Headers, ContentType};
+use hyper::method::Method;
+use hyper::server::{Handler, Request, Response};
+use hyper::status::StatusCode;
+use access::AccessControl;
+use pubsub::{Caller, SessionHandler};
+
+fn header<'a>(headers: &'a Headers, name: &str) -> Option<&'a str> {
+	headers.get_raw(name)
+		.and_then(|values| values.first())
+		.and_then(|value| str::from_utf8(value).ok())
+}
+
+fn respond(mut res: Response, status: StatusCode, body: &[u8]) {
+	*res.status_mut() = status;
+	if let Err(e) = res.send(body) {
+		debug!(target: "rpc", "Error sending HTTP response: {:?}", e);
+	}
+}
+
+/// Refuses requests failing the access checks and passes the rest to the wrapped handler.
+struct AccessMiddleware<H> {
+	access: AccessControl,
+	inner: H,
+}
+
+impl<H> Handler for AccessMiddleware<H> where H: Handler {
+	fn handle(&self, req: Request, res: Response) {
+		if !self.access.is_ip_allowed(&req.remote_addr.ip()) {
+			debug!(target: "rpc", "Refused HTTP request from {}", req.remote_addr);
+			return respond(res, StatusCode::Forbidden, b"");
+		}
+		if !self.access.is_host_allowed(header(&req.headers, "Host")) {
+			return respond(res, StatusCode::Forbidden, b"Provided Host header is not allowed.\n");
+		}
+		// CORS preflight requests never carry credentials.
+		if req.method != Method::Options && !self.access.is_authorized(header(&req.headers, "Authorization")) {
+			return respond(res, StatusCode::Unauthorized, b"");
+		}
+		self.inner.handle(req, res)
+	}
+}
+
+/// Serves JSON-RPC requests.
+struct RpcHandler {
+	handler: Arc<SessionHandler>,
+	cors_domain: String,
+}
+
+impl RpcHandler {
+	fn set_response_headers(&self, headers: &mut Headers) {
+		headers.set_raw("Allow", vec![b"OPTIONS, POST".to_vec()]);
+		headers.set_raw("Access-Control-Allow-Headers", vec![b"Origin, Content-Type, Accept, Authorization".to_vec()]);
+		headers.set_raw("Access-Control-Allow-Origin", vec![self.cors_domain.as_bytes().to_vec()]);
+		headers.set(ContentType::json());
+	}
+}
+
+impl Handler for RpcHandler {
+	fn handle(&self, mut req: Request, mut res: Response) {
+		match req.method {
+			Method::Options => {
+				self.set_response_headers(res.headers_mut());
+				respond(res, StatusCode::Ok, b"")
+			},
+			Method::Post => {
+				let mut request = String::new();
+				if req.read_to_string(&mut request).is_err() {
+					return respond(res, StatusCode::BadRequest, b"");
+				}
+				self.set_response_headers(res.headers_mut());
+				let handler = &self.handler;
+				match Caller::Address(req.remote_addr.ip()).with(|| handler.handle_request(&request, None)) {
+					Some(response) => respond(res, StatusCode::Ok, response.as_bytes()),
+					None => respond(res, StatusCode::Ok, b""),
+				}
+			},
+			_ => respond(res, StatusCode::MethodNotAllowed, b"Used HTTP method is not allowed. POST or OPTIONS is required.\n"),
+		}
+	}
+}
+
+/// Listens on `addr` and serves requests with `threads` worker threads. Blocks until the server is closed.
+pub fn serve(addr: &str, threads: usize, handler: Arc<SessionHandler>, access: AccessControl, cors_domain: String) -> hyper::Result<()> {
+	let server = try!(hyper::Server::http(addr));
+	let _listening = try!(server.handle_threads(AccessMiddleware {
+		access: access,
+		inner: RpcHandler {
+			handler: handler,
+			cors_domain: cors_domain,
+		},
+	}, threads));
+	// `Listening` joins the acceptor thread when dropped.
+	Ok(())
+}
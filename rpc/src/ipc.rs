@@ -0,0 +1,196 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Unix domain socket transport.
+//!
+//! Requests are plain JSON values written back to back on the stream (optionally
+//! separated with whitespace); every response is terminated with a newline.
+
+use std::io::{self, Read, Write};
+use std::fs;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::os::unix::fs::FileTypeExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use pubsub::{Session, SessionHandler, Sink};
+
+const READ_CHUNK: usize = 4096;
+/// Largest request accepted; connections sending more without completing a request are closed.
+const MAX_REQUEST_SIZE: usize = 5 * 1024 * 1024;
+
+/// Binds a listening socket at `path`, replacing a stale socket file left by a previous run.
+/// Fails if anything else is at `path`, including a socket somebody is still listening on.
+pub fn bind(path: &str) -> io::Result<UnixListener> {
+	if let Ok(metadata) = fs::symlink_metadata(path) {
+		if !metadata.file_type().is_socket() {
+			return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("{} exists and is not a socket", path)));
+		}
+		match UnixStream::connect(path) {
+			Ok(_) => return Err(io::Error::new(io::ErrorKind::AddrInUse, format!("{} is in use", path))),
+			Err(ref e) if e.kind() == io::ErrorKind::ConnectionRefused => try!(fs::remove_file(path)),
+			Err(e) => return Err(e),
+		}
+	}
+	UnixListener::bind(path)
+}
+
+struct IpcSink {
+	stream: Mutex<UnixStream>,
+}
+
+impl Sink for IpcSink {
+	fn send(&self, message: String) -> bool {
+		let mut stream = self.stream.lock().unwrap();
+		stream.write_all(message.as_bytes()).and_then(|_| stream.write_all(b"\n")).is_ok()
+	}
+}
+
+/// Accepts connections forever, serving each of them from a separate thread.
+pub fn serve(listener: UnixListener, handler: Arc<SessionHandler>) {
+	for stream in listener.incoming() {
+		match stream {
+			Ok(stream) => {
+				let handler = handler.clone();
+				thread::Builder::new().name("jsonrpc_ipc_conn".to_owned()).spawn(move || {
+					if let Err(e) = serve_connection(stream, &handler) {
+						debug!(target: "rpc", "IPC connection closed: {:?}", e);
+					}
+				}).expect("Error while creating jsonrpc ipc connection thread");
+			},
+			Err(e) => warn!(target: "rpc", "Error accepting IPC connection: {:?}", e),
+		}
+	}
+}
+
+fn serve_connection(mut stream: UnixStream, handler: &SessionHandler) -> io::Result<()> {
+	let session = Arc::new(Session::new(Box::new(IpcSink {
+		stream: Mutex::new(try!(stream.try_clone())),
+	})));
+	let result = read_requests(&mut stream, handler, &session);
+	handler.session_closed(&session);
+	result
+}
+
+fn read_requests(stream: &mut UnixStream, handler: &SessionHandler, session: &Arc<Session>) -> io::Result<()> {
+	let mut buf = Vec::new();
+	let mut chunk = [0u8; READ_CHUNK];
+	loop {
+		let read = try!(stream.read(&mut chunk));
+		if read == 0 {
+			return Ok(());
+		}
+		buf.extend_from_slice(&chunk[..read]);
+
+		while let Some(len) = next_request(&buf) {
+			let request: Vec<u8> = buf.drain(..len).collect();
+			let response = match ::std::str::from_utf8(&request) {
+				Ok(request) => handler.handle_request(request.trim(), Some(session)),
+				Err(_) => None,
+			};
+			if let Some(response) = response {
+				if !session.send(response) {
+					return Err(io::Error::new(io::ErrorKind::BrokenPipe, "Error writing IPC response"));
+				}
+			}
+		}
+		if buf.len() > MAX_REQUEST_SIZE {
+			return Err(io::Error::new(io::ErrorKind::InvalidData, "IPC request too large"));
+		}
+	}
+}
+
+/// Returns the length of the first complete JSON object or array in `buf`, including any leading whitespace.
+fn next_request(buf: &[u8]) -> Option<usize> {
+	let mut depth = 0usize;
+	let mut in_string = false;
+	let mut escaped = false;
+	let mut started = false;
+	for (i, &b) in buf.iter().enumerate() {
+		if in_string {
+			match b {
+				_ if escaped => escaped = false,
+				b'\\' => escaped = true,
+				b'"' => in_string = false,
+				_ => {}
+			}
+			continue;
+		}
+		match b {
+			b'"' => in_string = true,
+			b'{' | b'[' => { depth += 1; started = true; },
+			b'}' | b']' if depth > 0 => {
+				depth -= 1;
+				if depth == 0 {
+					return Some(i + 1);
+				}
+			},
+			b' ' | b'\t' | b'\r' | b'\n' => {},
+			// garbage outside of any value; hand it over so the handler reports a parse error.
+			_ if !started => return Some(i + 1),
+			_ => {}
+		}
+	}
+	None
+}
+
+#[cfg(test)]
+mod tests {
+	use std::env;
+	use std::fs::{self, File};
+	use std::path::PathBuf;
+	use super::{bind, next_request};
+
+	fn socket_path(name: &str) -> PathBuf {
+		let path = env::temp_dir().join(format!("parity-ipc-test-{}", name));
+		let _ = fs::remove_file(&path);
+		path
+	}
+
+	#[test]
+	fn should_replace_stale_socket_only() {
+		let path = socket_path("stale");
+		let path = path.to_str().unwrap();
+		drop(bind(path).unwrap());
+		// nobody listens on the file left behind
+		let listener = bind(path).unwrap();
+		assert!(bind(path).is_err());
+		drop(listener);
+		fs::remove_file(path).unwrap();
+	}
+
+	#[test]
+	fn should_not_remove_other_files() {
+		let path = socket_path("file");
+		File::create(&path).unwrap();
+		assert!(bind(path.to_str().unwrap()).is_err());
+		assert!(path.exists());
+		fs::remove_file(&path).unwrap();
+	}
+
+	#[test]
+	fn should_split_concatenated_requests() {
+		let buf = br#"{"id":1,"method":"a}"}  [{"id":2}]{"id""#;
+		assert_eq!(next_request(buf), Some(22));
+		assert_eq!(next_request(&buf[22..]), Some(12));
+		assert_eq!(next_request(&buf[34..]), None);
+	}
+
+	#[test]
+	fn should_handle_escaped_quotes() {
+		let buf = br#"{"a":"\"}"}"#;
+		assert_eq!(next_request(buf), Some(buf.len()));
+	}
+}
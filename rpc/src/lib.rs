@@ -19,26 +19,44 @@
 #![cfg_attr(feature="nightly", feature(custom_derive, custom_attribute, plugin))]
 #![cfg_attr(feature="nightly", plugin(serde_macros, clippy))]
 
+#[macro_use]
+extern crate log;
 extern crate rustc_serialize;
 extern crate serde;
 extern crate serde_json;
 extern crate jsonrpc_core;
-extern crate jsonrpc_http_server;
+extern crate hyper;
+extern crate ws;
 extern crate ethcore_util as util;
 extern crate ethcore;
 extern crate ethsync;
 extern crate transient_hashmap;
+extern crate time;
 
-use std::sync::Arc;
+use std::io;
+use std::sync::{Arc, Mutex};
 use std::thread;
 use util::panics::PanicHandler;
 use self::jsonrpc_core::{IoHandler, IoDelegate};
+use self::pubsub::{PubSubHandler, SessionHandler};
+
+pub use self::access::{AccessControl, ApiSet, Authorization};
+pub use self::metrics::{RpcStats, MethodStats, LATENCY_BUCKETS_MS};
 
 pub mod v1;
+pub mod pubsub;
+mod access;
+mod metrics;
+mod http;
+#[cfg(unix)]
+mod ipc;
+mod websockets;
 
-/// Http server.
+/// JSON-RPC server. All transports started from the same server share its delegates.
 pub struct RpcServer {
 	handler: Arc<IoHandler>,
+	pubsub: Mutex<Option<Arc<PubSubHandler>>>,
+	stats: Arc<RpcStats>,
 }
 
 impl RpcServer {
@@ -46,6 +64,8 @@ impl RpcServer {
 	pub fn new() -> RpcServer {
 		RpcServer {
 			handler: Arc::new(IoHandler::new()),
+			pubsub: Mutex::new(None),
+			stats: Arc::new(RpcStats::new()),
 		}
 	}
 
@@ -54,18 +74,70 @@ impl RpcServer {
 		self.handler.add_delegate(delegate);
 	}
 
+	/// Set handler of subscription requests. Subscriptions are only available over persistent transports.
+	pub fn set_pubsub(&self, handler: Arc<PubSubHandler>) {
+		*self.pubsub.lock().unwrap() = Some(handler);
+	}
+
+	/// Returns statistics of calls served over all transports.
+	pub fn stats(&self) -> Arc<RpcStats> {
+		self.stats.clone()
+	}
+
+	fn session_handler(&self, apis: ApiSet) -> Arc<SessionHandler> {
+		Arc::new(SessionHandler::new(self.handler.clone(), self.pubsub.lock().unwrap().clone(), apis, self.stats.clone()))
+	}
+
 	/// Start server asynchronously in new thread and returns panic handler.
-	pub fn start_http(&self, addr: &str, cors_domain: &str, threads: usize) -> Arc<PanicHandler> {
+	pub fn start_http(&self, addr: &str, cors_domain: &str, threads: usize, access: AccessControl) -> Arc<PanicHandler> {
 		let addr = addr.to_owned();
 		let cors_domain = cors_domain.to_owned();
+		let handler = self.session_handler(access.apis.clone());
 		let panic_handler = PanicHandler::new_in_arc();
 		let ph = panic_handler.clone();
-		let server = jsonrpc_http_server::Server::new(self.handler.clone());
 		thread::Builder::new().name("jsonrpc_http".to_string()).spawn(move || {
 			ph.catch_panic(move || {
-				server.start(addr.as_ref(), jsonrpc_http_server::AccessControlAllowOrigin::Value(cors_domain), threads);
+				http::serve(&addr, threads, handler, access, cors_domain).expect("Error while starting jsonrpc http server");
 			}).unwrap()
 		}).expect("Error while creating jsonrpc http thread");
 		panic_handler
 	}
+
+	/// Start IPC server listening on the unix socket at `path` and returns panic handler.
+	/// Fails if the socket cannot be created.
+	///
+	/// IPC is a local transport, so unsafe namespaces listed in `apis` are always allowed.
+	#[cfg(unix)]
+	pub fn start_ipc(&self, path: &str, apis: ApiSet) -> Result<Arc<PanicHandler>, io::Error> {
+		let listener = try!(ipc::bind(path));
+		let handler = self.session_handler(apis.allow_unsafe());
+		let panic_handler = PanicHandler::new_in_arc();
+		let ph = panic_handler.clone();
+		thread::Builder::new().name("jsonrpc_ipc".to_string()).spawn(move || {
+			ph.catch_panic(move || {
+				ipc::serve(listener, handler);
+			}).unwrap()
+		}).expect("Error while creating jsonrpc ipc thread");
+		Ok(panic_handler)
+	}
+
+	/// IPC transport is only available on unix platforms.
+	#[cfg(not(unix))]
+	pub fn start_ipc(&self, _path: &str, _apis: ApiSet) -> Result<Arc<PanicHandler>, io::Error> {
+		Err(io::Error::new(io::ErrorKind::Other, "IPC transport is not supported on this platform"))
+	}
+
+	/// Start WebSocket server asynchronously in new thread and returns panic handler.
+	pub fn start_ws(&self, addr: &str, access: AccessControl) -> Arc<PanicHandler> {
+		let addr = addr.to_owned();
+		let handler = self.session_handler(access.apis.clone());
+		let panic_handler = PanicHandler::new_in_arc();
+		let ph = panic_handler.clone();
+		thread::Builder::new().name("jsonrpc_ws".to_string()).spawn(move || {
+			ph.catch_panic(move || {
+				websockets::serve(&addr, handler, access).expect("Error while starting jsonrpc websocket server");
+			}).unwrap()
+		}).expect("Error while creating jsonrpc websocket thread");
+		panic_handler
+	}
 }
@@ -0,0 +1,110 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Per-method statistics of served calls.
+
+use std::collections::{HashMap, BTreeMap};
+use std::sync::RwLock;
+
+/// Upper bounds (in milliseconds) of latency histogram buckets. Slower calls fall into the last, unbounded bucket.
+pub const LATENCY_BUCKETS_MS: [u64; 7] = [1, 5, 10, 50, 100, 500, 1000];
+
+/// Statistics of a single method.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MethodStats {
+	/// Number of calls.
+	pub calls: u64,
+	/// Number of calls which returned an error.
+	pub errors: u64,
+	/// Total time spent serving the method in nanoseconds.
+	pub total_time_ns: u64,
+	/// Longest call in nanoseconds.
+	pub max_time_ns: u64,
+	/// Number of calls in each of `LATENCY_BUCKETS_MS` and the overflow bucket.
+	pub histogram: [u64; 8],
+}
+
+impl MethodStats {
+	fn record(&mut self, elapsed_ns: u64, failed: bool) {
+		self.calls += 1;
+		if failed {
+			self.errors += 1;
+		}
+		self.total_time_ns += elapsed_ns;
+		if elapsed_ns > self.max_time_ns {
+			self.max_time_ns = elapsed_ns;
+		}
+		let elapsed_ms = elapsed_ns / 1_000_000;
+		let bucket = LATENCY_BUCKETS_MS.iter().position(|&bound| elapsed_ms < bound).unwrap_or(LATENCY_BUCKETS_MS.len());
+		self.histogram[bucket] += 1;
+	}
+}
+
+/// Statistics of all methods served by the server.
+#[derive(Default)]
+pub struct RpcStats {
+	methods: RwLock<HashMap<String, MethodStats>>,
+}
+
+impl RpcStats {
+	/// Creates empty statistics.
+	pub fn new() -> Self {
+		RpcStats::default()
+	}
+
+	/// Records single call of `method`.
+	pub fn record(&self, method: &str, elapsed_ns: u64, failed: bool) {
+		let mut methods = self.methods.write().unwrap();
+		if !methods.contains_key(method) {
+			methods.insert(method.to_owned(), MethodStats::default());
+		}
+		methods.get_mut(method).expect("inserted above; qed").record(elapsed_ns, failed);
+	}
+
+	/// Returns statistics of every called method, ordered by name.
+	pub fn methods(&self) -> BTreeMap<String, MethodStats> {
+		self.methods.read().unwrap().iter().map(|(name, stats)| (name.clone(), stats.clone())).collect()
+	}
+
+	/// Returns total number of calls and errors.
+	pub fn totals(&self) -> (u64, u64) {
+		self.methods.read().unwrap().values().fold((0, 0), |(calls, errors), stats| (calls + stats.calls, errors + stats.errors))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::RpcStats;
+
+	#[test]
+	fn should_record_calls() {
+		let stats = RpcStats::new();
+		stats.record("eth_call", 500_000, false);
+		stats.record("eth_call", 7_000_000, true);
+		stats.record("eth_call", 2_000_000_000, false);
+		stats.record("net_version", 100, false);
+
+		let methods = stats.methods();
+		let call = &methods["eth_call"];
+		assert_eq!(call.calls, 3);
+		assert_eq!(call.errors, 1);
+		assert_eq!(call.max_time_ns, 2_000_000_000);
+		assert_eq!(call.total_time_ns, 2_007_500_000);
+		assert_eq!(call.histogram, [1, 0, 1, 0, 0, 0, 0, 1]);
+		assert_eq!(methods["net_version"].calls, 1);
+		assert_eq!(stats.totals(), (4, 1));
+	}
+}
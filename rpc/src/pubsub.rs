@@ -0,0 +1,364 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Request routing shared by the transports: API filtering, sessions and publish-subscribe.
+
+use std::cell::Cell;
+use std::collections::{BTreeMap, HashSet};
+use std::net::IpAddr;
+use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicUsize, Ordering, ATOMIC_USIZE_INIT};
+use serde_json;
+use serde_json::value;
+use jsonrpc_core::{IoHandler, Params, Value, Error, ErrorCode};
+use time;
+use access::ApiSet;
+use metrics::RpcStats;
+
+static NEXT_SESSION_ID: AtomicUsize = ATOMIC_USIZE_INIT;
+
+/// JSON-RPC error code of calls to unknown methods.
+const METHOD_NOT_FOUND: i64 = -32601;
+/// JSON-RPC error codes of calls which never reached a method.
+const NOT_DISPATCHED: [i64; 3] = [-32700, -32600, METHOD_NOT_FOUND];
+/// Name under which calls to unknown methods are recorded.
+const UNKNOWN_METHOD: &'static str = "unknown";
+
+/// Outgoing half of a persistent connection.
+pub trait Sink: Send + Sync {
+	/// Sends a message to the peer. Returns `false` if the connection is gone.
+	fn send(&self, message: String) -> bool;
+}
+
+/// Session identifier, unique within the process.
+pub type SessionId = usize;
+
+/// Client whose request is being handled. Methods can't tell who called them, so transports
+/// set it for the thread handling the request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Caller {
+	/// Peer of a persistent connection.
+	Session(SessionId),
+	/// Remote address of a request without a session.
+	Address(IpAddr),
+	/// Not known, e.g. a call made on `IoHandler` directly.
+	Unknown,
+}
+
+thread_local!(static CALLER: Cell<Caller> = Cell::new(Caller::Unknown));
+
+impl Caller {
+	/// Returns client whose request is being handled on this thread.
+	pub fn current() -> Caller {
+		CALLER.with(Cell::get)
+	}
+
+	/// Runs `f` as a request of this client.
+	pub fn with<F, R>(self, f: F) -> R where F: FnOnce() -> R {
+		let previous = CALLER.with(Cell::get);
+		CALLER.with(|caller| caller.set(self));
+		let result = f();
+		CALLER.with(|caller| caller.set(previous));
+		result
+	}
+}
+
+/// Persistent connection which may receive notifications.
+pub struct Session {
+	id: SessionId,
+	sink: Box<Sink>,
+}
+
+impl Session {
+	/// Creates new session writing to `sink`.
+	pub fn new(sink: Box<Sink>) -> Self {
+		Session {
+			id: NEXT_SESSION_ID.fetch_add(1, Ordering::Relaxed),
+			sink: sink,
+		}
+	}
+
+	/// Returns session identifier.
+	pub fn id(&self) -> SessionId {
+		self.id
+	}
+
+	/// Sends raw message to the peer. Returns `false` if the connection is gone.
+	pub fn send(&self, message: String) -> bool {
+		self.sink.send(message)
+	}
+
+	/// Sends JSON-RPC notification. Returns `false` if the connection is gone.
+	pub fn notify(&self, method: &str, params: Value) -> bool {
+		let mut notification = BTreeMap::new();
+		notification.insert("jsonrpc".to_owned(), Value::String("2.0".to_owned()));
+		notification.insert("method".to_owned(), Value::String(method.to_owned()));
+		notification.insert("params".to_owned(), params);
+		self.send(serde_json::to_string(&Value::Object(notification)).expect("Value serialization is infallible"))
+	}
+}
+
+/// Handles subscription requests coming from persistent transports.
+pub trait PubSubHandler: Send + Sync {
+	/// Creates a subscription bound to `session` and returns its id.
+	fn subscribe(&self, params: Params, session: &Arc<Session>) -> Result<Value, Error>;
+
+	/// Cancels a subscription previously created by `session`.
+	fn unsubscribe(&self, params: Params, session: &Arc<Session>) -> Result<Value, Error>;
+
+	/// Drops all subscriptions of a closed session.
+	fn session_closed(&self, session: SessionId);
+}
+
+/// Request handler of a transport.
+///
+/// Calls of a batch are handled one by one, so a failing call doesn't affect the others.
+/// Calls to namespaces outside of the transport's `ApiSet` are refused. Subscription calls
+/// are routed to the pub-sub handler together with the calling session, everything else
+/// goes to the shared `IoHandler`. Every call is recorded in `RpcStats`, under its own name
+/// only once a response has shown that the method exists; notifications get no response, so
+/// those of methods not seen yet are recorded as unknown.
+pub struct SessionHandler {
+	io: Arc<IoHandler>,
+	pubsub: Option<Arc<PubSubHandler>>,
+	apis: ApiSet,
+	stats: Arc<RpcStats>,
+	known_methods: RwLock<HashSet<String>>,
+}
+
+impl SessionHandler {
+	/// Creates new handler.
+	pub fn new(io: Arc<IoHandler>, pubsub: Option<Arc<PubSubHandler>>, apis: ApiSet, stats: Arc<RpcStats>) -> Self {
+		SessionHandler {
+			io: io,
+			pubsub: pubsub,
+			apis: apis,
+			stats: stats,
+			known_methods: RwLock::new(HashSet::new()),
+		}
+	}
+
+	/// Handles request sent by `session`. Requests of transports without sessions can't subscribe.
+	pub fn handle_request(&self, request: &str, session: Option<&Arc<Session>>) -> Option<String> {
+		match session {
+			Some(session) => Caller::Session(session.id()).with(|| self.dispatch(request, Some(session))),
+			None => self.dispatch(request, None),
+		}
+	}
+
+	fn dispatch(&self, request: &str, session: Option<&Arc<Session>>) -> Option<String> {
+		let response = match serde_json::from_str::<Value>(request) {
+			Ok(Value::Object(ref call)) => self.handle_call(call, request, session),
+			Ok(Value::Array(ref calls)) if !calls.is_empty() => {
+				let responses = calls.iter().filter_map(|call| match *call {
+					Value::Object(ref object) => {
+						let request = serde_json::to_string(call).expect("Value serialization is infallible");
+						self.handle_call(object, &request, session)
+					},
+					_ => Some(response(Value::Null, Err(invalid_request()))),
+				}).collect::<Vec<_>>();
+				match responses.is_empty() {
+					// batch of notifications only
+					true => None,
+					false => Some(Value::Array(responses)),
+				}
+			},
+			// parse errors and empty batches are reported by `IoHandler`.
+			_ => return self.io.handle_request(request),
+		};
+		response.map(|response| serde_json::to_string(&response).expect("Value serialization is infallible"))
+	}
+
+	/// Notifies the handler that `session` has been closed.
+	pub fn session_closed(&self, session: &Session) {
+		if let Some(ref pubsub) = self.pubsub {
+			pubsub.session_closed(session.id());
+		}
+	}
+
+	/// Handles single call. `request` is its serialized form.
+	fn handle_call(&self, call: &BTreeMap<String, Value>, request: &str, session: Option<&Arc<Session>>) -> Option<Value> {
+		let start = time::precise_time_ns();
+		let response = match self.intercept(call, session) {
+			Some(response) => response,
+			None => self.io.handle_request(request).and_then(|response| serde_json::from_str(&response).ok()),
+		};
+		let elapsed = time::precise_time_ns() - start;
+		trace!(target: "rpc", "Request: {} Response: {:?}", request, response);
+
+		if let Some(method) = call.get("method").and_then(Value::as_string) {
+			let error_code = response.as_ref()
+				.and_then(|response| response.find("error"))
+				.map(|error| error.find("code").and_then(Value::as_i64));
+			// don't keep a record for every bogus method name.
+			let name = match error_code {
+				Some(Some(code)) if NOT_DISPATCHED.contains(&code) => UNKNOWN_METHOD,
+				Some(_) => {
+					if !self.known_methods.read().unwrap().contains(method) {
+						self.known_methods.write().unwrap().insert(method.to_owned());
+					}
+					method
+				},
+				None if self.known_methods.read().unwrap().contains(method) => method,
+				None => UNKNOWN_METHOD,
+			};
+			self.stats.record(name, elapsed, error_code.is_some());
+		}
+		response
+	}
+
+	/// Handles a call which doesn't go to `IoHandler`. Returns `None` if it isn't one of those,
+	/// otherwise its response, which notifications don't get.
+	fn intercept(&self, call: &BTreeMap<String, Value>, session: Option<&Arc<Session>>) -> Option<Option<Value>> {
+		let method = match call.get("method").and_then(Value::as_string) {
+			Some(method) => method,
+			None => return None,
+		};
+		let result = if !self.apis.is_allowed(method) {
+			Err(method_not_found())
+		} else {
+			let (pubsub, session) = match (self.pubsub.as_ref(), session) {
+				(Some(pubsub), Some(session)) => (pubsub, session),
+				_ => return None,
+			};
+			match method {
+				"eth_subscribe" => pubsub.subscribe(params(call), session),
+				"eth_unsubscribe" => pubsub.unsubscribe(params(call), session),
+				_ => return None,
+			}
+		};
+		Some(call.get("id").cloned().map(|id| response(id, result)))
+	}
+}
+
+fn invalid_request() -> Error {
+	Error {
+		code: ErrorCode::InvalidRequest,
+		message: "Invalid request".to_owned(),
+		data: None,
+	}
+}
+
+fn method_not_found() -> Error {
+	Error {
+		code: ErrorCode::MethodNotFound,
+		message: "Method not found".to_owned(),
+		data: None,
+	}
+}
+
+fn params(call: &BTreeMap<String, Value>) -> Params {
+	match call.get("params") {
+		Some(&Value::Array(ref params)) => Params::Array(params.clone()),
+		Some(&Value::Object(ref params)) => Params::Map(params.clone()),
+		_ => Params::None,
+	}
+}
+
+fn response(id: Value, result: Result<Value, Error>) -> Value {
+	let mut response = BTreeMap::new();
+	response.insert("jsonrpc".to_owned(), Value::String("2.0".to_owned()));
+	match result {
+		Ok(result) => response.insert("result".to_owned(), result),
+		Err(error) => response.insert("error".to_owned(), value::to_value(&error)),
+	};
+	response.insert("id".to_owned(), id);
+	Value::Object(response)
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+	use serde_json;
+	use jsonrpc_core::{IoHandler, IoDelegate, Params, Value, Error};
+	use access::ApiSet;
+	use metrics::RpcStats;
+	use super::SessionHandler;
+
+	struct Test;
+
+	impl Test {
+		fn ok(&self, _: Params) -> Result<Value, Error> {
+			Ok(Value::Bool(true))
+		}
+
+		fn fail(&self, _: Params) -> Result<Value, Error> {
+			Err(Error::internal_error())
+		}
+	}
+
+	fn error_code(response: &Value) -> Option<i64> {
+		response.find("error").and_then(|error| error.find("code")).and_then(Value::as_i64)
+	}
+
+	#[test]
+	fn should_isolate_batch_calls() {
+		let io = IoHandler::new();
+		let mut delegate = IoDelegate::new(Arc::new(Test));
+		delegate.add_method("test_ok", Test::ok);
+		delegate.add_method("test_fail", Test::fail);
+		io.add_delegate(delegate);
+		let stats = Arc::new(RpcStats::new());
+		let handler = SessionHandler::new(Arc::new(io), None, ApiSet::list(&["test"]), stats.clone());
+
+		let request = r#"[
+			{"jsonrpc":"2.0","method":"test_ok","params":[],"id":1},
+			{"jsonrpc":"2.0","method":"test_fail","params":[],"id":2},
+			1,
+			{"jsonrpc":"2.0","method":"net_version","params":[],"id":3}
+		]"#;
+		let response: Value = serde_json::from_str(&handler.handle_request(request, None).unwrap()).unwrap();
+		let responses = response.as_array().unwrap();
+
+		assert_eq!(responses.len(), 4);
+		assert_eq!(responses[0].find("result"), Some(&Value::Bool(true)));
+		assert_eq!(error_code(&responses[1]), Some(-32603));
+		assert_eq!(error_code(&responses[2]), Some(-32600));
+		assert_eq!(error_code(&responses[3]), Some(-32601));
+
+		let methods = stats.methods();
+		assert_eq!((methods["test_ok"].calls, methods["test_ok"].errors), (1, 0));
+		assert_eq!((methods["test_fail"].calls, methods["test_fail"].errors), (1, 1));
+		assert_eq!((methods["unknown"].calls, methods["unknown"].errors), (1, 1));
+		assert_eq!(stats.totals(), (3, 2));
+	}
+
+	#[test]
+	fn should_not_record_notifications_of_unknown_methods_by_name() {
+		let io = IoHandler::new();
+		let mut delegate = IoDelegate::new(Arc::new(Test));
+		delegate.add_method("test_ok", Test::ok);
+		io.add_delegate(delegate);
+		let stats = Arc::new(RpcStats::new());
+		let handler = SessionHandler::new(Arc::new(io), None, ApiSet::list(&["test"]), stats.clone());
+
+		for i in 0..100 {
+			let request = format!(r#"{{"jsonrpc":"2.0","method":"test_random{}","params":[]}}"#, i);
+			assert_eq!(handler.handle_request(&request, None), None);
+		}
+		// not allowed by the transport; still no response to a notification.
+		assert_eq!(handler.handle_request(r#"{"jsonrpc":"2.0","method":"personal_listAccounts","params":[]}"#, None), None);
+		assert_eq!(handler.handle_request(r#"{"jsonrpc":"2.0","method":"test_ok","params":[]}"#, None), None);
+		assert_eq!(stats.methods().keys().collect::<Vec<_>>(), vec!["unknown"]);
+		assert_eq!(stats.methods()["unknown"].calls, 102);
+
+		// once a call showed the method exists its notifications are recorded by name.
+		handler.handle_request(r#"{"jsonrpc":"2.0","method":"test_ok","params":[],"id":1}"#, None).unwrap();
+		assert_eq!(handler.handle_request(r#"{"jsonrpc":"2.0","method":"test_ok","params":[]}"#, None), None);
+		assert_eq!(stats.methods()["test_ok"].calls, 2);
+		assert_eq!(stats.methods().len(), 2);
+	}
+}
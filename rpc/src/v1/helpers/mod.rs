@@ -16,6 +16,10 @@
 
 mod poll_manager;
 mod poll_filter;
+mod subscribers;
+mod subscription;
 
 pub use self::poll_manager::PollManager;
 pub use self::poll_filter::PollFilter;
+pub use self::subscribers::{Subscribers, SubscriptionId};
+pub use self::subscription::Subscription;
@@ -1,10 +1,12 @@
 //! Helper type with all filter possibilities.
 
+use util::numbers::H256;
 use ethcore::filter::Filter;
 
 #[derive(Clone)]
 pub enum PollFilter {
-	Block,
+	/// Hash of the best block at the time of the last poll.
+	Block(H256),
 	PendingTransaction,
 	Logs(Filter)
 }
@@ -17,62 +17,68 @@
 //! Indexes all rpc poll requests.
 
 use transient_hashmap::{TransientHashMap, Timer, StandardTimer};
-
-/// Lifetime of poll (in seconds).
-const POLL_LIFETIME: u64 = 60;
+use pubsub::Caller;
 
 pub type PollId = usize;
 pub type BlockNumber = u64;
 
 pub struct PollInfo<F> {
 	pub filter: F,
-	pub block_number: BlockNumber
+	pub block_number: BlockNumber,
+	pub owner: Caller,
 }
 
 impl<F> Clone for PollInfo<F> where F: Clone {
 	fn clone(&self) -> Self {
 		PollInfo {
 			filter: self.filter.clone(),
-			block_number: self.block_number.clone()
+			block_number: self.block_number.clone(),
+			owner: self.owner,
 		}
 	}
 }
 
 /// Indexes all poll requests.
 ///
-/// Lazily garbage collects unused polls info.
+/// Lazily garbage collects polls which were not used for `lifetime` seconds.
 pub struct PollManager<F, T = StandardTimer> where T: Timer {
 	polls: TransientHashMap<PollId, PollInfo<F>, T>,
+	max_polls: usize,
 	next_available_id: PollId
 }
 
 impl<F> PollManager<F, StandardTimer> {
 	/// Creates new instance of indexer.
-	pub fn new() -> Self {
-		PollManager::new_with_timer(Default::default())
+	pub fn new(lifetime: u64, max_polls: usize) -> Self {
+		PollManager::new_with_timer(lifetime, max_polls, Default::default())
 	}
 }
 
 impl<F, T> PollManager<F, T> where T: Timer {
-	pub fn new_with_timer(timer: T) -> Self {
+	pub fn new_with_timer(lifetime: u64, max_polls: usize, timer: T) -> Self {
 		PollManager {
-			polls: TransientHashMap::new_with_timer(POLL_LIFETIME, timer),
+			polls: TransientHashMap::new_with_timer(lifetime, timer),
+			max_polls: max_polls,
 			next_available_id: 0,
 		}
 	}
 
-	/// Returns id which can be used for new poll.
+	/// Returns id which can be used for new poll of `owner` or `None` if `owner` has already `max_polls` active polls.
 	///
 	/// Stores information when last poll happend.
-	pub fn create_poll(&mut self, filter: F, block: BlockNumber) -> PollId {
+	pub fn create_poll(&mut self, owner: Caller, filter: F, block: BlockNumber) -> Option<PollId> {
 		self.polls.prune();
+		if self.polls.direct().values().filter(|info| info.owner == owner).count() >= self.max_polls {
+			return None;
+		}
 		let id = self.next_available_id;
 		self.next_available_id += 1;
 		self.polls.insert(id, PollInfo {
 			filter: filter,
 			block_number: block,
+			owner: owner,
 		});
-		id
+		Some(id)
 	}
 
 	/// Updates information when last poll happend.
@@ -83,6 +89,14 @@ impl<F, T> PollManager<F, T> where T: Timer {
 		}
 	}
 
+	/// Replaces filter of the poll.
+	pub fn update_filter(&mut self, id: &PollId, filter: F) {
+		self.polls.prune();
+		if let Some(info) = self.polls.get_mut(id) {
+			info.filter = filter;
+		}
+	}
+
 	/// Returns number of block when last poll happend.
 	pub fn poll_info(&mut self, id: &PollId) -> Option<&PollInfo<F>> {
 		self.polls.prune();
@@ -98,7 +112,9 @@ impl<F, T> PollManager<F, T> where T: Timer {
 #[cfg(test)]
 mod tests {
 	use std::cell::RefCell;
+	use std::net::{IpAddr, Ipv4Addr};
 	use transient_hashmap::Timer;
+	use pubsub::Caller;
 	use v1::helpers::PollManager;
 
 	struct TestTimer<'a> {
@@ -118,9 +134,9 @@ mod tests {
 			time: &time,
 		};
 
-		let mut indexer = PollManager::new_with_timer(timer);
-		assert_eq!(indexer.create_poll(false, 20), 0);
-		assert_eq!(indexer.create_poll(true, 20), 1);
+		let mut indexer = PollManager::new_with_timer(60, 10, timer);
+		assert_eq!(indexer.create_poll(Caller::Unknown, false, 20), Some(0));
+		assert_eq!(indexer.create_poll(Caller::Unknown, true, 20), Some(1));
 
 		*time.borrow_mut() = 10;
 		indexer.update_poll(&0, 21);
@@ -141,4 +157,53 @@ mod tests {
 		indexer.remove_poll(&1);
 		assert!(indexer.poll_info(&1).is_none());
 	}
+
+	#[test]
+	fn test_poll_limit() {
+		let time = RefCell::new(0);
+		let timer = TestTimer {
+			time: &time,
+		};
+
+		let mut indexer = PollManager::new_with_timer(30, 2, timer);
+		assert_eq!(indexer.create_poll(Caller::Unknown, false, 20), Some(0));
+		assert_eq!(indexer.create_poll(Caller::Unknown, false, 20), Some(1));
+		assert_eq!(indexer.create_poll(Caller::Unknown, false, 20), None);
+
+		indexer.remove_poll(&0);
+		assert_eq!(indexer.create_poll(Caller::Unknown, false, 20), Some(2));
+
+		*time.borrow_mut() = 10;
+		indexer.update_poll(&2, 21);
+
+		// poll 1 expires, poll 2 was used recently
+		*time.borrow_mut() = 35;
+		assert_eq!(indexer.create_poll(Caller::Unknown, true, 21), Some(3));
+		assert_eq!(indexer.create_poll(Caller::Unknown, true, 21), None);
+		assert_eq!(indexer.poll_info(&2).unwrap().block_number, 21);
+	}
+
+	#[test]
+	fn test_poll_limit_per_owner() {
+		let time = RefCell::new(0);
+		let timer = TestTimer {
+			time: &time,
+		};
+		let session = Caller::Session(1);
+		let remote = Caller::Address(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)));
+
+		let mut indexer = PollManager::new_with_timer(30, 2, timer);
+		assert_eq!(indexer.create_poll(session, false, 20), Some(0));
+		assert_eq!(indexer.create_poll(session, false, 20), Some(1));
+		assert_eq!(indexer.create_poll(session, false, 20), None);
+
+		// the other client isn't affected by the first one reaching its limit.
+		assert_eq!(indexer.create_poll(remote, false, 20), Some(2));
+		assert_eq!(indexer.create_poll(remote, false, 20), Some(3));
+		assert_eq!(indexer.create_poll(remote, false, 20), None);
+
+		indexer.remove_poll(&0);
+		assert_eq!(indexer.create_poll(remote, false, 20), None);
+		assert_eq!(indexer.create_poll(session, false, 20), Some(4));
+	}
 }
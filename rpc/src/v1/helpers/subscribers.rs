@@ -0,0 +1,108 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Indexes all rpc subscriptions.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use pubsub::{Session, SessionId};
+
+pub type SubscriptionId = usize;
+
+/// Indexes subscriptions of persistent sessions.
+///
+/// Subscriptions live until they are cancelled or their session is closed.
+pub struct Subscribers<F> {
+	subscriptions: HashMap<SubscriptionId, (Arc<Session>, F)>,
+	next_available_id: SubscriptionId,
+}
+
+impl<F> Subscribers<F> where F: Clone {
+	/// Creates new instance of indexer.
+	pub fn new() -> Self {
+		Subscribers {
+			subscriptions: HashMap::new(),
+			next_available_id: 0,
+		}
+	}
+
+	/// Adds subscription of `session` and returns its id.
+	pub fn add(&mut self, session: Arc<Session>, filter: F) -> SubscriptionId {
+		let id = self.next_available_id;
+		self.next_available_id += 1;
+		self.subscriptions.insert(id, (session, filter));
+		id
+	}
+
+	/// Removes subscription if it belongs to given session. Returns true if anything was removed.
+	pub fn remove(&mut self, id: &SubscriptionId, session: SessionId) -> bool {
+		let owned = self.subscriptions.get(id).map_or(false, |&(ref s, _)| s.id() == session);
+		if owned {
+			self.subscriptions.remove(id);
+		}
+		owned
+	}
+
+	/// Removes all subscriptions of given session.
+	pub fn remove_session(&mut self, session: SessionId) {
+		let ids = self.subscriptions.iter()
+			.filter(|&(_, &(ref s, _))| s.id() == session)
+			.map(|(id, _)| *id)
+			.collect::<Vec<_>>();
+		for id in ids {
+			self.subscriptions.remove(&id);
+		}
+	}
+
+	/// Returns a snapshot of all subscriptions.
+	pub fn subscriptions(&self) -> Vec<(SubscriptionId, Arc<Session>, F)> {
+		self.subscriptions.iter()
+			.map(|(id, &(ref session, ref filter))| (*id, session.clone(), filter.clone()))
+			.collect()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+	use pubsub::{Session, Sink};
+	use v1::helpers::Subscribers;
+
+	struct NullSink;
+
+	impl Sink for NullSink {
+		fn send(&self, _message: String) -> bool { true }
+	}
+
+	#[test]
+	fn test_subscribers() {
+		let a = Arc::new(Session::new(Box::new(NullSink)));
+		let b = Arc::new(Session::new(Box::new(NullSink)));
+
+		let mut subscribers = Subscribers::new();
+		assert_eq!(subscribers.add(a.clone(), 1), 0);
+		assert_eq!(subscribers.add(b.clone(), 2), 1);
+		assert_eq!(subscribers.add(a.clone(), 3), 2);
+
+		// only the owner can cancel a subscription
+		assert!(!subscribers.remove(&1, a.id()));
+		assert!(subscribers.remove(&1, b.id()));
+		assert_eq!(subscribers.subscriptions().len(), 2);
+
+		subscribers.remove_session(a.id());
+		assert!(subscribers.subscriptions().is_empty());
+	}
+}
@@ -28,8 +28,9 @@ use ethcore::views::*;
 use ethcore::ethereum::Ethash;
 use ethcore::ethereum::denominations::shannon;
 use v1::traits::{Eth, EthFilter};
-use v1::types::{Block, BlockTransactions, BlockNumber, Bytes, SyncStatus, SyncInfo, Transaction, OptionalValue, Index, Filter, Log};
+use v1::types::{Block, BlockTransactions, BlockNumber, Bytes, SyncStatus, SyncInfo, Transaction, OptionalValue, Index, Filter, Log, AccountProof, WorkPolicy};
 use v1::helpers::{PollFilter, PollManager};
+use pubsub::Caller;
 
 /// Eth rpc implementation.
 pub struct EthClient<C, S> where C: BlockChainClient, S: SyncProvider {
@@ -180,6 +181,14 @@ impl<C, S> Eth for EthClient<C, S> where C: BlockChainClient + 'static, S: SyncP
 			.and_then(|(address, _block_number)| to_value(&take_weak!(self.client).code(&address).map_or_else(Bytes::default, Bytes::new)))
 	}
 
+	fn proof(&self, params: Params) -> Result<Value, Error> {
+		from_params::<(Address, Vec<H256>, BlockNumber)>(params)
+			.and_then(|(address, keys, block_number)| match take_weak!(self.client).prove_account(&address, &keys, block_number.into()) {
+				Some(proof) => to_value(&AccountProof::new(address, proof)),
+				None => Ok(Value::Null),
+			})
+	}
+
 	fn block_by_hash(&self, params: Params) -> Result<Value, Error> {
 		from_params::<(H256, bool)>(params)
 			.and_then(|(hash, include_txs)| self.block(BlockId::Hash(hash), include_txs))
@@ -226,7 +235,8 @@ impl<C, S> Eth for EthClient<C, S> where C: BlockChainClient + 'static, S: SyncP
 						let pow_hash = b.hash();
 						let target = Ethash::difficulty_to_boundary(b.block().header().difficulty());
 						let seed_hash = Ethash::get_seedhash(b.block().header().number());
-						to_value(&(pow_hash, seed_hash, target))
+						let policy = WorkPolicy::new(b.block().header().number(), *b.block().header().gas_limit(), &c.sealing_config());
+						to_value(&(pow_hash, seed_hash, target, policy))
 					}
 					_ => Err(Error::invalid_params())
 				}
@@ -262,10 +272,23 @@ pub struct EthFilterClient<C> where C: BlockChainClient {
 
 impl<C> EthFilterClient<C> where C: BlockChainClient {
 	/// Creates new Eth filter client.
-	pub fn new(client: &Arc<C>) -> Self {
+	///
+	/// Filters not polled for `filter_lifetime` seconds are removed. Each client may have at most `max_filters` filters active at once.
+	pub fn new(client: &Arc<C>, filter_lifetime: u64, max_filters: usize) -> Self {
 		EthFilterClient {
 			client: Arc::downgrade(client),
-			polls: Mutex::new(PollManager::new())
+			polls: Mutex::new(PollManager::new(filter_lifetime, max_filters))
+		}
+	}
+
+	fn create_poll(&self, filter: PollFilter, block: u64) -> Result<Value, Error> {
+		match self.polls.lock().unwrap().create_poll(Caller::current(), filter, block) {
+			Some(id) => to_value(&U256::from(id)),
+			None => Err(Error {
+				code: ErrorCode::ServerError(-32000),
+				message: "Filter limit reached. Uninstall unused filters.".to_owned(),
+				data: None,
+			}),
 		}
 	}
 }
@@ -274,18 +297,15 @@ impl<C> EthFilter for EthFilterClient<C> where C: BlockChainClient + 'static {
 	fn new_filter(&self, params: Params) -> Result<Value, Error> {
 		from_params::<(Filter,)>(params)
 			.and_then(|(filter,)| {
-				let mut polls = self.polls.lock().unwrap();
-				let id = polls.create_poll(PollFilter::Logs(filter.into()), take_weak!(self.client).chain_info().best_block_number);
-				to_value(&U256::from(id))
+				self.create_poll(PollFilter::Logs(filter.into()), take_weak!(self.client).chain_info().best_block_number)
 			})
 	}
 
 	fn new_block_filter(&self, params: Params) -> Result<Value, Error> {
 		match params {
 			Params::None => {
-				let mut polls = self.polls.lock().unwrap();
-				let id = polls.create_poll(PollFilter::Block, take_weak!(self.client).chain_info().best_block_number);
-				to_value(&U256::from(id))
+				let info = take_weak!(self.client).chain_info();
+				self.create_poll(PollFilter::Block(info.best_block_hash), info.best_block_number)
 			},
 			_ => Err(Error::invalid_params())
 		}
@@ -294,9 +314,7 @@ impl<C> EthFilter for EthFilterClient<C> where C: BlockChainClient + 'static {
 	fn new_pending_transaction_filter(&self, params: Params) -> Result<Value, Error> {
 		match params {
 			Params::None => {
-				let mut polls = self.polls.lock().unwrap();
-				let id = polls.create_poll(PollFilter::PendingTransaction, take_weak!(self.client).chain_info().best_block_number);
-				to_value(&U256::from(id))
+				self.create_poll(PollFilter::PendingTransaction, take_weak!(self.client).chain_info().best_block_number)
 			},
 			_ => Err(Error::invalid_params())
 		}
@@ -310,15 +328,17 @@ impl<C> EthFilter for EthFilterClient<C> where C: BlockChainClient + 'static {
 				match info {
 					None => Ok(Value::Array(vec![] as Vec<Value>)),
 					Some(info) => match info.filter {
-						PollFilter::Block => {
-							// + 1, cause we want to return hashes including current block hash.
-							let current_number = client.chain_info().best_block_number + 1;
-							let hashes = (info.block_number..current_number).into_iter()
-								.map(BlockId::Number)
-								.filter_map(|id| client.block_hash(id))
-								.collect::<Vec<H256>>();
-
-							self.polls.lock().unwrap().update_poll(&index.value(), current_number);
+						PollFilter::Block(last_hash) => {
+							let chain_info = client.chain_info();
+							// blocks which became canonical since the last poll; retracted ones are skipped.
+							let hashes = match client.tree_route(&last_hash, &chain_info.best_block_hash) {
+								Some(route) => route.blocks[route.index..].to_vec(),
+								None => vec![chain_info.best_block_hash.clone()],
+							};
+
+							let mut polls = self.polls.lock().unwrap();
+							polls.update_filter(&index.value(), PollFilter::Block(chain_info.best_block_hash));
+							polls.update_poll(&index.value(), chain_info.best_block_number);
 
 							to_value(&hashes)
 						},
@@ -344,6 +364,25 @@ impl<C> EthFilter for EthFilterClient<C> where C: BlockChainClient + 'static {
 			})
 	}
 
+	fn filter_logs(&self, params: Params) -> Result<Value, Error> {
+		let client = take_weak!(self.client);
+		from_params::<(Index,)>(params)
+			.and_then(|(index,)| {
+				let info = self.polls.lock().unwrap().poll_info(&index.value()).cloned();
+				match info.map(|info| info.filter) {
+					Some(PollFilter::Logs(filter)) => {
+						let logs = client.logs(filter)
+							.into_iter()
+							.map(From::from)
+							.collect::<Vec<Log>>();
+						to_value(&logs)
+					},
+					// unknown filter or not a log filter
+					_ => Err(Error::invalid_params()),
+				}
+			})
+	}
+
 	fn uninstall_filter(&self, params: Params) -> Result<Value, Error> {
 		from_params::<(Index,)>(params)
 			.and_then(|(index,)| {
@@ -0,0 +1,373 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Eth pub-sub implementation.
+use std::collections::BTreeMap;
+use std::sync::{Arc, Weak, Mutex, RwLock};
+use serde_json::value;
+use ethsync::{SyncProvider, SyncState};
+use jsonrpc_core::*;
+use util::numbers::*;
+use util::io::{IoHandler, IoContext, TimerToken};
+use util::network::UserMessage;
+use ethcore::client::*;
+use ethcore::service::{NetSyncMessage, SyncMessage};
+use ethcore::views::HeaderView;
+use pubsub::{PubSubHandler, Session, SessionId};
+use v1::types::{Block, BlockTransactions, Bytes, Filter, Log, OptionalValue, SyncStatus, SyncInfo};
+use v1::helpers::{Subscribers, Subscription, SubscriptionId};
+
+const SYNC_STATUS_TIMER: TimerToken = 0;
+const SYNC_STATUS_TIMER_MS: u64 = 1000;
+
+/// Eth pub-sub implementation.
+///
+/// Must be registered as an IO handler to receive chain events.
+pub struct EthPubSubClient<C, S> where C: BlockChainClient, S: SyncProvider {
+	client: Weak<C>,
+	sync: Weak<S>,
+	subscribers: RwLock<Subscribers<Subscription>>,
+	syncing: Mutex<bool>,
+}
+
+impl<C, S> EthPubSubClient<C, S> where C: BlockChainClient, S: SyncProvider {
+	/// Creates new EthPubSubClient.
+	pub fn new(client: &Arc<C>, sync: &Arc<S>) -> Self {
+		EthPubSubClient {
+			client: Arc::downgrade(client),
+			sync: Arc::downgrade(sync),
+			subscribers: RwLock::new(Subscribers::new()),
+			syncing: Mutex::new(false),
+		}
+	}
+
+	fn header(client: &C, hash: &H256) -> Option<Block> {
+		let id = BlockId::Hash(hash.clone());
+		match (client.block_header(id.clone()), client.block_total_difficulty(id)) {
+			(Some(bytes), Some(total_difficulty)) => {
+				let view = HeaderView::new(&bytes);
+				Some(Block {
+					hash: OptionalValue::Value(view.sha3()),
+					parent_hash: view.parent_hash(),
+					uncles_hash: view.uncles_hash(),
+					author: view.author(),
+					miner: view.author(),
+					state_root: view.state_root(),
+					transactions_root: view.transactions_root(),
+					receipts_root: view.receipts_root(),
+					number: OptionalValue::Value(U256::from(view.number())),
+					gas_used: view.gas_used(),
+					gas_limit: view.gas_limit(),
+					logs_bloom: view.log_bloom(),
+					timestamp: U256::from(view.timestamp()),
+					difficulty: view.difficulty(),
+					total_difficulty: total_difficulty,
+					uncles: vec![],
+					transactions: BlockTransactions::Hashes(vec![]),
+					extra_data: Bytes::new(view.extra_data())
+				})
+			},
+			_ => None
+		}
+	}
+
+	/// Notifies every subscription with the results `filter` yields for it. Subscriptions of closed sessions are dropped.
+	fn notify<F>(&self, filter: F) where F: Fn(&Subscription) -> Vec<Value> {
+		let mut closed = vec![];
+		// don't hold the lock while sending.
+		let subscriptions = self.subscribers.read().unwrap().subscriptions();
+		for (id, session, subscription) in subscriptions {
+			for result in filter(&subscription) {
+				let mut params = BTreeMap::new();
+				params.insert("subscription".to_owned(), value::to_value(&U256::from(id)));
+				params.insert("result".to_owned(), result);
+				if !session.notify("eth_subscription", Value::Object(params)) {
+					closed.push((id, session.id()));
+					break;
+				}
+			}
+		}
+
+		let mut subscribers = self.subscribers.write().unwrap();
+		for (id, session) in closed {
+			subscribers.remove(&id, session);
+		}
+	}
+
+	fn new_blocks(&self, enacted: &[H256], retracted: &[H256]) {
+		let client = match self.client.upgrade() {
+			Some(client) => client,
+			None => return,
+		};
+		let client = &*client;
+
+		self.notify(|subscription| match *subscription {
+			Subscription::NewHeads => enacted.iter()
+				.filter_map(|hash| Self::header(client, hash))
+				.map(|header| value::to_value(&header))
+				.collect(),
+			Subscription::Logs(ref filter) => {
+				let removed = retracted.iter()
+					.flat_map(|hash| client.block_logs(hash, filter))
+					.map(|entry| {
+						let mut log = Log::from(entry);
+						log.removed = true;
+						log
+					});
+				let added = enacted.iter()
+					.flat_map(|hash| client.block_logs(hash, filter))
+					.map(Log::from);
+				removed.chain(added).map(|log| value::to_value(&log)).collect()
+			},
+			_ => vec![],
+		});
+	}
+
+	fn new_transactions(&self, hashes: &[H256]) {
+		self.notify(|subscription| match *subscription {
+			Subscription::NewPendingTransactions => hashes.iter().map(value::to_value).collect(),
+			_ => vec![],
+		});
+	}
+
+	fn check_sync_status(&self) {
+		let (sync, client) = match (self.sync.upgrade(), self.client.upgrade()) {
+			(Some(sync), Some(client)) => (sync, client),
+			_ => return,
+		};
+		let status = sync.status();
+		let is_syncing = match status.state {
+			SyncState::NotSynced | SyncState::Idle => false,
+			SyncState::Waiting | SyncState::Blocks | SyncState::NewBlocks => true,
+		};
+		{
+			let mut syncing = self.syncing.lock().unwrap();
+			if *syncing == is_syncing {
+				return;
+			}
+			*syncing = is_syncing;
+		}
+
+		let result = match is_syncing {
+			false => SyncStatus::None,
+			true => SyncStatus::Info(SyncInfo {
+				starting_block: U256::from(status.start_block_number),
+				current_block: U256::from(client.chain_info().best_block_number),
+				highest_block: U256::from(status.highest_block_number.unwrap_or(status.start_block_number))
+			}),
+		};
+		let result = value::to_value(&result);
+		self.notify(|subscription| match *subscription {
+			Subscription::Syncing => vec![result.clone()],
+			_ => vec![],
+		});
+	}
+}
+
+impl<C, S> PubSubHandler for EthPubSubClient<C, S> where C: BlockChainClient + 'static, S: SyncProvider + 'static {
+	fn subscribe(&self, params: Params, session: &Arc<Session>) -> Result<Value, Error> {
+		let params = match params {
+			Params::Array(params) => params,
+			_ => return Err(Error::invalid_params()),
+		};
+		let subscription = match (params.get(0).and_then(Value::as_string), params.get(1)) {
+			(Some("newHeads"), None) => Subscription::NewHeads,
+			(Some("logs"), None) => Subscription::Logs(Filter::default().into()),
+			(Some("logs"), Some(filter)) => {
+				let filter: Filter = try!(value::from_value(filter.clone()).map_err(|_| Error::invalid_params()));
+				Subscription::Logs(filter.into())
+			},
+			(Some("newPendingTransactions"), None) => Subscription::NewPendingTransactions,
+			(Some("syncing"), None) => Subscription::Syncing,
+			_ => return Err(Error::invalid_params()),
+		};
+		let id = self.subscribers.write().unwrap().add(session.clone(), subscription);
+		to_value(&U256::from(id))
+	}
+
+	fn unsubscribe(&self, params: Params, session: &Arc<Session>) -> Result<Value, Error> {
+		from_params::<(U256,)>(params)
+			.and_then(|(id,)| {
+				let id = id.low_u64() as SubscriptionId;
+				to_value(&self.subscribers.write().unwrap().remove(&id, session.id()))
+			})
+	}
+
+	fn session_closed(&self, session: SessionId) {
+		self.subscribers.write().unwrap().remove_session(session);
+	}
+}
+
+impl<C, S> IoHandler<NetSyncMessage> for EthPubSubClient<C, S> where C: BlockChainClient + 'static, S: SyncProvider + 'static {
+	fn initialize(&self, io: &IoContext<NetSyncMessage>) {
+		io.register_timer(SYNC_STATUS_TIMER, SYNC_STATUS_TIMER_MS).expect("Error registering pubsub sync status timer");
+	}
+
+	fn timeout(&self, _io: &IoContext<NetSyncMessage>, timer: TimerToken) {
+		if timer == SYNC_STATUS_TIMER {
+			self.check_sync_status();
+		}
+	}
+
+	fn message(&self, _io: &IoContext<NetSyncMessage>, net_message: &NetSyncMessage) {
+		if let UserMessage(ref message) = *net_message {
+			match *message {
+				SyncMessage::NewChainBlocks { ref enacted, ref retracted, .. } => self.new_blocks(enacted, retracted),
+				SyncMessage::NewTransactions(ref hashes) => self.new_transactions(hashes),
+				_ => {},
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::{Arc, Mutex};
+	use serde_json::{self, Value};
+	use serde_json::value;
+	use jsonrpc_core::Params;
+	use util::numbers::*;
+	use ethcore::header::Header;
+	use ethcore::log_entry::{LocalizedLogEntry, LogEntry};
+	use ethsync::SyncState;
+	use pubsub::{PubSubHandler, Session, Sink};
+	use v1::types::{Bytes, SyncStatus, SyncInfo};
+	use v1::tests::helpers::{TestBlockChainClient, TestSyncProvider};
+	use super::EthPubSubClient;
+
+	struct TestSink(Arc<Mutex<Vec<String>>>);
+
+	impl Sink for TestSink {
+		fn send(&self, message: String) -> bool {
+			self.0.lock().unwrap().push(message);
+			true
+		}
+	}
+
+	struct Tester {
+		client: Arc<TestBlockChainClient>,
+		sync: Arc<TestSyncProvider>,
+		pubsub: EthPubSubClient<TestBlockChainClient, TestSyncProvider>,
+		session: Arc<Session>,
+		sent: Arc<Mutex<Vec<String>>>,
+	}
+
+	impl Tester {
+		fn new() -> Self {
+			let client = Arc::new(TestBlockChainClient::default());
+			let sync = Arc::new(TestSyncProvider::new());
+			let sent = Arc::new(Mutex::new(vec![]));
+			Tester {
+				pubsub: EthPubSubClient::new(&client, &sync),
+				client: client,
+				sync: sync,
+				session: Arc::new(Session::new(Box::new(TestSink(sent.clone())))),
+				sent: sent,
+			}
+		}
+
+		fn subscribe(&self, kind: &str) {
+			self.pubsub.subscribe(Params::Array(vec![Value::String(kind.to_owned())]), &self.session).unwrap();
+		}
+
+		/// Results of the notifications sent since the last call.
+		fn results(&self) -> Vec<Value> {
+			self.sent.lock().unwrap().drain(..).map(|message| {
+				let notification: Value = serde_json::from_str(&message).unwrap();
+				notification.find_path(&["params", "result"]).unwrap().clone()
+			}).collect()
+		}
+	}
+
+	fn log_in(hash: &H256, data: u8) -> LocalizedLogEntry {
+		LocalizedLogEntry {
+			entry: LogEntry {
+				address: Address::from(1),
+				topics: vec![],
+				data: vec![data],
+			},
+			block_hash: hash.clone(),
+			..LocalizedLogEntry::default()
+		}
+	}
+
+	#[test]
+	fn should_notify_new_heads_with_extra_data() {
+		let tester = Tester::new();
+		let mut header = Header::new();
+		header.extra_data = vec![1, 2, 3];
+		let hash = tester.client.add_header(header);
+		tester.subscribe("newHeads");
+
+		tester.pubsub.new_blocks(&[hash], &[]);
+
+		let results = tester.results();
+		assert_eq!(results.len(), 1);
+		assert_eq!(results[0].find("extraData"), Some(&value::to_value(&Bytes::new(vec![1, 2, 3]))));
+	}
+
+	#[test]
+	fn should_notify_removed_logs_of_retracted_blocks_first() {
+		let tester = Tester::new();
+		let (retracted, enacted) = (H256::from(1), H256::from(2));
+		tester.client.logs.write().unwrap().insert(retracted.clone(), vec![log_in(&retracted, 1)]);
+		tester.client.logs.write().unwrap().insert(enacted.clone(), vec![log_in(&enacted, 2)]);
+		tester.subscribe("logs");
+
+		tester.pubsub.new_blocks(&[enacted.clone()], &[retracted.clone()]);
+
+		let results = tester.results();
+		assert_eq!(results.len(), 2);
+		assert_eq!(results[0].find("blockHash"), Some(&value::to_value(&retracted)));
+		assert_eq!(results[0].find("removed"), Some(&Value::Bool(true)));
+		assert_eq!(results[1].find("blockHash"), Some(&value::to_value(&enacted)));
+		assert_eq!(results[1].find("removed"), None);
+	}
+
+	#[test]
+	fn should_notify_pending_transactions() {
+		let tester = Tester::new();
+		tester.subscribe("newPendingTransactions");
+		tester.subscribe("newHeads");
+
+		tester.pubsub.new_transactions(&[H256::from(5), H256::from(6)]);
+
+		assert_eq!(tester.results(), vec![value::to_value(&H256::from(5)), value::to_value(&H256::from(6))]);
+	}
+
+	#[test]
+	fn should_notify_sync_state_changes_only() {
+		let tester = Tester::new();
+		tester.subscribe("syncing");
+		tester.pubsub.check_sync_status();
+		assert!(tester.results().is_empty());
+
+		*tester.sync.state.write().unwrap() = SyncState::Blocks;
+		*tester.sync.highest_block_number.write().unwrap() = Some(10);
+		*tester.client.best_block_number.write().unwrap() = 3;
+		tester.pubsub.check_sync_status();
+		tester.pubsub.check_sync_status();
+		assert_eq!(tester.results(), vec![value::to_value(&SyncStatus::Info(SyncInfo {
+			starting_block: U256::zero(),
+			current_block: U256::from(3),
+			highest_block: U256::from(10),
+		}))]);
+
+		*tester.sync.state.write().unwrap() = SyncState::Idle;
+		tester.pubsub.check_sync_status();
+		assert_eq!(tester.results(), vec![Value::Bool(false)]);
+	}
+}
@@ -27,10 +27,14 @@ macro_rules! take_weak {
 
 mod web3;
 mod eth;
+mod eth_pubsub;
 mod net;
 mod personal;
+mod parity;
 
 pub use self::web3::Web3Client;
 pub use self::eth::{EthClient, EthFilterClient};
+pub use self::eth_pubsub::EthPubSubClient;
 pub use self::net::NetClient;
 pub use self::personal::PersonalClient;
+pub use self::parity::ParityClient;
@@ -0,0 +1,61 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Parity-specific rpc implementation.
+use std::collections::BTreeMap;
+use std::sync::{Arc, Weak};
+use jsonrpc_core::*;
+use metrics::{RpcStats, LATENCY_BUCKETS_MS};
+use v1::traits::Parity;
+
+/// Parity-specific rpc implementation.
+pub struct ParityClient {
+	stats: Weak<RpcStats>,
+}
+
+impl ParityClient {
+	/// Creates new ParityClient.
+	pub fn new(stats: &Arc<RpcStats>) -> Self {
+		ParityClient {
+			stats: Arc::downgrade(stats),
+		}
+	}
+}
+
+impl Parity for ParityClient {
+	fn rpc_stats(&self, params: Params) -> Result<Value, Error> {
+		match params {
+			Params::None => {
+				let methods = take_weak!(self.stats).methods().into_iter().map(|(name, stats)| {
+					let mut method = BTreeMap::new();
+					method.insert("calls".to_owned(), Value::U64(stats.calls));
+					method.insert("errors".to_owned(), Value::U64(stats.errors));
+					method.insert("totalTimeMicros".to_owned(), Value::U64(stats.total_time_ns / 1000));
+					method.insert("maxTimeMicros".to_owned(), Value::U64(stats.max_time_ns / 1000));
+					method.insert("histogram".to_owned(), Value::Array(stats.histogram.iter().map(|&n| Value::U64(n)).collect()));
+					(name, Value::Object(method))
+				}).collect();
+
+				let mut result = BTreeMap::new();
+				// upper bounds of histogram buckets, the last bucket is unbounded.
+				result.insert("bucketsMillis".to_owned(), Value::Array(LATENCY_BUCKETS_MS.iter().map(|&ms| Value::U64(ms)).collect()));
+				result.insert("methods".to_owned(), Value::Object(methods));
+				Ok(Value::Object(result))
+			},
+			_ => Err(Error::invalid_params())
+		}
+	}
+}
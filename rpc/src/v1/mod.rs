@@ -25,5 +25,5 @@ mod types;
 mod tests;
 mod helpers;
 
-pub use self::traits::{Web3, Eth, EthFilter, Personal, Net};
+pub use self::traits::{Web3, Eth, EthFilter, Personal, Net, Parity};
 pub use self::impls::*;
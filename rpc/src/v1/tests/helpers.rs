@@ -0,0 +1,212 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Test implementations of the providers rpc clients depend on.
+
+use std::collections::HashMap;
+use std::sync::{Mutex, RwLock};
+use util::numbers::*;
+use util::rlp::encode;
+use util::bytes::Bytes;
+use ethcore::client::{BlockChainClient, BlockStatus, TreeRoute, BlockChainInfo, TransactionId, BlockId, BlockQueueInfo, AccountProof};
+use ethcore::header::{Header, BlockNumber};
+use ethcore::error::{Error, ImportResult};
+use ethcore::transaction::{LocalizedTransaction, SignedTransaction};
+use ethcore::filter::Filter;
+use ethcore::log_entry::LocalizedLogEntry;
+use ethcore::block::ClosedBlock;
+use ethcore::sealing::SealingConfig;
+use ethsync::{SyncProvider, SyncStatus, SyncState};
+
+/// Client serving headers and logs of blocks added by the test.
+#[derive(Default)]
+pub struct TestBlockChainClient {
+	/// Header rlp by block hash.
+	pub headers: RwLock<HashMap<H256, Bytes>>,
+	/// Logs by block hash. Filters are not applied.
+	pub logs: RwLock<HashMap<H256, Vec<LocalizedLogEntry>>>,
+	/// Number of the best block.
+	pub best_block_number: RwLock<BlockNumber>,
+}
+
+impl TestBlockChainClient {
+	/// Adds `header` and returns its hash.
+	pub fn add_header(&self, header: Header) -> H256 {
+		let hash = header.hash();
+		self.headers.write().unwrap().insert(hash.clone(), encode(&header).to_vec());
+		hash
+	}
+}
+
+impl BlockChainClient for TestBlockChainClient {
+	fn block_header(&self, id: BlockId) -> Option<Bytes> {
+		match id {
+			BlockId::Hash(hash) => self.headers.read().unwrap().get(&hash).cloned(),
+			_ => unimplemented!(),
+		}
+	}
+
+	fn block_body(&self, _id: BlockId) -> Option<Bytes> {
+		unimplemented!();
+	}
+
+	fn block(&self, _id: BlockId) -> Option<Bytes> {
+		unimplemented!();
+	}
+
+	fn block_status(&self, _id: BlockId) -> BlockStatus {
+		unimplemented!();
+	}
+
+	fn block_total_difficulty(&self, id: BlockId) -> Option<U256> {
+		self.block_header(id).map(|_| U256::zero())
+	}
+
+	fn nonce(&self, _address: &Address) -> Option<U256> {
+		unimplemented!();
+	}
+
+	fn block_hash(&self, _id: BlockId) -> Option<H256> {
+		unimplemented!();
+	}
+
+	fn code(&self, _address: &Address) -> Option<Bytes> {
+		unimplemented!();
+	}
+
+	fn transaction(&self, _id: TransactionId) -> Option<LocalizedTransaction> {
+		unimplemented!();
+	}
+
+	fn tree_route(&self, _from: &H256, _to: &H256) -> Option<TreeRoute> {
+		unimplemented!();
+	}
+
+	fn state_data(&self, _hash: &H256) -> Option<Bytes> {
+		unimplemented!();
+	}
+
+	fn block_receipts(&self, _hash: &H256) -> Option<Bytes> {
+		unimplemented!();
+	}
+
+	fn prove_account(&self, _address: &Address, _storage_keys: &[H256], _id: BlockId) -> Option<AccountProof> {
+		unimplemented!();
+	}
+
+	fn import_block(&self, _bytes: Bytes) -> ImportResult {
+		unimplemented!();
+	}
+
+	fn queue_info(&self) -> BlockQueueInfo {
+		unimplemented!();
+	}
+
+	fn clear_queue(&self) {
+		unimplemented!();
+	}
+
+	fn chain_info(&self) -> BlockChainInfo {
+		BlockChainInfo {
+			total_difficulty: U256::zero(),
+			pending_total_difficulty: U256::zero(),
+			genesis_hash: H256::new(),
+			best_block_hash: H256::new(),
+			best_block_number: *self.best_block_number.read().unwrap(),
+		}
+	}
+
+	fn blocks_with_bloom(&self, _bloom: &H2048, _from_block: BlockId, _to_block: BlockId) -> Option<Vec<BlockNumber>> {
+		unimplemented!();
+	}
+
+	fn logs(&self, _filter: Filter) -> Vec<LocalizedLogEntry> {
+		unimplemented!();
+	}
+
+	fn block_logs(&self, hash: &H256, _filter: &Filter) -> Vec<LocalizedLogEntry> {
+		self.logs.read().unwrap().get(hash).cloned().unwrap_or_else(Vec::new)
+	}
+
+	fn sealing_block(&self) -> &Mutex<Option<ClosedBlock>> {
+		unimplemented!();
+	}
+
+	fn submit_seal(&self, _pow_hash: H256, _seal: Vec<Bytes>) -> Result<(), Error> {
+		unimplemented!();
+	}
+
+	fn seals_internally(&self) -> bool {
+		false
+	}
+
+	fn is_sealing_enabled(&self) -> bool {
+		false
+	}
+
+	fn seal_transactions(&self, _transactions: Vec<SignedTransaction>) -> Option<H256> {
+		None
+	}
+
+	fn sealing_config(&self) -> SealingConfig {
+		SealingConfig::default()
+	}
+
+	fn set_transactions_to_seal(&self, _transactions: Vec<SignedTransaction>) {}
+
+	fn verify_transaction_chain_id(&self, _t: &SignedTransaction) -> Result<(), Error> {
+		Ok(())
+	}
+}
+
+/// Sync provider reporting the state set by the test.
+pub struct TestSyncProvider {
+	/// State reported in the status.
+	pub state: RwLock<SyncState>,
+	/// Highest block number reported in the status.
+	pub highest_block_number: RwLock<Option<BlockNumber>>,
+}
+
+impl TestSyncProvider {
+	/// Creates a provider which is not syncing.
+	pub fn new() -> Self {
+		TestSyncProvider {
+			state: RwLock::new(SyncState::Idle),
+			highest_block_number: RwLock::new(None),
+		}
+	}
+}
+
+impl SyncProvider for TestSyncProvider {
+	fn status(&self) -> SyncStatus {
+		SyncStatus {
+			state: *self.state.read().unwrap(),
+			protocol_version: 63,
+			start_block_number: 0,
+			last_imported_block_number: None,
+			highest_block_number: *self.highest_block_number.read().unwrap(),
+			blocks_total: 0,
+			blocks_received: 0,
+			num_peers: 0,
+			num_active_peers: 0,
+			mem_used: 0,
+		}
+	}
+
+	fn insert_transaction(&self, _transaction: SignedTransaction) {
+		unimplemented!();
+	}
+}
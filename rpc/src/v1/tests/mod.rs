@@ -1 +1,3 @@
 //TODO: load custom blockchain state and test
+
+pub mod helpers;
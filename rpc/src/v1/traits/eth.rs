@@ -68,6 +68,9 @@ pub trait Eth: Sized + Send + Sync + 'static {
 	/// Returns the code at given address at given time (block number).
 	fn code_at(&self, _: Params) -> Result<Value, Error> { rpc_unimplemented!() }
 
+	/// Returns the account and storage values at given address with Merkle proofs of them.
+	fn proof(&self, _: Params) -> Result<Value, Error> { rpc_unimplemented!() }
+
 	/// Sends transaction.
 	fn send_transaction(&self, _: Params) -> Result<Value, Error> { rpc_unimplemented!() }
 
@@ -129,6 +132,7 @@ pub trait Eth: Sized + Send + Sync + 'static {
 		delegate.add_method("eth_blockNumber", Eth::block_number);
 		delegate.add_method("eth_balance", Eth::balance);
 		delegate.add_method("eth_getStorageAt", Eth::storage_at);
+		delegate.add_method("eth_getProof", Eth::proof);
 		delegate.add_method("eth_getTransactionCount", Eth::transaction_count);
 		delegate.add_method("eth_getBlockTransactionCountByHash", Eth::block_transaction_count);
 		delegate.add_method("eth_getBlockTransactionCountByNumber", Eth::block_transaction_count);
@@ -47,7 +47,7 @@ impl<T> Deserialize for VariadicValue<T> where T: Deserialize {
 pub type FilterAddress = VariadicValue<Address>;
 pub type Topic = VariadicValue<H256>;
 
-#[derive(Debug, PartialEq, Deserialize)]
+#[derive(Debug, Default, PartialEq, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct Filter {
 	#[serde(rename="fromBlock")]
@@ -56,6 +56,7 @@ pub struct Filter {
 	pub to_block: Option<BlockNumber>,
 	pub address: Option<FilterAddress>,
 	pub topics: Option<Vec<Topic>>,
+	pub limit: Option<usize>,
 }
 
 impl Into<EthFilter> for Filter {
@@ -75,7 +76,8 @@ impl Into<EthFilter> for Filter {
 					VariadicValue::Multiple(t) => Some(t)
 				}).filter_map(|m| m).collect()).into_iter();
 				[iter.next(), iter.next(), iter.next(), iter.next()]
-			}
+			},
+			limit: self.limit,
 		}
 	}
 }
@@ -110,7 +112,21 @@ mod tests {
 			from_block: Some(BlockNumber::Earliest),
 			to_block: Some(BlockNumber::Latest),
 			address: None,
-			topics: None
+			topics: None,
+			limit: None,
+		});
+	}
+
+	#[test]
+	fn filter_with_limit_deserialization() {
+		let s = r#"{"address":"0x0000000000000000000000000000000000000001","limit":10}"#;
+		let deserialized: Filter = serde_json::from_str(s).unwrap();
+		assert_eq!(deserialized, Filter {
+			from_block: None,
+			to_block: None,
+			address: Some(VariadicValue::Single(Address::from(1))),
+			topics: None,
+			limit: Some(10),
 		});
 	}
 }
@@ -33,6 +33,13 @@ pub struct Log {
 	transaction_index: U256,
 	#[serde(rename="logIndex")]
 	log_index: U256,
+	/// Set when the log was removed from the canon chain by a reorganisation.
+	#[serde(skip_serializing_if="is_false")]
+	pub removed: bool,
+}
+
+fn is_false(b: &bool) -> bool {
+	!*b
 }
 
 impl From<LocalizedLogEntry> for Log {
@@ -45,7 +52,8 @@ impl From<LocalizedLogEntry> for Log {
 			block_number: From::from(e.block_number),
 			transaction_hash: e.transaction_hash,
 			transaction_index: From::from(e.transaction_index),
-			log_index: From::from(e.log_index)
+			log_index: From::from(e.log_index),
+			removed: false,
 		}
 	}
 }
@@ -72,7 +80,8 @@ mod tests {
 			block_number: U256::from(0x4510c),
 			transaction_hash: H256::new(),
 			transaction_index: U256::zero(),
-			log_index: U256::one()
+			log_index: U256::one(),
+			removed: false,
 		};
 
 		let serialized = serde_json::to_string(&log).unwrap();
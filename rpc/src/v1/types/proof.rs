@@ -0,0 +1,92 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+
+use util::numbers::*;
+use ethcore::client;
+use v1::types::Bytes;
+
+#[derive(Debug, Serialize)]
+pub struct StorageProof {
+	key: H256,
+	value: H256,
+	proof: Vec<Bytes>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AccountProof {
+	address: Address,
+	balance: U256,
+	nonce: U256,
+	#[serde(rename="codeHash")]
+	code_hash: H256,
+	#[serde(rename="storageHash")]
+	storage_hash: H256,
+	#[serde(rename="accountProof")]
+	account_proof: Vec<Bytes>,
+	#[serde(rename="storageProof")]
+	storage_proof: Vec<StorageProof>,
+}
+
+impl From<client::StorageProof> for StorageProof {
+	fn from(p: client::StorageProof) -> StorageProof {
+		StorageProof {
+			key: p.key,
+			value: p.value,
+			proof: p.proof.into_iter().map(Bytes::new).collect(),
+		}
+	}
+}
+
+impl AccountProof {
+	/// Proof of the account at `address`.
+	pub fn new(address: Address, p: client::AccountProof) -> AccountProof {
+		AccountProof {
+			address: address,
+			balance: p.balance,
+			nonce: p.nonce,
+			code_hash: p.code_hash,
+			storage_hash: p.storage_root,
+			account_proof: p.proof.into_iter().map(Bytes::new).collect(),
+			storage_proof: p.storage.into_iter().map(From::from).collect(),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json;
+	use util::numbers::*;
+	use ethcore::client;
+	use super::AccountProof;
+
+	#[test]
+	fn account_proof_serialization() {
+		let s = r#"{"address":"0x0000000000000000000000000000000000000001","balance":"0x45","nonce":"0x00","codeHash":"0x0000000000000000000000000000000000000000000000000000000000000002","storageHash":"0x0000000000000000000000000000000000000000000000000000000000000003","accountProof":["0x0102"],"storageProof":[{"key":"0x0000000000000000000000000000000000000000000000000000000000000000","value":"0x0000000000000000000000000000000000000000000000000000000000000000","proof":[]}]}"#;
+
+		let proof = AccountProof::new(Address::from(1), client::AccountProof {
+			balance: U256::from(0x45),
+			nonce: U256::zero(),
+			code_hash: H256::from(2),
+			storage_root: H256::from(3),
+			proof: vec![vec![1, 2]],
+			storage: vec![client::StorageProof { key: H256::new(), value: H256::new(), proof: vec![] }],
+		});
+
+		let serialized = serde_json::to_string(&proof).unwrap();
+		assert_eq!(serialized, s);
+	}
+}
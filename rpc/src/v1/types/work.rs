@@ -0,0 +1,73 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+use util::numbers::*;
+use ethcore::sealing::{SealingConfig, UnclePolicy};
+
+/// Authoring policy of the block offered by `eth_getWork`.
+#[derive(Default, Debug, Serialize, PartialEq)]
+pub struct WorkPolicy {
+	pub number: U256,
+	#[serde(rename="gasLimit")]
+	pub gas_limit: U256,
+	#[serde(rename="gasFloorTarget")]
+	pub gas_floor_target: U256,
+	#[serde(rename="gasCeilTarget")]
+	pub gas_ceil_target: U256,
+	#[serde(rename="minGasPrice")]
+	pub min_gas_price: U256,
+	#[serde(rename="maxGasPerTransaction")]
+	pub max_gas_per_transaction: Option<U256>,
+	/// `None` if as many as the engine allows.
+	#[serde(rename="maxUncles")]
+	pub max_uncles: Option<U256>,
+	#[serde(rename="reservedLocalGas")]
+	pub reserved_local_gas: U256,
+}
+
+impl WorkPolicy {
+	pub fn new(number: u64, gas_limit: U256, config: &SealingConfig) -> WorkPolicy {
+		WorkPolicy {
+			number: U256::from(number),
+			gas_limit: gas_limit,
+			gas_floor_target: config.gas_floor_target,
+			gas_ceil_target: config.gas_ceil_target,
+			min_gas_price: config.min_gas_price,
+			max_gas_per_transaction: config.max_gas_per_tx,
+			max_uncles: match config.uncles {
+				UnclePolicy::All => None,
+				UnclePolicy::AtMost(n) => Some(U256::from(n)),
+			},
+			reserved_local_gas: config.reserved_local_gas,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json;
+	use ethcore::sealing::{SealingConfig, UnclePolicy};
+	use util::numbers::*;
+	use super::*;
+
+	#[test]
+	fn test_serialize_work_policy() {
+		let config = SealingConfig { uncles: UnclePolicy::AtMost(1), ..SealingConfig::default() };
+		let t = WorkPolicy::new(1, U256::from(0x300000), &config);
+		let serialized = serde_json::to_string(&t).unwrap();
+		assert_eq!(serialized, r#"{"number":"0x01","gasLimit":"0x300000","gasFloorTarget":"0x2fefba","gasCeilTarget":"0x5fdfb0","minGasPrice":"0x00","maxGasPerTransaction":null,"maxUncles":"0x01","reservedLocalGas":"0x00"}"#);
+	}
+}
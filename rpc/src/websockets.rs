@@ -0,0 +1,103 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! WebSocket transport.
+//!
+//! Every text frame is a single JSON-RPC request (or batch) and is answered with a single text frame.
+//! Access control is applied to the opening handshake. The peer address is only known once the
+//! handshake is done, so messages of connections from addresses not allowed are dropped until they're closed.
+
+use std::str;
+use std::sync::Arc;
+use ws;
+use access::AccessControl;
+use pubsub::{Session, SessionHandler, Sink};
+
+struct WsSink {
+	out: ws::Sender,
+}
+
+impl Sink for WsSink {
+	fn send(&self, message: String) -> bool {
+		self.out.send(message).is_ok()
+	}
+}
+
+struct Connection {
+	out: ws::Sender,
+	session: Arc<Session>,
+	handler: Arc<SessionHandler>,
+	access: Arc<AccessControl>,
+	/// Whether the peer address is allowed; set once the connection is open.
+	allowed: bool,
+}
+
+fn header<'a>(req: &'a ws::Request, name: &str) -> Option<&'a str> {
+	req.header(name).and_then(|value| str::from_utf8(value).ok())
+}
+
+impl ws::Handler for Connection {
+	fn on_request(&mut self, req: &ws::Request) -> ws::Result<ws::Response> {
+		if !self.access.is_host_allowed(header(req, "Host")) {
+			return Ok(ws::Response::new(403, "Forbidden"));
+		}
+		if !self.access.is_authorized(header(req, "Authorization")) {
+			return Ok(ws::Response::new(401, "Unauthorized"));
+		}
+		ws::Response::from_request(req)
+	}
+
+	fn on_open(&mut self, shake: ws::Handshake) -> ws::Result<()> {
+		self.allowed = shake.peer_addr.map_or(false, |addr| self.access.is_ip_allowed(&addr.ip()));
+		if !self.allowed {
+			debug!(target: "rpc", "Refused WebSocket connection from {:?}", shake.peer_addr);
+			return self.out.close(ws::CloseCode::Policy);
+		}
+		Ok(())
+	}
+
+	fn on_message(&mut self, msg: ws::Message) -> ws::Result<()> {
+		if !self.allowed {
+			return Ok(());
+		}
+		let response = match msg {
+			ws::Message::Text(ref request) => self.handler.handle_request(request, Some(&self.session)),
+			ws::Message::Binary(_) => None,
+		};
+		if let Some(response) = response {
+			self.session.send(response);
+		}
+		Ok(())
+	}
+
+	fn on_close(&mut self, _code: ws::CloseCode, _reason: &str) {
+		self.handler.session_closed(&self.session);
+	}
+}
+
+/// Listens on `addr` and serves connections until the event loop is shut down.
+pub fn serve(addr: &str, handler: Arc<SessionHandler>, access: AccessControl) -> ws::Result<()> {
+	let access = Arc::new(access);
+	let socket = try!(ws::WebSocket::new(move |out: ws::Sender| Connection {
+		out: out.clone(),
+		session: Arc::new(Session::new(Box::new(WsSink { out: out }))),
+		handler: handler.clone(),
+		access: access.clone(),
+		allowed: false,
+	}));
+	try!(socket.listen(addr));
+	Ok(())
+}
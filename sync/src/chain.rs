@@ -71,20 +71,21 @@ const MAX_BODIES_TO_REQUEST: usize = 256;
 const MIN_PEERS_PROPAGATION: usize = 4;
 const MAX_PEERS_PROPAGATION: usize = 128;
 const MAX_PEER_LAG_PROPAGATION: BlockNumber = 20;
+const MAX_TRANSACTIONS_TO_SEAL: usize = 1024;
 
 const STATUS_PACKET: u8 = 0x00;
 const NEW_BLOCK_HASHES_PACKET: u8 = 0x01;
 const TRANSACTIONS_PACKET: u8 = 0x02;
 const GET_BLOCK_HEADERS_PACKET: u8 = 0x03;
 const BLOCK_HEADERS_PACKET: u8 = 0x04;
-const GET_BLOCK_BODIES_PACKET: u8 = 0x05;
-const BLOCK_BODIES_PACKET: u8 = 0x06;
+pub const GET_BLOCK_BODIES_PACKET: u8 = 0x05;
+pub const BLOCK_BODIES_PACKET: u8 = 0x06;
 const NEW_BLOCK_PACKET: u8 = 0x07;
 
-const GET_NODE_DATA_PACKET: u8 = 0x0d;
-const NODE_DATA_PACKET: u8 = 0x0e;
-const GET_RECEIPTS_PACKET: u8 = 0x0f;
-const RECEIPTS_PACKET: u8 = 0x10;
+pub const GET_NODE_DATA_PACKET: u8 = 0x0d;
+pub const NODE_DATA_PACKET: u8 = 0x0e;
+pub const GET_RECEIPTS_PACKET: u8 = 0x0f;
+pub const RECEIPTS_PACKET: u8 = 0x10;
 
 const CONNECTION_TIMEOUT_SEC: f64 = 5f64;
 
@@ -213,6 +214,8 @@ pub struct ChainSync {
 	max_download_ahead_blocks: usize,
 	/// Network ID
 	network_id: U256,
+	/// Download headers only, leaving bodies to the light client
+	headers_only: bool,
 	/// Transactions Queue
 	transaction_queue: Mutex<TransactionQueue>,
 }
@@ -240,6 +243,7 @@ impl ChainSync {
 			last_sent_block_number: 0,
 			max_download_ahead_blocks: max(MAX_HEADERS_TO_REQUEST, config.max_download_ahead_blocks),
 			network_id: config.network_id,
+			headers_only: config.headers_only,
 			transaction_queue: Mutex::new(TransactionQueue::new()),
 		}
 	}
@@ -411,8 +415,8 @@ impl ChainSync {
 						uncles: info.uncles_hash
 					};
 					trace!(target: "sync", "Got header {} ({})", number, hash);
-					if header_id.transactions_root == rlp::SHA3_NULL_RLP && header_id.uncles == rlp::SHA3_EMPTY_LIST_RLP {
-						//empty body, just mark as downloaded
+					if self.headers_only || (header_id.transactions_root == rlp::SHA3_NULL_RLP && header_id.uncles == rlp::SHA3_EMPTY_LIST_RLP) {
+						//empty body or not needed, just mark as downloaded
 						let mut body_stream = RlpStream::new_list(2);
 						body_stream.append_raw(&rlp::NULL_RLP, 1);
 						body_stream.append_raw(&rlp::EMPTY_LIST_RLP, 1);
@@ -929,15 +933,28 @@ impl ChainSync {
 	}
 	/// Called when peer sends us new transactions
 	fn on_peer_transactions(&mut self, io: &mut SyncIo, peer_id: PeerId, r: &UntrustedRlp) -> Result<(), PacketDecodeError> {
-		let chain = io.chain();
 		let item_count = r.item_count();
 		trace!(target: "sync", "{} -> Transactions ({} entries)", peer_id, item_count);
-		let fetch_latest_nonce = |a : &Address| chain.nonce(a);
 
-		let mut transaction_queue = self.transaction_queue.lock().unwrap();
+		let mut txs = Vec::with_capacity(item_count);
 		for i in 0..item_count {
 			let tx: SignedTransaction = try!(r.val_at(i));
-			let _ = transaction_queue.add(tx, &fetch_latest_nonce);
+			txs.push(tx);
+		}
+		let mut imported = Vec::new();
+		{
+			let (txs, nonces) = admissible_with_nonces(io.chain(), txs);
+			let fetch_latest_nonce = |a: &Address| nonces[a];
+			let mut transaction_queue = self.transaction_queue.lock().unwrap();
+			for tx in txs {
+				let hash = tx.hash();
+				if transaction_queue.add(tx, &fetch_latest_nonce).is_ok() {
+					imported.push(hash);
+				}
+			}
+		}
+		if !imported.is_empty() {
+			io.transactions_imported(imported);
 		}
  		Ok(())
 	}
@@ -1086,6 +1103,17 @@ impl ChainSync {
 		}
 	}
 
+	/// Answers a request for data not kept in headers only mode with an empty list,
+	/// so the peer doesn't wait for a reply until it times out.
+	fn return_empty_list(packet_id: PacketId) -> RlpResponseResult {
+		let response_id = match packet_id {
+			GET_BLOCK_BODIES_PACKET => BLOCK_BODIES_PACKET,
+			GET_RECEIPTS_PACKET => RECEIPTS_PACKET,
+			_ => NODE_DATA_PACKET,
+		};
+		Ok(Some((response_id, RlpStream::new_list(0))))
+	}
+
 	/// Dispatch incoming requests and responses
 	pub fn on_packet(&mut self, io: &mut SyncIo, peer: PeerId, packet_id: u8, data: &[u8]) {
 		let rlp = UntrustedRlp::new(data);
@@ -1102,6 +1130,10 @@ impl ChainSync {
 			NEW_BLOCK_PACKET => self.on_peer_new_block(io, peer, &rlp),
 			NEW_BLOCK_HASHES_PACKET => self.on_peer_new_hashes(io, peer, &rlp),
 
+			GET_BLOCK_BODIES_PACKET | GET_RECEIPTS_PACKET | GET_NODE_DATA_PACKET if self.headers_only => self.return_rlp(io, &rlp,
+				|_, _| ChainSync::return_empty_list(packet_id),
+				|e| format!("Error sending empty response: {:?}", e)),
+
 			GET_BLOCK_BODIES_PACKET => self.return_rlp(io, &rlp,
 				ChainSync::return_block_bodies,
 				|e| format!("Error sending block bodies: {:?}", e)),
@@ -1128,6 +1160,11 @@ impl ChainSync {
 		})
 	}
 
+	/// Peers able to serve node data and receipts.
+	pub fn data_peers(&self) -> Vec<PeerId> {
+		self.peers.iter().filter(|&(_, p)| p.protocol_version >= PROTOCOL_VERSION as u32).map(|(id, _)| *id).collect()
+	}
+
 	pub fn maintain_peers(&self, io: &mut SyncIo) {
 		let tick = time::precise_time_s();
 		for (peer_id, peer) in &self.peers {
@@ -1283,17 +1320,15 @@ impl ChainSync {
 			let bad = bad.par_iter().map(|h| fetch_transactions(chain, h));
 
 			good.for_each(|txs| {
+				let (txs, nonces) = admissible_with_nonces(chain, txs);
 				let mut transaction_queue = self.transaction_queue.lock().unwrap();
 				let hashes = txs.iter().map(|tx| tx.hash()).collect::<Vec<H256>>();
-				transaction_queue.remove_all(&hashes, |a| chain.nonce(a));
+				transaction_queue.remove_all(&hashes, |a| nonces[a]);
 			});
 			bad.for_each(|txs| {
-				// populate sender
-				for tx in &txs {
-					let _sender = tx.sender();
-				}
+				let (txs, nonces) = admissible_with_nonces(chain, txs);
 				let mut transaction_queue = self.transaction_queue.lock().unwrap();
-				let _ = transaction_queue.add_all(txs, |a| chain.nonce(a));
+				let _ = transaction_queue.add_all(txs, |a| nonces[a]);
 			});
 		}
 
@@ -1302,15 +1337,52 @@ impl ChainSync {
 		// TODO [todr] propagate transactions?
 	}
 
+	/// Transactions from the queue which are ready to be sealed, by priority.
+	pub fn transactions_to_seal(&self) -> Vec<SignedTransaction> {
+		self.transaction_queue.lock().unwrap().top_transactions(MAX_TRANSACTIONS_TO_SEAL)
+	}
+
 	/// Add transaction to the transaction queue
-	pub fn insert_transaction<T>(&self, transaction: ethcore::transaction::SignedTransaction, fetch_nonce: &T)
+	pub fn insert_transaction<T>(&self, transaction: ethcore::transaction::SignedTransaction, fetch_nonce: &T) -> Result<(), Error>
 		where T: Fn(&Address) -> U256
 	{
 		let mut queue = self.transaction_queue.lock().unwrap();
-		queue.add(transaction, fetch_nonce);
+		queue.add(transaction, fetch_nonce)
 	}
 }
 
+/// Filters `txs` down to the ones which may enter the transaction queue and fetches the state
+/// nonces of their senders. Transactions signed for another chain, whose sender can't be recovered
+/// or whose sender's nonce couldn't be retrieved are left out, so every sender of the returned
+/// transactions has its nonce in the map.
+fn admissible_with_nonces(chain: &BlockChainClient, txs: Vec<SignedTransaction>) -> (Vec<SignedTransaction>, HashMap<Address, U256>) {
+	let mut nonces = HashMap::new();
+	let txs = txs.into_iter().filter(|tx| {
+		if let Err(e) = chain.verify_transaction_chain_id(tx) {
+			debug!(target: "sync", "Skipping transaction {:?}: {:?}", tx.hash(), e);
+			return false;
+		}
+		let sender = match tx.sender() {
+			Ok(sender) => sender,
+			Err(_) => return false,
+		};
+		if nonces.contains_key(&sender) {
+			return true;
+		}
+		match chain.nonce(&sender) {
+			Some(nonce) => {
+				nonces.insert(sender, nonce);
+				true
+			},
+			None => {
+				debug!(target: "sync", "Skipping transaction {:?}: nonce of {:?} is unavailable", tx.hash(), sender);
+				false
+			}
+		}
+	}).collect();
+	(txs, nonces)
+}
+
 #[cfg(test)]
 mod tests {
 	use tests::helpers::*;
@@ -1428,6 +1500,27 @@ mod tests {
 		assert_eq!(1, io.queue.len());
 	}
 
+	#[test]
+	fn returns_empty_lists_in_headers_only_mode() {
+		let mut client = TestBlockChainClient::new();
+		let mut queue = VecDeque::new();
+		let mut io = TestIo::new(&mut client, &mut queue, None);
+		let mut sync = dummy_sync_with_peer(H256::new());
+		sync.headers_only = true;
+
+		let mut request = RlpStream::new_list(1);
+		request.append(&H256::from("ffffffffffffffffffffffffffffffffffffffffffffaaaaaaaaaaaaaaaaaaaa"));
+		let request = request.out();
+		io.sender = Some(2usize);
+		sync.on_packet(&mut io, 0usize, super::GET_BLOCK_BODIES_PACKET, &request);
+		sync.on_packet(&mut io, 0usize, super::GET_RECEIPTS_PACKET, &request);
+		sync.on_packet(&mut io, 0usize, super::GET_NODE_DATA_PACKET, &request);
+
+		let ids: Vec<_> = io.queue.iter().map(|p| p.packet_id).collect();
+		assert_eq!(ids, vec![super::BLOCK_BODIES_PACKET, super::RECEIPTS_PACKET, super::NODE_DATA_PACKET]);
+		assert!(io.queue.iter().all(|p| UntrustedRlp::new(&p.data).item_count() == 0));
+	}
+
 	fn dummy_sync_with_peer(peer_latest_hash: H256) -> ChainSync {
 		let mut sync = ChainSync::new(SyncConfig::default());
 		sync.peers.insert(0,
@@ -15,7 +15,7 @@
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
 use ethcore::client::BlockChainClient;
-use util::{NetworkContext, PeerId, PacketId,};
+use util::{NetworkContext, PeerId, PacketId, H256};
 use util::error::UtilError;
 use ethcore::service::SyncMessage;
 
@@ -37,6 +37,8 @@ pub trait SyncIo {
 	fn peer_info(&self, peer_id: PeerId) -> String {
 		peer_id.to_string()
 	}
+	/// Notify other IO handlers about transactions added to the queue.
+	fn transactions_imported(&mut self, _hashes: Vec<H256>) {}
 }
 
 /// Wraps `NetworkContext` and the blockchain client
@@ -79,6 +81,10 @@ impl<'s, 'h> SyncIo for NetSyncIo<'s, 'h> {
 	fn peer_info(&self, peer_id: PeerId) -> String {
 		self.network.peer_info(peer_id)
 	}
+
+	fn transactions_imported(&mut self, hashes: Vec<H256>) {
+		self.network.message(SyncMessage::NewTransactions(hashes));
+	}
 }
 
 
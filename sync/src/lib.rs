@@ -60,19 +60,21 @@ extern crate heapsize;
 
 use std::ops::*;
 use std::sync::*;
-use ethcore::client::Client;
+use ethcore::client::BlockChainClient;
 use util::network::{NetworkProtocolHandler, NetworkService, NetworkContext, PeerId};
-use util::TimerToken;
+use util::{TimerToken, IoChannel, NetworkIoMessage};
 use util::{U256, ONE_U256};
 use chain::ChainSync;
-use ethcore::service::SyncMessage;
+use ethcore::service::{SyncMessage, NetSyncMessage};
 use io::NetSyncIo;
 
 mod chain;
 mod io;
+mod on_demand;
 mod range_collection;
 mod transaction_queue;
 pub use transaction_queue::TransactionQueue;
+pub use on_demand::OnDemandService;
 
 #[cfg(test)]
 mod tests;
@@ -83,6 +85,8 @@ pub struct SyncConfig {
 	pub max_download_ahead_blocks: usize,
 	/// Network ID
 	pub network_id: U256,
+	/// Download headers only. Used with the light client.
+	pub headers_only: bool,
 }
 
 impl Default for SyncConfig {
@@ -90,6 +94,7 @@ impl Default for SyncConfig {
 		SyncConfig {
 			max_download_ahead_blocks: 20000,
 			network_id: ONE_U256,
+			headers_only: false,
 		}
 	}
 }
@@ -105,19 +110,40 @@ pub trait SyncProvider: Send + Sync {
 /// Ethereum network protocol handler
 pub struct EthSync {
 	/// Shared blockchain client. TODO: this should evetually become an IPC endpoint
-	chain: Arc<Client>,
+	chain: Arc<BlockChainClient>,
 	/// Sync strategy
-	sync: RwLock<ChainSync>
+	sync: RwLock<ChainSync>,
+	/// Channel used to notify other IO handlers about locally inserted transactions
+	io_channel: IoChannel<NetSyncMessage>,
+	/// Light client requests, if syncing for the light client
+	on_demand: Option<Arc<OnDemandService>>,
 }
 
 pub use self::chain::{SyncStatus, SyncState};
 
+const SYNC_TIMER: TimerToken = 0;
+const ON_DEMAND_TIMER: TimerToken = 1;
+const ON_DEMAND_TIMER_MS: u64 = 100;
+
 impl EthSync {
 	/// Creates and register protocol with the network service
-	pub fn register(service: &mut NetworkService<SyncMessage>, config: SyncConfig, chain: Arc<Client>) -> Arc<EthSync> {
+	pub fn register(service: &mut NetworkService<SyncMessage>, config: SyncConfig, chain: Arc<BlockChainClient>) -> Arc<EthSync> {
+		EthSync::register_with(service, config, chain, None)
+	}
+
+	/// Creates and register protocol with the network service for a light client
+	/// which sends its requests through `on_demand`. Only headers are synced.
+	pub fn register_light(service: &mut NetworkService<SyncMessage>, mut config: SyncConfig, chain: Arc<BlockChainClient>, on_demand: Arc<OnDemandService>) -> Arc<EthSync> {
+		config.headers_only = true;
+		EthSync::register_with(service, config, chain, Some(on_demand))
+	}
+
+	fn register_with(service: &mut NetworkService<SyncMessage>, config: SyncConfig, chain: Arc<BlockChainClient>, on_demand: Option<Arc<OnDemandService>>) -> Arc<EthSync> {
 		let sync = Arc::new(EthSync {
 			chain: chain,
 			sync: RwLock::new(ChainSync::new(config)),
+			io_channel: service.io().channel(),
+			on_demand: on_demand,
 		});
 		service.register_protocol(sync.clone(), "eth", &[62u8, 63u8]).expect("Error registering eth protocol handler");
 		sync
@@ -144,18 +170,38 @@ impl SyncProvider for EthSync {
 	fn insert_transaction(&self, transaction: ethcore::transaction::SignedTransaction) {
 		use util::numbers::*;
 
-		let nonce_fn = |a: &Address| self.chain.state().nonce(a) + U256::one();
+		if let Err(e) = self.chain.verify_transaction_chain_id(&transaction) {
+			warn!(target: "sync", "Not inserting transaction {:?}: {:?}", transaction.hash(), e);
+			return;
+		}
+		let nonce = match transaction.sender().ok().and_then(|sender| self.chain.nonce(&sender)) {
+			Some(nonce) => nonce,
+			None => {
+				warn!(target: "sync", "Not inserting transaction {:?}: sender nonce is unavailable", transaction.hash());
+				return;
+			}
+		};
+		let nonce_fn = |_: &Address| nonce + U256::one();
+		let hash = transaction.hash();
 		let sync = self.sync.write().unwrap();
-		sync.insert_transaction(transaction, &nonce_fn);
+		if sync.insert_transaction(transaction, &nonce_fn).is_ok() {
+			let _ = self.io_channel.send(NetworkIoMessage::User(SyncMessage::NewTransactions(vec![hash])));
+		}
 	}
 }
 
 impl NetworkProtocolHandler<SyncMessage> for EthSync {
 	fn initialize(&self, io: &NetworkContext<SyncMessage>) {
-		io.register_timer(0, 1000).expect("Error registering sync timer");
+		io.register_timer(SYNC_TIMER, 1000).expect("Error registering sync timer");
+		if self.on_demand.is_some() {
+			io.register_timer(ON_DEMAND_TIMER, ON_DEMAND_TIMER_MS).expect("Error registering light client request timer");
+		}
 	}
 
 	fn read(&self, io: &NetworkContext<SyncMessage>, peer: &PeerId, packet_id: u8, data: &[u8]) {
+		if self.on_demand.as_ref().map_or(false, |on_demand| on_demand.on_packet(*peer, packet_id, data)) {
+			return;
+		}
 		self.sync.write().unwrap().on_packet(&mut NetSyncIo::new(io, self.chain.deref()) , *peer, packet_id, data);
 	}
 
@@ -167,17 +213,35 @@ impl NetworkProtocolHandler<SyncMessage> for EthSync {
 		self.sync.write().unwrap().on_peer_aborting(&mut NetSyncIo::new(io, self.chain.deref()), *peer);
 	}
 
-	fn timeout(&self, io: &NetworkContext<SyncMessage>, _timer: TimerToken) {
-		self.sync.write().unwrap().maintain_peers(&mut NetSyncIo::new(io, self.chain.deref()));
-		self.sync.write().unwrap().maintain_sync(&mut NetSyncIo::new(io, self.chain.deref()));
+	fn timeout(&self, io: &NetworkContext<SyncMessage>, timer: TimerToken) {
+		match timer {
+			ON_DEMAND_TIMER => if let Some(ref on_demand) = self.on_demand {
+				let peers = self.sync.read().unwrap().data_peers();
+				on_demand.dispatch(&mut NetSyncIo::new(io, self.chain.deref()), &peers);
+			},
+			_ => {
+				self.sync.write().unwrap().maintain_peers(&mut NetSyncIo::new(io, self.chain.deref()));
+				self.sync.write().unwrap().maintain_sync(&mut NetSyncIo::new(io, self.chain.deref()));
+			},
+		}
 	}
 
 	fn message(&self, io: &NetworkContext<SyncMessage>, message: &SyncMessage) {
 		match *message {
-			SyncMessage::NewChainBlocks { ref good, ref bad, ref retracted } => {
+			SyncMessage::NewChainBlocks { ref good, ref bad, ref retracted, .. } => {
 				let mut sync_io = NetSyncIo::new(io, self.chain.deref());
 				self.sync.write().unwrap().chain_new_blocks(&mut sync_io, good, bad, retracted);
 			},
+			SyncMessage::NewTransactions(_) => {
+				// without a block being sealed there's nothing to update.
+				if self.chain.seals_internally() {
+					let transactions = self.sync.read().unwrap().transactions_to_seal();
+					self.chain.seal_transactions(transactions);
+				} else if self.chain.is_sealing_enabled() {
+					let transactions = self.sync.read().unwrap().transactions_to_seal();
+					self.chain.set_transactions_to_seal(transactions);
+				}
+			},
 			_ => {/* Ignore other messages */},
 		}
 	}
@@ -0,0 +1,285 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! On-demand retrieval of block bodies, receipts and state for the light client.
+//!
+//! Requests are queued by `fetch` and sent out on the sync IO thread, one request per peer
+//! at a time. Peers not responding in time have their request passed on to another peer.
+
+use std::sync::Condvar;
+use std::time::Duration;
+use util::*;
+use ethcore::light::{OnDemand, Request, FetchError};
+use io::SyncIo;
+use chain::{GET_BLOCK_BODIES_PACKET, BLOCK_BODIES_PACKET, GET_NODE_DATA_PACKET, NODE_DATA_PACKET, GET_RECEIPTS_PACKET, RECEIPTS_PACKET};
+use time;
+
+/// Time given to a peer to respond.
+const REQUEST_TIMEOUT_SEC: f64 = 5f64;
+/// Time after which `fetch` gives up.
+const FETCH_TIMEOUT_SEC: f64 = 20f64;
+/// Number of peers giving invalid data before `fetch` gives up.
+const MAX_BAD_RESPONSES: usize = 3;
+
+struct Pending {
+	id: usize,
+	request: Request,
+	/// Peers which failed to answer this request.
+	excluded: Vec<PeerId>,
+}
+
+struct InFlight {
+	pending: Pending,
+	sent: f64,
+}
+
+#[derive(Default)]
+struct Requests {
+	next_id: usize,
+	queued: VecDeque<Pending>,
+	in_flight: HashMap<PeerId, InFlight>,
+	responses: HashMap<usize, (PeerId, Bytes)>,
+	bad_peers: Vec<PeerId>,
+}
+
+impl Requests {
+	fn remove(&mut self, id: usize) {
+		self.queued.retain(|p| p.id != id);
+		let peers: Vec<PeerId> = self.in_flight.iter().filter(|&(_, r)| r.pending.id == id).map(|(peer, _)| *peer).collect();
+		for peer in peers {
+			self.in_flight.remove(&peer);
+		}
+		self.responses.remove(&id);
+	}
+}
+
+/// Sends light client requests to full peers.
+pub struct OnDemandService {
+	requests: Mutex<Requests>,
+	responded: Condvar,
+}
+
+impl OnDemandService {
+	/// Creates a service without any requests.
+	pub fn new() -> OnDemandService {
+		OnDemandService {
+			requests: Mutex::new(Requests::default()),
+			responded: Condvar::new(),
+		}
+	}
+
+	fn request_packet(request: &Request) -> (PacketId, Bytes) {
+		let (packet_id, hash) = match *request {
+			Request::Body(ref hash) => (GET_BLOCK_BODIES_PACKET, hash),
+			Request::Receipts(ref hash) => (GET_RECEIPTS_PACKET, hash),
+			Request::Node(ref hash) => (GET_NODE_DATA_PACKET, hash),
+		};
+		let mut rlp = RlpStream::new_list(1);
+		rlp.append(hash);
+		(packet_id, rlp.out())
+	}
+
+	fn response_packet_id(request: &Request) -> PacketId {
+		match *request {
+			Request::Body(_) => BLOCK_BODIES_PACKET,
+			Request::Receipts(_) => RECEIPTS_PACKET,
+			Request::Node(_) => NODE_DATA_PACKET,
+		}
+	}
+
+	/// Queues `request` and waits for any peer to answer it.
+	fn wait_for(&self, request: Request, excluded: &[PeerId]) -> Option<(PeerId, Bytes)> {
+		let deadline = time::precise_time_s() + FETCH_TIMEOUT_SEC;
+		let mut requests = self.requests.lock().unwrap();
+		let id = requests.next_id;
+		requests.next_id += 1;
+		requests.queued.push_back(Pending {
+			id: id,
+			request: request,
+			excluded: excluded.to_vec(),
+		});
+		loop {
+			if let Some(response) = requests.responses.remove(&id) {
+				return Some(response);
+			}
+			let now = time::precise_time_s();
+			if now >= deadline {
+				requests.remove(id);
+				return None;
+			}
+			requests = self.responded.wait_timeout(requests, Duration::from_millis(((deadline - now) * 1000f64) as u64 + 1)).unwrap().0;
+		}
+	}
+
+	/// Sends queued requests to idle `peers` and requeues those not answered in time.
+	/// Peers which gave invalid data are disabled.
+	pub fn dispatch(&self, io: &mut SyncIo, peers: &[PeerId]) {
+		let mut requests = self.requests.lock().unwrap();
+		for peer in requests.bad_peers.drain(..) {
+			io.disable_peer(peer);
+		}
+
+		let now = time::precise_time_s();
+		let expired: Vec<PeerId> = requests.in_flight.iter()
+			.filter(|&(peer, r)| now - r.sent > REQUEST_TIMEOUT_SEC || !peers.contains(peer))
+			.map(|(peer, _)| *peer)
+			.collect();
+		for peer in expired {
+			let mut pending = requests.in_flight.remove(&peer).unwrap().pending;
+			trace!(target: "sync", "{} did not answer {:?}", peer, pending.request);
+			pending.excluded.push(peer);
+			requests.queued.push_front(pending);
+		}
+
+		let queued = mem::replace(&mut requests.queued, VecDeque::new());
+		for pending in queued {
+			let peer = peers.iter().find(|p| !requests.in_flight.contains_key(p) && !pending.excluded.contains(p)).cloned();
+			match peer {
+				Some(peer) => {
+					let (packet_id, packet) = OnDemandService::request_packet(&pending.request);
+					trace!(target: "sync", "{} <- {:?}", peer, pending.request);
+					match io.send(peer, packet_id, packet) {
+						Ok(_) => {
+							requests.in_flight.insert(peer, InFlight { pending: pending, sent: now });
+						},
+						Err(e) => {
+							warn!(target: "sync", "Error sending light client request: {:?}", e);
+							requests.queued.push_back(pending);
+						},
+					}
+				},
+				None => requests.queued.push_back(pending),
+			}
+		}
+	}
+
+	/// Handles a packet from `peer`. Returns false if it's not a response to a request of this service.
+	pub fn on_packet(&self, peer: PeerId, packet_id: u8, data: &[u8]) -> bool {
+		let mut requests = self.requests.lock().unwrap();
+		if !requests.in_flight.get(&peer).map_or(false, |r| OnDemandService::response_packet_id(&r.pending.request) == packet_id) {
+			return false;
+		}
+		let mut pending = requests.in_flight.remove(&peer).unwrap().pending;
+		let rlp = UntrustedRlp::new(data);
+		let item = rlp.at(0).and_then(|item| match pending.request {
+			Request::Node(_) => item.data().map(|d| d.to_vec()),
+			_ => Ok(item.as_raw().to_vec()),
+		});
+		match item {
+			Ok(item) => {
+				requests.responses.insert(pending.id, (peer, item));
+				self.responded.notify_all();
+			},
+			Err(_) => {
+				// peer doesn't have the data
+				trace!(target: "sync", "{} -> No data for {:?}", peer, pending.request);
+				pending.excluded.push(peer);
+				requests.queued.push_back(pending);
+			},
+		}
+		true
+	}
+}
+
+impl OnDemand for OnDemandService {
+	fn fetch(&self, request: Request, verify: &Fn(&[u8]) -> bool) -> Result<Bytes, FetchError> {
+		let mut excluded = Vec::new();
+		while excluded.len() < MAX_BAD_RESPONSES {
+			let (peer, data) = match self.wait_for(request.clone(), &excluded) {
+				Some(response) => response,
+				None => return Err(FetchError::Timeout),
+			};
+			if verify(&data) {
+				return Ok(data);
+			}
+			debug!(target: "sync", "{} -> Invalid data for {:?}", peer, request);
+			excluded.push(peer);
+			self.requests.lock().unwrap().bad_peers.push(peer);
+		}
+		Err(FetchError::BadResponse)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::thread;
+	use std::time::Duration;
+	use util::*;
+	use ethcore::light::{OnDemand, Request};
+	use chain::{GET_NODE_DATA_PACKET, NODE_DATA_PACKET};
+	use tests::helpers::*;
+	use super::*;
+
+	fn node_response(node: &[u8]) -> Bytes {
+		let mut rlp = RlpStream::new_list(1);
+		rlp.append(&node.to_vec());
+		rlp.out()
+	}
+
+	fn wait_until_queued(service: &OnDemandService) {
+		while service.requests.lock().unwrap().queued.is_empty() {
+			thread::sleep(Duration::from_millis(10));
+		}
+	}
+
+	fn fetch_node(service: &Arc<OnDemandService>, node: &Bytes) -> thread::JoinHandle<Option<Bytes>> {
+		let service = service.clone();
+		let hash = node.sha3();
+		thread::spawn(move || {
+			let verify = move |data: &[u8]| data.sha3() == hash;
+			service.fetch(Request::Node(hash), &verify).ok()
+		})
+	}
+
+	#[test]
+	fn fetches_node_from_peer() {
+		let service = Arc::new(OnDemandService::new());
+		let node = vec![1u8, 2, 3];
+		let handle = fetch_node(&service, &node);
+		wait_until_queued(&service);
+
+		let mut chain = TestBlockChainClient::new();
+		let mut queue = VecDeque::new();
+		service.dispatch(&mut TestIo::new(&mut chain, &mut queue, None), &[1]);
+		let packet = queue.pop_front().unwrap();
+		assert_eq!(packet.packet_id, GET_NODE_DATA_PACKET);
+		assert_eq!(packet.recipient, 1);
+
+		assert!(!service.on_packet(2, NODE_DATA_PACKET, &node_response(&node)));
+		assert!(service.on_packet(1, NODE_DATA_PACKET, &node_response(&node)));
+		assert_eq!(handle.join().unwrap(), Some(node));
+	}
+
+	#[test]
+	fn asks_another_peer_after_invalid_data() {
+		let service = Arc::new(OnDemandService::new());
+		let node = vec![1u8, 2, 3];
+		let handle = fetch_node(&service, &node);
+		wait_until_queued(&service);
+
+		let mut chain = TestBlockChainClient::new();
+		let mut queue = VecDeque::new();
+		service.dispatch(&mut TestIo::new(&mut chain, &mut queue, None), &[1, 2]);
+		assert_eq!(queue.pop_front().unwrap().recipient, 1);
+		assert!(service.on_packet(1, NODE_DATA_PACKET, &node_response(&[4u8])));
+
+		wait_until_queued(&service);
+		service.dispatch(&mut TestIo::new(&mut chain, &mut queue, None), &[1, 2]);
+		assert_eq!(queue.pop_front().unwrap().recipient, 2);
+		assert!(service.on_packet(2, NODE_DATA_PACKET, &node_response(&node)));
+		assert_eq!(handle.join().unwrap(), Some(node));
+	}
+}
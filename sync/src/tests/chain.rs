@@ -17,7 +17,8 @@
 use util::*;
 use ethcore::client::{BlockChainClient, BlockId};
 use io::SyncIo;
-use chain::{SyncState};
+use chain::{SyncState, ChainSync};
+use ::SyncConfig;
 use super::helpers::*;
 
 #[test]
@@ -172,3 +173,16 @@ fn restart_on_malformed_block() {
 	assert_eq!(net.peer(0).chain.chain_info().best_block_number, 4);
 }
 
+
+#[test]
+fn headers_only() {
+	::env_logger::init().ok();
+	let mut net = TestNet::new(2);
+	net.peer_mut(0).sync = ChainSync::new(SyncConfig { headers_only: true, ..SyncConfig::default() });
+	net.peer_mut(1).chain.add_blocks(100, EachBlockWith::Uncle);
+	net.sync();
+
+	assert_eq!(net.peer(0).chain.chain_info().best_block_hash, net.peer(1).chain.chain_info().best_block_hash);
+	// bodies were never downloaded
+	assert!(net.peer(0).chain.block_body(BlockId::Number(50)) != net.peer(1).chain.block_body(BlockId::Number(50)));
+}
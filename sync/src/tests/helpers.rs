@@ -15,17 +15,18 @@
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
 use util::*;
-use ethcore::client::{BlockChainClient, BlockStatus, TreeRoute, BlockChainInfo, TransactionId, BlockId, BlockQueueInfo};
+use ethcore::client::{BlockChainClient, BlockStatus, TreeRoute, BlockChainInfo, TransactionId, BlockId, BlockQueueInfo, AccountProof};
 use ethcore::header::{Header as BlockHeader, BlockNumber};
 use ethcore::error::*;
 use io::SyncIo;
 use chain::ChainSync;
 use ::SyncConfig;
 use ethcore::receipt::Receipt;
-use ethcore::transaction::{LocalizedTransaction, Transaction, Action};
+use ethcore::transaction::{LocalizedTransaction, SignedTransaction, Transaction, Action};
 use ethcore::filter::Filter;
 use ethcore::log_entry::LocalizedLogEntry;
 use ethcore::block::ClosedBlock;
+use ethcore::sealing::SealingConfig;
 
 pub struct TestBlockChainClient {
 	pub blocks: RwLock<HashMap<H256, Bytes>>,
@@ -141,14 +142,18 @@ impl BlockChainClient for TestBlockChainClient {
 		unimplemented!();
 	}
 
-	fn nonce(&self, _address: &Address) -> U256 {
-		U256::zero()
+	fn nonce(&self, _address: &Address) -> Option<U256> {
+		Some(U256::zero())
 	}
 
 	fn code(&self, _address: &Address) -> Option<Bytes> {
 		unimplemented!();
 	}
 
+	fn prove_account(&self, _address: &Address, _storage_keys: &[H256], _id: BlockId) -> Option<AccountProof> {
+		unimplemented!();
+	}
+
 	fn transaction(&self, _id: TransactionId) -> Option<LocalizedTransaction> {
 		unimplemented!();
 	}
@@ -161,6 +166,10 @@ impl BlockChainClient for TestBlockChainClient {
 		unimplemented!();
 	}
 
+	fn block_logs(&self, _hash: &H256, _filter: &Filter) -> Vec<LocalizedLogEntry> {
+		unimplemented!();
+	}
+
 	fn sealing_block(&self) -> &Mutex<Option<ClosedBlock>> {
 		unimplemented!();
 	}
@@ -169,6 +178,28 @@ impl BlockChainClient for TestBlockChainClient {
 		unimplemented!();
 	}
 
+	fn seals_internally(&self) -> bool {
+		false
+	}
+
+	fn is_sealing_enabled(&self) -> bool {
+		false
+	}
+
+	fn seal_transactions(&self, _transactions: Vec<SignedTransaction>) -> Option<H256> {
+		None
+	}
+
+	fn sealing_config(&self) -> SealingConfig {
+		SealingConfig::default()
+	}
+
+	fn set_transactions_to_seal(&self, _transactions: Vec<SignedTransaction>) {}
+
+	fn verify_transaction_chain_id(&self, _t: &SignedTransaction) -> Result<(), Error> {
+		Ok(())
+	}
+
 	fn block_header(&self, id: BlockId) -> Option<Bytes> {
 		self.block_hash(id).and_then(|hash| self.blocks.read().unwrap().get(&hash).map(|r| Rlp::new(r).at(0).as_raw().to_vec()))
 	}
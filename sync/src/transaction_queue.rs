@@ -397,8 +397,6 @@ impl TransactionQueue {
 		self.future.enforce_limit(&mut self.by_hash);
 	}
 
-	// Will be used when mining merged
-	#[allow(dead_code)]
 	/// Returns top transactions from the queue ordered by priority.
 	pub fn top_transactions(&self, size: usize) -> Vec<SignedTransaction> {
 		self.current.by_priority
@@ -20,9 +20,76 @@ use common::*;
 use rlp::*;
 use hashdb::*;
 use memorydb::*;
-use kvdb::{Database, DBTransaction, DatabaseConfig};
+use kvdb::{KeyValueDB, Database, DBTransaction, DatabaseConfig};
 #[cfg(test)]
-use std::env;
+use kvdb::in_memory;
+
+/// Strategy of pruning the state database.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Algorithm {
+	/// Keep all the state ever written. Nothing is pruned.
+	Archive,
+	/// Journal recent commits on disk and reference-count keys they touch. Removals are
+	/// enacted once the era of the canonical commit leaves the recent history.
+	RefCounted,
+	/// Keep inserts of recent commits in memory; only the canonical ones are written
+	/// to the database once their era leaves the recent history.
+	OverlayRecent,
+}
+
+impl Algorithm {
+	/// Name of the algorithm, as accepted by `from_str`.
+	pub fn as_str(&self) -> &'static str {
+		match *self {
+			Algorithm::Archive => "archive",
+			Algorithm::RefCounted => "basic",
+			Algorithm::OverlayRecent => "fast",
+		}
+	}
+
+	/// Version tag stored in databases created with this algorithm.
+	fn version(&self) -> u32 {
+		match *self {
+			Algorithm::Archive => DB_VERSION_NO_JOURNAL,
+			Algorithm::RefCounted => DB_VERSION,
+			Algorithm::OverlayRecent => DB_VERSION_OVERLAY_RECENT,
+		}
+	}
+
+	fn from_version(version: u32) -> Option<Algorithm> {
+		match version {
+			DB_VERSION_NO_JOURNAL => Some(Algorithm::Archive),
+			DB_VERSION => Some(Algorithm::RefCounted),
+			DB_VERSION_OVERLAY_RECENT => Some(Algorithm::OverlayRecent),
+			_ => None,
+		}
+	}
+}
+
+impl Default for Algorithm {
+	fn default() -> Algorithm {
+		Algorithm::Archive
+	}
+}
+
+impl FromStr for Algorithm {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Algorithm, String> {
+		match s {
+			"archive" => Ok(Algorithm::Archive),
+			"basic" => Ok(Algorithm::RefCounted),
+			"fast" => Ok(Algorithm::OverlayRecent),
+			_ => Err(format!("Unknown pruning algorithm: {}", s)),
+		}
+	}
+}
+
+impl fmt::Display for Algorithm {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}", self.as_str())
+	}
+}
 
 /// Implementation of the HashDB trait for a disk-backed database with a memory overlay
 /// and latent-removal semantics.
@@ -30,11 +97,27 @@ use std::env;
 /// Like OverlayDB, there is a memory overlay; `commit()` must be called in order to
 /// write operations out to disk. Unlike OverlayDB, `remove()` operations do not take effect
 /// immediately. Rather some age (based on a linear but arbitrary metric) must pass before
-/// the removals actually take effect.
+/// the removals actually take effect. How removals are tracked depends on the `Algorithm`.
 pub struct JournalDB {
 	overlay: MemoryDB,
-	backing: Arc<Database>,
+	backing: Arc<KeyValueDB>,
+	algorithm: Algorithm,
 	counters: Option<Arc<RwLock<HashMap<H256, i32>>>>,
+	journal_overlay: Option<Arc<RwLock<JournalOverlay>>>,
+}
+
+/// Recent history kept in memory by `Algorithm::OverlayRecent`.
+struct JournalOverlay {
+	/// Values inserted by the journalled commits, referenced once per inserting commit.
+	backing_overlay: MemoryDB,
+	/// Journalled commits by era.
+	journal: HashMap<u64, Vec<JournalEntry>>,
+}
+
+struct JournalEntry {
+	id: H256,
+	insertions: Vec<H256>,
+	deletions: Vec<H256>,
 }
 
 impl Clone for JournalDB {
@@ -42,7 +125,9 @@ impl Clone for JournalDB {
 		JournalDB {
 			overlay: MemoryDB::new(),
 			backing: self.backing.clone(),
+			algorithm: self.algorithm,
 			counters: self.counters.clone(),
+			journal_overlay: self.journal_overlay.clone(),
 		}
 	}
 }
@@ -53,53 +138,72 @@ const VERSION_KEY : [u8; 12] = [ b'j', b'v', b'e', b'r', 0, 0, 0, 0, 0, 0, 0, 0
 
 const DB_VERSION : u32 = 3;
 const DB_VERSION_NO_JOURNAL : u32 = 3 + 256;
+const DB_VERSION_OVERLAY_RECENT : u32 = 3 + 512;
 
 const PADDING : [u8; 10] = [ 0u8; 10 ];
 
+/// Size of the key prefix the backing database should be configured with; must match account_db prefix.
+pub const DB_PREFIX_LEN : usize = 12;
+
 impl JournalDB {
 	/// Create a new instance from file
 	pub fn new(path: &str) -> JournalDB {
-		Self::from_prefs(path, true)
+		Self::from_prefs(path, Algorithm::RefCounted).unwrap_or_else(|e| {
+			panic!("Error opening state db: {:?}", e);
+		})
 	}
 
-	/// Create a new instance from file
-	pub fn from_prefs(path: &str, prefer_journal: bool) -> JournalDB {
-		let opts = DatabaseConfig {
-			prefix_size: Some(12) //use 12 bytes as prefix, this must match account_db prefix
-		};
-		let backing = Database::open(&opts, path).unwrap_or_else(|e| {
-			panic!("Error opening state db: {}", e);
-		});
-		let with_journal;
+	/// Create a new instance from file using given pruning algorithm.
+	pub fn from_prefs(path: &str, algorithm: Algorithm) -> Result<JournalDB, UtilError> {
+		let opts = DatabaseConfig::with_prefix(Some(DB_PREFIX_LEN));
+		let backing = try!(Database::open(&opts, path));
+		Self::new_with_db(Arc::new(backing), algorithm)
+	}
+
+	/// Create a new instance on top of given database.
+	/// Prefix lookups in `backing` should use `DB_PREFIX_LEN` bytes.
+	///
+	/// Fails if the database was created with a different pruning algorithm.
+	pub fn new_with_db(backing: Arc<KeyValueDB>, algorithm: Algorithm) -> Result<JournalDB, UtilError> {
 		if !backing.is_empty() {
-			match backing.get(&VERSION_KEY).map(|d| d.map(|v| decode::<u32>(&v))) {
-				Ok(Some(DB_VERSION)) => { with_journal = true; },
-				Ok(Some(DB_VERSION_NO_JOURNAL)) => { with_journal = false; },
-				v => panic!("Incompatible DB version, expected {}, got {:?}", DB_VERSION, v)
+			let version = try!(backing.get(&VERSION_KEY)).map(|v| decode::<u32>(&v));
+			match version.and_then(Algorithm::from_version) {
+				Some(a) if a == algorithm => {},
+				Some(a) => return Err(UtilError::SimpleString(format!(
+					"State database was created with `{}` pruning and can't be opened with `{}` pruning. \
+					Run with `--pruning {}` or remove the database to resync.", a, algorithm, a))),
+				None => return Err(UtilError::SimpleString(format!("Incompatible state database version: {:?}", version))),
 			}
 		} else {
-			backing.put(&VERSION_KEY, &encode(&(if prefer_journal { DB_VERSION } else { DB_VERSION_NO_JOURNAL }))).expect("Error writing version to database");
-			with_journal = prefer_journal;
+			try!(backing.put(&VERSION_KEY, &encode(&algorithm.version())));
 		}
 
-		let counters = if with_journal {
-			Some(Arc::new(RwLock::new(JournalDB::read_counters(&backing))))
-		} else {
-			None
+		let counters = match algorithm {
+			Algorithm::RefCounted => Some(Arc::new(RwLock::new(JournalDB::read_counters(&*backing)))),
+			_ => None,
 		};
-		JournalDB {
+		let journal_overlay = match algorithm {
+			Algorithm::OverlayRecent => Some(Arc::new(RwLock::new(JournalDB::read_overlay(&*backing)))),
+			_ => None,
+		};
+		Ok(JournalDB {
 			overlay: MemoryDB::new(),
-			backing: Arc::new(backing),
+			backing: backing,
+			algorithm: algorithm,
 			counters: counters,
-		}
+			journal_overlay: journal_overlay,
+		})
 	}
 
-	/// Create a new instance with an anonymous temporary database.
+	/// Create a new instance with an anonymous in-memory database.
 	#[cfg(test)]
 	pub fn new_temp() -> JournalDB {
-		let mut dir = env::temp_dir();
-		dir.push(H32::random().hex());
-		Self::new(dir.to_str().unwrap())
+		Self::new_with_db(Arc::new(in_memory()), Algorithm::RefCounted).unwrap()
+	}
+
+	/// Pruning algorithm of this database.
+	pub fn algorithm(&self) -> Algorithm {
+		self.algorithm
 	}
 
 	/// Check if this database has any commits
@@ -109,11 +213,10 @@ impl JournalDB {
 
 	/// Commit all recent insert operations.
 	pub fn commit(&mut self, now: u64, id: &H256, end: Option<(u64, H256)>) -> Result<u32, UtilError> {
-		let have_counters = self.counters.is_some();
-		if have_counters {
-			self.commit_with_counters(now, id, end)
-		} else {
-			self.commit_without_counters()
+		match self.algorithm {
+			Algorithm::Archive => self.commit_without_counters(),
+			Algorithm::RefCounted => self.commit_with_counters(now, id, end),
+			Algorithm::OverlayRecent => self.commit_with_overlay(now, id, end),
 		}
 	}
 
@@ -154,11 +257,11 @@ impl JournalDB {
 	// The next three are valid only as long as there is an insert operation of `key` in the journal.
 	fn set_already_in(batch: &DBTransaction, key: &H256) { batch.put(&Self::morph_key(key, 0), &[1u8]).expect("Low-level database error. Some issue with your hard disk?"); }
 	fn reset_already_in(batch: &DBTransaction, key: &H256) { batch.delete(&Self::morph_key(key, 0)).expect("Low-level database error. Some issue with your hard disk?"); }
-	fn is_already_in(backing: &Database, key: &H256) -> bool {
+	fn is_already_in(backing: &KeyValueDB, key: &H256) -> bool {
 		backing.get(&Self::morph_key(key, 0)).expect("Low-level database error. Some issue with your hard disk?").is_some()
 	}
 
-	fn insert_keys(inserts: &[(H256, Bytes)], backing: &Database, counters: &mut HashMap<H256, i32>, batch: &DBTransaction) {
+	fn insert_keys(inserts: &[(H256, Bytes)], backing: &KeyValueDB, counters: &mut HashMap<H256, i32>, batch: &DBTransaction) {
 		for &(ref h, ref d) in inserts {
 			if let Some(c) = counters.get_mut(h) {
 				// already counting. increment.
@@ -181,7 +284,7 @@ impl JournalDB {
 		}
 	}
 
-	fn replay_keys(inserts: &[H256], backing: &Database, counters: &mut HashMap<H256, i32>) {
+	fn replay_keys(inserts: &[H256], backing: &KeyValueDB, counters: &mut HashMap<H256, i32>) {
 		trace!("replay_keys: inserts={:?}, counters={:?}", inserts, counters);
 		for h in inserts {
 			if let Some(c) = counters.get_mut(h) {
@@ -314,7 +417,7 @@ impl JournalDB {
 			r.begin_list(inserts.len());
 			inserts.iter().foreach(|&(k, _)| {r.append(&k);});
 			r.append(&removes);
-			Self::insert_keys(&inserts, &self.backing, &mut counters, &batch);
+			Self::insert_keys(&inserts, &*self.backing, &mut counters, &batch);
 			try!(batch.put(&last, r.as_raw()));
 			try!(batch.put(&LATEST_ERA_KEY, &encode(&now)));
 		}
@@ -347,11 +450,157 @@ impl JournalDB {
 		Ok(0)
 	}
 
+	/// Commit all recent insert operations to the in-memory journal overlay and write
+	/// the canonical inserts and removals of the old era to the backing database.
+	fn commit_with_overlay(&mut self, now: u64, id: &H256, end: Option<(u64, H256)>) -> Result<u32, UtilError> {
+		// journal format:
+		// [era, 0] => [ id, [[key_0, value_0], ...], [remove_0, ...] ]
+		// [era, n] => [ ... ]
+
+		// Unlike `commit_with_counters`, nothing but the journal is written for recent commits.
+		// The inserted values live in `backing_overlay`, referenced once by every commit which
+		// inserted them. When an era passes into ancient history, the canonical commit's inserts
+		// are written to the backing database and its removals enacted, unless the removed key
+		// is still inserted by a recent commit. The journal on disk is only used to rebuild
+		// the overlay on reopen.
+		trace!("commit: #{} ({}), end era: {:?}", now, id, end);
+		let mut journal_overlay = self.journal_overlay.as_ref().unwrap().write().unwrap();
+		let batch = DBTransaction::new();
+		{
+			let mut index = 0usize;
+			while try!(self.backing.get(&Self::journal_key(now, index))).is_some() {
+				index += 1;
+			}
+
+			let drained = self.overlay.drain();
+			let removes: Vec<H256> = drained
+				.iter()
+				.filter_map(|(k, &(_, c))| if c < 0 {Some(k.clone())} else {None})
+				.collect();
+			let inserts: Vec<(H256, Bytes)> = drained
+				.into_iter()
+				.filter_map(|(k, (v, r))| if r > 0 { assert!(r == 1); Some((k, v)) } else { assert!(r >= -1); None })
+				.collect();
+
+			let mut r = RlpStream::new_list(3);
+			r.append(id);
+			r.begin_list(inserts.len());
+			for &(ref k, ref v) in &inserts {
+				r.begin_list(2);
+				r.append(k);
+				r.append(v);
+			}
+			r.append(&removes);
+			try!(batch.put(&Self::journal_key(now, index), r.as_raw()));
+			try!(batch.put(&LATEST_ERA_KEY, &encode(&now)));
+
+			let insertions = inserts.iter().map(|&(ref k, _)| k.clone()).collect();
+			for (k, v) in inserts {
+				journal_overlay.backing_overlay.emplace(k, v);
+			}
+			journal_overlay.journal.entry(now).or_insert_with(Vec::new).push(JournalEntry {
+				id: id.clone(),
+				insertions: insertions,
+				deletions: removes,
+			});
+		}
+
+		// apply old commits' details
+		if let Some((end_era, canon_id)) = end {
+			let mut canon_insertions = Vec::new();
+			let mut canon_deletions = Vec::new();
+			let mut overlay_deletions = Vec::new();
+			let entries = journal_overlay.journal.remove(&end_era).unwrap_or_else(Vec::new);
+			for (index, entry) in entries.into_iter().enumerate() {
+				if entry.id == canon_id {
+					for h in &entry.insertions {
+						if let Some(&(ref d, rc)) = journal_overlay.backing_overlay.raw(h) {
+							if rc > 0 {
+								canon_insertions.push((h.clone(), d.clone()));
+							}
+						}
+					}
+					canon_deletions = entry.deletions;
+				}
+				overlay_deletions.extend(entry.insertions);
+				try!(batch.delete(&Self::journal_key(end_era, index)));
+			}
+
+			for (k, v) in canon_insertions {
+				try!(batch.put(&k.bytes(), &v));
+			}
+			for k in &overlay_deletions {
+				journal_overlay.backing_overlay.kill(k);
+			}
+			journal_overlay.backing_overlay.purge();
+			for k in canon_deletions {
+				if !journal_overlay.backing_overlay.exists(&k) {
+					try!(batch.delete(&k.bytes()));
+				}
+			}
+			trace!("JournalDB: delete journal for time #{}, (canon was {})", end_era, canon_id);
+		}
+
+		// keep the overlay locked until the canonical inserts are readable from the backing database.
+		try!(self.backing.write(batch));
+		Ok(0)
+	}
+
+	fn journal_key(era: u64, index: usize) -> Bytes {
+		let mut r = RlpStream::new_list(3);
+		r.append(&era);
+		r.append(&index);
+		r.append(&&PADDING[..]);
+		r.drain()
+	}
+
+	fn read_overlay(db: &KeyValueDB) -> JournalOverlay {
+		let mut overlay = JournalOverlay {
+			backing_overlay: MemoryDB::new(),
+			journal: HashMap::new(),
+		};
+		if let Some(val) = db.get(&LATEST_ERA_KEY).expect("Low-level database error.") {
+			let mut era = decode::<u64>(&val);
+			loop {
+				let mut index = 0usize;
+				while let Some(rlp_data) = db.get(&Self::journal_key(era, index)).expect("Low-level database error.") {
+					trace!("read_overlay: era={}, index={}", era, index);
+					let rlp = Rlp::new(&rlp_data);
+					let inserts = rlp.at(1);
+					let mut insertions = Vec::new();
+					for r in inserts.iter() {
+						let k: H256 = r.val_at(0);
+						let v: Bytes = r.val_at(1);
+						overlay.backing_overlay.emplace(k.clone(), v);
+						insertions.push(k);
+					}
+					overlay.journal.entry(era).or_insert_with(Vec::new).push(JournalEntry {
+						id: rlp.val_at(0),
+						insertions: insertions,
+						deletions: rlp.val_at(2),
+					});
+					index += 1;
+				};
+				if index == 0 || era == 0 {
+					break;
+				}
+				era -= 1;
+			}
+		}
+		trace!("Recovered {} journalled eras", overlay.journal.len());
+		overlay
+	}
+
 	fn payload(&self, key: &H256) -> Option<Bytes> {
+		if let Some(ref journal_overlay) = self.journal_overlay {
+			if let Some(v) = journal_overlay.read().unwrap().backing_overlay.lookup(key) {
+				return Some(v.to_vec());
+			}
+		}
 		self.backing.get(&key.bytes()).expect("Low-level database error. Some issue with your hard disk?").map(|v| v.to_vec())
 	}
 
-	fn read_counters(db: &Database) -> HashMap<H256, i32> {
+	fn read_counters(db: &KeyValueDB) -> HashMap<H256, i32> {
 		let mut counters = HashMap::new();
 		if let Some(val) = db.get(&LATEST_ERA_KEY).expect("Low-level database error.") {
 			let mut era = decode::<u64>(&val);
@@ -385,6 +634,9 @@ impl JournalDB {
 		self.overlay.mem_used() + match self.counters {
 			Some(ref c) => c.read().unwrap().heap_size_of_children(),
 			None => 0
+		} + match self.journal_overlay {
+			Some(ref o) => o.read().unwrap().backing_overlay.mem_used(),
+			None => 0
 		}
  	}
  }
@@ -397,6 +649,12 @@ impl HashDB for JournalDB {
 			ret.insert(h, 1);
 		}
 
+		if let Some(ref journal_overlay) = self.journal_overlay {
+			for (key, _) in journal_overlay.read().unwrap().backing_overlay.keys().into_iter() {
+				ret.insert(key, 1);
+			}
+		}
+
 		for (key, refs) in self.overlay.keys().into_iter() {
 			let refs = *ret.get(&key).unwrap_or(&0) + refs;
 			ret.insert(key, refs);
@@ -439,6 +697,11 @@ mod tests {
 	use common::*;
 	use super::*;
 	use hashdb::*;
+	use kvdb::{KeyValueDB, in_memory};
+
+	fn new_overlay_recent(backing: &Arc<KeyValueDB>) -> JournalDB {
+		JournalDB::new_with_db(backing.clone(), Algorithm::OverlayRecent).unwrap()
+	}
 
 	#[test]
 	fn insert_same_in_fork() {
@@ -670,4 +933,113 @@ mod tests {
 			assert!(!jdb.exists(&bar));
 		}
 	}
+
+	#[test]
+	fn overlay_recent_complex() {
+		// history is 1
+		let mut jdb = new_overlay_recent(&(Arc::new(in_memory()) as Arc<KeyValueDB>));
+
+		let foo = jdb.insert(b"foo");
+		let bar = jdb.insert(b"bar");
+		jdb.commit(0, &b"0".sha3(), None).unwrap();
+		assert!(jdb.exists(&foo));
+		assert!(jdb.exists(&bar));
+
+		jdb.remove(&foo);
+		jdb.remove(&bar);
+		let baz = jdb.insert(b"baz");
+		jdb.commit(1, &b"1".sha3(), Some((0, b"0".sha3()))).unwrap();
+		assert!(jdb.exists(&foo));
+		assert!(jdb.exists(&bar));
+		assert!(jdb.exists(&baz));
+
+		let foo = jdb.insert(b"foo");
+		jdb.remove(&baz);
+		jdb.commit(2, &b"2".sha3(), Some((1, b"1".sha3()))).unwrap();
+		assert!(jdb.exists(&foo));
+		assert!(!jdb.exists(&bar));
+		assert!(jdb.exists(&baz));
+
+		jdb.remove(&foo);
+		jdb.commit(3, &b"3".sha3(), Some((2, b"2".sha3()))).unwrap();
+		assert!(jdb.exists(&foo));
+		assert!(!jdb.exists(&bar));
+		assert!(!jdb.exists(&baz));
+
+		jdb.commit(4, &b"4".sha3(), Some((3, b"3".sha3()))).unwrap();
+		assert!(!jdb.exists(&foo));
+		assert!(!jdb.exists(&bar));
+		assert!(!jdb.exists(&baz));
+	}
+
+	#[test]
+	fn overlay_recent_writes_only_canonical() {
+		// history is 1
+		let backing: Arc<KeyValueDB> = Arc::new(in_memory());
+		let mut jdb = new_overlay_recent(&backing);
+		jdb.commit(0, &b"0".sha3(), None).unwrap();
+
+		let foo = jdb.insert(b"foo");
+		jdb.commit(1, &b"1a".sha3(), Some((0, b"0".sha3()))).unwrap();
+		let bar = jdb.insert(b"bar");
+		jdb.commit(1, &b"1b".sha3(), Some((0, b"0".sha3()))).unwrap();
+		assert!(jdb.exists(&foo));
+		assert!(jdb.exists(&bar));
+		assert!(backing.get(&foo.bytes()).unwrap().is_none());
+		assert!(backing.get(&bar.bytes()).unwrap().is_none());
+
+		jdb.commit(2, &b"2b".sha3(), Some((1, b"1b".sha3()))).unwrap();
+		assert!(!jdb.exists(&foo));
+		assert!(jdb.exists(&bar));
+		assert!(backing.get(&bar.bytes()).unwrap().is_some());
+	}
+
+	#[test]
+	fn overlay_recent_reopen() {
+		let backing: Arc<KeyValueDB> = Arc::new(in_memory());
+		let (foo, bar, baz) = {
+			let mut jdb = new_overlay_recent(&backing);
+			// history is 1
+			let foo = jdb.insert(b"foo");
+			let bar = jdb.insert(b"bar");
+			jdb.commit(0, &b"0".sha3(), None).unwrap();
+			jdb.remove(&foo);
+			let baz = jdb.insert(b"baz");
+			jdb.commit(1, &b"1a".sha3(), Some((0, b"0".sha3()))).unwrap();
+
+			jdb.remove(&bar);
+			jdb.commit(1, &b"1b".sha3(), Some((0, b"0".sha3()))).unwrap();
+			(foo, bar, baz)
+		};
+
+		{
+			let mut jdb = new_overlay_recent(&backing);
+			assert!(jdb.exists(&baz));
+			jdb.commit(2, &b"2b".sha3(), Some((1, b"1b".sha3()))).unwrap();
+			assert!(jdb.exists(&foo));
+			assert!(!jdb.exists(&baz));
+			assert!(!jdb.exists(&bar));
+		}
+	}
+
+	#[test]
+	fn refuses_other_algorithm() {
+		let backing: Arc<KeyValueDB> = Arc::new(in_memory());
+		{
+			let mut jdb = JournalDB::new_with_db(backing.clone(), Algorithm::Archive).unwrap();
+			jdb.insert(b"foo");
+			jdb.commit(0, &b"0".sha3(), None).unwrap();
+		}
+		assert!(JournalDB::new_with_db(backing.clone(), Algorithm::OverlayRecent).is_err());
+		assert!(JournalDB::new_with_db(backing.clone(), Algorithm::RefCounted).is_err());
+		assert_eq!(JournalDB::new_with_db(backing, Algorithm::Archive).unwrap().algorithm(), Algorithm::Archive);
+	}
+
+	#[test]
+	fn algorithm_names() {
+		for a in &[Algorithm::Archive, Algorithm::RefCounted, Algorithm::OverlayRecent] {
+			assert_eq!(a.as_str().parse::<Algorithm>().unwrap(), *a);
+		}
+		assert!("none".parse::<Algorithm>().is_err());
+	}
 }
@@ -161,12 +161,18 @@ impl SecretStore {
 
 	/// Creates new account
 	pub fn new_account(&mut self, pass: &str) -> Result<Address, ::std::io::Error> {
-		let secret = H256::random();
+		let pair = try!(crypto::KeyPair::create().map_err(|_| ::std::io::Error::new(::std::io::ErrorKind::Other, "Failed to generate key pair")));
+		self.insert_account(pair.secret().clone(), pass)
+	}
+
+	/// Adds account with given secret
+	pub fn insert_account(&mut self, secret: crypto::Secret, pass: &str) -> Result<Address, ::std::io::Error> {
+		let pair = try!(crypto::KeyPair::from_secret(secret).map_err(|_| ::std::io::Error::new(::std::io::ErrorKind::InvalidInput, "Invalid secret")));
 		let key_id = H128::random();
 		self.insert(key_id.clone(), secret, pass);
 
 		let mut key_file = self.directory.get(&key_id).expect("the key was just inserted");
-		let address = Address::random();
+		let address = Address::from(pair.public().sha3());
 		key_file.account = Some(address);
 		try!(self.directory.save(key_file));
 		Ok(address)
@@ -471,6 +477,19 @@ mod tests {
 		assert!(signature != x!(0));
 	}
 
+	#[test]
+	fn signs_with_account_key() {
+		let temp = RandomTempPath::create_dir();
+		let mut sstore = SecretStore::new_test(&temp);
+		let address = sstore.new_account("123").unwrap();
+		sstore.unlock_account(&address, "123").unwrap();
+
+		let message = H256::random();
+		let signature = sstore.sign(&address, &message).unwrap();
+		let public = crypto::ec::recover(&signature, &message).unwrap();
+		assert_eq!(Address::from(public.sha3()), address);
+	}
+
 	#[test]
 	fn can_import_account() {
 		use keys::directory::{KeyFileContent, KeyFileCrypto};
@@ -14,37 +14,168 @@
 // You should have received a copy of the GNU General Public License
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
-//! Key-Value store abstraction with RocksDB backend.
+//! Key-Value store abstraction with RocksDB and in-memory backends.
 
-use rocksdb::{DB, Writable, WriteBatch, IteratorMode, DBVector, DBIterator,
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::sync::RwLock;
+use bytes::Bytes;
+use rocksdb::{DB, Writable, WriteBatch, WriteOptions, IteratorMode, DBIterator,
 	IndexType, Options, DBCompactionStyle, BlockBasedOptions, Direction};
 
+enum DBOp {
+	Insert { key: Bytes, value: Bytes },
+	Delete { key: Bytes },
+}
+
 /// Write transaction. Batches a sequence of put/delete operations for efficiency.
 pub struct DBTransaction {
-	batch: WriteBatch,
+	ops: RefCell<Vec<DBOp>>,
 }
 
 impl DBTransaction {
 	/// Create new transaction.
 	pub fn new() -> DBTransaction {
-		DBTransaction { batch: WriteBatch::new() }
+		DBTransaction { ops: RefCell::new(Vec::new()) }
 	}
 
 	/// Insert a key-value pair in the transaction. Any existing value value will be overwritten upon write.
 	pub fn put(&self, key: &[u8], value: &[u8]) -> Result<(), String> {
-		self.batch.put(key, value)
+		self.ops.borrow_mut().push(DBOp::Insert { key: key.to_vec(), value: value.to_vec() });
+		Ok(())
 	}
 
 	/// Delete value by key.
 	pub fn delete(&self, key: &[u8]) -> Result<(), String> {
-		self.batch.delete(key)
+		self.ops.borrow_mut().push(DBOp::Delete { key: key.to_vec() });
+		Ok(())
+	}
+}
+
+/// Generic key-value database.
+pub trait KeyValueDB: Send + Sync {
+	/// Get value by key.
+	fn get(&self, key: &[u8]) -> Result<Option<Bytes>, String>;
+
+	/// Get value by partial key. Prefix size should match configured prefix size.
+	fn get_by_prefix(&self, prefix: &[u8]) -> Option<Box<[u8]>>;
+
+	/// Commit transaction to database.
+	fn write(&self, tr: DBTransaction) -> Result<(), String>;
+
+	/// Iterate over all key-value pairs in key order.
+	fn iter<'a>(&'a self) -> Box<Iterator<Item=(Box<[u8]>, Box<[u8]>)> + 'a>;
+
+	/// Insert a key-value pair. Any existing value value will be overwritten.
+	fn put(&self, key: &[u8], value: &[u8]) -> Result<(), String> {
+		let tr = DBTransaction::new();
+		try!(tr.put(key, value));
+		self.write(tr)
+	}
+
+	/// Delete value by key.
+	fn delete(&self, key: &[u8]) -> Result<(), String> {
+		let tr = DBTransaction::new();
+		try!(tr.delete(key));
+		self.write(tr)
+	}
+
+	/// Reclaim space of deleted and overwritten entries. Does nothing for backends without compaction.
+	fn compact(&self) {}
+
+	/// Check if there is anything in the database.
+	fn is_empty(&self) -> bool {
+		self.iter().next().is_none()
+	}
+}
+
+/// Compaction style of the database.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CompactionStyle {
+	/// Level compaction. Lower space amplification, more writes.
+	Level,
+	/// Universal compaction. Fewer writes, more space.
+	Universal,
+}
+
+/// Compaction settings suited to a kind of storage.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CompactionProfile {
+	/// Compaction style.
+	pub style: CompactionStyle,
+	/// Target size of level-1 files in bytes.
+	pub initial_file_size: u64,
+	/// Target file size multiplier of each following level.
+	pub file_size_multiplier: i32,
+}
+
+impl CompactionProfile {
+	/// Profile for solid state drives.
+	pub fn ssd() -> CompactionProfile {
+		CompactionProfile {
+			style: CompactionStyle::Universal,
+			initial_file_size: 32 * 1024 * 1024,
+			file_size_multiplier: 2,
+		}
+	}
+
+	/// Profile for rotational drives. Large files keep the number of seeks down.
+	pub fn hdd() -> CompactionProfile {
+		CompactionProfile {
+			style: CompactionStyle::Level,
+			initial_file_size: 192 * 1024 * 1024,
+			file_size_multiplier: 1,
+		}
+	}
+}
+
+impl Default for CompactionProfile {
+	fn default() -> CompactionProfile {
+		CompactionProfile::ssd()
 	}
 }
 
 /// Database configuration
+#[derive(Debug, Clone)]
 pub struct DatabaseConfig {
 	/// Optional prefix size in bytes. Allows lookup by partial key.
-	pub prefix_size: Option<usize>
+	pub prefix_size: Option<usize>,
+	/// Maximum number of open files.
+	pub max_open_files: i32,
+	/// Block cache size in megabytes. RocksDB default if `None`.
+	pub cache_size: Option<usize>,
+	/// Write buffer size in megabytes. RocksDB default if `None`.
+	pub write_buffer_size: Option<usize>,
+	/// Compaction settings.
+	pub compaction: CompactionProfile,
+	/// Write to the write-ahead log. Without it, writes since the last flush are lost on an unclean shutdown.
+	pub wal: bool,
+	/// Use fsync instead of fdatasync.
+	pub fsync: bool,
+}
+
+impl DatabaseConfig {
+	/// Default configuration with given prefix size.
+	pub fn with_prefix(prefix_size: Option<usize>) -> DatabaseConfig {
+		DatabaseConfig {
+			prefix_size: prefix_size,
+			..DatabaseConfig::default()
+		}
+	}
+}
+
+impl Default for DatabaseConfig {
+	fn default() -> DatabaseConfig {
+		DatabaseConfig {
+			prefix_size: None,
+			max_open_files: 256,
+			cache_size: None,
+			write_buffer_size: None,
+			compaction: CompactionProfile::default(),
+			wal: true,
+			fsync: false,
+		}
+	}
 }
 
 /// Database iterator
@@ -60,41 +191,36 @@ impl<'a> Iterator for DatabaseIterator<'a> {
 	}
 }
 
-/// Key-Value database.
+/// Key-Value database backed by RocksDB.
 pub struct Database {
 	db: DB,
+	write_opts: WriteOptions,
 }
 
 impl Database {
 	/// Open database with default settings.
 	pub fn open_default(path: &str) -> Result<Database, String> {
-		Database::open(&DatabaseConfig { prefix_size: None }, path)
+		Database::open(&DatabaseConfig::default(), path)
 	}
 
 	/// Open database file. Creates if it does not exist.
 	pub fn open(config: &DatabaseConfig, path: &str) -> Result<Database, String> {
 		let mut opts = Options::new();
-		opts.set_max_open_files(256);
+		opts.set_max_open_files(config.max_open_files);
 		opts.create_if_missing(true);
-		opts.set_use_fsync(false);
-		opts.set_compaction_style(DBCompactionStyle::DBUniversalCompaction);
-		/*
-		opts.set_bytes_per_sync(8388608);
-		opts.set_disable_data_sync(false);
-		opts.set_block_cache_size_mb(1024);
-		opts.set_table_cache_num_shard_bits(6);
-		opts.set_max_write_buffer_number(32);
-		opts.set_write_buffer_size(536870912);
-		opts.set_target_file_size_base(1073741824);
-		opts.set_min_write_buffer_number_to_merge(4);
-		opts.set_level_zero_stop_writes_trigger(2000);
-		opts.set_level_zero_slowdown_writes_trigger(0);
-		opts.set_compaction_style(DBUniversalCompaction);
-		opts.set_max_background_compactions(4);
-		opts.set_max_background_flushes(4);
-		opts.set_filter_deletes(false);
-		opts.set_disable_auto_compactions(false);
-		*/
+		opts.set_use_fsync(config.fsync);
+		opts.set_compaction_style(match config.compaction.style {
+			CompactionStyle::Level => DBCompactionStyle::DBLevelCompaction,
+			CompactionStyle::Universal => DBCompactionStyle::DBUniversalCompaction,
+		});
+		opts.set_target_file_size_base(config.compaction.initial_file_size);
+		opts.set_target_file_size_multiplier(config.compaction.file_size_multiplier);
+		if let Some(cache_size) = config.cache_size {
+			opts.set_block_cache_size_mb(cache_size as u64);
+		}
+		if let Some(buffer_size) = config.write_buffer_size {
+			opts.set_write_buffer_size(buffer_size * 1024 * 1024);
+		}
 
 		if let Some(size) = config.prefix_size {
 			let mut block_opts = BlockBasedOptions::new();
@@ -102,48 +228,94 @@ impl Database {
 			opts.set_block_based_table_factory(&block_opts);
 			opts.set_prefix_extractor_fixed_size(size);
 		}
+
+		let mut write_opts = WriteOptions::new();
+		if !config.wal {
+			write_opts.disable_wal(true);
+		}
 		let db = try!(DB::open(&opts, path));
-		Ok(Database { db: db })
+		Ok(Database {
+			db: db,
+			write_opts: write_opts,
+		})
 	}
+}
 
-	/// Insert a key-value pair in the transaction. Any existing value value will be overwritten.
-	pub fn put(&self, key: &[u8], value: &[u8]) -> Result<(), String> {
-		self.db.put(key, value)
+impl KeyValueDB for Database {
+	fn get(&self, key: &[u8]) -> Result<Option<Bytes>, String> {
+		self.db.get(key).map(|v| v.map(|v| v.to_vec()))
 	}
 
-	/// Delete value by key.
-	pub fn delete(&self, key: &[u8]) -> Result<(), String> {
-		self.db.delete(key)
+	fn get_by_prefix(&self, prefix: &[u8]) -> Option<Box<[u8]>> {
+		let mut iter = self.db.iterator(IteratorMode::From(prefix, Direction::forward));
+		match iter.next() {
+			// TODO: use prefix_same_as_start read option (not availabele in C API currently)
+			Some((k, v)) => if k[0 .. prefix.len()] == prefix[..] { Some(v) } else { None },
+			_ => None
+		}
 	}
 
-	/// Commit transaction to database.
-	pub fn write(&self, tr: DBTransaction) -> Result<(), String> {
-		self.db.write(tr.batch)
+	fn write(&self, tr: DBTransaction) -> Result<(), String> {
+		let batch = WriteBatch::new();
+		for op in tr.ops.into_inner() {
+			match op {
+				DBOp::Insert { key, value } => try!(batch.put(&key, &value)),
+				DBOp::Delete { key } => try!(batch.delete(&key)),
+			}
+		}
+		self.db.write_opt(batch, &self.write_opts)
 	}
 
-	/// Get value by key.
-	pub fn get(&self, key: &[u8]) -> Result<Option<DBVector>, String> {
-		self.db.get(key)
+	fn compact(&self) {
+		self.db.compact_range(None, None);
 	}
 
-	/// Get value by partial key. Prefix size should match configured prefix size.
-	pub fn get_by_prefix(&self, prefix: &[u8]) -> Option<Box<[u8]>> {
-		let mut iter = self.db.iterator(IteratorMode::From(prefix, Direction::forward));
-		match iter.next() {
-			// TODO: use prefix_same_as_start read option (not availabele in C API currently)
-			Some((k, v)) => if k[0 .. prefix.len()] == prefix[..] { Some(v) } else { None },
-			_ => None
+	fn iter<'a>(&'a self) -> Box<Iterator<Item=(Box<[u8]>, Box<[u8]>)> + 'a> {
+		Box::new(DatabaseIterator { iter: self.db.iterator(IteratorMode::Start) })
+	}
+}
+
+/// Key-Value database kept in memory. Contents are lost when it is dropped.
+#[derive(Default)]
+pub struct InMemory {
+	data: RwLock<BTreeMap<Bytes, Bytes>>,
+}
+
+/// Create an empty in-memory database.
+pub fn in_memory() -> InMemory {
+	InMemory::default()
+}
+
+impl KeyValueDB for InMemory {
+	fn get(&self, key: &[u8]) -> Result<Option<Bytes>, String> {
+		Ok(self.data.read().unwrap().get(key).cloned())
+	}
+
+	fn get_by_prefix(&self, prefix: &[u8]) -> Option<Box<[u8]>> {
+		self.data.read().unwrap().iter()
+			.find(|&(ref k, _)| k.starts_with(prefix))
+			.map(|(_, v)| v.clone().into_boxed_slice())
+	}
+
+	fn write(&self, tr: DBTransaction) -> Result<(), String> {
+		let mut data = self.data.write().unwrap();
+		for op in tr.ops.into_inner() {
+			match op {
+				DBOp::Insert { key, value } => { data.insert(key, value); },
+				DBOp::Delete { key } => { data.remove(&key); },
+			}
 		}
+		Ok(())
 	}
 
-	/// Check if there is anything in the database.
-	pub fn is_empty(&self) -> bool {
-		self.db.iterator(IteratorMode::Start).next().is_none()
+	fn iter<'a>(&'a self) -> Box<Iterator<Item=(Box<[u8]>, Box<[u8]>)> + 'a> {
+		// snapshot, so that the lock is not held while iterating.
+		let data = self.data.read().unwrap().clone();
+		Box::new(data.into_iter().map(|(k, v)| (k.into_boxed_slice(), v.into_boxed_slice())))
 	}
 
-	/// Check if there is anything in the database.
-	pub fn iter(&self) -> DatabaseIterator {
-		DatabaseIterator { iter: self.db.iterator(IteratorMode::Start) }
+	fn is_empty(&self) -> bool {
+		self.data.read().unwrap().is_empty()
 	}
 }
 
@@ -158,6 +330,10 @@ mod tests {
 	fn test_db(config: &DatabaseConfig) {
 		let path = RandomTempPath::create_dir();
 		let db = Database::open(config, path.as_path().to_str().unwrap()).unwrap();
+		test_kvdb(&db, config.prefix_size.is_some());
+	}
+
+	fn test_kvdb(db: &KeyValueDB, prefix_lookup: bool) {
 		let key1 = H256::from_str("02c69be41d0b7e40352fc85be1cd65eb03d40ef8427a0ca4596b1ead9a00e9fc").unwrap();
 		let key2 = H256::from_str("03c69be41d0b7e40352fc85be1cd65eb03d40ef8427a0ca4596b1ead9a00e9fc").unwrap();
 		let key3 = H256::from_str("01c69be41d0b7e40352fc85be1cd65eb03d40ef8427a0ca4596b1ead9a00e9fc").unwrap();
@@ -185,7 +361,7 @@ mod tests {
 		assert!(db.get(&key1).unwrap().is_none());
 		assert_eq!(db.get(&key3).unwrap().unwrap().deref(), b"elephant");
 
-		if config.prefix_size.is_some() {
+		if prefix_lookup {
 			assert_eq!(db.get_by_prefix(&key3).unwrap().deref(), b"elephant");
 			assert_eq!(db.get_by_prefix(&key2).unwrap().deref(), b"dog");
 		}
@@ -196,10 +372,17 @@ mod tests {
 		let path = RandomTempPath::create_dir();
 		let smoke = Database::open_default(path.as_path().to_str().unwrap()).unwrap();
 		assert!(smoke.is_empty());
-		test_db(&DatabaseConfig { prefix_size: None });
-		test_db(&DatabaseConfig { prefix_size: Some(1) });
-		test_db(&DatabaseConfig { prefix_size: Some(8) });
-		test_db(&DatabaseConfig { prefix_size: Some(32) });
+		test_db(&DatabaseConfig::with_prefix(None));
+		test_db(&DatabaseConfig::with_prefix(Some(1)));
+		test_db(&DatabaseConfig::with_prefix(Some(8)));
+		test_db(&DatabaseConfig::with_prefix(Some(32)));
+	}
+
+	#[test]
+	fn in_memory_kvdb() {
+		let db = in_memory();
+		assert!(db.is_empty());
+		test_kvdb(&db, true);
 	}
 }
 
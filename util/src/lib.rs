@@ -132,6 +132,7 @@ pub mod memorydb;
 pub mod overlaydb;
 pub mod journaldb;
 pub mod kvdb;
+pub mod migration;
 mod math;
 pub mod crypto;
 pub mod triehash;
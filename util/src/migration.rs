@@ -0,0 +1,445 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Database schema migrations.
+//!
+//! Every database directory has a schema version stored in a `<name>.version` file next to it.
+//! A database without the file is at version 0. Migrations are applied in order, each one
+//! streaming all key/value pairs of the database into a fresh one. Once all of them succeed
+//! the migrated directory is marked with its version and renamed to `<name>.migrated`, then the
+//! original directory is kept as `<name>.bak` and the migrated one takes its place. A swap
+//! interrupted by a crash is finished (or rolled back) the next time the database is migrated.
+
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::fmt;
+use kvdb::{KeyValueDB, Database, DBTransaction, DatabaseConfig};
+
+/// Single step of a database migration.
+pub trait Migration {
+	/// Schema version the database is at after this migration.
+	fn version(&self) -> u32;
+	/// Transforms single key/value pair. Returning `None` drops the entry.
+	fn migrate(&self, key: Vec<u8>, value: Vec<u8>) -> Option<(Vec<u8>, Vec<u8>)>;
+}
+
+/// Migration error.
+#[derive(Debug)]
+pub enum Error {
+	/// Migration was registered out of order.
+	CannotAddMigration,
+	/// Database is at a version no registered migration leads from.
+	MigrationImpossible(u32),
+	/// Database is newer than the client supports.
+	UnsupportedVersion(u32),
+	/// Filesystem error.
+	Io(::std::io::Error),
+	/// Database error.
+	Database(String),
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			Error::CannotAddMigration => write!(f, "Migrations must be added in increasing version order"),
+			Error::MigrationImpossible(v) => write!(f, "No migration path from database version {}", v),
+			Error::UnsupportedVersion(v) => write!(f, "Database version {} is newer than supported by this client", v),
+			Error::Io(ref e) => write!(f, "IO error during migration: {}", e),
+			Error::Database(ref e) => write!(f, "Database error during migration: {}", e),
+		}
+	}
+}
+
+impl From<::std::io::Error> for Error {
+	fn from(e: ::std::io::Error) -> Error {
+		Error::Io(e)
+	}
+}
+
+impl From<String> for Error {
+	fn from(e: String) -> Error {
+		Error::Database(e)
+	}
+}
+
+/// Number of entries between progress reports.
+const PROGRESS_INTERVAL: usize = 100_000;
+
+/// Migration configuration.
+pub struct Config {
+	/// Number of entries written in a single batch.
+	pub batch_size: usize,
+}
+
+impl Default for Config {
+	fn default() -> Config {
+		Config {
+			batch_size: 1024,
+		}
+	}
+}
+
+/// Path of the file holding schema version of database at `db_path`.
+pub fn version_file_path(db_path: &Path) -> PathBuf {
+	db_path.with_extension("version")
+}
+
+/// Path of the backup of database at `db_path` left after migration.
+pub fn backup_path(db_path: &Path) -> PathBuf {
+	db_path.with_extension("bak")
+}
+
+fn temp_path(db_path: &Path) -> PathBuf {
+	db_path.with_extension("migration")
+}
+
+fn step_path(db_path: &Path, version: u32) -> PathBuf {
+	db_path.with_extension(format!("migration{}", version))
+}
+
+fn migrated_path(db_path: &Path) -> PathBuf {
+	db_path.with_extension("migrated")
+}
+
+/// Name of the file inside a fully migrated directory holding its version until the swap is done.
+const MIGRATED_VERSION_FILE: &'static str = "MIGRATED_VERSION";
+
+fn read_marker(dir: &Path) -> Result<Option<u32>, Error> {
+	let path = dir.join(MIGRATED_VERSION_FILE);
+	if !path.exists() {
+		return Ok(None);
+	}
+	let mut s = String::new();
+	try!(try!(fs::File::open(&path)).read_to_string(&mut s));
+	s.trim().parse().map(Some).map_err(|_| Error::Database(format!("Invalid version file: {}", path.display())))
+}
+
+fn write_marker(dir: &Path, version: u32) -> Result<(), Error> {
+	let mut file = try!(fs::File::create(dir.join(MIGRATED_VERSION_FILE)));
+	try!(file.write_all(format!("{}", version).as_bytes()));
+	try!(file.sync_all());
+	Ok(())
+}
+
+/// Reads schema version of database at `db_path`. Returns 0 if it was never recorded.
+pub fn read_version(db_path: &Path) -> Result<u32, Error> {
+	let path = version_file_path(db_path);
+	if !path.exists() {
+		return Ok(0);
+	}
+	let mut s = String::new();
+	try!(try!(fs::File::open(&path)).read_to_string(&mut s));
+	s.trim().parse().map_err(|_| Error::Database(format!("Invalid version file: {}", path.display())))
+}
+
+/// Records schema version of database at `db_path`.
+pub fn write_version(db_path: &Path, version: u32) -> Result<(), Error> {
+	let mut file = try!(fs::File::create(version_file_path(db_path)));
+	try!(file.write_all(format!("{}", version).as_bytes()));
+	Ok(())
+}
+
+/// Ordered set of migrations of a single database.
+pub struct Manager {
+	config: Config,
+	migrations: Vec<Box<Migration>>,
+}
+
+impl Manager {
+	/// Creates a manager without migrations.
+	pub fn new(config: Config) -> Manager {
+		Manager {
+			config: config,
+			migrations: Vec::new(),
+		}
+	}
+
+	/// Registers next migration. Its version must be higher than the version of all previous ones.
+	pub fn add_migration<T>(&mut self, migration: T) -> Result<(), Error> where T: Migration + 'static {
+		let is_new = match self.migrations.last() {
+			Some(last) => migration.version() > last.version(),
+			None => true,
+		};
+		if !is_new {
+			return Err(Error::CannotAddMigration);
+		}
+		self.migrations.push(Box::new(migration));
+		Ok(())
+	}
+
+	/// Version of the database after all registered migrations.
+	pub fn latest_version(&self) -> u32 {
+		self.migrations.last().map_or(0, |m| m.version())
+	}
+
+	/// Returns true if database at `version` needs migration.
+	pub fn is_needed(&self, version: u32) -> bool {
+		version < self.latest_version()
+	}
+
+	/// Brings database at `db_path` to the latest version. A database which doesn't exist yet
+	/// is just marked as being at the latest version.
+	pub fn migrate(&self, db_path: &Path, db_config: &DatabaseConfig) -> Result<(), Error> {
+		try!(self.recover(db_path));
+		if !db_path.exists() {
+			if let Some(parent) = db_path.parent() {
+				try!(fs::create_dir_all(parent));
+			}
+			return write_version(db_path, self.latest_version());
+		}
+		let version = try!(read_version(db_path));
+		if version > self.latest_version() {
+			return Err(Error::UnsupportedVersion(version));
+		}
+		if !self.is_needed(version) {
+			return Ok(());
+		}
+
+		// Only a database at one of the registered versions (or the initial one) can be migrated.
+		if version != 0 && !self.migrations.iter().any(|m| m.version() == version) {
+			return Err(Error::MigrationImpossible(version));
+		}
+
+		let temp = temp_path(db_path);
+		let mut source_path = db_path.to_path_buf();
+		for migration in self.migrations.iter().filter(|m| m.version() > version) {
+			info!(target: "migration", "Migrating {} to version {}", db_path.display(), migration.version());
+			if temp.exists() {
+				try!(fs::remove_dir_all(&temp));
+			}
+			try!(self.migrate_step(&**migration, &source_path, &temp, db_config));
+			// intermediate results replace each other; the original stays untouched until the end.
+			if source_path.as_path() != db_path {
+				try!(fs::remove_dir_all(&source_path));
+			}
+			source_path = step_path(db_path, migration.version());
+			try!(fs::rename(&temp, &source_path));
+		}
+
+		// from here on the migrated database is complete and the swap can always be finished.
+		try!(write_marker(&source_path, self.latest_version()));
+		let migrated = migrated_path(db_path);
+		try!(fs::rename(&source_path, &migrated));
+		try!(self.swap(db_path));
+		info!(target: "migration", "Migrated {} to version {}. Old database kept at {}", db_path.display(), self.latest_version(), backup_path(db_path).display());
+		Ok(())
+	}
+
+	/// Replaces database at `db_path` with the marked one at `<name>.migrated`, keeping the old
+	/// one as a backup, then records the new version.
+	fn swap(&self, db_path: &Path) -> Result<(), Error> {
+		let migrated = migrated_path(db_path);
+		let version = match try!(read_marker(&migrated)) {
+			Some(version) => version,
+			None => return Err(Error::Database(format!("Missing version of migrated database: {}", migrated.display()))),
+		};
+		let backup = backup_path(db_path);
+		if db_path.exists() {
+			if backup.exists() {
+				try!(fs::remove_dir_all(&backup));
+			}
+			try!(fs::rename(db_path, &backup));
+		}
+		try!(fs::rename(&migrated, db_path));
+		try!(write_version(db_path, version));
+		try!(fs::remove_file(db_path.join(MIGRATED_VERSION_FILE)));
+		Ok(())
+	}
+
+	/// Brings database at `db_path` back to a consistent state after a migration interrupted by
+	/// a crash: a swap that was started is finished, one that lost the database is rolled back and
+	/// partial results are removed.
+	fn recover(&self, db_path: &Path) -> Result<(), Error> {
+		if migrated_path(db_path).exists() {
+			warn!(target: "migration", "Finishing interrupted migration of {}", db_path.display());
+			try!(self.swap(db_path));
+		} else if let Some(version) = try!(read_marker(db_path)) {
+			// crashed after the swap, but before the version was recorded.
+			try!(write_version(db_path, version));
+			try!(fs::remove_file(db_path.join(MIGRATED_VERSION_FILE)));
+		} else if !db_path.exists() && backup_path(db_path).exists() {
+			warn!(target: "migration", "Restoring {} from backup after interrupted migration", db_path.display());
+			try!(fs::rename(backup_path(db_path), db_path));
+		}
+
+		let temp = temp_path(db_path);
+		if temp.exists() {
+			try!(fs::remove_dir_all(&temp));
+		}
+		for migration in &self.migrations {
+			let step = step_path(db_path, migration.version());
+			if step.exists() {
+				try!(fs::remove_dir_all(&step));
+			}
+		}
+		Ok(())
+	}
+
+	fn migrate_step(&self, migration: &Migration, source: &Path, dest: &Path, db_config: &DatabaseConfig) -> Result<(), Error> {
+		let source = try!(Database::open(db_config, &source.to_string_lossy()));
+		let dest = try!(Database::open(db_config, &dest.to_string_lossy()));
+
+		let mut batch = DBTransaction::new();
+		let mut in_batch = 0;
+		let mut total = 0usize;
+		for (key, value) in source.iter() {
+			if let Some((key, value)) = migration.migrate(key.to_vec(), value.to_vec()) {
+				try!(batch.put(&key, &value));
+				in_batch += 1;
+			}
+			total += 1;
+			if in_batch == self.config.batch_size {
+				try!(dest.write(batch));
+				batch = DBTransaction::new();
+				in_batch = 0;
+			}
+			if total % PROGRESS_INTERVAL == 0 {
+				info!(target: "migration", "Migrated {} entries", total);
+			}
+		}
+		try!(dest.write(batch));
+		info!(target: "migration", "Migrated {} entries", total);
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::path::Path;
+	use kvdb::{KeyValueDB, Database, DatabaseConfig};
+	use devtools::RandomTempPath;
+	use super::*;
+
+	struct Double;
+
+	impl Migration for Double {
+		fn version(&self) -> u32 { 1 }
+		fn migrate(&self, key: Vec<u8>, value: Vec<u8>) -> Option<(Vec<u8>, Vec<u8>)> {
+			Some((key, value.iter().chain(value.iter()).cloned().collect()))
+		}
+	}
+
+	struct DropOdd;
+
+	impl Migration for DropOdd {
+		fn version(&self) -> u32 { 2 }
+		fn migrate(&self, key: Vec<u8>, value: Vec<u8>) -> Option<(Vec<u8>, Vec<u8>)> {
+			if key[0] % 2 == 1 { None } else { Some((key, value)) }
+		}
+	}
+
+	fn config() -> DatabaseConfig {
+		DatabaseConfig::default()
+	}
+
+	fn open(path: &Path) -> Database {
+		Database::open(&config(), path.to_str().unwrap()).unwrap()
+	}
+
+	#[test]
+	fn should_refuse_out_of_order_migrations() {
+		let mut manager = Manager::new(Config::default());
+		manager.add_migration(DropOdd).unwrap();
+		assert!(manager.add_migration(Double).is_err());
+	}
+
+	#[test]
+	fn should_mark_new_database_as_latest() {
+		let temp = RandomTempPath::create_dir();
+		let db_path = temp.as_path().join("extras");
+		let mut manager = Manager::new(Config::default());
+		manager.add_migration(Double).unwrap();
+		manager.migrate(&db_path, &config()).unwrap();
+		assert_eq!(read_version(&db_path).unwrap(), 1);
+	}
+
+	#[test]
+	fn should_migrate_and_keep_backup() {
+		let temp = RandomTempPath::create_dir();
+		let db_path = temp.as_path().join("extras");
+		{
+			let db = open(&db_path);
+			db.put(&[1u8], &[1u8]).unwrap();
+			db.put(&[2u8], &[2u8]).unwrap();
+		}
+
+		let mut manager = Manager::new(Config { batch_size: 1 });
+		manager.add_migration(Double).unwrap();
+		manager.add_migration(DropOdd).unwrap();
+		manager.migrate(&db_path, &config()).unwrap();
+
+		assert_eq!(read_version(&db_path).unwrap(), 2);
+		{
+			let db = open(&db_path);
+			assert!(db.get(&[1u8]).unwrap().is_none());
+			assert_eq!(&*db.get(&[2u8]).unwrap().unwrap(), &[2u8, 2u8]);
+		}
+		let backup = open(&backup_path(&db_path));
+		assert_eq!(&*backup.get(&[1u8]).unwrap().unwrap(), &[1u8]);
+	}
+
+	#[test]
+	fn should_finish_interrupted_swap() {
+		let temp = RandomTempPath::create_dir();
+		let db_path = temp.as_path().join("extras");
+		{
+			let db = open(&db_path);
+			db.put(&[2u8], &[2u8]).unwrap();
+		}
+		let mut manager = Manager::new(Config::default());
+		manager.add_migration(Double).unwrap();
+		manager.migrate(&db_path, &config()).unwrap();
+
+		// crash right after the original database was moved away.
+		::std::fs::rename(&db_path, db_path.with_extension("migrated")).unwrap();
+		write_marker(&db_path.with_extension("migrated"), 1).unwrap();
+		write_version(&db_path, 0).unwrap();
+
+		manager.migrate(&db_path, &config()).unwrap();
+		assert_eq!(read_version(&db_path).unwrap(), 1);
+		assert!(!db_path.with_extension("migrated").exists());
+		assert!(!db_path.join(MIGRATED_VERSION_FILE).exists());
+		let db = open(&db_path);
+		assert_eq!(&*db.get(&[2u8]).unwrap().unwrap(), &[2u8, 2u8]);
+	}
+
+	#[test]
+	fn should_restore_backup_of_lost_database() {
+		let temp = RandomTempPath::create_dir();
+		let db_path = temp.as_path().join("extras");
+		{
+			let db = open(&backup_path(&db_path));
+			db.put(&[2u8], &[2u8]).unwrap();
+		}
+		let mut manager = Manager::new(Config::default());
+		manager.add_migration(Double).unwrap();
+		manager.migrate(&db_path, &config()).unwrap();
+
+		assert_eq!(read_version(&db_path).unwrap(), 1);
+		let db = open(&db_path);
+		assert_eq!(&*db.get(&[2u8]).unwrap().unwrap(), &[2u8, 2u8]);
+	}
+
+	#[test]
+	fn should_refuse_newer_database() {
+		let temp = RandomTempPath::create_dir();
+		let db_path = temp.as_path().join("extras");
+		open(&db_path);
+		write_version(&db_path, 3).unwrap();
+		assert!(Manager::new(Config::default()).migrate(&db_path, &config()).is_err());
+	}
+}
@@ -24,9 +24,8 @@ use hashdb::*;
 use memorydb::*;
 use std::ops::*;
 use std::sync::*;
-use std::env;
 use std::collections::HashMap;
-use kvdb::{Database};
+use kvdb::{KeyValueDB, in_memory};
 
 /// Implementation of the HashDB trait for a disk-backed database with a memory overlay.
 ///
@@ -38,23 +37,21 @@ use kvdb::{Database};
 /// queries have an immediate effect in terms of these functions.
 pub struct OverlayDB {
 	overlay: MemoryDB,
-	backing: Arc<Database>,
+	backing: Arc<KeyValueDB>,
 }
 
 impl OverlayDB {
 	/// Create a new instance of OverlayDB given a `backing` database.
-	pub fn new(backing: Database) -> OverlayDB { Self::new_with_arc(Arc::new(backing)) }
+	pub fn new<D>(backing: D) -> OverlayDB where D: KeyValueDB + 'static { Self::new_with_arc(Arc::new(backing)) }
 
 	/// Create a new instance of OverlayDB given a `backing` database.
-	pub fn new_with_arc(backing: Arc<Database>) -> OverlayDB {
+	pub fn new_with_arc(backing: Arc<KeyValueDB>) -> OverlayDB {
 		OverlayDB{ overlay: MemoryDB::new(), backing: backing }
 	}
 
-	/// Create a new instance of OverlayDB with an anonymous temporary database.
+	/// Create a new instance of OverlayDB with an anonymous in-memory database.
 	pub fn new_temp() -> OverlayDB {
-		let mut dir = env::temp_dir();
-		dir.push(H32::random().hex());
-		Self::new(Database::open_default(dir.to_str().unwrap()).unwrap())
+		Self::new(in_memory())
 	}
 
 	/// Commit all memory operations to the backing database.
@@ -317,6 +314,7 @@ fn overlaydb_complex() {
 #[test]
 fn playpen() {
 	use std::fs;
+	use kvdb::Database;
 	{
 		let db: Database = Database::open_default("/tmp/test").unwrap();
 		db.put(b"test", b"test2").unwrap();
@@ -32,6 +32,8 @@ pub mod triedbmut;
 pub mod sectriedb;
 /// Export the sectriedbmut module.
 pub mod sectriedbmut;
+/// Export the proof module.
+pub mod proof;
 
 pub use self::trietraits::*;
 pub use self::standardmap::*;
@@ -39,3 +41,4 @@ pub use self::triedbmut::*;
 pub use self::triedb::*;
 pub use self::sectriedbmut::*;
 pub use self::sectriedb::*;
+pub use self::proof::{ProofError, verify_proof, verify_sec_proof};
@@ -0,0 +1,166 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+
+//! Merkle proofs of trie values.
+//!
+//! A proof is the list of RLP-encoded nodes on the path from the root of a trie to the value
+//! of a key (or to the point where the path shows the key is absent). Given only the root,
+//! anyone holding a proof can check the value without access to the rest of the trie.
+
+use common::*;
+use rlp::*;
+use nibbleslice::*;
+use super::node::*;
+
+/// Error returned when checking a proof.
+#[derive(Debug, PartialEq, Clone)]
+pub enum ProofError {
+	/// A node on the path of the key is not in the proof.
+	MissingNode(H256),
+}
+
+impl fmt::Display for ProofError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			ProofError::MissingNode(ref hash) => write!(f, "Proof is missing trie node {}", hash),
+		}
+	}
+}
+
+/// Walk the trie with `root` looking for `key`, getting each referenced node from `fetch`.
+///
+/// Nodes are requested in order from the root down, so `fetch` sees exactly the nodes
+/// which make up a proof of the result. Returns `Err` with the hash of the first node
+/// `fetch` could not provide.
+pub fn lookup<F>(root: &H256, key: &[u8], mut fetch: F) -> Result<Option<Bytes>, H256> where F: FnMut(&H256) -> Option<Bytes> {
+	if root == &SHA3_NULL_RLP {
+		return Ok(None);
+	}
+	let mut node = try!(fetch(root).ok_or(root.clone()));
+	let mut offset = 0;
+	loop {
+		let next = {
+			let key = NibbleSlice::new_offset(key, offset);
+			match Node::decoded(&node) {
+				Node::Leaf(ref slice, value) => return Ok(if &key == slice { Some(value.to_vec()) } else { None }),
+				Node::Extension(ref slice, item) if key.starts_with(slice) => {
+					offset += slice.len();
+					item.to_vec()
+				},
+				Node::Branch(ref nodes, value) => match key.is_empty() {
+					true => return Ok(value.map(|v| v.to_vec())),
+					false => {
+						offset += 1;
+						nodes[key.at(0) as usize].to_vec()
+					}
+				},
+				_ => return Ok(None),
+			}
+		};
+		// child is either referenced by hash or inlined
+		let hash = {
+			let rlp = Rlp::new(&next);
+			match rlp.is_data() && rlp.size() == 32 {
+				true => Some(rlp.as_val::<H256>()),
+				false => None,
+			}
+		};
+		node = match hash {
+			Some(hash) => try!(fetch(&hash).ok_or(hash)),
+			None => next,
+		};
+	}
+}
+
+/// Check `proof` against `root` and return the value it proves for `key`.
+///
+/// `Ok(None)` means the proof shows the key is not in the trie. Nodes in the proof which are
+/// not on the path of the key are ignored.
+pub fn verify_proof(root: &H256, key: &[u8], proof: &[Bytes]) -> Result<Option<Bytes>, ProofError> {
+	let nodes: HashMap<H256, &Bytes> = proof.iter().map(|node| (node.sha3(), node)).collect();
+	lookup(root, key, |hash| nodes.get(hash).map(|node| node.to_vec())).map_err(ProofError::MissingNode)
+}
+
+/// Check `proof` of a `SecTrieDB` value against `root`. The key is hashed before lookup.
+pub fn verify_sec_proof(root: &H256, key: &[u8], proof: &[Bytes]) -> Result<Option<Bytes>, ProofError> {
+	verify_proof(root, &key.sha3(), proof)
+}
+
+#[cfg(test)]
+mod tests {
+	use common::*;
+	use memorydb::*;
+	use super::super::triedbmut::*;
+	use super::super::triedb::*;
+	use super::super::sectriedbmut::*;
+	use super::super::sectriedb::*;
+	use super::*;
+
+	fn populate(memdb: &mut MemoryDB, root: &mut H256) {
+		let mut t = TrieDBMut::new(memdb, root);
+		for i in 0u8..64 {
+			t.insert(&[i, 0x20, i], &vec![i; 40]);
+		}
+		t.insert(b"dog", b"puppy");
+		t.insert(b"doge", b"coin");
+	}
+
+	#[test]
+	fn proves_present_and_absent_keys() {
+		let mut memdb = MemoryDB::new();
+		let mut root = H256::new();
+		populate(&mut memdb, &mut root);
+		let t = TrieDB::new(&memdb, &root);
+
+		let (value, proof) = t.get_with_proof(b"doge");
+		assert_eq!(value, Some(b"coin".to_vec()));
+		assert_eq!(verify_proof(&root, b"doge", &proof), Ok(Some(b"coin".to_vec())));
+
+		let (value, proof) = t.get_with_proof(&[7u8, 0x20, 7]);
+		assert_eq!(value, Some(vec![7u8; 40]));
+		assert_eq!(verify_proof(&root, &[7u8, 0x20, 7], &proof), Ok(Some(vec![7u8; 40])));
+
+		let (value, proof) = t.get_with_proof(b"cat");
+		assert_eq!(value, None);
+		assert_eq!(verify_proof(&root, b"cat", &proof), Ok(None));
+	}
+
+	#[test]
+	fn rejects_incomplete_proof() {
+		let mut memdb = MemoryDB::new();
+		let mut root = H256::new();
+		populate(&mut memdb, &mut root);
+		let (_, mut proof) = TrieDB::new(&memdb, &root).get_with_proof(b"dog");
+		let last = proof.pop().unwrap();
+		assert_eq!(verify_proof(&root, b"dog", &proof), Err(ProofError::MissingNode(last.sha3())));
+		assert!(verify_proof(&H256::random(), b"dog", &[last]).is_err());
+	}
+
+	#[test]
+	fn proves_sec_trie_keys() {
+		let mut memdb = MemoryDB::new();
+		let mut root = H256::new();
+		{
+			let mut t = SecTrieDBMut::new(&mut memdb, &mut root);
+			t.insert(b"foo", b"bar");
+			t.insert(b"fog", b"baz");
+		}
+		let (value, proof) = SecTrieDB::new(&memdb, &root).get_with_proof(b"foo");
+		assert_eq!(value, Some(b"bar".to_vec()));
+		assert_eq!(verify_sec_proof(&root, b"foo", &proof), Ok(Some(b"bar".to_vec())));
+	}
+}
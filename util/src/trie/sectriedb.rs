@@ -15,6 +15,7 @@
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
 use hash::*;
+use bytes::*;
 use sha3::*;
 use hashdb::*;
 use rlp::*;
@@ -41,6 +42,11 @@ impl<'db> SecTrieDB<'db> {
 		&self.raw
 	}
 
+	/// Get the value of `key` together with a proof of it. See `TrieDB::get_with_proof`.
+	pub fn get_with_proof(&self, key: &[u8]) -> (Option<Bytes>, Vec<Bytes>) {
+		self.raw.get_with_proof(&key.sha3())
+	}
+
 	/// Get a mutable reference to the underlying raw TrieDB struct.
 	pub fn raw_mut(&mut self) -> &TrieDB {
 		&mut self.raw
@@ -20,6 +20,7 @@ use nibbleslice::*;
 use rlp::*;
 use super::trietraits::*;
 use super::node::*;
+use super::proof;
 
 /// A `Trie` implementation using a generic `HashDB` backing database.
 ///
@@ -181,6 +182,19 @@ impl<'db> TrieDB<'db> {
 		Ok(())
 	}
 
+	/// Get the value of `key` together with a proof of it: the RLP of every node on the path
+	/// from the root, in order. The proof shows absence if the value is `None`.
+	/// Panics if a node on the path is missing from the backing database.
+	pub fn get_with_proof(&self, key: &[u8]) -> (Option<Bytes>, Vec<Bytes>) {
+		let mut nodes = Vec::new();
+		let value = proof::lookup(self.root, key, |hash| {
+			let node = self.db.lookup(hash).unwrap_or_else(|| panic!("Not found! {:?}", hash)).to_vec();
+			nodes.push(node.clone());
+			Some(node)
+		}).expect("Every fetched node is returned; qed");
+		(value, nodes)
+	}
+
 	/// Return optional data for a key given as a `NibbleSlice`. Returns `None` if no data exists.
 	fn do_lookup<'a, 'key>(&'a self, key: &NibbleSlice<'key>) -> Option<&'a [u8]> where 'a: 'key {
 		let root_rlp = self.db.lookup(&self.root).expect("Trie root not found!");